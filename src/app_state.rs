@@ -1,27 +1,85 @@
 use crate::{
-    db::DBPool, file_driver::FileDriver, route_collections::collection_service::CollectionService,
+    changes::ChangeBroker, db::DBPool, file_driver::FileDriver, import::ImportRunner,
+    pagination::CursorCodec, reconcile::Reconciler,
+    route_archives::archive_service::ArchiveService, route_audit::audit_service::AuditService,
+    route_collections::collection_service::CollectionService,
+    route_files::file_service::FileService, route_imports::import_service::ImportService,
+    route_jobs::job_service::JobService, route_quotas::quota_service::QuotaService,
+    route_storage::storage_service::StorageService,
+    route_thumbnails::thumbnail_service::ThumbnailService,
+    route_webhooks::webhook_service::WebhookService, search::SearchBackend,
 };
 use axum::extract::FromRef;
-use meilisearch_sdk::Client;
 use std::sync::Arc;
 
 #[derive(Clone)]
 pub struct AppState {
     pub db_pool: DBPool,
     pub file_driver: FileDriver,
-    pub meilisearch_client: Arc<Client>,
+    pub search_backend: Arc<dyn SearchBackend>,
+    pub cursor_codec: CursorCodec,
+    pub change_broker: ChangeBroker,
     pub collection_service: CollectionService,
+    pub file_service: FileService,
+    pub audit_service: AuditService,
+    pub webhook_service: WebhookService,
+    pub import_service: ImportService,
+    pub archive_service: ArchiveService,
+    pub thumbnail_service: ThumbnailService,
+    pub job_service: JobService,
+    pub storage_service: StorageService,
+    pub quota_service: QuotaService,
 }
 
 impl AppState {
-    pub fn new(db_pool: DBPool, file_driver: FileDriver, meilisearch_client: Client) -> Self {
-        let collection_service = CollectionService::new(db_pool.clone());
+    pub fn new(
+        db_pool: DBPool,
+        file_driver: FileDriver,
+        search_backend: Arc<dyn SearchBackend>,
+        cursor_codec: CursorCodec,
+        change_broker: ChangeBroker,
+    ) -> Self {
+        let collection_service = CollectionService::new(db_pool.clone(), cursor_codec.clone());
+        let file_service = FileService::new(
+            db_pool.clone(),
+            file_driver.clone(),
+            search_backend.clone(),
+            cursor_codec.clone(),
+        );
+        let audit_service = AuditService::new(db_pool.clone(), cursor_codec.clone());
+        let webhook_service = WebhookService::new(db_pool.clone(), cursor_codec.clone());
+        let import_service = ImportService::new(
+            db_pool.clone(),
+            ImportRunner::new(db_pool.clone(), file_driver.clone(), search_backend.clone()),
+        );
+
+        let archive_service =
+            ArchiveService::new(db_pool.clone(), file_driver.clone(), search_backend.clone());
+        let thumbnail_service = ThumbnailService::new(db_pool.clone(), file_driver.clone());
+        let job_service = JobService::new(db_pool.clone(), cursor_codec.clone());
+        let storage_service = StorageService::new(
+            db_pool.clone(),
+            cursor_codec.clone(),
+            Reconciler::new(db_pool.clone(), file_driver.clone()),
+        );
+        let quota_service = QuotaService::new(db_pool.clone());
 
         Self {
             db_pool,
             file_driver,
-            meilisearch_client: Arc::new(meilisearch_client),
+            search_backend,
+            cursor_codec,
+            change_broker,
             collection_service,
+            file_service,
+            audit_service,
+            webhook_service,
+            import_service,
+            archive_service,
+            thumbnail_service,
+            job_service,
+            storage_service,
+            quota_service,
         }
     }
 }
@@ -38,9 +96,15 @@ impl FromRef<AppState> for FileDriver {
     }
 }
 
-impl FromRef<AppState> for Arc<Client> {
+impl FromRef<AppState> for Arc<dyn SearchBackend> {
+    fn from_ref(input: &AppState) -> Self {
+        input.search_backend.clone()
+    }
+}
+
+impl FromRef<AppState> for ChangeBroker {
     fn from_ref(input: &AppState) -> Self {
-        input.meilisearch_client.clone()
+        input.change_broker.clone()
     }
 }
 
@@ -49,3 +113,57 @@ impl FromRef<AppState> for CollectionService {
         input.collection_service.clone()
     }
 }
+
+impl FromRef<AppState> for FileService {
+    fn from_ref(input: &AppState) -> Self {
+        input.file_service.clone()
+    }
+}
+
+impl FromRef<AppState> for AuditService {
+    fn from_ref(input: &AppState) -> Self {
+        input.audit_service.clone()
+    }
+}
+
+impl FromRef<AppState> for WebhookService {
+    fn from_ref(input: &AppState) -> Self {
+        input.webhook_service.clone()
+    }
+}
+
+impl FromRef<AppState> for ImportService {
+    fn from_ref(input: &AppState) -> Self {
+        input.import_service.clone()
+    }
+}
+
+impl FromRef<AppState> for ArchiveService {
+    fn from_ref(input: &AppState) -> Self {
+        input.archive_service.clone()
+    }
+}
+
+impl FromRef<AppState> for ThumbnailService {
+    fn from_ref(input: &AppState) -> Self {
+        input.thumbnail_service.clone()
+    }
+}
+
+impl FromRef<AppState> for JobService {
+    fn from_ref(input: &AppState) -> Self {
+        input.job_service.clone()
+    }
+}
+
+impl FromRef<AppState> for StorageService {
+    fn from_ref(input: &AppState) -> Self {
+        input.storage_service.clone()
+    }
+}
+
+impl FromRef<AppState> for QuotaService {
+    fn from_ref(input: &AppState) -> Self {
+        input.quota_service.clone()
+    }
+}
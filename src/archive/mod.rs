@@ -0,0 +1,54 @@
+use crate::schema::dto_in::{CreateFileTagDto, FindFilesBodyDto};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+pub mod tar;
+
+/// Path of the manifest, always the first entry of an archive.
+pub const MANIFEST_PATH: &str = "manifest.json";
+
+pub const MANIFEST_VERSION: u32 = 1;
+
+/// Description of an exported collection, and of the files stored next to it in the archive.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct Manifest {
+    pub version: u32,
+    pub exported_at: DateTime<Utc>,
+    pub collection: ManifestCollection,
+    pub files: Vec<ManifestFile>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ManifestCollection {
+    pub uuid: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    /// Smart collections are exported as their query, without files.
+    pub smart_query: Option<FindFilesBodyDto>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ManifestFile {
+    /// Path of the entry that holds the content of the file.
+    pub path: String,
+    pub uuid: Uuid,
+    pub name: String,
+    pub mime: String,
+    pub size: u64,
+    pub hash: u32,
+    pub tags: Vec<CreateFileTagDto>,
+    pub created_at: DateTime<Utc>,
+    pub uploaded_at: DateTime<Utc>,
+}
+
+impl ManifestFile {
+    pub fn entry_path(uuid: Uuid) -> String {
+        format!("files/{}", uuid)
+    }
+}
@@ -0,0 +1,319 @@
+//! Minimal streaming support for ustar archives, enough to read back what the export writes.
+
+use axum::body::Bytes;
+use std::{
+    io,
+    pin::Pin,
+    task::{Context, Poll},
+};
+use tokio::io::{AsyncRead, AsyncReadExt, ReadBuf, Take};
+
+pub const BLOCK_SIZE: u64 = 512;
+
+/// Largest size that fits in the octal size field; larger sizes use the base-256 extension.
+const MAX_OCTAL_SIZE: u64 = 0o77777777777;
+
+/// Builds the header of a regular file entry; `path` must fit in the 100 bytes name field.
+pub fn entry_header(path: &str, size: u64, modified_at: i64) -> Bytes {
+    assert!(path.len() <= 100, "tar entry path `{}` is too long", path);
+
+    let mut header = [0u8; BLOCK_SIZE as usize];
+    header[..path.len()].copy_from_slice(path.as_bytes());
+    header[100..108].copy_from_slice(b"0000644\0");
+    header[108..116].copy_from_slice(b"0000000\0");
+    header[116..124].copy_from_slice(b"0000000\0");
+
+    if size <= MAX_OCTAL_SIZE {
+        header[124..136].copy_from_slice(format!("{:011o}\0", size).as_bytes());
+    } else {
+        header[124] = 0x80;
+        header[128..136].copy_from_slice(&size.to_be_bytes());
+    }
+
+    let modified_at = modified_at.clamp(0, MAX_OCTAL_SIZE as i64);
+    header[136..148].copy_from_slice(format!("{:011o}\0", modified_at).as_bytes());
+    header[156] = b'0';
+    header[257..263].copy_from_slice(b"ustar\0");
+    header[263..265].copy_from_slice(b"00");
+
+    header[148..156].copy_from_slice(b"        ");
+    let checksum = header.iter().map(|&byte| byte as u32).sum::<u32>();
+    header[148..156].copy_from_slice(format!("{:06o}\0 ", checksum).as_bytes());
+
+    Bytes::copy_from_slice(&header)
+}
+
+/// Zeros that pad an entry of `size` bytes to a whole number of blocks.
+pub fn entry_padding(size: u64) -> Bytes {
+    Bytes::from(vec![0u8; padding_size(size) as usize])
+}
+
+/// The two empty blocks that end an archive.
+pub fn archive_end() -> Bytes {
+    Bytes::from(vec![0u8; 2 * BLOCK_SIZE as usize])
+}
+
+fn padding_size(size: u64) -> u64 {
+    (BLOCK_SIZE - size % BLOCK_SIZE) % BLOCK_SIZE
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TarEntry {
+    pub path: String,
+    pub size: u64,
+}
+
+/// Reads the regular file entries of an archive in order; the reader itself reads the content of
+/// the current entry.
+pub struct TarReader<R> {
+    reader: Take<R>,
+    padding: u64,
+}
+
+impl<R: AsyncRead + Unpin> TarReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader: reader.take(0),
+            padding: 0,
+        }
+    }
+
+    /// Skips what is left of the current entry, and moves to the next regular file entry.
+    pub async fn next_entry(&mut self) -> io::Result<Option<TarEntry>> {
+        loop {
+            tokio::io::copy(&mut self.reader, &mut tokio::io::sink()).await?;
+            self.reader.set_limit(self.padding);
+            tokio::io::copy(&mut self.reader, &mut tokio::io::sink()).await?;
+
+            let mut header = [0u8; BLOCK_SIZE as usize];
+            self.reader.set_limit(BLOCK_SIZE);
+            self.reader.read_exact(&mut header).await?;
+
+            if header.iter().all(|&byte| byte == 0) {
+                self.reader.set_limit(0);
+                self.padding = 0;
+                return Ok(None);
+            }
+
+            let entry = parse_header(&header)?;
+            self.reader.set_limit(entry.size);
+            self.padding = padding_size(entry.size);
+
+            // Directories, links and extended headers carry nothing to import.
+            if matches!(header[156], b'0' | b'\0') {
+                return Ok(Some(entry));
+            }
+        }
+    }
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for TarReader<R> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.reader).poll_read(cx, buf)
+    }
+}
+
+fn parse_header(header: &[u8; BLOCK_SIZE as usize]) -> io::Result<TarEntry> {
+    let checksum = parse_octal(&header[148..156])?;
+    let actual_checksum = header
+        .iter()
+        .enumerate()
+        .map(|(index, &byte)| match index {
+            148..=155 => b' ' as u64,
+            _ => byte as u64,
+        })
+        .sum::<u64>();
+
+    if checksum != actual_checksum {
+        return Err(invalid_data("tar header checksum mismatch"));
+    }
+
+    let name = parse_string(&header[..100])?;
+    let prefix = match &header[257..263] {
+        b"ustar\0" => parse_string(&header[345..500])?,
+        _ => "",
+    };
+    let path = match prefix {
+        "" => name.to_owned(),
+        prefix => format!("{}/{}", prefix, name),
+    };
+
+    let size = match header[124] & 0x80 {
+        0 => parse_octal(&header[124..136])?,
+        _ => header[125..136]
+            .iter()
+            .try_fold(header[124] as u64 & 0x7f, |size, &byte| {
+                size.checked_mul(256).map(|size| size | byte as u64)
+            })
+            .ok_or_else(|| invalid_data("tar entry size overflows"))?,
+    };
+
+    Ok(TarEntry { path, size })
+}
+
+fn parse_string(field: &[u8]) -> io::Result<&str> {
+    let length = field
+        .iter()
+        .position(|&byte| byte == 0)
+        .unwrap_or(field.len());
+    std::str::from_utf8(&field[..length]).map_err(|_| invalid_data("tar entry path is not UTF-8"))
+}
+
+fn parse_octal(field: &[u8]) -> io::Result<u64> {
+    let digits = parse_string(field)?.trim_matches(' ');
+
+    match digits {
+        "" => Ok(0),
+        digits => {
+            u64::from_str_radix(digits, 8).map_err(|_| invalid_data("invalid tar header number"))
+        }
+    }
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds an entry with its content and padding.
+    fn entry(path: &str, content: &[u8]) -> Vec<u8> {
+        let mut entry = entry_header(path, content.len() as u64, 0).to_vec();
+        entry.extend_from_slice(content);
+        entry.extend_from_slice(&entry_padding(content.len() as u64));
+        entry
+    }
+
+    /// Builds an entry of another type than a regular file, such as a directory.
+    fn typed_entry(path: &str, type_flag: u8, content: &[u8]) -> Vec<u8> {
+        let mut entry = entry(path, content);
+        entry[156] = type_flag;
+        entry[148..156].copy_from_slice(b"        ");
+        let checksum = entry[..BLOCK_SIZE as usize]
+            .iter()
+            .map(|&byte| byte as u32)
+            .sum::<u32>();
+        entry[148..156].copy_from_slice(format!("{:06o}\0 ", checksum).as_bytes());
+        entry
+    }
+
+    /// Reads back every regular file entry of an archive along with its content.
+    async fn read_all(archive: &[u8]) -> io::Result<Vec<(TarEntry, Vec<u8>)>> {
+        let mut reader = TarReader::new(archive);
+        let mut entries = vec![];
+
+        while let Some(entry) = reader.next_entry().await? {
+            let mut content = vec![];
+            reader.read_to_end(&mut content).await?;
+            entries.push((entry, content));
+        }
+
+        Ok(entries)
+    }
+
+    fn archive(entries: &[Vec<u8>]) -> Vec<u8> {
+        let mut archive = entries.concat();
+        archive.extend_from_slice(&archive_end());
+        archive
+    }
+
+    #[tokio::test]
+    async fn round_trips_entries() {
+        let exact = vec![7u8; BLOCK_SIZE as usize];
+        let archive = archive(&[
+            entry("empty.txt", b""),
+            entry("exact.bin", &exact),
+            entry("files/Foo.txt", b"foo"),
+        ]);
+
+        let entries = read_all(&archive).await.unwrap();
+
+        assert_eq!(
+            entries,
+            vec![
+                (
+                    TarEntry {
+                        path: "empty.txt".to_owned(),
+                        size: 0
+                    },
+                    vec![]
+                ),
+                (
+                    TarEntry {
+                        path: "exact.bin".to_owned(),
+                        size: BLOCK_SIZE
+                    },
+                    exact
+                ),
+                (
+                    TarEntry {
+                        path: "files/Foo.txt".to_owned(),
+                        size: 3
+                    },
+                    b"foo".to_vec()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn pads_entries_to_whole_blocks() {
+        assert_eq!(entry_padding(0).len(), 0);
+        assert_eq!(entry_padding(1).len(), BLOCK_SIZE as usize - 1);
+        assert_eq!(entry_padding(BLOCK_SIZE).len(), 0);
+    }
+
+    #[test]
+    fn round_trips_sizes_beyond_the_octal_field() {
+        for size in [MAX_OCTAL_SIZE, MAX_OCTAL_SIZE + 1, 1 << 40] {
+            let header = entry_header("large.bin", size, 0);
+            let header = <&[u8; BLOCK_SIZE as usize]>::try_from(&header[..]).unwrap();
+
+            assert_eq!(header[124] & 0x80 != 0, MAX_OCTAL_SIZE < size);
+            assert_eq!(parse_header(header).unwrap().size, size);
+        }
+    }
+
+    #[tokio::test]
+    async fn rejects_corrupted_checksum() {
+        let mut corrupted = entry("Foo.txt", b"foo");
+        corrupted[0] = b'G';
+
+        let err = read_all(&archive(&[corrupted])).await.unwrap_err();
+
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[tokio::test]
+    async fn skips_non_regular_entries() {
+        let archive = archive(&[
+            typed_entry("files", b'5', b""),
+            typed_entry(
+                "PaxHeaders/Foo.txt",
+                b'x',
+                b"30 mtime=1700000000.000000000\n",
+            ),
+            entry("files/Foo.txt", b"foo"),
+            typed_entry("files/Bar.txt", b'2', b""),
+        ]);
+
+        let entries = read_all(&archive).await.unwrap();
+
+        assert_eq!(
+            entries,
+            vec![(
+                TarEntry {
+                    path: "files/Foo.txt".to_owned(),
+                    size: 3
+                },
+                b"foo".to_vec()
+            )]
+        );
+    }
+}
@@ -0,0 +1,150 @@
+use crate::{
+    db::schema::audit_events,
+    schema::{
+        dto_in::{AuditActionDto, AuditResourceDto},
+        dto_out::AuditEventDto,
+    },
+    webhooks::enqueue_deliveries,
+};
+use axum::{async_trait, extract::FromRequestParts, http::request::Parts};
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use serde::{de::DeserializeOwned, Serialize};
+use uuid::Uuid;
+
+/// Maximum length of the request id and actor taken from request headers.
+const MAX_HEADER_LENGTH: usize = 128;
+
+/// Who caused a mutation, and in which request.
+///
+/// The service has no authentication yet, so the actor is whatever the client claims in the
+/// `X-Actor` header; it is recorded for traceability, not trusted.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AuditContext {
+    pub request_id: String,
+    pub actor: Option<String>,
+}
+
+impl AuditContext {
+    /// The context of mutations made by the server itself, such as purging the trash.
+    pub fn system() -> Self {
+        Self {
+            request_id: Uuid::new_v4().to_string(),
+            actor: Some("system".to_owned()),
+        }
+    }
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for AuditContext
+where
+    S: Send + Sync,
+{
+    type Rejection = std::convert::Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let header = |name: &str| {
+            parts
+                .headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(|value| value.trim())
+                .filter(|value| !value.is_empty() && value.len() <= MAX_HEADER_LENGTH)
+                .map(|value| value.to_owned())
+        };
+
+        Ok(Self {
+            request_id: header("x-request-id").unwrap_or_else(|| Uuid::new_v4().to_string()),
+            actor: header("x-actor"),
+        })
+    }
+}
+
+impl AuditActionDto {
+    pub fn resource_type(self) -> AuditResourceDto {
+        match self {
+            Self::CreateCollection
+            | Self::UpdateCollection
+            | Self::MoveCollection
+            | Self::RemoveCollection
+            | Self::RestoreCollection
+            | Self::PurgeCollection
+            | Self::AddCollectionFile
+            | Self::RemoveCollectionFile
+            | Self::UpdateCollectionQuota
+            | Self::UpdateCollectionUploadPolicy => AuditResourceDto::Collection,
+            Self::CreateFile
+            | Self::UploadFile
+            | Self::ProcessFile
+            | Self::UpdateFile
+            | Self::RemoveFile
+            | Self::RestoreFile
+            | Self::PurgeFile
+            | Self::ExpireFile
+            | Self::FailFile
+            | Self::CorruptFile
+            | Self::RestoreFileVersion
+            | Self::PruneFileVersions => AuditResourceDto::File,
+            Self::UpdateUserQuota => AuditResourceDto::UserQuota,
+        }
+    }
+}
+
+/// Serializes a resource into the snapshot stored in an audit event.
+pub fn snapshot(resource: &impl Serialize) -> serde_json::Value {
+    serde_json::to_value(resource).expect("failed to serialize audit snapshot")
+}
+
+/// Appends an event to the audit log, and queues its delivery to the subscribed webhooks.
+///
+/// Callers run it in the transaction of the mutation, so that the event is recorded if and only
+/// if the mutation is committed.
+pub async fn record_event(
+    db_conn: &mut AsyncPgConnection,
+    context: &AuditContext,
+    action: AuditActionDto,
+    resource_uuid: Uuid,
+    before: Option<serde_json::Value>,
+    after: Option<serde_json::Value>,
+) -> QueryResult<()> {
+    let (uuid, occurred_at) = diesel::insert_into(audit_events::table)
+        .values((
+            audit_events::request_id.eq(&context.request_id),
+            audit_events::actor.eq(&context.actor),
+            audit_events::action.eq(enum_name(&action)),
+            audit_events::resource_type.eq(enum_name(&action.resource_type())),
+            audit_events::resource_uuid.eq(resource_uuid),
+            audit_events::before.eq(&before),
+            audit_events::after.eq(&after),
+        ))
+        .returning((audit_events::uuid, audit_events::occurred_at))
+        .get_result::<(Uuid, NaiveDateTime)>(db_conn)
+        .await?;
+
+    let event = AuditEventDto {
+        uuid,
+        occurred_at: occurred_at.and_utc(),
+        request_id: context.request_id.clone(),
+        actor: context.actor.clone(),
+        action,
+        resource_type: action.resource_type(),
+        resource_uuid,
+        before,
+        after,
+    };
+    enqueue_deliveries(db_conn, &event).await
+}
+
+/// Returns the serialized name of a unit enum variant, which is how enums are stored.
+pub fn enum_name(value: &impl Serialize) -> String {
+    match snapshot(value) {
+        serde_json::Value::String(name) => name,
+        _ => unreachable!("unit enum variants serialize to strings"),
+    }
+}
+
+/// Parses a unit enum variant from its serialized name, as stored by [`enum_name`].
+pub fn parse_enum_name<T: DeserializeOwned>(name: String) -> T {
+    serde_json::from_value(serde_json::Value::String(name)).expect("failed to parse stored enum")
+}
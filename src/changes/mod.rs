@@ -0,0 +1,191 @@
+use crate::schema::{
+    dto_in::{ChangeResourceDto, SubscribeChangesQueryDto},
+    dto_out::ChangeEventDto,
+};
+use futures::StreamExt;
+use tokio::sync::{broadcast, mpsc};
+use tokio_postgres::{AsyncMessage, Client, NoTls, Statement};
+use tokio_util::sync::CancellationToken;
+use uuid::Uuid;
+
+/// Channel on which database triggers publish changes.
+const CHANGES_CHANNEL: &str = "poly_tag_changes";
+
+/// Number of changes buffered for each subscriber; slower subscribers are dropped.
+const SUBSCRIBER_CAPACITY: usize = 1024;
+
+/// Delay before listening again after the listening connection is lost.
+const RECONNECT_DELAY: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// Selects the live collections holding a file.
+const FILE_COLLECTIONS_QUERY: &str = "SELECT collections.uuid FROM collection_file_pairs \
+    JOIN files ON files.id = collection_file_pairs.file_id \
+    JOIN collections ON collections.id = collection_file_pairs.collection_id \
+    WHERE files.uuid = $1 AND collections.deleted_at IS NULL";
+
+/// A change published by the database, along with what subscriptions may filter it on.
+#[derive(Debug, Clone)]
+pub struct Change {
+    pub event: ChangeEventDto,
+    /// Live collections holding the changed file, or the file of the changed tag.
+    pub file_collections: Vec<Uuid>,
+}
+
+/// Fans out changes published by the database to subscribers of this server.
+///
+/// Changes are published with `NOTIFY` by triggers, so every server instance sees the changes
+/// made through any instance.
+#[derive(Clone)]
+pub struct ChangeBroker {
+    sender: broadcast::Sender<Change>,
+    shutdown: CancellationToken,
+}
+
+impl ChangeBroker {
+    pub fn subscribe(&self) -> broadcast::Receiver<Change> {
+        self.sender.subscribe()
+    }
+
+    /// Signals subscribers that the server is shutting down, so that their streams end and the
+    /// graceful shutdown does not wait for them forever.
+    pub fn shutdown(&self) {
+        self.shutdown.cancel();
+    }
+
+    /// Completes once the server is shutting down.
+    pub async fn shutting_down(&self) {
+        self.shutdown.cancelled().await
+    }
+}
+
+impl SubscribeChangesQueryDto {
+    /// Returns whether a change passes the filters of the subscription.
+    pub fn matches(&self, change: &Change) -> bool {
+        let event = &change.event;
+
+        if let Some(resource) = self.resource {
+            if event.resource != resource {
+                return false;
+            }
+        }
+
+        if let Some(collection_uuid) = self.collection_uuid {
+            if event.uuid != collection_uuid
+                && event.collection_uuid != Some(collection_uuid)
+                && !change.file_collections.contains(&collection_uuid)
+            {
+                return false;
+            }
+        }
+
+        if let Some(tag) = &self.tag {
+            if event.tag.as_ref() != Some(tag) {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+/// Initializes the change broker, and spawns a background task listening to the database.
+pub fn init_change_broker() -> ChangeBroker {
+    tracing::info!("listening to database changes");
+
+    let database_url = std::env::var("DATABASE_URL").expect("env var `DATABASE_URL` must be set");
+    let (sender, _) = broadcast::channel(SUBSCRIBER_CAPACITY);
+    let broker = ChangeBroker {
+        sender,
+        shutdown: CancellationToken::new(),
+    };
+
+    let listener_sender = broker.sender.clone();
+    tokio::spawn(async move {
+        loop {
+            if let Err(err) = listen_changes(&database_url, &listener_sender).await {
+                tracing::error!("failed to listen to database changes: {:#?}", err);
+            }
+
+            tokio::time::sleep(RECONNECT_DELAY).await;
+        }
+    });
+
+    broker
+}
+
+async fn listen_changes(
+    database_url: &str,
+    sender: &broadcast::Sender<Change>,
+) -> Result<(), tokio_postgres::Error> {
+    let (client, mut connection) = tokio_postgres::connect(database_url, NoTls).await?;
+
+    // The connection has to be polled for the client to make progress, so notifications are
+    // forwarded from a separate task.
+    let (notification_sender, mut notifications) = mpsc::unbounded_channel();
+    let connection = tokio::spawn(async move {
+        let mut messages = futures::stream::poll_fn(move |cx| connection.poll_message(cx));
+
+        while let Some(message) = messages.next().await {
+            if let AsyncMessage::Notification(notification) = message? {
+                if notification_sender.send(notification).is_err() {
+                    break;
+                }
+            }
+        }
+
+        Ok::<_, tokio_postgres::Error>(())
+    });
+
+    client
+        .batch_execute(&format!("LISTEN {}", CHANGES_CHANNEL))
+        .await?;
+    let file_collections_statement = client.prepare(FILE_COLLECTIONS_QUERY).await?;
+
+    while let Some(notification) = notifications.recv().await {
+        match serde_json::from_str::<ChangeEventDto>(notification.payload()) {
+            // Changes are dropped when nobody is subscribed.
+            Ok(_) if sender.receiver_count() == 0 => {}
+            Ok(event) => {
+                let file_collections =
+                    find_file_collections(&client, &file_collections_statement, &event).await?;
+                let _ = sender.send(Change {
+                    event,
+                    file_collections,
+                });
+            }
+            Err(err) => {
+                tracing::warn!("ignoring malformed change notification: {:#?}", err);
+            }
+        }
+    }
+
+    match connection.await {
+        Ok(result) => result,
+        Err(err) => {
+            tracing::error!("listening connection panicked: {:#?}", err);
+            Ok(())
+        }
+    }
+}
+
+/// Finds the live collections holding the file a change is about, as the triggers only publish
+/// the changed row.
+///
+/// A file removed for good has already left its collections, so its removal matches none.
+async fn find_file_collections(
+    client: &Client,
+    statement: &Statement,
+    event: &ChangeEventDto,
+) -> Result<Vec<Uuid>, tokio_postgres::Error> {
+    let file_uuid = match event.resource {
+        ChangeResourceDto::Collection => return Ok(vec![]),
+        ChangeResourceDto::File => event.uuid,
+        ChangeResourceDto::Tag => match event.file_uuid {
+            Some(file_uuid) => file_uuid,
+            None => return Ok(vec![]),
+        },
+    };
+
+    let rows = client.query(statement, &[&file_uuid]).await?;
+    Ok(rows.iter().map(|row| row.get(0)).collect())
+}
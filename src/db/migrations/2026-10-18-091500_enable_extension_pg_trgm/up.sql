@@ -0,0 +1,3 @@
+-- Your SQL goes here
+
+CREATE EXTENSION "pg_trgm";
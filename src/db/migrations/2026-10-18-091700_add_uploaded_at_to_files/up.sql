@@ -0,0 +1,3 @@
+-- Your SQL goes here
+
+ALTER TABLE files ADD COLUMN uploaded_at TIMESTAMP NULL;
@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+
+DROP INDEX files_name_trgm_idx;
+DROP INDEX files_name_tsvector_idx;
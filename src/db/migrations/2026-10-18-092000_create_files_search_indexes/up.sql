@@ -0,0 +1,4 @@
+-- Your SQL goes here
+
+CREATE INDEX files_name_tsvector_idx ON files USING GIN (to_tsvector('simple', name));
+CREATE INDEX files_name_trgm_idx ON files USING GIN (name gin_trgm_ops);
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+
+UPDATE files SET uploaded_at = NULL WHERE uploaded_at = created_at;
@@ -0,0 +1,3 @@
+-- Your SQL goes here
+
+UPDATE files SET uploaded_at = created_at WHERE uploaded_at IS NULL AND hash IS NOT NULL;
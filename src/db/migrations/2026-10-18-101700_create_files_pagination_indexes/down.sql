@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+
+DROP INDEX files_uploaded_at_uuid_idx;
+DROP INDEX files_size_uuid_idx;
+DROP INDEX files_name_uuid_idx;
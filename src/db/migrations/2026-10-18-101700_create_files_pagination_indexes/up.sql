@@ -0,0 +1,5 @@
+-- Your SQL goes here
+
+CREATE INDEX files_name_uuid_idx ON files(name, uuid);
+CREATE INDEX files_size_uuid_idx ON files(size, uuid);
+CREATE INDEX files_uploaded_at_uuid_idx ON files(uploaded_at, uuid);
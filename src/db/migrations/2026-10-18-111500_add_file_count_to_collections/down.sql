@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+
+DROP TRIGGER collection_file_pairs_update_file_count ON collection_file_pairs;
+DROP FUNCTION collections_update_file_count();
+ALTER TABLE collections DROP COLUMN file_count;
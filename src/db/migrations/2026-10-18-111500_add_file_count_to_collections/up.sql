@@ -0,0 +1,25 @@
+-- Your SQL goes here
+
+ALTER TABLE collections ADD COLUMN file_count BIGINT NOT NULL DEFAULT 0;
+
+UPDATE collections SET file_count = (
+  SELECT COUNT(*) FROM collection_file_pairs WHERE collection_file_pairs.collection_id = collections.id
+);
+
+CREATE FUNCTION collections_update_file_count() RETURNS TRIGGER AS $$
+BEGIN
+  IF TG_OP IN ('INSERT', 'UPDATE') THEN
+    UPDATE collections SET file_count = file_count + 1 WHERE id = NEW.collection_id;
+  END IF;
+
+  IF TG_OP IN ('DELETE', 'UPDATE') THEN
+    UPDATE collections SET file_count = file_count - 1 WHERE id = OLD.collection_id;
+  END IF;
+
+  RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER collection_file_pairs_update_file_count
+AFTER INSERT OR UPDATE OF collection_id OR DELETE ON collection_file_pairs
+FOR EACH ROW EXECUTE FUNCTION collections_update_file_count();
@@ -0,0 +1,7 @@
+-- This file should undo anything in `up.sql`
+
+DROP INDEX collections_file_count_id_idx;
+DROP INDEX collections_created_at_id_idx;
+DROP INDEX collections_name_id_idx;
+DROP INDEX collections_description_trgm_idx;
+DROP INDEX collections_name_trgm_idx;
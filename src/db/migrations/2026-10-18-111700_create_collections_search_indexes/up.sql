@@ -0,0 +1,7 @@
+-- Your SQL goes here
+
+CREATE INDEX collections_name_trgm_idx ON collections USING GIN (name gin_trgm_ops);
+CREATE INDEX collections_description_trgm_idx ON collections USING GIN (description gin_trgm_ops);
+CREATE INDEX collections_name_id_idx ON collections (name, id);
+CREATE INDEX collections_created_at_id_idx ON collections (created_at, id);
+CREATE INDEX collections_file_count_id_idx ON collections (file_count, id);
@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+
+DROP INDEX collections_parent_id_idx;
+ALTER TABLE collections DROP COLUMN parent_id;
@@ -0,0 +1,6 @@
+-- Your SQL goes here
+
+ALTER TABLE collections
+  ADD COLUMN parent_id INT NULL REFERENCES collections(id) ON UPDATE CASCADE ON DELETE RESTRICT,
+  ADD CONSTRAINT collections_parent_id_check CHECK (parent_id <> id);
+CREATE INDEX collections_parent_id_idx ON collections (parent_id);
@@ -0,0 +1,27 @@
+-- This file should undo anything in `up.sql`
+
+DROP TRIGGER files_update_collection_file_count ON files;
+DROP FUNCTION files_update_collection_file_count();
+
+CREATE OR REPLACE FUNCTION collections_update_file_count() RETURNS TRIGGER AS $$
+BEGIN
+  IF TG_OP IN ('INSERT', 'UPDATE') THEN
+    UPDATE collections SET file_count = file_count + 1 WHERE id = NEW.collection_id;
+  END IF;
+
+  IF TG_OP IN ('DELETE', 'UPDATE') THEN
+    UPDATE collections SET file_count = file_count - 1 WHERE id = OLD.collection_id;
+  END IF;
+
+  RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+UPDATE collections SET file_count = (
+  SELECT COUNT(*) FROM collection_file_pairs WHERE collection_file_pairs.collection_id = collections.id
+);
+
+DROP INDEX files_deleted_at_uuid_idx;
+DROP INDEX collections_deleted_at_id_idx;
+ALTER TABLE files DROP COLUMN deleted_at;
+ALTER TABLE collections DROP COLUMN deleted_at;
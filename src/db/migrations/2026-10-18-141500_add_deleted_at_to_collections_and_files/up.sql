@@ -0,0 +1,43 @@
+-- Your SQL goes here
+
+ALTER TABLE collections ADD COLUMN deleted_at TIMESTAMP NULL;
+ALTER TABLE files ADD COLUMN deleted_at TIMESTAMP NULL;
+CREATE INDEX collections_deleted_at_id_idx ON collections (deleted_at, id) WHERE deleted_at IS NOT NULL;
+CREATE INDEX files_deleted_at_uuid_idx ON files (deleted_at, uuid) WHERE deleted_at IS NOT NULL;
+
+-- Trashed files are not counted.
+CREATE OR REPLACE FUNCTION collections_update_file_count() RETURNS TRIGGER AS $$
+BEGIN
+  IF TG_OP IN ('INSERT', 'UPDATE') THEN
+    UPDATE collections SET file_count = file_count + 1
+    WHERE id = NEW.collection_id
+      AND EXISTS (SELECT 1 FROM files WHERE files.id = NEW.file_id AND files.deleted_at IS NULL);
+  END IF;
+
+  IF TG_OP IN ('DELETE', 'UPDATE') THEN
+    UPDATE collections SET file_count = file_count - 1
+    WHERE id = OLD.collection_id
+      AND EXISTS (SELECT 1 FROM files WHERE files.id = OLD.file_id AND files.deleted_at IS NULL);
+  END IF;
+
+  RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE FUNCTION files_update_collection_file_count() RETURNS TRIGGER AS $$
+BEGIN
+  IF OLD.deleted_at IS NULL AND NEW.deleted_at IS NOT NULL THEN
+    UPDATE collections SET file_count = file_count - 1
+    WHERE id IN (SELECT collection_id FROM collection_file_pairs WHERE file_id = NEW.id);
+  ELSIF OLD.deleted_at IS NOT NULL AND NEW.deleted_at IS NULL THEN
+    UPDATE collections SET file_count = file_count + 1
+    WHERE id IN (SELECT collection_id FROM collection_file_pairs WHERE file_id = NEW.id);
+  END IF;
+
+  RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER files_update_collection_file_count
+AFTER UPDATE OF deleted_at ON files
+FOR EACH ROW EXECUTE FUNCTION files_update_collection_file_count();
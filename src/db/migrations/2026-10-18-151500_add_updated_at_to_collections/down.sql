@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+
+DROP TRIGGER set_updated_at ON collections;
+ALTER TABLE collections DROP COLUMN updated_at;
@@ -0,0 +1,5 @@
+-- Your SQL goes here
+
+ALTER TABLE collections ADD COLUMN updated_at TIMESTAMP NOT NULL DEFAULT NOW();
+UPDATE collections SET updated_at = created_at;
+SELECT diesel_manage_updated_at('collections');
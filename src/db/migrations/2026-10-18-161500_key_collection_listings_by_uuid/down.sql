@@ -0,0 +1,10 @@
+-- This file should undo anything in `up.sql`
+
+DROP INDEX collections_name_uuid_idx;
+DROP INDEX collections_created_at_uuid_idx;
+DROP INDEX collections_file_count_uuid_idx;
+DROP INDEX collections_deleted_at_uuid_idx;
+CREATE INDEX collections_name_id_idx ON collections (name, id);
+CREATE INDEX collections_created_at_id_idx ON collections (created_at, id);
+CREATE INDEX collections_file_count_id_idx ON collections (file_count, id);
+CREATE INDEX collections_deleted_at_id_idx ON collections (deleted_at, id) WHERE deleted_at IS NOT NULL;
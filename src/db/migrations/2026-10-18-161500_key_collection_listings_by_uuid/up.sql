@@ -0,0 +1,10 @@
+-- Your SQL goes here
+
+DROP INDEX collections_name_id_idx;
+DROP INDEX collections_created_at_id_idx;
+DROP INDEX collections_file_count_id_idx;
+DROP INDEX collections_deleted_at_id_idx;
+CREATE INDEX collections_name_uuid_idx ON collections (name, uuid);
+CREATE INDEX collections_created_at_uuid_idx ON collections (created_at, uuid);
+CREATE INDEX collections_file_count_uuid_idx ON collections (file_count, uuid);
+CREATE INDEX collections_deleted_at_uuid_idx ON collections (deleted_at, uuid) WHERE deleted_at IS NOT NULL;
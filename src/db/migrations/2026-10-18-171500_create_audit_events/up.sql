@@ -0,0 +1,34 @@
+-- Your SQL goes here
+
+CREATE TABLE audit_events (
+  id BIGSERIAL PRIMARY KEY,
+  uuid UUID NOT NULL DEFAULT uuid_generate_v4(),
+  occurred_at TIMESTAMP NOT NULL DEFAULT NOW(),
+  request_id TEXT NOT NULL,
+  actor TEXT NULL,
+  action TEXT NOT NULL,
+  resource_type TEXT NOT NULL,
+  resource_uuid UUID NOT NULL,
+  before JSONB NULL,
+  after JSONB NULL
+);
+
+CREATE UNIQUE INDEX ON audit_events(uuid);
+CREATE INDEX audit_events_occurred_at_uuid_idx ON audit_events (occurred_at, uuid);
+CREATE INDEX audit_events_resource_uuid_idx ON audit_events (resource_uuid);
+CREATE INDEX audit_events_request_id_idx ON audit_events (request_id);
+
+-- The audit log is append-only.
+CREATE FUNCTION audit_events_reject_change() RETURNS TRIGGER AS $$
+BEGIN
+  RAISE EXCEPTION 'audit events cannot be changed nor removed';
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER audit_events_reject_change
+BEFORE UPDATE OR DELETE ON audit_events
+FOR EACH ROW EXECUTE FUNCTION audit_events_reject_change();
+
+CREATE TRIGGER audit_events_reject_truncate
+BEFORE TRUNCATE ON audit_events
+FOR EACH STATEMENT EXECUTE FUNCTION audit_events_reject_change();
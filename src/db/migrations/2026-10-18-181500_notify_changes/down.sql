@@ -0,0 +1,12 @@
+-- This file should undo anything in `up.sql`
+
+DROP TRIGGER collection_file_pairs_notify_change ON collection_file_pairs;
+DROP FUNCTION collection_file_pairs_notify_change();
+DROP TRIGGER tags_notify_change ON tags;
+DROP FUNCTION tags_notify_change();
+DROP TRIGGER files_notify_change ON files;
+DROP FUNCTION files_notify_change();
+DROP TRIGGER collections_notify_change ON collections;
+DROP FUNCTION collections_notify_change();
+DROP FUNCTION change_action(TEXT, TIMESTAMP, TIMESTAMP);
+DROP FUNCTION notify_change(TEXT, TEXT, UUID, UUID, UUID, TEXT);
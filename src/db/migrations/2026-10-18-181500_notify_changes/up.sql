@@ -0,0 +1,162 @@
+-- Your SQL goes here
+
+-- Changes are published on the `poly_tag_changes` channel, one JSON notification per changed row.
+-- Notifications are only delivered when the transaction commits.
+CREATE FUNCTION notify_change(
+  resource TEXT,
+  action TEXT,
+  resource_uuid UUID,
+  collection_uuid UUID,
+  file_uuid UUID,
+  tag TEXT
+) RETURNS VOID AS $$
+BEGIN
+  PERFORM pg_notify('poly_tag_changes', json_build_object(
+    'resource', resource,
+    'action', action,
+    'uuid', resource_uuid,
+    'collectionUuid', collection_uuid,
+    'fileUuid', file_uuid,
+    'tag', tag
+  )::text);
+END;
+$$ LANGUAGE plpgsql;
+
+-- Returns the action of a row change, telling moves to and from the trash apart from updates.
+CREATE FUNCTION change_action(
+  operation TEXT,
+  old_deleted_at TIMESTAMP,
+  new_deleted_at TIMESTAMP
+) RETURNS TEXT AS $$
+BEGIN
+  IF operation = 'INSERT' THEN
+    RETURN 'created';
+  ELSIF operation = 'DELETE' THEN
+    RETURN 'deleted';
+  ELSIF old_deleted_at IS NULL AND new_deleted_at IS NOT NULL THEN
+    RETURN 'trashed';
+  ELSIF old_deleted_at IS NOT NULL AND new_deleted_at IS NULL THEN
+    RETURN 'restored';
+  ELSE
+    RETURN 'updated';
+  END IF;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE FUNCTION collections_notify_change() RETURNS TRIGGER AS $$
+DECLARE
+  changed collections;
+  parent_uuid UUID;
+BEGIN
+  IF TG_OP = 'DELETE' THEN
+    changed := OLD;
+  ELSE
+    changed := NEW;
+  END IF;
+
+  SELECT uuid INTO parent_uuid FROM collections WHERE id = changed.parent_id;
+  PERFORM notify_change(
+    'collection',
+    change_action(TG_OP, OLD.deleted_at, NEW.deleted_at),
+    changed.uuid,
+    parent_uuid,
+    NULL,
+    NULL
+  );
+  RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER collections_notify_change
+AFTER INSERT OR UPDATE OR DELETE ON collections
+FOR EACH ROW EXECUTE FUNCTION collections_notify_change();
+
+CREATE FUNCTION files_notify_change() RETURNS TRIGGER AS $$
+DECLARE
+  changed files;
+BEGIN
+  IF TG_OP = 'DELETE' THEN
+    changed := OLD;
+  ELSE
+    changed := NEW;
+  END IF;
+
+  PERFORM notify_change(
+    'file',
+    change_action(TG_OP, OLD.deleted_at, NEW.deleted_at),
+    changed.uuid,
+    NULL,
+    NULL,
+    NULL
+  );
+  RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER files_notify_change
+AFTER INSERT OR UPDATE OR DELETE ON files
+FOR EACH ROW EXECUTE FUNCTION files_notify_change();
+
+CREATE FUNCTION tags_notify_change() RETURNS TRIGGER AS $$
+DECLARE
+  changed tags;
+  file_uuid UUID;
+BEGIN
+  IF TG_OP = 'DELETE' THEN
+    changed := OLD;
+  ELSE
+    changed := NEW;
+  END IF;
+
+  -- Tags removed along with their file are covered by the event of the file.
+  SELECT uuid INTO file_uuid FROM files WHERE id = changed.file_id;
+  IF file_uuid IS NOT NULL THEN
+    PERFORM notify_change(
+      'tag',
+      change_action(TG_OP, NULL, NULL),
+      file_uuid,
+      NULL,
+      file_uuid,
+      changed.title
+    );
+  END IF;
+  RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER tags_notify_change
+AFTER INSERT OR UPDATE OR DELETE ON tags
+FOR EACH ROW EXECUTE FUNCTION tags_notify_change();
+
+-- Adding a file to a collection or removing it from one is an update of the collection.
+CREATE FUNCTION collection_file_pairs_notify_change() RETURNS TRIGGER AS $$
+DECLARE
+  changed collection_file_pairs;
+  collection_uuid UUID;
+  file_uuid UUID;
+BEGIN
+  IF TG_OP = 'DELETE' THEN
+    changed := OLD;
+  ELSE
+    changed := NEW;
+  END IF;
+
+  SELECT uuid INTO collection_uuid FROM collections WHERE id = changed.collection_id;
+  SELECT uuid INTO file_uuid FROM files WHERE id = changed.file_id;
+  IF collection_uuid IS NOT NULL THEN
+    PERFORM notify_change(
+      'collection',
+      'updated',
+      collection_uuid,
+      collection_uuid,
+      file_uuid,
+      NULL
+    );
+  END IF;
+  RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER collection_file_pairs_notify_change
+AFTER INSERT OR DELETE ON collection_file_pairs
+FOR EACH ROW EXECUTE FUNCTION collection_file_pairs_notify_change();
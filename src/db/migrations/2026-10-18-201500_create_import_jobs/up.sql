@@ -0,0 +1,26 @@
+-- Your SQL goes here
+
+CREATE TABLE import_jobs (
+  id SERIAL PRIMARY KEY,
+  uuid UUID NOT NULL DEFAULT uuid_generate_v4(),
+  root TEXT NOT NULL,
+  rules TEXT[] NOT NULL,
+  mode TEXT NOT NULL CHECK (mode IN ('copy', 'hardLink')),
+  status TEXT NOT NULL DEFAULT 'pending' CHECK (status IN ('pending', 'running', 'completed', 'failed', 'cancelled')),
+  total_count BIGINT NULL,
+  processed_count BIGINT NOT NULL DEFAULT 0,
+  imported_count BIGINT NOT NULL DEFAULT 0,
+  failed_count BIGINT NOT NULL DEFAULT 0,
+  last_path TEXT NULL,
+  last_error TEXT NULL,
+  request_id TEXT NOT NULL,
+  actor TEXT NULL,
+  heartbeat_at TIMESTAMP NULL,
+  created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+  updated_at TIMESTAMP NOT NULL DEFAULT NOW(),
+  finished_at TIMESTAMP NULL
+);
+
+CREATE UNIQUE INDEX ON import_jobs(uuid);
+CREATE INDEX import_jobs_status_idx ON import_jobs (status) WHERE status IN ('pending', 'running');
+SELECT diesel_manage_updated_at('import_jobs');
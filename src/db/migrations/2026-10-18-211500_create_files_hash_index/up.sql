@@ -0,0 +1,3 @@
+-- Your SQL goes here
+
+CREATE INDEX files_hash_size_idx ON files(hash, size) WHERE deleted_at IS NULL;
@@ -0,0 +1,18 @@
+-- Your SQL goes here
+
+CREATE TABLE thumbnail_jobs (
+  file_id INTEGER PRIMARY KEY REFERENCES files(id) ON UPDATE CASCADE ON DELETE CASCADE,
+  status TEXT NOT NULL DEFAULT 'pending' CHECK (status IN ('pending', 'running', 'completed', 'failed', 'unsupported')),
+  -- Sizes rendered by the last completed run.
+  sizes TEXT[] NOT NULL DEFAULT '{}',
+  last_error TEXT NULL,
+  -- Bumped on every request; a run only completes the request it was claimed for.
+  requested_at TIMESTAMP NOT NULL DEFAULT NOW(),
+  claimed_at TIMESTAMP NULL,
+  generated_at TIMESTAMP NULL
+);
+
+CREATE INDEX thumbnail_jobs_status_idx ON thumbnail_jobs (status) WHERE status IN ('pending', 'running');
+
+INSERT INTO thumbnail_jobs (file_id)
+SELECT id FROM files WHERE uploaded_at IS NOT NULL AND deleted_at IS NULL;
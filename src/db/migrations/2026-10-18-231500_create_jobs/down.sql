@@ -0,0 +1,9 @@
+-- This file should undo anything in `up.sql`
+
+ALTER TABLE thumbnail_jobs ADD COLUMN claimed_at TIMESTAMP NULL;
+
+ALTER TABLE files
+  DROP COLUMN processing_status,
+  DROP COLUMN processing_error;
+
+DROP TABLE jobs;
@@ -0,0 +1,44 @@
+-- Your SQL goes here
+
+CREATE TABLE jobs (
+  id BIGSERIAL PRIMARY KEY,
+  uuid UUID NOT NULL DEFAULT uuid_generate_v4(),
+  job_type TEXT NOT NULL CHECK (job_type IN ('processFile', 'indexFile', 'renderThumbnails')),
+  payload JSONB NOT NULL,
+  -- Jobs of the same type and key are the same work; only one of them is pending at a time.
+  dedup_key TEXT NULL,
+  status TEXT NOT NULL DEFAULT 'pending' CHECK (status IN ('pending', 'running', 'succeeded', 'dead')),
+  attempt_count INTEGER NOT NULL DEFAULT 0,
+  max_attempts INTEGER NOT NULL,
+  -- When a pending job is run next, or when the lease of a running job expires.
+  run_at TIMESTAMP NOT NULL DEFAULT NOW(),
+  last_error TEXT NULL,
+  created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+  updated_at TIMESTAMP NOT NULL DEFAULT NOW(),
+  finished_at TIMESTAMP NULL
+);
+
+CREATE UNIQUE INDEX ON jobs(uuid);
+CREATE UNIQUE INDEX jobs_job_type_dedup_key_idx ON jobs (job_type, dedup_key) WHERE status = 'pending';
+CREATE INDEX jobs_created_at_uuid_idx ON jobs (created_at, uuid);
+-- Only pending and running jobs are scheduled.
+CREATE INDEX jobs_job_type_run_at_idx ON jobs (job_type, run_at) WHERE status IN ('pending', 'running');
+SELECT diesel_manage_updated_at('jobs');
+
+-- Uploaded files are processed in the background; the status is absent until the first upload.
+ALTER TABLE files
+  ADD COLUMN processing_status TEXT NULL CHECK (processing_status IN ('pending', 'completed', 'failed')),
+  ADD COLUMN processing_error TEXT NULL;
+
+UPDATE files SET processing_status = 'completed' WHERE uploaded_at IS NOT NULL;
+
+-- Thumbnails are now rendered by jobs, which hold their own leases.
+ALTER TABLE thumbnail_jobs DROP COLUMN claimed_at;
+
+UPDATE thumbnail_jobs SET status = 'pending' WHERE status = 'running';
+
+INSERT INTO jobs (job_type, payload, dedup_key, max_attempts)
+SELECT 'renderThumbnails', json_build_object('fileUuid', files.uuid), files.uuid::text, 3
+FROM thumbnail_jobs
+INNER JOIN files ON files.id = thumbnail_jobs.file_id
+WHERE thumbnail_jobs.status = 'pending';
@@ -0,0 +1,17 @@
+-- This file should undo anything in `up.sql`
+
+DROP INDEX files_status_idx;
+
+ALTER TABLE files RENAME COLUMN failure_reason TO processing_error;
+ALTER TABLE files ADD COLUMN processing_status TEXT NULL CHECK (processing_status IN ('pending', 'completed', 'failed'));
+
+UPDATE files SET processing_status = CASE status
+  WHEN 'processing' THEN 'pending'
+  WHEN 'ready' THEN 'completed'
+  WHEN 'failed' THEN 'failed'
+  ELSE NULL
+END;
+
+ALTER TABLE files DROP COLUMN status;
+
+DROP TYPE file_status;
@@ -0,0 +1,18 @@
+-- Your SQL goes here
+
+CREATE TYPE file_status AS ENUM ('prepared', 'uploading', 'processing', 'ready', 'failed');
+
+ALTER TABLE files ADD COLUMN status file_status NOT NULL DEFAULT 'prepared';
+
+UPDATE files SET status = CASE processing_status
+  WHEN 'pending' THEN 'processing'
+  WHEN 'completed' THEN 'ready'
+  WHEN 'failed' THEN 'failed'
+  ELSE 'prepared'
+END::file_status;
+
+ALTER TABLE files DROP COLUMN processing_status;
+ALTER TABLE files RENAME COLUMN processing_error TO failure_reason;
+
+-- Listings show ready files by default; the others are only looked up by status.
+CREATE INDEX files_status_idx ON files (status) WHERE status <> 'ready';
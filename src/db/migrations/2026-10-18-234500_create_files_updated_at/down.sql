@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+
+DROP TRIGGER set_updated_at ON files;
+
+ALTER TABLE files DROP COLUMN updated_at;
@@ -0,0 +1,7 @@
+-- Your SQL goes here
+
+ALTER TABLE files ADD COLUMN updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP;
+
+UPDATE files SET updated_at = GREATEST(created_at, uploaded_at, deleted_at);
+
+SELECT diesel_manage_updated_at('files');
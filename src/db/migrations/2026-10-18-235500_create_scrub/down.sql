@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+
+DROP TABLE scrub_mismatches;
+DROP TABLE scrub_state;
@@ -0,0 +1,33 @@
+-- Your SQL goes here
+
+-- Progress of the integrity scrub, which checks the stored contents pass after pass; a single row.
+CREATE TABLE scrub_state (
+  id BOOLEAN PRIMARY KEY DEFAULT TRUE CHECK (id),
+  -- Files are checked in order of id; the last one checked by the current pass.
+  last_file_id INTEGER NULL,
+  pass_started_at TIMESTAMP NULL,
+  checked_count BIGINT NOT NULL DEFAULT 0,
+  checked_size BIGINT NOT NULL DEFAULT 0,
+  mismatch_count BIGINT NOT NULL DEFAULT 0,
+  last_pass_completed_at TIMESTAMP NULL
+);
+
+INSERT INTO scrub_state DEFAULT VALUES;
+
+CREATE TABLE scrub_mismatches (
+  id SERIAL PRIMARY KEY,
+  uuid UUID NOT NULL DEFAULT uuid_generate_v4(),
+  file_id INTEGER NOT NULL REFERENCES files(id) ON UPDATE CASCADE ON DELETE CASCADE,
+  file_uuid UUID NOT NULL,
+  kind TEXT NOT NULL CHECK (kind IN ('missing', 'unreadable', 'size', 'hash')),
+  expected_size BIGINT NOT NULL,
+  actual_size BIGINT NULL,
+  expected_hash BIGINT NULL,
+  actual_hash BIGINT NULL,
+  error TEXT NULL,
+  detected_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE UNIQUE INDEX ON scrub_mismatches(uuid);
+CREATE INDEX scrub_mismatches_detected_at_uuid_idx ON scrub_mismatches (detected_at, uuid);
+CREATE INDEX ON scrub_mismatches(file_id);
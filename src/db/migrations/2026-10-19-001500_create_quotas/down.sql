@@ -0,0 +1,11 @@
+-- This file should undo anything in `up.sql`
+
+DROP TRIGGER files_remove_collection_file_pairs ON files;
+DROP FUNCTION files_remove_collection_file_pairs();
+DROP TRIGGER collection_file_pairs_update_quota_usage ON collection_file_pairs;
+DROP FUNCTION quotas_update_collection_usage();
+DROP TRIGGER files_update_quota_usage ON files;
+DROP FUNCTION quotas_update_file_usage();
+DROP TABLE collection_quotas;
+DROP TABLE user_quotas;
+ALTER TABLE files DROP COLUMN owner;
@@ -0,0 +1,101 @@
+-- Your SQL goes here
+
+-- The actor that created a file; the service has no users yet, so quotas are per actor.
+ALTER TABLE files ADD COLUMN owner TEXT NULL;
+
+UPDATE files SET owner = audit_events.actor
+FROM audit_events
+WHERE audit_events.action = 'createFile' AND audit_events.resource_uuid = files.uuid;
+
+-- Limits and usage of the storage; a limit of NULL is unlimited. Every stored file counts,
+-- including the trashed ones and the partial content of an interrupted upload.
+CREATE TABLE user_quotas (
+  owner TEXT PRIMARY KEY,
+  max_bytes BIGINT NULL CHECK (max_bytes >= 0),
+  max_files BIGINT NULL CHECK (max_files >= 0),
+  used_bytes BIGINT NOT NULL DEFAULT 0,
+  used_files BIGINT NOT NULL DEFAULT 0
+);
+
+CREATE TABLE collection_quotas (
+  collection_id INTEGER PRIMARY KEY REFERENCES collections(id) ON UPDATE CASCADE ON DELETE CASCADE,
+  max_bytes BIGINT NULL CHECK (max_bytes >= 0),
+  max_files BIGINT NULL CHECK (max_files >= 0),
+  used_bytes BIGINT NOT NULL DEFAULT 0,
+  used_files BIGINT NOT NULL DEFAULT 0
+);
+
+INSERT INTO user_quotas (owner, used_bytes, used_files)
+SELECT owner, COALESCE(SUM(size), 0), COUNT(*) FROM files WHERE owner IS NOT NULL GROUP BY owner;
+
+INSERT INTO collection_quotas (collection_id, used_bytes, used_files)
+SELECT collection_file_pairs.collection_id, COALESCE(SUM(files.size), 0), COUNT(*)
+FROM collection_file_pairs JOIN files ON files.id = collection_file_pairs.file_id
+GROUP BY collection_file_pairs.collection_id;
+
+CREATE FUNCTION quotas_update_file_usage() RETURNS TRIGGER AS $$
+BEGIN
+  IF TG_OP = 'INSERT' AND NEW.owner IS NOT NULL THEN
+    INSERT INTO user_quotas (owner, used_bytes, used_files)
+    VALUES (NEW.owner, COALESCE(NEW.size, 0), 1)
+    ON CONFLICT (owner) DO UPDATE SET
+      used_bytes = user_quotas.used_bytes + EXCLUDED.used_bytes,
+      used_files = user_quotas.used_files + 1;
+  ELSIF TG_OP = 'UPDATE' AND COALESCE(NEW.size, 0) <> COALESCE(OLD.size, 0) THEN
+    UPDATE user_quotas SET used_bytes = used_bytes + COALESCE(NEW.size, 0) - COALESCE(OLD.size, 0)
+    WHERE owner = NEW.owner;
+    UPDATE collection_quotas SET used_bytes = used_bytes + COALESCE(NEW.size, 0) - COALESCE(OLD.size, 0)
+    WHERE collection_id IN (SELECT collection_id FROM collection_file_pairs WHERE file_id = NEW.id);
+  ELSIF TG_OP = 'DELETE' THEN
+    UPDATE user_quotas SET used_bytes = used_bytes - COALESCE(OLD.size, 0), used_files = used_files - 1
+    WHERE owner = OLD.owner;
+  END IF;
+
+  RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER files_update_quota_usage
+AFTER INSERT OR UPDATE OF size OR DELETE ON files
+FOR EACH ROW EXECUTE FUNCTION quotas_update_file_usage();
+
+CREATE FUNCTION quotas_update_collection_usage() RETURNS TRIGGER AS $$
+DECLARE
+  file_size BIGINT;
+BEGIN
+  IF TG_OP = 'INSERT' THEN
+    SELECT COALESCE(size, 0) INTO file_size FROM files WHERE id = NEW.file_id;
+
+    INSERT INTO collection_quotas (collection_id, used_bytes, used_files)
+    VALUES (NEW.collection_id, COALESCE(file_size, 0), 1)
+    ON CONFLICT (collection_id) DO UPDATE SET
+      used_bytes = collection_quotas.used_bytes + EXCLUDED.used_bytes,
+      used_files = collection_quotas.used_files + 1;
+  ELSE
+    SELECT COALESCE(size, 0) INTO file_size FROM files WHERE id = OLD.file_id;
+
+    UPDATE collection_quotas SET used_bytes = used_bytes - COALESCE(file_size, 0), used_files = used_files - 1
+    WHERE collection_id = OLD.collection_id;
+  END IF;
+
+  RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER collection_file_pairs_update_quota_usage
+AFTER INSERT OR DELETE ON collection_file_pairs
+FOR EACH ROW EXECUTE FUNCTION quotas_update_collection_usage();
+
+-- The pairs of a removed file are removed before it, so that its size is still known when they
+-- are taken from the usage of their collections; a cascade would only run after the removal.
+CREATE FUNCTION files_remove_collection_file_pairs() RETURNS TRIGGER AS $$
+BEGIN
+  DELETE FROM collection_file_pairs WHERE file_id = OLD.id;
+
+  RETURN OLD;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER files_remove_collection_file_pairs
+BEFORE DELETE ON files
+FOR EACH ROW EXECUTE FUNCTION files_remove_collection_file_pairs();
@@ -0,0 +1,10 @@
+-- Your SQL goes here
+
+-- Settings of the server-wide upload policy a collection overrides for the uploads of its files;
+-- NULL keeps the server-wide setting.
+CREATE TABLE collection_upload_policies (
+  collection_id INTEGER PRIMARY KEY REFERENCES collections(id) ON UPDATE CASCADE ON DELETE CASCADE,
+  max_file_size BIGINT NULL CHECK (max_file_size >= 0),
+  allowed_mime_types TEXT[] NULL,
+  denied_mime_types TEXT[] NULL
+);
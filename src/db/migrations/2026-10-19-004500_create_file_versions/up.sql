@@ -0,0 +1,28 @@
+-- Your SQL goes here
+
+-- Contents a file held, one per completed upload or restore. The content of a version is kept
+-- apart from the one of its file once it is replaced.
+CREATE TABLE file_versions (
+  id SERIAL PRIMARY KEY,
+  uuid UUID NOT NULL DEFAULT uuid_generate_v4(),
+  file_id INTEGER NOT NULL REFERENCES files(id) ON UPDATE CASCADE ON DELETE CASCADE,
+  number INTEGER NOT NULL,
+  size BIGINT NOT NULL,
+  -- Set once the content is processed.
+  hash BIGINT NULL,
+  mime TEXT NULL,
+  -- Equal to `files.uploaded_at` while the version is the current content of its file.
+  uploaded_at TIMESTAMP NOT NULL,
+  UNIQUE (file_id, number),
+  UNIQUE (file_id, uploaded_at)
+);
+
+CREATE UNIQUE INDEX ON file_versions(uuid);
+
+-- The current content of the files uploaded so far is their first version.
+INSERT INTO file_versions (file_id, number, size, hash, mime, uploaded_at)
+SELECT id, 1, size, hash, mime, uploaded_at
+FROM files
+WHERE uploaded_at IS NOT NULL
+  AND size IS NOT NULL
+  AND status IN ('processing', 'ready');
@@ -0,0 +1,72 @@
+-- This file should undo anything in `up.sql`
+
+CREATE OR REPLACE FUNCTION quotas_update_collection_usage() RETURNS TRIGGER AS $$
+DECLARE
+  file_size BIGINT;
+BEGIN
+  IF TG_OP = 'INSERT' THEN
+    SELECT COALESCE(size, 0) INTO file_size FROM files WHERE id = NEW.file_id;
+
+    INSERT INTO collection_quotas (collection_id, used_bytes, used_files)
+    VALUES (NEW.collection_id, COALESCE(file_size, 0), 1)
+    ON CONFLICT (collection_id) DO UPDATE SET
+      used_bytes = collection_quotas.used_bytes + EXCLUDED.used_bytes,
+      used_files = collection_quotas.used_files + 1;
+  ELSE
+    SELECT COALESCE(size, 0) INTO file_size FROM files WHERE id = OLD.file_id;
+
+    UPDATE collection_quotas SET used_bytes = used_bytes - COALESCE(file_size, 0), used_files = used_files - 1
+    WHERE collection_id = OLD.collection_id;
+  END IF;
+
+  RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER files_update_quota_usage ON files;
+CREATE OR REPLACE FUNCTION quotas_update_file_usage() RETURNS TRIGGER AS $$
+BEGIN
+  IF TG_OP = 'INSERT' AND NEW.owner IS NOT NULL THEN
+    INSERT INTO user_quotas (owner, used_bytes, used_files)
+    VALUES (NEW.owner, COALESCE(NEW.size, 0), 1)
+    ON CONFLICT (owner) DO UPDATE SET
+      used_bytes = user_quotas.used_bytes + EXCLUDED.used_bytes,
+      used_files = user_quotas.used_files + 1;
+  ELSIF TG_OP = 'UPDATE' AND COALESCE(NEW.size, 0) <> COALESCE(OLD.size, 0) THEN
+    UPDATE user_quotas SET used_bytes = used_bytes + COALESCE(NEW.size, 0) - COALESCE(OLD.size, 0)
+    WHERE owner = NEW.owner;
+    UPDATE collection_quotas SET used_bytes = used_bytes + COALESCE(NEW.size, 0) - COALESCE(OLD.size, 0)
+    WHERE collection_id IN (SELECT collection_id FROM collection_file_pairs WHERE file_id = NEW.id);
+  ELSIF TG_OP = 'DELETE' THEN
+    UPDATE user_quotas SET used_bytes = used_bytes - COALESCE(OLD.size, 0), used_files = used_files - 1
+    WHERE owner = OLD.owner;
+  END IF;
+
+  RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+CREATE TRIGGER files_update_quota_usage
+AFTER INSERT OR UPDATE OF size OR DELETE ON files
+FOR EACH ROW EXECUTE FUNCTION quotas_update_file_usage();
+
+DROP TRIGGER file_versions_update_kept_bytes ON file_versions;
+DROP FUNCTION file_versions_update_kept_bytes();
+DROP TRIGGER files_update_kept_bytes ON files;
+DROP FUNCTION files_update_kept_bytes();
+DROP FUNCTION files_find_kept_bytes(INTEGER, TIMESTAMP);
+
+UPDATE user_quotas SET used_bytes = used_bytes - usage.bytes
+FROM (
+  SELECT owner, SUM(kept_bytes) AS bytes FROM files WHERE owner IS NOT NULL GROUP BY owner
+) AS usage
+WHERE user_quotas.owner = usage.owner;
+
+UPDATE collection_quotas SET used_bytes = used_bytes - usage.bytes
+FROM (
+  SELECT collection_file_pairs.collection_id, SUM(files.kept_bytes) AS bytes
+  FROM collection_file_pairs JOIN files ON files.id = collection_file_pairs.file_id
+  GROUP BY collection_file_pairs.collection_id
+) AS usage
+WHERE collection_quotas.collection_id = usage.collection_id;
+
+ALTER TABLE files DROP COLUMN kept_bytes;
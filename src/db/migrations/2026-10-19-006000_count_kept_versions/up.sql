@@ -0,0 +1,125 @@
+-- Your SQL goes here
+
+-- Bytes of the versions a file keeps apart from its current content. They count toward the
+-- quotas along with `size`, so that keeping versions cannot go beyond them.
+ALTER TABLE files ADD COLUMN kept_bytes BIGINT NOT NULL DEFAULT 0;
+
+UPDATE files SET kept_bytes = kept.bytes
+FROM (
+  SELECT file_versions.file_id, SUM(file_versions.size) AS bytes
+  FROM file_versions JOIN files ON files.id = file_versions.file_id
+  WHERE file_versions.uploaded_at IS DISTINCT FROM files.uploaded_at
+  GROUP BY file_versions.file_id
+) AS kept
+WHERE files.id = kept.file_id;
+
+CREATE FUNCTION files_find_kept_bytes(file_id INTEGER, current TIMESTAMP) RETURNS BIGINT AS $$
+  SELECT COALESCE(SUM(size), 0) FROM file_versions
+  WHERE file_versions.file_id = files_find_kept_bytes.file_id
+    AND file_versions.uploaded_at IS DISTINCT FROM current;
+$$ LANGUAGE sql STABLE;
+
+-- A new upload or restore turns the version that was current into a kept one.
+CREATE FUNCTION files_update_kept_bytes() RETURNS TRIGGER AS $$
+BEGIN
+  NEW.kept_bytes := files_find_kept_bytes(NEW.id, NEW.uploaded_at);
+
+  RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER files_update_kept_bytes
+BEFORE UPDATE OF uploaded_at ON files
+FOR EACH ROW EXECUTE FUNCTION files_update_kept_bytes();
+
+CREATE FUNCTION file_versions_update_kept_bytes() RETURNS TRIGGER AS $$
+DECLARE
+  target_id INTEGER;
+BEGIN
+  IF TG_OP = 'DELETE' THEN
+    target_id := OLD.file_id;
+  ELSE
+    target_id := NEW.file_id;
+  END IF;
+
+  -- Versions removed along with their file find nothing to update.
+  UPDATE files SET kept_bytes = files_find_kept_bytes(id, uploaded_at)
+  WHERE id = target_id AND kept_bytes <> files_find_kept_bytes(id, uploaded_at);
+
+  RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER file_versions_update_kept_bytes
+AFTER INSERT OR DELETE ON file_versions
+FOR EACH ROW EXECUTE FUNCTION file_versions_update_kept_bytes();
+
+CREATE OR REPLACE FUNCTION quotas_update_file_usage() RETURNS TRIGGER AS $$
+BEGIN
+  IF TG_OP = 'INSERT' AND NEW.owner IS NOT NULL THEN
+    INSERT INTO user_quotas (owner, used_bytes, used_files)
+    VALUES (NEW.owner, COALESCE(NEW.size, 0) + NEW.kept_bytes, 1)
+    ON CONFLICT (owner) DO UPDATE SET
+      used_bytes = user_quotas.used_bytes + EXCLUDED.used_bytes,
+      used_files = user_quotas.used_files + 1;
+  ELSIF TG_OP = 'UPDATE'
+    AND COALESCE(NEW.size, 0) + NEW.kept_bytes <> COALESCE(OLD.size, 0) + OLD.kept_bytes THEN
+    UPDATE user_quotas SET used_bytes = used_bytes
+      + COALESCE(NEW.size, 0) + NEW.kept_bytes - COALESCE(OLD.size, 0) - OLD.kept_bytes
+    WHERE owner = NEW.owner;
+    UPDATE collection_quotas SET used_bytes = used_bytes
+      + COALESCE(NEW.size, 0) + NEW.kept_bytes - COALESCE(OLD.size, 0) - OLD.kept_bytes
+    WHERE collection_id IN (SELECT collection_id FROM collection_file_pairs WHERE file_id = NEW.id);
+  ELSIF TG_OP = 'DELETE' THEN
+    UPDATE user_quotas SET
+      used_bytes = used_bytes - COALESCE(OLD.size, 0) - OLD.kept_bytes,
+      used_files = used_files - 1
+    WHERE owner = OLD.owner;
+  END IF;
+
+  RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER files_update_quota_usage ON files;
+CREATE TRIGGER files_update_quota_usage
+AFTER INSERT OR UPDATE OF size, uploaded_at, kept_bytes OR DELETE ON files
+FOR EACH ROW EXECUTE FUNCTION quotas_update_file_usage();
+
+CREATE OR REPLACE FUNCTION quotas_update_collection_usage() RETURNS TRIGGER AS $$
+DECLARE
+  file_size BIGINT;
+BEGIN
+  IF TG_OP = 'INSERT' THEN
+    SELECT COALESCE(size, 0) + kept_bytes INTO file_size FROM files WHERE id = NEW.file_id;
+
+    INSERT INTO collection_quotas (collection_id, used_bytes, used_files)
+    VALUES (NEW.collection_id, COALESCE(file_size, 0), 1)
+    ON CONFLICT (collection_id) DO UPDATE SET
+      used_bytes = collection_quotas.used_bytes + EXCLUDED.used_bytes,
+      used_files = collection_quotas.used_files + 1;
+  ELSE
+    SELECT COALESCE(size, 0) + kept_bytes INTO file_size FROM files WHERE id = OLD.file_id;
+
+    UPDATE collection_quotas SET used_bytes = used_bytes - COALESCE(file_size, 0), used_files = used_files - 1
+    WHERE collection_id = OLD.collection_id;
+  END IF;
+
+  RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+UPDATE user_quotas SET used_bytes = usage.bytes
+FROM (
+  SELECT owner, SUM(COALESCE(size, 0) + kept_bytes) AS bytes FROM files
+  WHERE owner IS NOT NULL GROUP BY owner
+) AS usage
+WHERE user_quotas.owner = usage.owner;
+
+UPDATE collection_quotas SET used_bytes = usage.bytes
+FROM (
+  SELECT collection_file_pairs.collection_id, SUM(COALESCE(files.size, 0) + files.kept_bytes) AS bytes
+  FROM collection_file_pairs JOIN files ON files.id = collection_file_pairs.file_id
+  GROUP BY collection_file_pairs.collection_id
+) AS usage
+WHERE collection_quotas.collection_id = usage.collection_id;
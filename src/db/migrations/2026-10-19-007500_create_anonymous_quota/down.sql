@@ -0,0 +1,31 @@
+-- This file should undo anything in `up.sql`
+
+CREATE OR REPLACE FUNCTION quotas_update_file_usage() RETURNS TRIGGER AS $$
+BEGIN
+  IF TG_OP = 'INSERT' AND NEW.owner IS NOT NULL THEN
+    INSERT INTO user_quotas (owner, used_bytes, used_files)
+    VALUES (NEW.owner, COALESCE(NEW.size, 0) + NEW.kept_bytes, 1)
+    ON CONFLICT (owner) DO UPDATE SET
+      used_bytes = user_quotas.used_bytes + EXCLUDED.used_bytes,
+      used_files = user_quotas.used_files + 1;
+  ELSIF TG_OP = 'UPDATE'
+    AND COALESCE(NEW.size, 0) + NEW.kept_bytes <> COALESCE(OLD.size, 0) + OLD.kept_bytes THEN
+    UPDATE user_quotas SET used_bytes = used_bytes
+      + COALESCE(NEW.size, 0) + NEW.kept_bytes - COALESCE(OLD.size, 0) - OLD.kept_bytes
+    WHERE owner = NEW.owner;
+    UPDATE collection_quotas SET used_bytes = used_bytes
+      + COALESCE(NEW.size, 0) + NEW.kept_bytes - COALESCE(OLD.size, 0) - OLD.kept_bytes
+    WHERE collection_id IN (SELECT collection_id FROM collection_file_pairs WHERE file_id = NEW.id);
+  ELSIF TG_OP = 'DELETE' THEN
+    UPDATE user_quotas SET
+      used_bytes = used_bytes - COALESCE(OLD.size, 0) - OLD.kept_bytes,
+      used_files = used_files - 1
+    WHERE owner = OLD.owner;
+  END IF;
+
+  RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+DELETE FROM user_quotas WHERE owner = '';
+ALTER TABLE user_quotas DROP COLUMN uuid;
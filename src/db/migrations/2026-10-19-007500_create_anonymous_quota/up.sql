@@ -0,0 +1,36 @@
+-- Your SQL goes here
+
+-- User quotas get a uuid, so that changes of their limits can be audited like other resources.
+ALTER TABLE user_quotas ADD COLUMN uuid UUID NOT NULL UNIQUE DEFAULT uuid_generate_v4();
+
+-- Files created without an actor count toward the quota of the empty owner, which no actor can
+-- claim, so that they are not left out of every user quota.
+INSERT INTO user_quotas (owner, used_bytes, used_files)
+SELECT '', COALESCE(SUM(COALESCE(size, 0) + kept_bytes), 0), COUNT(*) FROM files WHERE owner IS NULL;
+
+CREATE OR REPLACE FUNCTION quotas_update_file_usage() RETURNS TRIGGER AS $$
+BEGIN
+  IF TG_OP = 'INSERT' THEN
+    INSERT INTO user_quotas (owner, used_bytes, used_files)
+    VALUES (COALESCE(NEW.owner, ''), COALESCE(NEW.size, 0) + NEW.kept_bytes, 1)
+    ON CONFLICT (owner) DO UPDATE SET
+      used_bytes = user_quotas.used_bytes + EXCLUDED.used_bytes,
+      used_files = user_quotas.used_files + 1;
+  ELSIF TG_OP = 'UPDATE'
+    AND COALESCE(NEW.size, 0) + NEW.kept_bytes <> COALESCE(OLD.size, 0) + OLD.kept_bytes THEN
+    UPDATE user_quotas SET used_bytes = used_bytes
+      + COALESCE(NEW.size, 0) + NEW.kept_bytes - COALESCE(OLD.size, 0) - OLD.kept_bytes
+    WHERE owner = COALESCE(NEW.owner, '');
+    UPDATE collection_quotas SET used_bytes = used_bytes
+      + COALESCE(NEW.size, 0) + NEW.kept_bytes - COALESCE(OLD.size, 0) - OLD.kept_bytes
+    WHERE collection_id IN (SELECT collection_id FROM collection_file_pairs WHERE file_id = NEW.id);
+  ELSIF TG_OP = 'DELETE' THEN
+    UPDATE user_quotas SET
+      used_bytes = used_bytes - COALESCE(OLD.size, 0) - OLD.kept_bytes,
+      used_files = used_files - 1
+    WHERE owner = COALESCE(OLD.owner, '');
+  END IF;
+
+  RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
@@ -1,5 +1,26 @@
 // @generated automatically by Diesel CLI.
 
+pub mod sql_types {
+    #[derive(diesel::query_builder::QueryId, diesel::sql_types::SqlType)]
+    #[diesel(postgres_type(name = "file_status"))]
+    pub struct FileStatus;
+}
+
+diesel::table! {
+    audit_events (id) {
+        id -> Int8,
+        uuid -> Uuid,
+        occurred_at -> Timestamp,
+        request_id -> Text,
+        actor -> Nullable<Text>,
+        action -> Text,
+        resource_type -> Text,
+        resource_uuid -> Uuid,
+        before -> Nullable<Jsonb>,
+        after -> Nullable<Jsonb>,
+    }
+}
+
 diesel::table! {
     collection_file_pairs (collection_id, file_id) {
         collection_id -> Int4,
@@ -7,6 +28,25 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    collection_quotas (collection_id) {
+        collection_id -> Int4,
+        max_bytes -> Nullable<Int8>,
+        max_files -> Nullable<Int8>,
+        used_bytes -> Int8,
+        used_files -> Int8,
+    }
+}
+
+diesel::table! {
+    collection_upload_policies (collection_id) {
+        collection_id -> Int4,
+        max_file_size -> Nullable<Int8>,
+        allowed_mime_types -> Nullable<Array<Text>>,
+        denied_mime_types -> Nullable<Array<Text>>,
+    }
+}
+
 diesel::table! {
     collections (id) {
         id -> Int4,
@@ -14,10 +54,31 @@ diesel::table! {
         name -> Text,
         description -> Nullable<Text>,
         created_at -> Timestamp,
+        file_count -> Int8,
+        parent_id -> Nullable<Int4>,
+        smart_query -> Nullable<Jsonb>,
+        deleted_at -> Nullable<Timestamp>,
+        updated_at -> Timestamp,
     }
 }
 
 diesel::table! {
+    file_versions (id) {
+        id -> Int4,
+        uuid -> Uuid,
+        file_id -> Int4,
+        number -> Int4,
+        size -> Int8,
+        hash -> Nullable<Int8>,
+        mime -> Nullable<Text>,
+        uploaded_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::FileStatus;
+
     files (id) {
         id -> Int4,
         uuid -> Uuid,
@@ -26,6 +87,82 @@ diesel::table! {
         size -> Nullable<Int8>,
         hash -> Nullable<Int8>,
         created_at -> Timestamp,
+        uploaded_at -> Nullable<Timestamp>,
+        deleted_at -> Nullable<Timestamp>,
+        status -> FileStatus,
+        failure_reason -> Nullable<Text>,
+        updated_at -> Timestamp,
+        owner -> Nullable<Text>,
+        kept_bytes -> Int8,
+    }
+}
+
+diesel::table! {
+    import_jobs (id) {
+        id -> Int4,
+        uuid -> Uuid,
+        root -> Text,
+        rules -> Array<Text>,
+        mode -> Text,
+        status -> Text,
+        total_count -> Nullable<Int8>,
+        processed_count -> Int8,
+        imported_count -> Int8,
+        failed_count -> Int8,
+        last_path -> Nullable<Text>,
+        last_error -> Nullable<Text>,
+        request_id -> Text,
+        actor -> Nullable<Text>,
+        heartbeat_at -> Nullable<Timestamp>,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+        finished_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    jobs (id) {
+        id -> Int8,
+        uuid -> Uuid,
+        job_type -> Text,
+        payload -> Jsonb,
+        dedup_key -> Nullable<Text>,
+        status -> Text,
+        attempt_count -> Int4,
+        max_attempts -> Int4,
+        run_at -> Timestamp,
+        last_error -> Nullable<Text>,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+        finished_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    scrub_mismatches (id) {
+        id -> Int4,
+        uuid -> Uuid,
+        file_id -> Int4,
+        file_uuid -> Uuid,
+        kind -> Text,
+        expected_size -> Int8,
+        actual_size -> Nullable<Int8>,
+        expected_hash -> Nullable<Int8>,
+        actual_hash -> Nullable<Int8>,
+        error -> Nullable<Text>,
+        detected_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    scrub_state (id) {
+        id -> Bool,
+        last_file_id -> Nullable<Int4>,
+        pass_started_at -> Nullable<Timestamp>,
+        checked_count -> Int8,
+        checked_size -> Int8,
+        mismatch_count -> Int8,
+        last_pass_completed_at -> Nullable<Timestamp>,
     }
 }
 
@@ -38,13 +175,82 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    thumbnail_jobs (file_id) {
+        file_id -> Int4,
+        status -> Text,
+        sizes -> Array<Text>,
+        last_error -> Nullable<Text>,
+        requested_at -> Timestamp,
+        generated_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    user_quotas (owner) {
+        owner -> Text,
+        max_bytes -> Nullable<Int8>,
+        max_files -> Nullable<Int8>,
+        used_bytes -> Int8,
+        used_files -> Int8,
+        uuid -> Uuid,
+    }
+}
+
+diesel::table! {
+    webhook_deliveries (id) {
+        id -> Int8,
+        uuid -> Uuid,
+        webhook_id -> Int4,
+        event_type -> Text,
+        payload -> Jsonb,
+        status -> Text,
+        attempt_count -> Int4,
+        next_attempt_at -> Nullable<Timestamp>,
+        last_attempt_at -> Nullable<Timestamp>,
+        response_status -> Nullable<Int4>,
+        last_error -> Nullable<Text>,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    webhooks (id) {
+        id -> Int4,
+        uuid -> Uuid,
+        url -> Text,
+        secret -> Text,
+        event_types -> Array<Text>,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
 diesel::joinable!(collection_file_pairs -> collections (collection_id));
 diesel::joinable!(collection_file_pairs -> files (file_id));
+diesel::joinable!(collection_quotas -> collections (collection_id));
+diesel::joinable!(collection_upload_policies -> collections (collection_id));
+diesel::joinable!(file_versions -> files (file_id));
+diesel::joinable!(scrub_mismatches -> files (file_id));
 diesel::joinable!(tags -> files (file_id));
+diesel::joinable!(thumbnail_jobs -> files (file_id));
+diesel::joinable!(webhook_deliveries -> webhooks (webhook_id));
 
 diesel::allow_tables_to_appear_in_same_query!(
+    audit_events,
     collection_file_pairs,
+    collection_quotas,
+    collection_upload_policies,
     collections,
+    file_versions,
     files,
+    import_jobs,
+    jobs,
+    scrub_mismatches,
+    scrub_state,
     tags,
+    thumbnail_jobs,
+    user_quotas,
+    webhook_deliveries,
+    webhooks,
 );
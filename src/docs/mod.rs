@@ -7,23 +7,137 @@ use utoipa::{OpenApi, ToSchema};
         crate::route_collections::handlers::find_collection,
         crate::route_collections::handlers::create_collection,
         crate::route_collections::handlers::update_collection,
+        crate::route_collections::handlers::patch_collection,
         crate::route_collections::handlers::remove_collection,
+        crate::route_collections::handlers::find_trashed_collections,
+        crate::route_collections::handlers::restore_collection,
+        crate::route_collections::handlers::move_collection,
+        crate::route_collections::handlers::find_collection_files,
+        crate::route_collections::handlers::add_collection_file,
+        crate::route_collections::handlers::remove_collection_file,
+        crate::route_collections::handlers::find_collection_upload_policy,
+        crate::route_collections::handlers::update_collection_upload_policy,
+        crate::route_files::handlers::find_files,
+        crate::route_files::handlers::create_file,
+        crate::route_files::handlers::upload_file,
+        crate::route_files::handlers::patch_file,
+        crate::route_files::handlers::remove_file,
+        crate::route_files::handlers::find_trashed_files,
+        crate::route_files::handlers::find_system_tags,
+        crate::route_files::handlers::restore_file,
+        crate::route_files::handlers::find_file_versions,
+        crate::route_files::handlers::download_file_version,
+        crate::route_files::handlers::restore_file_version,
+        crate::route_files::handlers::prune_file_versions,
+        crate::route_thumbnails::handlers::find_file_thumbnail,
+        crate::route_thumbnails::handlers::find_file_thumbnail_status,
+        crate::route_thumbnails::handlers::generate_file_thumbnail,
+        crate::route_audit::handlers::find_audit_events,
+        crate::route_events::handlers::subscribe_changes,
+        crate::route_events::handlers::subscribe_changes_ws,
+        crate::route_archives::handlers::export_collection,
+        crate::route_archives::handlers::import_collection,
+        crate::route_webhooks::handlers::find_webhooks,
+        crate::route_webhooks::handlers::find_webhook,
+        crate::route_webhooks::handlers::create_webhook,
+        crate::route_webhooks::handlers::update_webhook,
+        crate::route_webhooks::handlers::remove_webhook,
+        crate::route_webhooks::handlers::find_webhook_deliveries,
+        crate::route_webhooks::handlers::redeliver_webhook_delivery,
+        crate::route_imports::handlers::find_imports,
+        crate::route_imports::handlers::find_import,
+        crate::route_imports::handlers::create_import,
+        crate::route_imports::handlers::cancel_import,
+        crate::route_jobs::handlers::find_jobs,
+        crate::route_jobs::handlers::find_job,
+        crate::route_jobs::handlers::retry_job,
+        crate::route_storage::handlers::reconcile_storage,
+        crate::route_storage::handlers::find_scrub_status,
+        crate::route_storage::handlers::find_scrub_mismatches,
+        crate::route_quotas::handlers::find_user_quota,
+        crate::route_quotas::handlers::update_user_quota,
+        crate::route_quotas::handlers::find_anonymous_quota,
+        crate::route_quotas::handlers::update_anonymous_quota,
+        crate::route_quotas::handlers::find_collection_quota,
+        crate::route_quotas::handlers::update_collection_quota,
     ),
     components(
         schemas(ErrorBody),
 
         schemas(crate::schema::dto_in::PaginationOrderDto),
+        schemas(crate::schema::dto_in::CollectionSortDto),
+        schemas(crate::schema::dto_in::NameMatchDto),
         schemas(crate::schema::dto_in::CreateCollectionBodyDto),
         schemas(crate::schema::dto_in::UpdateCollectionBodyDto),
+        schemas(crate::schema::dto_in::PatchCollectionBodyDto),
+        schemas(crate::schema::dto_in::MoveCollectionBodyDto),
+        schemas(crate::schema::dto_in::RemoveCollectionModeDto),
+        schemas(crate::schema::dto_in::FileSortDto),
+        schemas(crate::schema::dto_in::FileStatusDto),
+        schemas(crate::schema::dto_in::FindFilesBodyDto),
+        schemas(crate::schema::dto_in::FindFilesTagFilterDto),
+        schemas(crate::schema::dto_in::FindFilesTagValueFilterDto),
+        schemas(crate::schema::dto_in::CreateFileBodyDto),
+        schemas(crate::schema::dto_in::PatchFileBodyDto),
+        schemas(crate::schema::dto_in::CreateFileTagDto),
+        schemas(crate::schema::dto_in::ThumbnailSizeDto),
+        schemas(crate::schema::dto_in::AuditActionDto),
+        schemas(crate::schema::dto_in::AuditResourceDto),
+        schemas(crate::schema::dto_in::ChangeResourceDto),
+        schemas(crate::schema::dto_in::CreateWebhookBodyDto),
+        schemas(crate::schema::dto_in::UpdateWebhookBodyDto),
+        schemas(crate::schema::dto_in::WebhookDeliveryStatusDto),
+        schemas(crate::schema::dto_in::ImportModeDto),
+        schemas(crate::schema::dto_in::CreateImportBodyDto),
+        schemas(crate::schema::dto_in::JobTypeDto),
+        schemas(crate::schema::dto_in::JobStatusDto),
+        schemas(crate::schema::dto_in::ScrubMismatchKindDto),
+        schemas(crate::schema::dto_in::UpdateQuotaBodyDto),
+        schemas(crate::schema::dto_in::UpdateUploadPolicyBodyDto),
+        schemas(crate::schema::dto_in::PruneFileVersionsBodyDto),
         
         schemas(crate::schema::dto_out::PaginationMetadataDto),
+        schemas(crate::schema::dto_out::CursorPaginationMetadataDto),
+        schemas(crate::schema::dto_out::CollectionKindDto),
         schemas(crate::schema::dto_out::CollectionDto),
         schemas(crate::schema::dto_out::FindCollectionsResultDto),
+        schemas(crate::schema::dto_out::RemoveCollectionResultDto),
+        schemas(crate::schema::dto_out::FileDto),
+        schemas(crate::schema::dto_out::FindFilesResultDto),
+        schemas(crate::schema::dto_out::SystemTagDto),
+        schemas(crate::schema::dto_out::ThumbnailStatusDto),
+        schemas(crate::schema::dto_out::ThumbnailJobDto),
+        schemas(crate::schema::dto_out::AuditEventDto),
+        schemas(crate::schema::dto_out::FindAuditEventsResultDto),
+        schemas(crate::schema::dto_out::ChangeActionDto),
+        schemas(crate::schema::dto_out::ChangeEventDto),
+        schemas(crate::schema::dto_out::WebhookDto),
+        schemas(crate::schema::dto_out::WebhookDeliveryDto),
+        schemas(crate::schema::dto_out::FindWebhookDeliveriesResultDto),
+        schemas(crate::schema::dto_out::ImportStatusDto),
+        schemas(crate::schema::dto_out::ImportJobDto),
+        schemas(crate::schema::dto_out::ImportCollectionResultDto),
+        schemas(crate::schema::dto_out::JobDto),
+        schemas(crate::schema::dto_out::FindJobsResultDto),
+        schemas(crate::schema::dto_out::ReconcileStorageResultDto),
+        schemas(crate::schema::dto_out::ScrubStatusDto),
+        schemas(crate::schema::dto_out::ScrubMismatchDto),
+        schemas(crate::schema::dto_out::FindScrubMismatchesResultDto),
+        schemas(crate::schema::dto_out::QuotaDto),
+        schemas(crate::schema::dto_out::UploadPolicyDto),
+        schemas(crate::schema::dto_out::FileVersionDto),
     ),
     tags(
         (name = "tag-template", description = "Tag template API for file tagging."),
         (name = "file", description = "File API for file management."),
         (name = "collection", description = "Collection API for file collection management."),
+        (name = "audit", description = "Audit API for the log of mutations."),
+        (name = "event", description = "Event API for real-time change notifications."),
+        (name = "webhook", description = "Webhook API for notifying integrations of audited actions."),
+        (name = "import", description = "Import API for ingesting existing directory trees."),
+        (name = "job", description = "Job API for inspecting and retrying background work."),
+        (name = "storage", description = "Storage API for keeping the stored contents consistent with the files."),
+        (name = "quota", description = "Quota API for limiting the storage of users and collections."),
     ),
 )]
 pub struct ApiDoc;
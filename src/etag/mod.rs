@@ -0,0 +1,107 @@
+use axum::{
+    async_trait,
+    extract::FromRequestParts,
+    http::{
+        header::{ETAG, IF_MATCH, IF_NONE_MATCH},
+        request::Parts,
+        HeaderName, HeaderValue, StatusCode,
+    },
+};
+use chrono::{DateTime, Utc};
+
+/// A strong entity tag, derived from the last modification time of a resource.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ETag(String);
+
+impl ETag {
+    pub fn from_updated_at(updated_at: DateTime<Utc>) -> Self {
+        Self(format!("\"{:x}\"", updated_at.timestamp_micros()))
+    }
+
+    /// Returns the `ETag` header carrying this tag.
+    pub fn header(&self) -> [(HeaderName, HeaderValue); 1] {
+        // The tag only consists of quotes and hex digits.
+        [(ETAG, HeaderValue::from_str(&self.0).unwrap())]
+    }
+}
+
+/// The `If-Match` precondition of a request; absent when the request has no such header.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct IfMatch(Option<Vec<String>>);
+
+impl IfMatch {
+    /// Returns whether the current tag of a resource satisfies the precondition.
+    ///
+    /// Tags are compared strongly, so weak tags in the header never match.
+    pub fn matches(&self, etag: &ETag) -> bool {
+        match &self.0 {
+            Some(tags) => tags.iter().any(|tag| tag == "*" || *tag == etag.0),
+            None => true,
+        }
+    }
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for IfMatch
+where
+    S: Send + Sync,
+{
+    type Rejection = StatusCode;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        read_tags(parts, IF_MATCH).map(Self)
+    }
+}
+
+/// The `If-None-Match` precondition of a conditional `GET`; absent when the request has no such
+/// header.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct IfNoneMatch(Option<Vec<String>>);
+
+impl IfNoneMatch {
+    /// Returns whether the client already has the current representation of a resource.
+    ///
+    /// Tags are compared weakly, as caches may send back weak versions of them.
+    pub fn matches(&self, etag: &ETag) -> bool {
+        match &self.0 {
+            Some(tags) => tags
+                .iter()
+                .any(|tag| tag == "*" || tag.strip_prefix("W/").unwrap_or(tag) == etag.0),
+            None => false,
+        }
+    }
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for IfNoneMatch
+where
+    S: Send + Sync,
+{
+    type Rejection = StatusCode;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        read_tags(parts, IF_NONE_MATCH).map(Self)
+    }
+}
+
+/// Reads the comma-separated tags of every value of a header; none if the header is absent.
+fn read_tags(parts: &Parts, name: HeaderName) -> Result<Option<Vec<String>>, StatusCode> {
+    let mut values = parts.headers.get_all(name).iter().peekable();
+    if values.peek().is_none() {
+        return Ok(None);
+    }
+
+    let mut tags = Vec::new();
+    for value in values {
+        let value = value.to_str().map_err(|_| StatusCode::BAD_REQUEST)?;
+        tags.extend(
+            value
+                .split(',')
+                .map(|tag| tag.trim())
+                .filter(|tag| !tag.is_empty())
+                .map(|tag| tag.to_owned()),
+        );
+    }
+
+    Ok(Some(tags))
+}
@@ -2,12 +2,19 @@ use std::{
     path::Path,
     pin::Pin,
     task::{Context, Poll},
+    time::Duration,
 };
 use thiserror::Error;
-use tokio::io::{AsyncWrite, Error as IOError};
+use tokio::{
+    io::{AsyncReadExt, AsyncWrite, Error as IOError},
+    time::Instant,
+};
 
 const BUFFER_SIZE: usize = 4 * 1024 * 1024;
 
+/// Size of the chunks read between two pauses of a throttled hashing.
+const THROTTLED_CHUNK_SIZE: usize = 1024 * 1024;
+
 #[derive(Error, Debug)]
 pub enum ComputeFileHashError {
     #[error("failed to open file: {0}")]
@@ -28,6 +35,44 @@ pub async fn compute_file_hash(path: impl AsRef<Path>) -> Result<u32, ComputeFil
     Ok(hasher.into_inner().finalize())
 }
 
+/// Computes the hash of a file like [`compute_file_hash`], reading it at most
+/// `max_bytes_per_second` fast; also returns the number of bytes read.
+pub async fn compute_file_hash_throttled(
+    path: impl AsRef<Path>,
+    max_bytes_per_second: Option<u64>,
+) -> Result<(u32, u64), ComputeFileHashError> {
+    let mut file = tokio::fs::File::open(path)
+        .await
+        .map_err(ComputeFileHashError::OpenFileError)?;
+    let mut buffer = vec![0; THROTTLED_CHUNK_SIZE];
+    let mut hasher = crc32fast::Hasher::new();
+    let mut size = 0u64;
+    let started_at = Instant::now();
+
+    loop {
+        let length = file
+            .read(&mut buffer)
+            .await
+            .map_err(ComputeFileHashError::ReadFileError)?;
+
+        if length == 0 {
+            break;
+        }
+
+        hasher.update(&buffer[..length]);
+        size += length as u64;
+
+        if let Some(max_bytes_per_second) = max_bytes_per_second {
+            let due = Duration::from_secs_f64(size as f64 / max_bytes_per_second as f64);
+            if let Some(ahead) = due.checked_sub(started_at.elapsed()) {
+                tokio::time::sleep(ahead).await;
+            }
+        }
+    }
+
+    Ok((hasher.finalize(), size))
+}
+
 struct AsyncCrc32Hasher {
     inner: crc32fast::Hasher,
 }
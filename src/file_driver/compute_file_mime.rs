@@ -15,15 +15,13 @@ pub async fn compute_file_mime(
 ) -> Result<&'static str, ComputeFileMimeError> {
     let path = path.into();
     tokio::task::spawn_blocking(move || {
-        if let Some(mime) =
-            infer::get_from_path(&path).map_err(|err| ComputeFileMimeError::InferError(err))?
-        {
+        if let Some(mime) = infer::get_from_path(&path).map_err(ComputeFileMimeError::InferError)? {
             return Ok(mime.mime_type());
         }
 
         Ok(mime_guess::from_path(&path)
             .first_raw()
-            .unwrap_or_else(|| "application/octet-stream"))
+            .unwrap_or("application/octet-stream"))
     })
     .await?
 }
@@ -0,0 +1,37 @@
+use super::{system_tag, ExtractSystemTagsError, ALBUM, ARTIST, DURATION, TITLE, YEAR};
+use crate::schema::dto_in::CreateFileTagDto;
+use lofty::{Accessor, AudioFile, Probe, TaggedFileExt};
+use std::path::Path;
+
+/// Reads the artist, album, title and year of an audio track from its ID3, Vorbis or other tags,
+/// and its duration from its properties.
+pub fn extract(path: &Path) -> Result<Vec<CreateFileTagDto>, ExtractSystemTagsError> {
+    // Stored files have no extension, so the format is guessed from the content.
+    let file = Probe::open(path)?.guess_file_type()?.read()?;
+    let mut tags = Vec::new();
+
+    if let Some(tag) = file.primary_tag().or_else(|| file.first_tag()) {
+        if let Some(artist) = tag.artist() {
+            tags.push(system_tag(ARTIST, artist));
+        }
+
+        if let Some(album) = tag.album() {
+            tags.push(system_tag(ALBUM, album));
+        }
+
+        if let Some(title) = tag.title() {
+            tags.push(system_tag(TITLE, title));
+        }
+
+        if let Some(year) = tag.year() {
+            tags.push(system_tag(YEAR, year));
+        }
+    }
+
+    let duration = file.properties().duration();
+    if !duration.is_zero() {
+        tags.push(system_tag(DURATION, duration.as_secs()));
+    }
+
+    Ok(tags)
+}
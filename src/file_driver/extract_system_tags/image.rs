@@ -0,0 +1,100 @@
+use super::{system_tag, ExtractSystemTagsError, CAMERA, GPS, HEIGHT, TAKEN_AT, WIDTH};
+use crate::schema::dto_in::CreateFileTagDto;
+use exif::{DateTime, Exif, In, Tag, Value};
+use std::{fs::File, io::BufReader, path::Path};
+
+/// Reads the camera, time, location and dimensions of an image from its EXIF data.
+pub fn extract(path: &Path) -> Result<Vec<CreateFileTagDto>, ExtractSystemTagsError> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let exif = match exif::Reader::new().read_from_container(&mut reader) {
+        Ok(exif) => exif,
+        // Most images other than photos have no EXIF data at all.
+        Err(exif::Error::NotFound(_)) => return Ok(Vec::new()),
+        Err(err) => return Err(err.into()),
+    };
+
+    let mut tags = Vec::new();
+
+    let camera = [Tag::Make, Tag::Model]
+        .into_iter()
+        .filter_map(|tag| read_ascii(&exif, tag))
+        .collect::<Vec<_>>();
+    if !camera.is_empty() {
+        tags.push(system_tag(CAMERA, camera.join(" ")));
+    }
+
+    if let Some(taken_at) = [Tag::DateTimeOriginal, Tag::DateTime]
+        .into_iter()
+        .find_map(|tag| read_date_time(&exif, tag))
+    {
+        tags.push(system_tag(TAKEN_AT, taken_at));
+    }
+
+    if let (Some(latitude), Some(longitude)) = (
+        read_coordinate(&exif, Tag::GPSLatitude, Tag::GPSLatitudeRef, "S"),
+        read_coordinate(&exif, Tag::GPSLongitude, Tag::GPSLongitudeRef, "W"),
+    ) {
+        tags.push(system_tag(GPS, format!("{:.6},{:.6}", latitude, longitude)));
+    }
+
+    if let Some(width) = read_uint(&exif, Tag::PixelXDimension) {
+        tags.push(system_tag(WIDTH, width));
+    }
+
+    if let Some(height) = read_uint(&exif, Tag::PixelYDimension) {
+        tags.push(system_tag(HEIGHT, height));
+    }
+
+    Ok(tags)
+}
+
+fn read_ascii(exif: &Exif, tag: Tag) -> Option<String> {
+    match &exif.get_field(tag, In::PRIMARY)?.value {
+        Value::Ascii(values) => values
+            .first()
+            .map(|value| String::from_utf8_lossy(value).trim().to_owned())
+            .filter(|value| !value.is_empty()),
+        _ => None,
+    }
+}
+
+fn read_date_time(exif: &Exif, tag: Tag) -> Option<String> {
+    let value = match &exif.get_field(tag, In::PRIMARY)?.value {
+        Value::Ascii(values) => values.first()?,
+        _ => return None,
+    };
+    let date_time = DateTime::from_ascii(value).ok()?;
+
+    Some(format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+        date_time.year,
+        date_time.month,
+        date_time.day,
+        date_time.hour,
+        date_time.minute,
+        date_time.second
+    ))
+}
+
+/// Reads a coordinate in degrees, minutes and seconds as signed degrees.
+fn read_coordinate(exif: &Exif, tag: Tag, ref_tag: Tag, negative_ref: &str) -> Option<f64> {
+    let degrees = match &exif.get_field(tag, In::PRIMARY)?.value {
+        Value::Rational(values) if values.len() == 3 => {
+            values[0].to_f64() + values[1].to_f64() / 60.0 + values[2].to_f64() / 3600.0
+        }
+        _ => return None,
+    };
+
+    if !degrees.is_finite() {
+        return None;
+    }
+
+    match read_ascii(exif, ref_tag) {
+        Some(reference) if reference == negative_ref => Some(-degrees),
+        _ => Some(degrees),
+    }
+}
+
+fn read_uint(exif: &Exif, tag: Tag) -> Option<u32> {
+    exif.get_field(tag, In::PRIMARY)?.value.get_uint(0)
+}
@@ -0,0 +1,139 @@
+use crate::schema::dto_in::CreateFileTagDto;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+mod audio;
+mod image;
+mod pdf;
+mod video;
+
+/// Prefix of the titles of system tags; users cannot set tags with it.
+pub const SYSTEM_TAG_PREFIX: &str = "system:";
+
+pub const CAMERA: &str = "system:camera";
+pub const TAKEN_AT: &str = "system:takenAt";
+pub const GPS: &str = "system:gps";
+pub const WIDTH: &str = "system:width";
+pub const HEIGHT: &str = "system:height";
+pub const ARTIST: &str = "system:artist";
+pub const ALBUM: &str = "system:album";
+pub const TITLE: &str = "system:title";
+pub const YEAR: &str = "system:year";
+pub const AUTHOR: &str = "system:author";
+pub const PAGE_COUNT: &str = "system:pageCount";
+pub const DURATION: &str = "system:duration";
+
+/// Reserved tag, written from the metadata of the content of a file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SystemTagTemplate {
+    pub title: &'static str,
+    pub description: &'static str,
+}
+
+pub const SYSTEM_TAG_TEMPLATES: &[SystemTagTemplate] = &[
+    SystemTagTemplate {
+        title: CAMERA,
+        description: "make and model of the camera that took an image",
+    },
+    SystemTagTemplate {
+        title: TAKEN_AT,
+        description: "local time an image was taken at, as `YYYY-MM-DDTHH:MM:SS`",
+    },
+    SystemTagTemplate {
+        title: GPS,
+        description: "location an image was taken at, as `latitude,longitude` in degrees",
+    },
+    SystemTagTemplate {
+        title: WIDTH,
+        description: "width of an image or a video, in pixels",
+    },
+    SystemTagTemplate {
+        title: HEIGHT,
+        description: "height of an image or a video, in pixels",
+    },
+    SystemTagTemplate {
+        title: ARTIST,
+        description: "artist of an audio track",
+    },
+    SystemTagTemplate {
+        title: ALBUM,
+        description: "album of an audio track",
+    },
+    SystemTagTemplate {
+        title: TITLE,
+        description: "title of an audio track or a document",
+    },
+    SystemTagTemplate {
+        title: YEAR,
+        description: "release year of an audio track",
+    },
+    SystemTagTemplate {
+        title: AUTHOR,
+        description: "author of a document",
+    },
+    SystemTagTemplate {
+        title: PAGE_COUNT,
+        description: "number of pages of a document",
+    },
+    SystemTagTemplate {
+        title: DURATION,
+        description: "duration of an audio track or a video, in whole seconds",
+    },
+];
+
+pub fn is_system_tag_title(title: &str) -> bool {
+    title.starts_with(SYSTEM_TAG_PREFIX)
+}
+
+#[derive(Error, Debug)]
+#[allow(clippy::enum_variant_names)]
+pub enum ExtractSystemTagsError {
+    #[error("failed to read file: {0}")]
+    ReadFileError(#[from] std::io::Error),
+    #[error("failed to read exif: {0}")]
+    ExifError(#[from] exif::Error),
+    #[error("failed to read audio tags: {0}")]
+    AudioError(#[from] lofty::LoftyError),
+    #[error("failed to read pdf: {0}")]
+    PdfError(#[from] lopdf::Error),
+    #[error("failed to read video: {0}")]
+    VideoError(#[from] mp4::Error),
+    #[error("failed to join task: {0}")]
+    JoinError(#[from] tokio::task::JoinError),
+}
+
+type Extractor = fn(&Path) -> Result<Vec<CreateFileTagDto>, ExtractSystemTagsError>;
+
+fn find_extractor(mime: &str) -> Option<Extractor> {
+    match mime {
+        "application/pdf" => Some(pdf::extract),
+        "video/mp4" | "video/quicktime" | "video/x-m4v" => Some(video::extract),
+        mime if mime.starts_with("image/") => Some(image::extract),
+        mime if mime.starts_with("audio/") => Some(audio::extract),
+        _ => None,
+    }
+}
+
+/// Extracts the system tags of a file with the extractor of its mime type, if there is one.
+pub async fn extract_system_tags(
+    path: impl Into<PathBuf>,
+    mime: &str,
+) -> Result<Vec<CreateFileTagDto>, ExtractSystemTagsError> {
+    let extractor = match find_extractor(mime) {
+        Some(extractor) => extractor,
+        None => return Ok(Vec::new()),
+    };
+
+    let path = path.into();
+    let mut tags = tokio::task::spawn_blocking(move || extractor(&path)).await??;
+    tags.retain(|tag| tag.value.as_deref().is_some_and(|value| !value.is_empty()));
+
+    Ok(tags)
+}
+
+fn system_tag(title: &'static str, value: impl ToString) -> CreateFileTagDto {
+    CreateFileTagDto {
+        title: title.to_owned(),
+        value: Some(value.to_string().trim().to_owned()),
+    }
+}
@@ -0,0 +1,59 @@
+use super::{system_tag, ExtractSystemTagsError, AUTHOR, PAGE_COUNT, TITLE};
+use crate::schema::dto_in::CreateFileTagDto;
+use lopdf::{Dictionary, Document, Object};
+use std::path::Path;
+
+/// Documents are parsed in memory, so larger ones are skipped.
+const MAX_DOCUMENT_SIZE: u64 = 256 * 1024 * 1024;
+
+/// Reads the title and author of a document from its information dictionary, and counts its
+/// pages.
+pub fn extract(path: &Path) -> Result<Vec<CreateFileTagDto>, ExtractSystemTagsError> {
+    if std::fs::metadata(path)?.len() > MAX_DOCUMENT_SIZE {
+        return Ok(Vec::new());
+    }
+
+    let document = Document::load(path)?;
+    let mut tags = Vec::new();
+
+    let info = document
+        .trailer
+        .get(b"Info")
+        .and_then(|info| document.dereference(info))
+        .and_then(|(_, info)| info.as_dict());
+    if let Ok(info) = info {
+        if let Some(title) = read_text(&document, info, b"Title") {
+            tags.push(system_tag(TITLE, title));
+        }
+
+        if let Some(author) = read_text(&document, info, b"Author") {
+            tags.push(system_tag(AUTHOR, author));
+        }
+    }
+
+    tags.push(system_tag(PAGE_COUNT, document.get_pages().len()));
+
+    Ok(tags)
+}
+
+fn read_text(document: &Document, info: &Dictionary, key: &[u8]) -> Option<String> {
+    match info.get_deref(key, document).ok()? {
+        Object::String(bytes, _) => Some(decode_text(bytes)),
+        _ => None,
+    }
+}
+
+/// Decodes a text string, either UTF-16 with a byte order mark or PDFDocEncoding, read as
+/// Latin-1 which it matches for printable characters.
+fn decode_text(bytes: &[u8]) -> String {
+    match bytes {
+        [0xfe, 0xff, rest @ ..] => {
+            let units = rest
+                .chunks_exact(2)
+                .map(|unit| u16::from_be_bytes([unit[0], unit[1]]))
+                .collect::<Vec<_>>();
+            String::from_utf16_lossy(&units)
+        }
+        bytes => bytes.iter().map(|&byte| byte as char).collect(),
+    }
+}
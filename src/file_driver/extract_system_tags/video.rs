@@ -0,0 +1,28 @@
+use super::{system_tag, ExtractSystemTagsError, DURATION, HEIGHT, WIDTH};
+use crate::schema::dto_in::CreateFileTagDto;
+use mp4::{Mp4Reader, TrackType};
+use std::{fs::File, io::BufReader, path::Path};
+
+/// Reads the duration and resolution of an MP4 or QuickTime video from its container.
+pub fn extract(path: &Path) -> Result<Vec<CreateFileTagDto>, ExtractSystemTagsError> {
+    let file = File::open(path)?;
+    let size = file.metadata()?.len();
+    let video = Mp4Reader::read_header(BufReader::new(file), size)?;
+    let mut tags = Vec::new();
+
+    let duration = video.duration();
+    if !duration.is_zero() {
+        tags.push(system_tag(DURATION, duration.as_secs()));
+    }
+
+    if let Some(track) = video
+        .tracks()
+        .values()
+        .find(|track| matches!(track.track_type(), Ok(TrackType::Video)))
+    {
+        tags.push(system_tag(WIDTH, track.width()));
+        tags.push(system_tag(HEIGHT, track.height()));
+    }
+
+    Ok(tags)
+}
@@ -1,31 +1,51 @@
+use crate::schema::dto_in::{CreateFileTagDto, ImportModeDto, ThumbnailSizeDto};
 use axum::{body::Bytes, http::StatusCode, Error};
+use chrono::{DateTime, NaiveDateTime, Utc};
 use codegen::ErrorEnum;
 use compute_file_hash::*;
 use compute_file_mime::*;
-use futures::{Stream, TryStreamExt};
+use extract_system_tags::extract_system_tags;
+use futures::{Stream, StreamExt, TryStreamExt};
+use render_thumbnails::render_thumbnails;
 use std::{
     io::SeekFrom,
     path::{Path, PathBuf},
+    time::SystemTime,
 };
 use thiserror::Error;
 use tokio::{
-    fs::OpenOptions,
-    io::{AsyncSeekExt, AsyncWriteExt, BufWriter},
+    fs::{File, OpenOptions},
+    io::{AsyncBufReadExt, AsyncReadExt, AsyncSeekExt, AsyncWriteExt, BufReader, BufWriter},
 };
+use upload_policy::{MIME_SNIFF_LENGTH, UNKNOWN_MIME};
 use uuid::Uuid;
 
 mod compute_file_hash;
 mod compute_file_mime;
+mod extract_system_tags;
+mod render_thumbnails;
+mod upload_policy;
+
+pub use compute_file_hash::ComputeFileHashError;
+pub use extract_system_tags::{
+    is_system_tag_title, SystemTagTemplate, SYSTEM_TAG_PREFIX, SYSTEM_TAG_TEMPLATES,
+};
+pub use render_thumbnails::RenderThumbnailsError;
+pub use upload_policy::{is_mime_type_pattern, UploadPolicy};
 
 #[derive(Debug, Clone)]
 pub struct FileDriver {
     pub files_path: PathBuf,
+    pub thumbnails_path: PathBuf,
+    pub versions_path: PathBuf,
 }
 
 impl FileDriver {
     pub fn new(root: impl AsRef<Path>) -> Self {
         Self {
             files_path: root.as_ref().join("files"),
+            thumbnails_path: root.as_ref().join("thumbnails"),
+            versions_path: root.as_ref().join("versions"),
         }
     }
 
@@ -33,10 +53,20 @@ impl FileDriver {
         let current_dir = std::env::current_dir().expect("failed to get current directory");
 
         self.files_path = {
-            let mut path = current_dir;
+            let mut path = current_dir.clone();
             path.push(&self.files_path);
             path
         };
+        self.thumbnails_path = {
+            let mut path = current_dir.clone();
+            path.push(&self.thumbnails_path);
+            path
+        };
+        self.versions_path = {
+            let mut path = current_dir;
+            path.push(&self.versions_path);
+            path
+        };
 
         tracing::info!(
             "creating files directory at `{}`",
@@ -44,10 +74,38 @@ impl FileDriver {
         );
         tokio::fs::create_dir_all(&self.files_path)
             .await
-            .expect(&format!(
-                "failed to create files directory at `{}`",
-                self.files_path.display()
-            ));
+            .unwrap_or_else(|_| {
+                panic!(
+                    "failed to create files directory at `{}`",
+                    self.files_path.display()
+                )
+            });
+
+        tracing::info!(
+            "creating thumbnails directory at `{}`",
+            self.thumbnails_path.display()
+        );
+        tokio::fs::create_dir_all(&self.thumbnails_path)
+            .await
+            .unwrap_or_else(|_| {
+                panic!(
+                    "failed to create thumbnails directory at `{}`",
+                    self.thumbnails_path.display()
+                )
+            });
+
+        tracing::info!(
+            "creating versions directory at `{}`",
+            self.versions_path.display()
+        );
+        tokio::fs::create_dir_all(&self.versions_path)
+            .await
+            .unwrap_or_else(|_| {
+                panic!(
+                    "failed to create versions directory at `{}`",
+                    self.versions_path.display()
+                )
+            });
     }
 
     pub async fn read_file_size(&self, uuid: Uuid) -> Result<Option<u64>, ReadFileSizeError> {
@@ -60,10 +118,23 @@ impl FileDriver {
         }
     }
 
+    /// Writes the content of a file from `offset`, and returns its size.
+    ///
+    /// The content may not grow beyond `max_size` bytes: an upload announced by its
+    /// `content_length` to go beyond is refused before anything is written, and any other is
+    /// stopped at the limit, keeping what was written. It must also satisfy every policy in
+    /// `policies`; its type is inferred from its leading bytes before anything is written, while
+    /// its size is checked as it is written.
+    ///
+    /// A content shared with a kept version or with the source of a hard-linked import is never
+    /// written in place; the file gets its own copy first.
     pub async fn write_file(
         &self,
         uuid: Uuid,
         offset: Option<u64>,
+        content_length: Option<u64>,
+        max_size: Option<u64>,
+        policies: &[UploadPolicy],
         stream: impl Stream<Item = Result<Bytes, Error>>,
     ) -> Result<u64, WriteFileError> {
         let path = self.files_path.join(uuid.to_string());
@@ -87,6 +158,80 @@ impl FileDriver {
             }
         }
 
+        let max_file_size = policies
+            .iter()
+            .filter_map(|policy| policy.max_file_size)
+            .min();
+
+        if let Some(content_length) = content_length {
+            let size = offset + content_length;
+
+            if let Some(max_size) = max_file_size.filter(|max_size| *max_size < size) {
+                return Err(WriteFileError::FileTooLarge { size, max_size });
+            }
+
+            if let Some(max_size) = max_size.filter(|max_size| *max_size < size) {
+                return Err(WriteFileError::ContentTooLarge { size, max_size });
+            }
+        }
+
+        futures::pin_mut!(stream);
+
+        // The chunks read to infer the type are written along with the rest.
+        let mut read_chunks = Vec::new();
+        if policies.iter().any(UploadPolicy::restricts_mime_types) {
+            let mut head = Vec::new();
+            if offset != 0 {
+                File::open(&path)
+                    .await
+                    .map_err(WriteFileError::ReadFileMetadata)?
+                    .take(offset.min(MIME_SNIFF_LENGTH as u64))
+                    .read_to_end(&mut head)
+                    .await
+                    .map_err(WriteFileError::ReadFileMetadata)?;
+            }
+
+            while head.len() < MIME_SNIFF_LENGTH {
+                match stream.try_next().await? {
+                    Some(chunk) => {
+                        head.extend_from_slice(&chunk);
+                        read_chunks.push(Ok(chunk));
+                    }
+                    None => break,
+                }
+            }
+
+            let mime = infer::get(&head).map_or(UNKNOWN_MIME, |mime| mime.mime_type());
+            if !policies.iter().all(|policy| policy.accepts_mime_type(mime)) {
+                return Err(WriteFileError::MimeTypeNotAllowed(mime.to_owned()));
+            }
+        }
+
+        let metadata = file
+            .metadata()
+            .await
+            .map_err(WriteFileError::ReadFileMetadata)?;
+        if is_shared(&metadata) {
+            let temp_path = path.with_extension("tmp");
+            if offset == 0 {
+                File::create(&temp_path)
+                    .await
+                    .map_err(WriteFileError::CreateFile)?;
+            } else {
+                tokio::fs::copy(&path, &temp_path)
+                    .await
+                    .map_err(WriteFileError::CreateFile)?;
+            }
+            tokio::fs::rename(&temp_path, &path)
+                .await
+                .map_err(WriteFileError::CreateFile)?;
+            file = OpenOptions::new()
+                .append(true)
+                .open(&path)
+                .await
+                .map_err(WriteFileError::CreateFile)?;
+        }
+
         file.seek(SeekFrom::Start(offset))
             .await
             .map_err(WriteFileError::WriteToFile)?;
@@ -95,15 +240,39 @@ impl FileDriver {
             .map_err(WriteFileError::WriteToFile)?;
 
         let mut writer = BufWriter::new(&mut file);
+        let mut size = offset;
 
+        let stream = futures::stream::iter(read_chunks).chain(stream);
         futures::pin_mut!(stream);
         while let Some(chunk) = stream.try_next().await? {
+            if let Some(max_size) =
+                max_file_size.filter(|max_size| *max_size < size + chunk.len() as u64)
+            {
+                writer.flush().await.map_err(WriteFileError::WriteToFile)?;
+                return Err(WriteFileError::FileSizeExceeded { max_size });
+            }
+
+            if let Some(max_size) =
+                max_size.filter(|max_size| *max_size < size + chunk.len() as u64)
+            {
+                // What fits is kept, so that the upload can be resumed once there is room.
+                writer
+                    .write_all(&chunk[..max_size.saturating_sub(size) as usize])
+                    .await
+                    .map_err(WriteFileError::WriteToFile)?;
+                writer.flush().await.map_err(WriteFileError::WriteToFile)?;
+                return Err(WriteFileError::QuotaExceeded { max_size });
+            }
+
             writer
                 .write_all(&chunk)
                 .await
                 .map_err(WriteFileError::WriteToFile)?;
+            size += chunk.len() as u64;
         }
 
+        writer.flush().await.map_err(WriteFileError::WriteToFile)?;
+
         let metadata = file
             .metadata()
             .await
@@ -111,14 +280,339 @@ impl FileDriver {
         Ok(metadata.len())
     }
 
+    /// Removes the content of a file; a file that was never uploaded is not an error.
+    pub async fn remove_file(&self, uuid: Uuid) -> Result<(), RemoveFileError> {
+        let path = self.files_path.join(uuid.to_string());
+        match tokio::fs::remove_file(&path).await {
+            Ok(()) => Ok(()),
+            Err(err) if err.kind() == tokio::io::ErrorKind::NotFound => Ok(()),
+            Err(err) => Err(RemoveFileError::RemoveFile(err)),
+        }
+    }
+
+    /// Keeps the content of a file as the content of one of its versions, unless it already is;
+    /// a file that was never uploaded is not an error.
+    pub async fn keep_version(&self, uuid: Uuid, version_uuid: Uuid) -> Result<(), VersionError> {
+        let path = self.files_path.join(uuid.to_string());
+        let version_path = self.versions_path.join(version_uuid.to_string());
+        match tokio::fs::hard_link(&path, &version_path).await {
+            Ok(()) => Ok(()),
+            Err(err)
+                if matches!(
+                    err.kind(),
+                    tokio::io::ErrorKind::AlreadyExists | tokio::io::ErrorKind::NotFound
+                ) =>
+            {
+                Ok(())
+            }
+            Err(err) => Err(VersionError::LinkFile(err)),
+        }
+    }
+
+    /// Replaces the content of a file with the kept content of one of its versions at once.
+    pub async fn restore_version(
+        &self,
+        uuid: Uuid,
+        version_uuid: Uuid,
+    ) -> Result<(), VersionError> {
+        let path = self.files_path.join(uuid.to_string());
+        let temp_path = path.with_extension("tmp");
+        let version_path = self.versions_path.join(version_uuid.to_string());
+
+        match tokio::fs::remove_file(&temp_path).await {
+            Ok(()) => {}
+            Err(err) if err.kind() == tokio::io::ErrorKind::NotFound => {}
+            Err(err) => return Err(VersionError::ReplaceFile(err)),
+        }
+        tokio::fs::hard_link(&version_path, &temp_path)
+            .await
+            .map_err(VersionError::LinkFile)?;
+        tokio::fs::rename(&temp_path, &path)
+            .await
+            .map_err(VersionError::ReplaceFile)
+    }
+
+    /// Opens the kept content of a version for reading; it is `None` if it was never kept apart
+    /// from the content of its file.
+    pub async fn open_version(&self, version_uuid: Uuid) -> Result<Option<File>, OpenFileError> {
+        let path = self.versions_path.join(version_uuid.to_string());
+        match File::open(&path).await {
+            Ok(file) => Ok(Some(file)),
+            Err(err) if err.kind() == tokio::io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(OpenFileError::OpenFile(err)),
+        }
+    }
+
+    /// Removes the kept content of a version; one that was never kept is not an error.
+    pub async fn remove_version(&self, version_uuid: Uuid) -> Result<(), RemoveFileError> {
+        let path = self.versions_path.join(version_uuid.to_string());
+        match tokio::fs::remove_file(&path).await {
+            Ok(()) => Ok(()),
+            Err(err) if err.kind() == tokio::io::ErrorKind::NotFound => Ok(()),
+            Err(err) => Err(RemoveFileError::RemoveFile(err)),
+        }
+    }
+
+    /// Stores the content of a file from a file on the server, and returns its size.
+    ///
+    /// A hard-linked content shares its inode with the source; `write_file` copies it before
+    /// writing, so uploads to the file never alter the source.
+    pub async fn import_file(
+        &self,
+        uuid: Uuid,
+        source: &Path,
+        mode: ImportModeDto,
+    ) -> Result<u64, ImportFileError> {
+        let path = self.files_path.join(uuid.to_string());
+        match mode {
+            ImportModeDto::Copy => tokio::fs::copy(source, &path)
+                .await
+                .map_err(ImportFileError::CopyFile),
+            ImportModeDto::HardLink => {
+                tokio::fs::hard_link(source, &path)
+                    .await
+                    .map_err(ImportFileError::LinkFile)?;
+                let metadata = tokio::fs::metadata(&path)
+                    .await
+                    .map_err(ImportFileError::ReadFileMetadata)?;
+                Ok(metadata.len())
+            }
+        }
+    }
+
+    /// Reads the content of a file again to check it, at most `max_bytes_per_second` fast; the
+    /// content is `None` if it is missing.
+    pub async fn check_file(
+        &self,
+        uuid: Uuid,
+        max_bytes_per_second: Option<u64>,
+    ) -> Result<Option<CheckedFile>, ComputeFileHashError> {
+        let path = self.files_path.join(uuid.to_string());
+        match compute_file_hash_throttled(&path, max_bytes_per_second).await {
+            Ok((hash, size)) => Ok(Some(CheckedFile { size, hash })),
+            Err(ComputeFileHashError::OpenFileError(err))
+                if err.kind() == tokio::io::ErrorKind::NotFound =>
+            {
+                Ok(None)
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Lists the stored contents; entries not named after a file are left out.
+    pub async fn list_files(&self) -> Result<Vec<StoredFile>, ListFilesError> {
+        let mut entries = tokio::fs::read_dir(&self.files_path)
+            .await
+            .map_err(ListFilesError::ReadDir)?;
+        let mut stored_files = Vec::new();
+
+        while let Some(entry) = entries
+            .next_entry()
+            .await
+            .map_err(ListFilesError::ReadDir)?
+        {
+            let name = entry.file_name();
+            let uuid = match name.to_str().and_then(|name| {
+                Uuid::try_parse(name)
+                    .ok()
+                    .filter(|uuid| uuid.to_string() == name)
+            }) {
+                Some(uuid) => uuid,
+                None => continue,
+            };
+            let metadata = match entry.metadata().await {
+                Ok(metadata) => metadata,
+                // Removed since it was listed.
+                Err(err) if err.kind() == tokio::io::ErrorKind::NotFound => continue,
+                Err(err) => return Err(ListFilesError::ReadFileMetadata(err)),
+            };
+
+            if metadata.is_file() {
+                stored_files.push(StoredFile {
+                    uuid,
+                    changed_at: changed_at(&metadata),
+                });
+            }
+        }
+
+        Ok(stored_files)
+    }
+
+    /// Opens the content of a file for reading.
+    pub async fn open_file(&self, uuid: Uuid) -> Result<File, OpenFileError> {
+        let path = self.files_path.join(uuid.to_string());
+        File::open(&path).await.map_err(OpenFileError::OpenFile)
+    }
+
+    /// Compares the contents of two files byte by byte.
+    pub async fn compare_files(&self, lhs: Uuid, rhs: Uuid) -> Result<bool, OpenFileError> {
+        let mut lhs = BufReader::new(self.open_file(lhs).await?);
+        let mut rhs = BufReader::new(self.open_file(rhs).await?);
+
+        loop {
+            let lhs_chunk = lhs.fill_buf().await.map_err(OpenFileError::ReadFile)?;
+            let rhs_chunk = rhs.fill_buf().await.map_err(OpenFileError::ReadFile)?;
+            let length = lhs_chunk.len().min(rhs_chunk.len());
+
+            if length == 0 {
+                return Ok(lhs_chunk.is_empty() && rhs_chunk.is_empty());
+            }
+
+            if lhs_chunk[..length] != rhs_chunk[..length] {
+                return Ok(false);
+            }
+
+            lhs.consume(length);
+            rhs.consume(length);
+        }
+    }
+
     pub async fn read_file_info(&self, uuid: Uuid) -> Result<FileInfo, ReadFileInfoError> {
         let path = self.files_path.join(uuid.to_string());
         let hash = compute_file_hash(&path);
         let mime = compute_file_mime(&path);
         let hash = hash.await?;
         let mime = mime.await?;
-        Ok(FileInfo { hash, mime })
+
+        // Metadata is a bonus; content that cannot be parsed is stored without it.
+        let system_tags = match extract_system_tags(&path, mime).await {
+            Ok(system_tags) => system_tags,
+            Err(err) => {
+                tracing::warn!("failed to extract system tags of `{}`: {}", uuid, err);
+                Vec::new()
+            }
+        };
+
+        Ok(FileInfo {
+            hash,
+            mime,
+            system_tags,
+        })
     }
+
+    /// Renders and stores the thumbnails of a file in every size; returns the sizes, or nothing
+    /// if there is no preview for its mime type.
+    ///
+    /// Each thumbnail replaces the previous one of its size at once, so readers never see a
+    /// partial one.
+    pub async fn generate_thumbnails(
+        &self,
+        uuid: Uuid,
+        mime: &str,
+    ) -> Result<Option<Vec<ThumbnailSizeDto>>, GenerateThumbnailsError> {
+        let path = self.files_path.join(uuid.to_string());
+        let thumbnails = match render_thumbnails(path, mime).await? {
+            Some(thumbnails) => thumbnails,
+            None => return Ok(None),
+        };
+
+        let mut sizes = Vec::with_capacity(thumbnails.len());
+        for (size, bytes) in thumbnails {
+            let path = self.thumbnail_path(uuid, size);
+            let temp_path = path.with_extension("jpg.tmp");
+            tokio::fs::write(&temp_path, bytes)
+                .await
+                .map_err(GenerateThumbnailsError::WriteThumbnail)?;
+            tokio::fs::rename(&temp_path, &path)
+                .await
+                .map_err(GenerateThumbnailsError::WriteThumbnail)?;
+            sizes.push(size);
+        }
+
+        Ok(Some(sizes))
+    }
+
+    /// Opens a stored thumbnail of a file for reading.
+    pub async fn open_thumbnail(
+        &self,
+        uuid: Uuid,
+        size: ThumbnailSizeDto,
+    ) -> Result<File, OpenFileError> {
+        File::open(self.thumbnail_path(uuid, size))
+            .await
+            .map_err(OpenFileError::OpenFile)
+    }
+
+    /// Removes the thumbnails of a file; missing ones are not an error.
+    pub async fn remove_thumbnails(&self, uuid: Uuid) -> Result<(), RemoveFileError> {
+        for size in ThumbnailSizeDto::ALL {
+            match tokio::fs::remove_file(self.thumbnail_path(uuid, size)).await {
+                Ok(()) => {}
+                Err(err) if err.kind() == tokio::io::ErrorKind::NotFound => {}
+                Err(err) => return Err(RemoveFileError::RemoveFile(err)),
+            }
+        }
+
+        Ok(())
+    }
+
+    fn thumbnail_path(&self, uuid: Uuid, size: ThumbnailSizeDto) -> PathBuf {
+        self.thumbnails_path
+            .join(format!("{}-{}.jpg", uuid, size.file_suffix()))
+    }
+}
+
+/// Tells when a content was last written or linked; a hard link keeps the modification time of
+/// its source, but not its change time. Unknown times are taken as now.
+fn changed_at(metadata: &std::fs::Metadata) -> NaiveDateTime {
+    let modified = metadata.modified().unwrap_or_else(|_| SystemTime::now());
+
+    #[cfg(unix)]
+    let modified = {
+        use std::{
+            os::unix::fs::MetadataExt,
+            time::{Duration, UNIX_EPOCH},
+        };
+
+        let changed = u64::try_from(metadata.ctime())
+            .map(|secs| UNIX_EPOCH + Duration::new(secs, metadata.ctime_nsec() as u32))
+            .unwrap_or_else(|_| SystemTime::now());
+        modified.max(changed)
+    };
+
+    DateTime::<Utc>::from(modified).naive_utc()
+}
+
+/// Tells whether a content has other hard links, such as a kept version or the source of an
+/// import; without a link count, any content is taken as shared.
+fn is_shared(metadata: &std::fs::Metadata) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+
+        metadata.nlink() > 1
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = metadata;
+        true
+    }
+}
+
+/// A content as read again by a check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CheckedFile {
+    pub size: u64,
+    pub hash: u32,
+}
+
+/// A content in the files directory.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct StoredFile {
+    pub uuid: Uuid,
+    /// When the content was last written.
+    pub changed_at: NaiveDateTime,
+}
+
+#[derive(ErrorEnum, Error, Debug)]
+pub enum ListFilesError {
+    #[error("internal server error")]
+    #[status(StatusCode::INTERNAL_SERVER_ERROR)]
+    ReadDir(tokio::io::Error),
+    #[error("internal server error")]
+    #[status(StatusCode::INTERNAL_SERVER_ERROR)]
+    ReadFileMetadata(tokio::io::Error),
 }
 
 #[derive(ErrorEnum, Error, Debug)]
@@ -139,6 +633,23 @@ pub enum WriteFileError {
     #[error("invalid offset; offset is `{offset}`, but file size is `{file_size}`")]
     #[status(StatusCode::UNPROCESSABLE_ENTITY)]
     InvalidOffset { offset: u64, file_size: u64 },
+    #[error(
+        "content of `{size}` bytes exceeds the storage quota; the file may hold at most `{max_size}` bytes"
+    )]
+    #[status(StatusCode::PAYLOAD_TOO_LARGE)]
+    ContentTooLarge { size: u64, max_size: u64 },
+    #[error("storage quota is exhausted; the file may hold at most `{max_size}` bytes")]
+    #[status(StatusCode::INSUFFICIENT_STORAGE)]
+    QuotaExceeded { max_size: u64 },
+    #[error("content of `{size}` bytes exceeds the maximum file size of `{max_size}` bytes")]
+    #[status(StatusCode::PAYLOAD_TOO_LARGE)]
+    FileTooLarge { size: u64, max_size: u64 },
+    #[error("content exceeds the maximum file size of `{max_size}` bytes")]
+    #[status(StatusCode::PAYLOAD_TOO_LARGE)]
+    FileSizeExceeded { max_size: u64 },
+    #[error("content type `{0}` is not allowed")]
+    #[status(StatusCode::UNSUPPORTED_MEDIA_TYPE)]
+    MimeTypeNotAllowed(String),
     #[error("internal server error")]
     #[status(StatusCode::INTERNAL_SERVER_ERROR)]
     ReadFromStream(#[from] Error),
@@ -147,10 +658,62 @@ pub enum WriteFileError {
     WriteToFile(tokio::io::Error),
 }
 
+#[derive(ErrorEnum, Error, Debug)]
+pub enum ImportFileError {
+    #[error("internal server error")]
+    #[status(StatusCode::INTERNAL_SERVER_ERROR)]
+    CopyFile(tokio::io::Error),
+    #[error("internal server error")]
+    #[status(StatusCode::INTERNAL_SERVER_ERROR)]
+    LinkFile(tokio::io::Error),
+    #[error("internal server error")]
+    #[status(StatusCode::INTERNAL_SERVER_ERROR)]
+    ReadFileMetadata(tokio::io::Error),
+}
+
+#[derive(ErrorEnum, Error, Debug)]
+pub enum VersionError {
+    #[error("internal server error")]
+    #[status(StatusCode::INTERNAL_SERVER_ERROR)]
+    LinkFile(tokio::io::Error),
+    #[error("internal server error")]
+    #[status(StatusCode::INTERNAL_SERVER_ERROR)]
+    ReplaceFile(tokio::io::Error),
+}
+
+#[derive(ErrorEnum, Error, Debug)]
+pub enum OpenFileError {
+    #[error("internal server error")]
+    #[status(StatusCode::INTERNAL_SERVER_ERROR)]
+    OpenFile(tokio::io::Error),
+    #[error("internal server error")]
+    #[status(StatusCode::INTERNAL_SERVER_ERROR)]
+    ReadFile(tokio::io::Error),
+}
+
+#[derive(ErrorEnum, Error, Debug)]
+pub enum GenerateThumbnailsError {
+    #[error("internal server error")]
+    #[status(StatusCode::INTERNAL_SERVER_ERROR)]
+    RenderThumbnails(#[from] RenderThumbnailsError),
+    #[error("internal server error")]
+    #[status(StatusCode::INTERNAL_SERVER_ERROR)]
+    WriteThumbnail(tokio::io::Error),
+}
+
+#[derive(ErrorEnum, Error, Debug)]
+pub enum RemoveFileError {
+    #[error("internal server error")]
+    #[status(StatusCode::INTERNAL_SERVER_ERROR)]
+    RemoveFile(tokio::io::Error),
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct FileInfo {
     pub mime: &'static str,
     pub hash: u32,
+    /// Tags extracted from the metadata of the content, all titled with [`SYSTEM_TAG_PREFIX`].
+    pub system_tags: Vec<CreateFileTagDto>,
 }
 
 #[derive(ErrorEnum, Error, Debug)]
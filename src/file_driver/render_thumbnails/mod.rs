@@ -0,0 +1,153 @@
+use crate::schema::dto_in::ThumbnailSizeDto;
+use image::{codecs::jpeg::JpegEncoder, DynamicImage, ImageFormat, RgbImage};
+use std::{ffi::OsStr, path::PathBuf, process::Stdio};
+use thiserror::Error;
+use tokio::process::Command;
+
+mod pdf;
+mod raster;
+mod video;
+
+/// Quality of the JPEG encoding of thumbnails.
+const JPEG_QUALITY: u8 = 80;
+
+/// Time an external tool has to render a preview.
+const TOOL_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(60);
+
+impl ThumbnailSizeDto {
+    pub const ALL: [Self; 3] = [Self::Small, Self::Medium, Self::Large];
+
+    /// Returns the largest width or height of a thumbnail of this size.
+    pub fn pixels(self) -> u32 {
+        match self {
+            Self::Small => 128,
+            Self::Medium => 512,
+            Self::Large => 1024,
+        }
+    }
+
+    /// Returns the name of this size in the file names of thumbnails.
+    pub fn file_suffix(self) -> &'static str {
+        match self {
+            Self::Small => "small",
+            Self::Medium => "medium",
+            Self::Large => "large",
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+#[allow(clippy::enum_variant_names)]
+pub enum RenderThumbnailsError {
+    #[error("failed to read file: {0}")]
+    ReadFileError(#[from] std::io::Error),
+    #[error("failed to decode image: {0}")]
+    ImageError(#[from] image::ImageError),
+    #[error("`{0}` is not installed; set `{1}` to its path")]
+    ToolNotFoundError(String, &'static str),
+    #[error("`{0}` failed: {1}")]
+    ToolError(String, String),
+    #[error("`{0}` did not finish in time")]
+    ToolTimeoutError(String),
+    #[error("failed to join task: {0}")]
+    JoinError(#[from] tokio::task::JoinError),
+}
+
+/// Renders the thumbnails of a file in every size, encoded as JPEG, or nothing if there is no
+/// preview for its mime type.
+///
+/// Images are decoded in process; the first page of a PDF and a frame of a video are rendered by
+/// `pdftoppm` and `ffmpeg`.
+pub async fn render_thumbnails(
+    path: impl Into<PathBuf>,
+    mime: &str,
+) -> Result<Option<Vec<(ThumbnailSizeDto, Vec<u8>)>>, RenderThumbnailsError> {
+    let path = path.into();
+    let preview = match mime {
+        "application/pdf" => {
+            let png = pdf::render(&path).await?;
+            tokio::task::spawn_blocking(move || image::load_from_memory(&png)).await??
+        }
+        mime if mime.starts_with("video/") => {
+            let png = video::render(&path).await?;
+            tokio::task::spawn_blocking(move || image::load_from_memory(&png)).await??
+        }
+        mime => match ImageFormat::from_mime_type(mime) {
+            Some(format) if format.reading_enabled() => {
+                tokio::task::spawn_blocking(move || raster::render(&path, format)).await??
+            }
+            _ => return Ok(None),
+        },
+    };
+
+    let thumbnails = tokio::task::spawn_blocking(move || {
+        ThumbnailSizeDto::ALL
+            .into_iter()
+            .map(|size| Ok((size, encode_thumbnail(&preview, size.pixels())?)))
+            .collect::<Result<Vec<_>, RenderThumbnailsError>>()
+    })
+    .await??;
+
+    Ok(Some(thumbnails))
+}
+
+/// Scales a preview down to fit in a square, never up, and encodes it as JPEG over white.
+fn encode_thumbnail(preview: &DynamicImage, pixels: u32) -> Result<Vec<u8>, RenderThumbnailsError> {
+    let thumbnail = if preview.width() <= pixels && preview.height() <= pixels {
+        preview.to_rgba8()
+    } else {
+        preview.thumbnail(pixels, pixels).to_rgba8()
+    };
+
+    // JPEG has no alpha channel, so transparent areas are blended over white.
+    let flattened = RgbImage::from_fn(thumbnail.width(), thumbnail.height(), |x, y| {
+        let [r, g, b, a] = thumbnail.get_pixel(x, y).0;
+        let blend =
+            |channel: u8| ((channel as u32 * a as u32 + 255 * (255 - a as u32)) / 255) as u8;
+        image::Rgb([blend(r), blend(g), blend(b)])
+    });
+
+    let mut bytes = Vec::new();
+    JpegEncoder::new_with_quality(&mut bytes, JPEG_QUALITY).encode_image(&flattened)?;
+
+    Ok(bytes)
+}
+
+/// Runs an external tool named by an env var, or by its default name, and returns its output.
+async fn run_tool(
+    env_var: &'static str,
+    default_program: &str,
+    args: &[&OsStr],
+) -> Result<Vec<u8>, RenderThumbnailsError> {
+    let program = std::env::var(env_var).unwrap_or_else(|_| default_program.to_owned());
+    let child = Command::new(&program)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn();
+    let child = match child {
+        Ok(child) => child,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+            return Err(RenderThumbnailsError::ToolNotFoundError(program, env_var));
+        }
+        Err(err) => return Err(err.into()),
+    };
+
+    let output = tokio::time::timeout(TOOL_TIMEOUT, child.wait_with_output())
+        .await
+        .map_err(|_| RenderThumbnailsError::ToolTimeoutError(program.clone()))??;
+
+    if !output.status.success() || output.stdout.is_empty() {
+        let stderr = String::from_utf8_lossy(&output.stderr).trim().to_owned();
+        let reason = if stderr.is_empty() {
+            output.status.to_string()
+        } else {
+            stderr
+        };
+        return Err(RenderThumbnailsError::ToolError(program, reason));
+    }
+
+    Ok(output.stdout)
+}
@@ -0,0 +1,25 @@
+use super::{run_tool, RenderThumbnailsError};
+use crate::schema::dto_in::ThumbnailSizeDto;
+use std::{ffi::OsStr, path::Path};
+
+/// Renders the first page of a document as PNG with `pdftoppm`, scaled to the largest
+/// thumbnail; without an output root, the page is written to the standard output.
+pub async fn render(path: &Path) -> Result<Vec<u8>, RenderThumbnailsError> {
+    let scale = ThumbnailSizeDto::Large.pixels().to_string();
+    run_tool(
+        "PDFTOPPM_PATH",
+        "pdftoppm",
+        &[
+            OsStr::new("-f"),
+            OsStr::new("1"),
+            OsStr::new("-l"),
+            OsStr::new("1"),
+            OsStr::new("-singlefile"),
+            OsStr::new("-png"),
+            OsStr::new("-scale-to"),
+            OsStr::new(&scale),
+            path.as_os_str(),
+        ],
+    )
+    .await
+}
@@ -0,0 +1,29 @@
+use super::RenderThumbnailsError;
+use exif::{In, Tag};
+use image::{io::Reader, DynamicImage, ImageFormat};
+use std::{fs::File, io::BufReader, path::Path};
+
+/// Decodes an image, turned upright according to its EXIF orientation.
+pub fn render(path: &Path, format: ImageFormat) -> Result<DynamicImage, RenderThumbnailsError> {
+    let image = Reader::with_format(BufReader::new(File::open(path)?), format).decode()?;
+
+    Ok(match read_orientation(path) {
+        Some(2) => image.fliph(),
+        Some(3) => image.rotate180(),
+        Some(4) => image.flipv(),
+        Some(5) => image.rotate90().fliph(),
+        Some(6) => image.rotate90(),
+        Some(7) => image.rotate270().fliph(),
+        Some(8) => image.rotate270(),
+        _ => image,
+    })
+}
+
+/// Reads the EXIF orientation of an image; images without one are already upright.
+fn read_orientation(path: &Path) -> Option<u32> {
+    let mut reader = BufReader::new(File::open(path).ok()?);
+    let exif = exif::Reader::new().read_from_container(&mut reader).ok()?;
+    exif.get_field(Tag::Orientation, In::PRIMARY)?
+        .value
+        .get_uint(0)
+}
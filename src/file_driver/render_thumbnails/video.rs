@@ -0,0 +1,27 @@
+use super::{run_tool, RenderThumbnailsError};
+use std::{ffi::OsStr, path::Path};
+
+/// Renders a poster frame of a video as PNG with `ffmpeg`, picked by its `thumbnail` filter as
+/// the most representative of the first frames.
+pub async fn render(path: &Path) -> Result<Vec<u8>, RenderThumbnailsError> {
+    run_tool(
+        "FFMPEG_PATH",
+        "ffmpeg",
+        &[
+            OsStr::new("-v"),
+            OsStr::new("error"),
+            OsStr::new("-i"),
+            path.as_os_str(),
+            OsStr::new("-vf"),
+            OsStr::new("thumbnail"),
+            OsStr::new("-frames:v"),
+            OsStr::new("1"),
+            OsStr::new("-f"),
+            OsStr::new("image2pipe"),
+            OsStr::new("-c:v"),
+            OsStr::new("png"),
+            OsStr::new("-"),
+        ],
+    )
+    .await
+}
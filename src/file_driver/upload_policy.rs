@@ -0,0 +1,119 @@
+use super::WriteFileError;
+
+/// Number of leading bytes of a content its type is inferred from.
+pub const MIME_SNIFF_LENGTH: usize = 8192;
+
+/// Type of a content whose type cannot be inferred from its leading bytes.
+pub const UNKNOWN_MIME: &str = "application/octet-stream";
+
+/// What uploads may store. The server-wide policy is set by `UPLOAD_MAX_FILE_SIZE` (bytes),
+/// `UPLOAD_ALLOWED_MIME_TYPES` and `UPLOAD_DENIED_MIME_TYPES` (comma-separated, such as
+/// `image/png,video/*`), and each collection may override any of them.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UploadPolicy {
+    pub max_file_size: Option<u64>,
+    /// Types accepted, or any type when absent; denied types are refused even when listed.
+    pub allowed_mime_types: Option<Vec<String>>,
+    pub denied_mime_types: Vec<String>,
+}
+
+impl UploadPolicy {
+    pub fn from_env() -> Self {
+        let max_file_size = match std::env::var("UPLOAD_MAX_FILE_SIZE") {
+            Ok(size) => size.parse::<u64>().map(Some).unwrap_or_else(|_| {
+                tracing::warn!("env var `UPLOAD_MAX_FILE_SIZE` is not a number; not limiting");
+                None
+            }),
+            Err(_) => None,
+        };
+        let mime_types = |name: &str| {
+            std::env::var(name).ok().map(|mime_types| {
+                mime_types
+                    .split(',')
+                    .map(|mime_type| mime_type.trim().to_ascii_lowercase())
+                    .filter(|mime_type| !mime_type.is_empty())
+                    .collect::<Vec<_>>()
+            })
+        };
+
+        Self {
+            max_file_size,
+            allowed_mime_types: mime_types("UPLOAD_ALLOWED_MIME_TYPES"),
+            denied_mime_types: mime_types("UPLOAD_DENIED_MIME_TYPES").unwrap_or_default(),
+        }
+    }
+
+    /// Returns this policy with the settings a collection overrides replaced.
+    pub fn override_with(
+        &self,
+        max_file_size: Option<u64>,
+        allowed_mime_types: Option<Vec<String>>,
+        denied_mime_types: Option<Vec<String>>,
+    ) -> Self {
+        Self {
+            max_file_size: max_file_size.or(self.max_file_size),
+            allowed_mime_types: allowed_mime_types.or_else(|| self.allowed_mime_types.clone()),
+            denied_mime_types: denied_mime_types.unwrap_or_else(|| self.denied_mime_types.clone()),
+        }
+    }
+
+    /// Whether the type of a content has to be known to apply the policy.
+    pub fn restricts_mime_types(&self) -> bool {
+        self.allowed_mime_types.is_some() || !self.denied_mime_types.is_empty()
+    }
+
+    /// Checks a content already stored, of `size` bytes and of type `mime` once processed,
+    /// against the policy; the type of a content not processed yet is not checked.
+    pub fn check_stored_content(
+        &self,
+        mime: Option<&str>,
+        size: u64,
+    ) -> Result<(), WriteFileError> {
+        if let Some(max_size) = self.max_file_size.filter(|max_size| *max_size < size) {
+            return Err(WriteFileError::FileTooLarge { size, max_size });
+        }
+
+        if let Some(mime) = mime.filter(|mime| !self.accepts_mime_type(mime)) {
+            return Err(WriteFileError::MimeTypeNotAllowed(mime.to_owned()));
+        }
+
+        Ok(())
+    }
+
+    pub fn accepts_mime_type(&self, mime: &str) -> bool {
+        let matches = |patterns: &[String]| {
+            patterns
+                .iter()
+                .any(|pattern| mime_type_matches(pattern, mime))
+        };
+
+        !matches(&self.denied_mime_types)
+            && self.allowed_mime_types.as_deref().map_or(true, matches)
+    }
+}
+
+/// Whether a pattern is a valid type, such as `image/png`, or a whole top-level type, such as
+/// `image/*`.
+pub fn is_mime_type_pattern(pattern: &str) -> bool {
+    let is_token = |part: &str| {
+        !part.is_empty()
+            && part
+                .bytes()
+                .all(|byte| byte.is_ascii_alphanumeric() || b"!#$&-^_.+".contains(&byte))
+    };
+
+    match pattern.split_once('/') {
+        Some((type_, "*")) => is_token(type_),
+        Some((type_, subtype)) => is_token(type_) && is_token(subtype),
+        None => false,
+    }
+}
+
+fn mime_type_matches(pattern: &str, mime: &str) -> bool {
+    match pattern.strip_suffix("/*") {
+        Some(type_) => mime.split_once('/').map_or(false, |(mime_type, _)| {
+            mime_type.eq_ignore_ascii_case(type_)
+        }),
+        None => pattern.eq_ignore_ascii_case(mime),
+    }
+}
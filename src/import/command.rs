@@ -0,0 +1,84 @@
+use super::{ImportRunner, PathRule};
+use crate::{audit::AuditContext, schema::dto_in::ImportModeDto};
+use uuid::Uuid;
+
+const USAGE: &str = "usage:
+    poly-tag import <root> [--rule <pattern>]... [--hard-link]
+    poly-tag import --resume <uuid>";
+
+/// Runs `poly-tag import ...` in the foreground, printing usage and exiting on invalid arguments.
+///
+/// Unlike imports through the API, the root is not confined to `IMPORT_ROOT`; whoever runs the
+/// command already has access to the server's filesystem.
+pub async fn run_command(args: &[String], import_runner: ImportRunner) {
+    let mut root = None;
+    let mut rules = Vec::new();
+    let mut mode = ImportModeDto::Copy;
+    let mut resume = None;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--rule" => match args.next() {
+                Some(rule) => rules.push(rule.clone()),
+                None => exit_with_usage("`--rule` requires a pattern"),
+            },
+            "--hard-link" => mode = ImportModeDto::HardLink,
+            "--resume" => match args.next().map(|uuid| Uuid::parse_str(uuid)) {
+                Some(Ok(uuid)) => resume = Some(uuid),
+                _ => exit_with_usage("`--resume` requires the uuid of an import"),
+            },
+            arg if arg.starts_with("--") => exit_with_usage(&format!("unknown option `{}`", arg)),
+            arg if root.is_none() => root = Some(arg.to_owned()),
+            arg => exit_with_usage(&format!("unexpected argument `{}`", arg)),
+        }
+    }
+
+    let job = match (root, resume) {
+        (Some(root), None) => {
+            for rule in &rules {
+                if let Err(err) = PathRule::parse(rule) {
+                    exit_with_usage(&format!("invalid rule `{}`: {}", rule, err));
+                }
+            }
+
+            let root = match tokio::fs::canonicalize(&root).await {
+                Ok(root) if root.is_dir() => root,
+                _ => exit_with_usage(&format!("directory `{}` does not exist", root)),
+            };
+            let root = match root.to_str() {
+                Some(root) => root.to_owned(),
+                None => exit_with_usage("the root must be valid UTF-8"),
+            };
+
+            let context = AuditContext {
+                request_id: Uuid::new_v4().to_string(),
+                actor: Some("cli".to_owned()),
+            };
+            import_runner
+                .create_job(&root, &rules, mode, &context, true)
+                .await
+                .expect("failed to create import")
+        }
+        (None, Some(uuid)) if rules.is_empty() && mode == ImportModeDto::Copy => {
+            match import_runner.resume_job(uuid).await {
+                Ok(Some(job)) => job,
+                Ok(None) => exit_with_usage(&format!(
+                    "import `{}` does not exist, is finished or is running elsewhere",
+                    uuid
+                )),
+                Err(err) => panic!("failed to resume import: {:#?}", err),
+            }
+        }
+        (None, None) => exit_with_usage("a root or `--resume` is required"),
+        _ => exit_with_usage("`--resume` cannot be combined with a root or options"),
+    };
+
+    println!("import `{}` started", job.uuid);
+    import_runner.run_job(job).await;
+}
+
+fn exit_with_usage(message: &str) -> ! {
+    eprintln!("error: {}\n\n{}", message, USAGE);
+    std::process::exit(2);
+}
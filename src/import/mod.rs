@@ -0,0 +1,645 @@
+use crate::{
+    audit::{enum_name, parse_enum_name, AuditContext},
+    db::{schema::import_jobs, DBPool},
+    file_driver::{FileDriver, ImportFileError, ReadFileInfoError},
+    quota::QuotaError,
+    route_files::file_service::insert_stored_file,
+    schema::{dto_in::ImportModeDto, dto_out::ImportStatusDto},
+    search::{FileDocument, SearchBackend},
+};
+use chrono::{Duration, Utc};
+use diesel::{alias, dsl::now, prelude::*};
+use diesel_async::{scoped_futures::ScopedFutureExt, AsyncConnection, RunQueryDsl};
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+use uuid::Uuid;
+
+mod command;
+mod path_rule;
+
+pub use command::*;
+pub use path_rule::*;
+
+/// Interval between two looks for imports to run.
+const POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// Interval between two heartbeats of a running import.
+const HEARTBEAT_INTERVAL: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// Time after which a running import without heartbeat is considered abandoned, and resumed by
+/// another worker.
+const STALE_HEARTBEAT_SECONDS: i64 = 120;
+
+/// Number of processed files between two progress logs.
+const PROGRESS_LOG_INTERVAL: i64 = 1000;
+
+/// Maximum length of the error recorded for an import.
+const MAX_ERROR_LENGTH: usize = 1024;
+
+#[derive(thiserror::Error, Debug)]
+pub enum ImportError {
+    #[error("{0}")]
+    PoolError(#[from] diesel_async::pooled_connection::deadpool::PoolError),
+    #[error("{0}")]
+    DieselError(#[from] diesel::result::Error),
+    #[error("{0}")]
+    PathRuleError(#[from] PathRuleError),
+    #[error("failed to read import root `{0}`: {1}")]
+    ReadRoot(String, std::io::Error),
+}
+
+/// Why a single file could not be imported; the import goes on with the next file.
+#[derive(thiserror::Error, Debug)]
+enum ImportFileFailure {
+    #[error("failed to store file: {0:?}")]
+    ImportFile(#[from] ImportFileError),
+    #[error("failed to read file info: {0:?}")]
+    ReadFileInfo(#[from] ReadFileInfoError),
+    #[error("failed to read directory: {0}")]
+    ReadDir(std::io::Error),
+    #[error("path is not valid UTF-8")]
+    NonUtf8Path,
+    #[error("{0}")]
+    Quota(#[from] QuotaError),
+}
+
+/// An import claimed by a runner.
+#[derive(Queryable, Debug, Clone)]
+pub struct ImportJob {
+    id: i32,
+    pub uuid: Uuid,
+    root: String,
+    rules: Vec<String>,
+    mode: String,
+    total_count: Option<i64>,
+    processed_count: i64,
+    last_path: Option<String>,
+    request_id: String,
+    actor: Option<String>,
+}
+
+type ImportJobColumns = (
+    import_jobs::id,
+    import_jobs::uuid,
+    import_jobs::root,
+    import_jobs::rules,
+    import_jobs::mode,
+    import_jobs::total_count,
+    import_jobs::processed_count,
+    import_jobs::last_path,
+    import_jobs::request_id,
+    import_jobs::actor,
+);
+
+fn import_job_columns() -> ImportJobColumns {
+    (
+        import_jobs::id,
+        import_jobs::uuid,
+        import_jobs::root,
+        import_jobs::rules,
+        import_jobs::mode,
+        import_jobs::total_count,
+        import_jobs::processed_count,
+        import_jobs::last_path,
+        import_jobs::request_id,
+        import_jobs::actor,
+    )
+}
+
+/// Copies directory trees of the server into the storage, as files tagged after their paths.
+///
+/// An import walks its root in a fixed order and records the last processed path along with each
+/// file, so an interrupted import resumes right after it.
+#[derive(Clone)]
+pub struct ImportRunner {
+    db_pool: DBPool,
+    file_driver: FileDriver,
+    search_backend: Arc<dyn SearchBackend>,
+}
+
+impl ImportRunner {
+    pub fn new(
+        db_pool: DBPool,
+        file_driver: FileDriver,
+        search_backend: Arc<dyn SearchBackend>,
+    ) -> Self {
+        Self {
+            db_pool,
+            file_driver,
+            search_backend,
+        }
+    }
+
+    /// Spawns a background task that runs pending imports, and resumes abandoned ones.
+    pub fn spawn_worker(self) {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(POLL_INTERVAL);
+
+            loop {
+                interval.tick().await;
+
+                loop {
+                    match self.claim_job().await {
+                        Ok(Some(job)) => self.run_job(job).await,
+                        Ok(None) => break,
+                        Err(err) => {
+                            tracing::error!("failed to claim an import: {:#?}", err);
+                            break;
+                        }
+                    }
+                }
+            }
+        });
+    }
+
+    /// Claims an import that is pending or abandoned.
+    pub async fn claim_job(&self) -> Result<Option<ImportJob>, ImportError> {
+        let db_conn = &mut self.db_pool.get().await?;
+        let stale_threshold = (Utc::now() - Duration::seconds(STALE_HEARTBEAT_SECONDS)).naive_utc();
+        let running = enum_name(&ImportStatusDto::Running);
+
+        let claimable = alias!(import_jobs as claimable);
+        let claimable_ids = claimable
+            .select(claimable.field(import_jobs::id))
+            .filter(
+                claimable
+                    .field(import_jobs::status)
+                    .eq(enum_name(&ImportStatusDto::Pending))
+                    .or(claimable.field(import_jobs::status).eq(&running).and(
+                        claimable
+                            .field(import_jobs::heartbeat_at)
+                            .lt(stale_threshold),
+                    )),
+            )
+            .order(claimable.field(import_jobs::created_at).asc())
+            .limit(1)
+            .for_update()
+            .skip_locked();
+
+        let job = diesel::update(import_jobs::table)
+            .filter(import_jobs::id.eq_any(claimable_ids))
+            .set((
+                import_jobs::status.eq(&running),
+                import_jobs::heartbeat_at.eq(now),
+            ))
+            .returning(import_job_columns())
+            .get_result::<ImportJob>(db_conn)
+            .await
+            .optional()?;
+
+        Ok(job)
+    }
+
+    /// Claims a given import that did not complete, even if it failed or was cancelled.
+    ///
+    /// An import that is running elsewhere is left alone.
+    pub async fn resume_job(&self, uuid: Uuid) -> Result<Option<ImportJob>, ImportError> {
+        let db_conn = &mut self.db_pool.get().await?;
+        let stale_threshold = (Utc::now() - Duration::seconds(STALE_HEARTBEAT_SECONDS)).naive_utc();
+        let running = enum_name(&ImportStatusDto::Running);
+
+        let job = diesel::update(import_jobs::table)
+            .filter(import_jobs::uuid.eq(uuid))
+            .filter(import_jobs::status.ne(enum_name(&ImportStatusDto::Completed)))
+            .filter(
+                import_jobs::status
+                    .ne(&running)
+                    .or(import_jobs::heartbeat_at.lt(stale_threshold)),
+            )
+            .set((
+                import_jobs::status.eq(&running),
+                import_jobs::heartbeat_at.eq(now),
+                import_jobs::finished_at.eq(None::<chrono::NaiveDateTime>),
+            ))
+            .returning(import_job_columns())
+            .get_result::<ImportJob>(db_conn)
+            .await
+            .optional()?;
+
+        Ok(job)
+    }
+
+    /// Creates an import, either queued for a worker or already claimed by the caller.
+    pub async fn create_job(
+        &self,
+        root: &str,
+        rules: &[String],
+        mode: ImportModeDto,
+        context: &AuditContext,
+        claimed: bool,
+    ) -> Result<ImportJob, ImportError> {
+        let status = if claimed {
+            ImportStatusDto::Running
+        } else {
+            ImportStatusDto::Pending
+        };
+
+        let db_conn = &mut self.db_pool.get().await?;
+        let job = diesel::insert_into(import_jobs::table)
+            .values((
+                import_jobs::root.eq(root),
+                import_jobs::rules.eq(rules),
+                import_jobs::mode.eq(enum_name(&mode)),
+                import_jobs::status.eq(enum_name(&status)),
+                import_jobs::request_id.eq(&context.request_id),
+                import_jobs::actor.eq(&context.actor),
+                import_jobs::heartbeat_at.eq(claimed.then(|| Utc::now().naive_utc())),
+            ))
+            .returning(import_job_columns())
+            .get_result::<ImportJob>(db_conn)
+            .await?;
+
+        Ok(job)
+    }
+
+    /// Runs a claimed import until it completes, fails or is cancelled.
+    pub async fn run_job(&self, job: ImportJob) {
+        tracing::info!("running import `{}` of `{}`", job.uuid, job.root);
+
+        let heartbeat = {
+            let db_pool = self.db_pool.clone();
+            let id = job.id;
+            tokio::spawn(async move {
+                let mut interval = tokio::time::interval(HEARTBEAT_INTERVAL);
+                loop {
+                    interval.tick().await;
+                    if let Err(err) = beat(&db_pool, id).await {
+                        tracing::warn!("failed to record import heartbeat: {:#?}", err);
+                    }
+                }
+            })
+        };
+        let result = self.import(&job).await;
+        heartbeat.abort();
+
+        let (status, error) = match result {
+            Ok(true) => (ImportStatusDto::Completed, None),
+            Ok(false) => {
+                tracing::info!("import `{}` was cancelled", job.uuid);
+                return;
+            }
+            Err(err) => {
+                tracing::error!("import `{}` failed: {:#?}", job.uuid, err);
+                (ImportStatusDto::Failed, Some(err.to_string()))
+            }
+        };
+
+        let finished = async {
+            let db_conn = &mut self.db_pool.get().await?;
+            let update = diesel::update(import_jobs::table)
+                .filter(import_jobs::id.eq(job.id))
+                .filter(import_jobs::status.eq(enum_name(&ImportStatusDto::Running)));
+            match error {
+                Some(error) => {
+                    update
+                        .set((
+                            import_jobs::status.eq(enum_name(&status)),
+                            import_jobs::last_error.eq(truncate_error(error)),
+                            import_jobs::finished_at.eq(now.nullable()),
+                        ))
+                        .execute(db_conn)
+                        .await?
+                }
+                None => {
+                    update
+                        .set((
+                            import_jobs::status.eq(enum_name(&status)),
+                            import_jobs::finished_at.eq(now.nullable()),
+                        ))
+                        .execute(db_conn)
+                        .await?
+                }
+            };
+
+            Ok::<_, ImportError>(())
+        };
+        if let Err(err) = finished.await {
+            tracing::error!("failed to finish import `{}`: {:#?}", job.uuid, err);
+        } else {
+            tracing::info!("import `{}` is {}", job.uuid, enum_name(&status));
+        }
+    }
+
+    /// Imports the files of a job; returns whether the job ran to its end, or was cancelled.
+    async fn import(&self, job: &ImportJob) -> Result<bool, ImportError> {
+        let rules = job
+            .rules
+            .iter()
+            .map(|rule| PathRule::parse(rule))
+            .collect::<Result<Vec<_>, _>>()?;
+        let mode = parse_enum_name::<ImportModeDto>(job.mode.clone());
+        let root = PathBuf::from(&job.root);
+        let context = AuditContext {
+            request_id: job.request_id.clone(),
+            actor: job.actor.clone(),
+        };
+
+        tokio::fs::metadata(&root)
+            .await
+            .map_err(|err| ImportError::ReadRoot(job.root.clone(), err))?;
+
+        if job.total_count.is_none() {
+            let mut walker = Walker::new(&root, None)
+                .await
+                .map_err(|err| ImportError::ReadRoot(job.root.clone(), err))?;
+            let mut total_count = 0i64;
+            while let Some(entry) = walker.next().await {
+                if entry.is_ok() {
+                    total_count += 1;
+                }
+            }
+
+            let db_conn = &mut self.db_pool.get().await?;
+            diesel::update(import_jobs::table.find(job.id))
+                .set(import_jobs::total_count.eq(total_count))
+                .execute(db_conn)
+                .await?;
+            tracing::info!("import `{}` has {} files", job.uuid, total_count);
+        }
+
+        let mut walker = Walker::new(&root, job.last_path.as_deref().map(Path::new))
+            .await
+            .map_err(|err| ImportError::ReadRoot(job.root.clone(), err))?;
+        let mut processed_count = job.processed_count;
+
+        while let Some(entry) = walker.next().await {
+            let outcome = match entry {
+                Ok(relative_path) => match relative_path.to_str() {
+                    Some(relative_path) => {
+                        self.import_file(job, &root, relative_path, &rules, mode, &context)
+                            .await?
+                    }
+                    None => {
+                        let error = ImportFileFailure::NonUtf8Path;
+                        self.record_failure(job, None, &relative_path, error)
+                            .await?
+                    }
+                },
+                Err((relative_path, err)) => {
+                    let error = ImportFileFailure::ReadDir(err);
+                    self.record_failure(job, None, &relative_path, error)
+                        .await?
+                }
+            };
+            if !outcome {
+                return Ok(false);
+            }
+
+            processed_count += 1;
+            if processed_count % PROGRESS_LOG_INTERVAL == 0 {
+                tracing::info!("import `{}` processed {} files", job.uuid, processed_count);
+            }
+        }
+
+        Ok(true)
+    }
+
+    /// Imports a single file; returns whether the job is still running.
+    async fn import_file(
+        &self,
+        job: &ImportJob,
+        root: &Path,
+        relative_path: &str,
+        rules: &[PathRule],
+        mode: ImportModeDto,
+        context: &AuditContext,
+    ) -> Result<bool, ImportError> {
+        let segments = relative_path.split('/').collect::<Vec<_>>();
+        let name = segments.last().copied().unwrap_or_default();
+        let file_tags = rules
+            .iter()
+            .find_map(|rule| rule.apply(&segments))
+            .unwrap_or_default();
+        let file_uuid = Uuid::new_v4();
+
+        let stored = async {
+            let file_size = self
+                .file_driver
+                .import_file(file_uuid, &root.join(relative_path), mode)
+                .await?;
+            let file_info = self.file_driver.read_file_info(file_uuid).await?;
+            Ok::<_, ImportFileFailure>((file_size, file_info))
+        }
+        .await;
+        let (file_size, file_info) = match stored {
+            Ok(stored) => stored,
+            Err(err) => {
+                return self
+                    .record_failure(job, Some(file_uuid), Path::new(relative_path), err)
+                    .await;
+            }
+        };
+
+        let db_conn = &mut self.db_pool.get().await?;
+        let item = db_conn
+            .transaction(|db_conn| {
+                async move {
+                    let count = diesel::update(import_jobs::table)
+                        .filter(import_jobs::id.eq(job.id))
+                        .filter(import_jobs::status.eq(enum_name(&ImportStatusDto::Running)))
+                        .set((
+                            import_jobs::processed_count.eq(import_jobs::processed_count + 1),
+                            import_jobs::imported_count.eq(import_jobs::imported_count + 1),
+                            import_jobs::last_path.eq(relative_path),
+                        ))
+                        .execute(db_conn)
+                        .await?;
+                    if count == 0 {
+                        return Ok(None);
+                    }
+
+                    let item = insert_stored_file(
+                        db_conn, file_uuid, name, &file_info, file_size, &file_tags, context,
+                    )
+                    .await?;
+
+                    Ok::<_, QuotaError>(Some(item))
+                }
+                .scope_boxed()
+            })
+            .await;
+
+        let item = match item {
+            Ok(Some(item)) => item,
+            Ok(None) => {
+                remove_stored_file(&self.file_driver, file_uuid).await;
+                return Ok(false);
+            }
+            Err(QuotaError::DieselError(err)) => {
+                remove_stored_file(&self.file_driver, file_uuid).await;
+                return Err(err.into());
+            }
+            Err(err) => {
+                return self
+                    .record_failure(job, Some(file_uuid), Path::new(relative_path), err.into())
+                    .await;
+            }
+        };
+
+        if let Err(err) = self
+            .search_backend
+            .index_file(&FileDocument {
+                uuid: item.uuid,
+                name: item.name,
+            })
+            .await
+        {
+            tracing::error!("failed to index imported file `{}`: {:#?}", item.uuid, err);
+        }
+
+        Ok(true)
+    }
+
+    /// Records that a file could not be imported; returns whether the job is still running.
+    async fn record_failure(
+        &self,
+        job: &ImportJob,
+        stored_uuid: Option<Uuid>,
+        relative_path: &Path,
+        error: ImportFileFailure,
+    ) -> Result<bool, ImportError> {
+        tracing::warn!(
+            "import `{}` failed to import `{}`: {}",
+            job.uuid,
+            relative_path.display(),
+            error
+        );
+
+        let db_conn = &mut self.db_pool.get().await?;
+        let error = truncate_error(format!("`{}`: {}", relative_path.display(), error));
+        // Paths that are not valid UTF-8 cannot be recorded, so the position stays before them.
+        let count = match relative_path.to_str() {
+            Some(relative_path) => {
+                diesel::update(import_jobs::table)
+                    .filter(import_jobs::id.eq(job.id))
+                    .filter(import_jobs::status.eq(enum_name(&ImportStatusDto::Running)))
+                    .set((
+                        import_jobs::processed_count.eq(import_jobs::processed_count + 1),
+                        import_jobs::failed_count.eq(import_jobs::failed_count + 1),
+                        import_jobs::last_path.eq(relative_path),
+                        import_jobs::last_error.eq(error),
+                    ))
+                    .execute(db_conn)
+                    .await?
+            }
+            None => {
+                diesel::update(import_jobs::table)
+                    .filter(import_jobs::id.eq(job.id))
+                    .filter(import_jobs::status.eq(enum_name(&ImportStatusDto::Running)))
+                    .set((
+                        import_jobs::processed_count.eq(import_jobs::processed_count + 1),
+                        import_jobs::failed_count.eq(import_jobs::failed_count + 1),
+                        import_jobs::last_error.eq(error),
+                    ))
+                    .execute(db_conn)
+                    .await?
+            }
+        };
+
+        if let Some(stored_uuid) = stored_uuid {
+            remove_stored_file(&self.file_driver, stored_uuid).await;
+        }
+
+        Ok(count != 0)
+    }
+}
+
+/// Removes the content stored for a file that was not imported after all.
+async fn remove_stored_file(file_driver: &FileDriver, uuid: Uuid) {
+    if let Err(err) = file_driver.remove_file(uuid).await {
+        tracing::error!(
+            "failed to remove the contents of file `{}`: {:#?}",
+            uuid,
+            err
+        );
+    }
+}
+
+async fn beat(db_pool: &DBPool, id: i32) -> Result<(), ImportError> {
+    let db_conn = &mut db_pool.get().await?;
+    diesel::update(import_jobs::table)
+        .filter(import_jobs::id.eq(id))
+        .filter(import_jobs::status.eq(enum_name(&ImportStatusDto::Running)))
+        .set(import_jobs::heartbeat_at.eq(now))
+        .execute(db_conn)
+        .await?;
+
+    Ok(())
+}
+
+fn truncate_error(error: String) -> String {
+    error.chars().take(MAX_ERROR_LENGTH).collect()
+}
+
+/// Walks the regular files under a root in the order of their paths, after an optional path.
+///
+/// Entries of each directory are visited sorted by name, so paths come in the order of
+/// [`Path::cmp`], which compares them segment by segment. Symbolic links are not followed.
+struct Walker {
+    root: PathBuf,
+    after: Option<PathBuf>,
+    /// Paths left to visit, relative to the root, the next one last.
+    pending: Vec<PathBuf>,
+}
+
+impl Walker {
+    async fn new(root: &Path, after: Option<&Path>) -> std::io::Result<Self> {
+        let mut walker = Self {
+            root: root.to_owned(),
+            after: after.map(|after| after.to_owned()),
+            pending: Vec::new(),
+        };
+        walker.push_children(Path::new("")).await?;
+
+        Ok(walker)
+    }
+
+    /// Returns the path of the next file, or the path of a directory that could not be read.
+    async fn next(&mut self) -> Option<Result<PathBuf, (PathBuf, std::io::Error)>> {
+        while let Some(path) = self.pending.pop() {
+            let metadata = match tokio::fs::symlink_metadata(self.root.join(&path)).await {
+                Ok(metadata) => metadata,
+                Err(err) => return Some(Err((path, err))),
+            };
+
+            if metadata.is_dir() {
+                // A directory entirely before the resume position is skipped at once.
+                let is_done = self
+                    .after
+                    .as_ref()
+                    .is_some_and(|after| path < *after && !after.starts_with(&path));
+                if is_done {
+                    continue;
+                }
+
+                if let Err(err) = self.push_children(&path).await {
+                    return Some(Err((path, err)));
+                }
+            } else if metadata.is_file() {
+                if self.after.as_ref().is_some_and(|after| path <= *after) {
+                    continue;
+                }
+
+                return Some(Ok(path));
+            }
+        }
+
+        None
+    }
+
+    async fn push_children(&mut self, directory: &Path) -> std::io::Result<()> {
+        let mut entries = tokio::fs::read_dir(self.root.join(directory)).await?;
+        let mut names = Vec::new();
+        while let Some(entry) = entries.next_entry().await? {
+            names.push(entry.file_name());
+        }
+
+        names.sort_unstable();
+        self.pending
+            .extend(names.into_iter().rev().map(|name| directory.join(name)));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,212 @@
+use crate::{file_driver::is_system_tag_title, schema::dto_in::CreateFileTagDto};
+use thiserror::Error;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum PathRuleError {
+    #[error("rule `{0}` is empty")]
+    Empty(String),
+    #[error("rule `{0}` has an empty segment")]
+    EmptySegment(String),
+    #[error("rule `{0}` has an empty tag title")]
+    EmptyTagTitle(String),
+    #[error("rule `{0}` has `...` before its last segment")]
+    MisplacedRest(String),
+    #[error("rule `{rule}` tags the title `{title}` more than once")]
+    DuplicatedTagTitle { rule: String, title: String },
+    #[error("rule `{rule}` tags the title `{title}`, which is reserved for system tags")]
+    ReservedTagTitle { rule: String, title: String },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Segment {
+    /// Tags the file with the path segment.
+    Tag(String),
+    /// Matches any path segment.
+    Any,
+    /// Matches the path segment equal to it.
+    Literal(String),
+}
+
+/// A rule deriving tags from the path of a file, such as `{artist}/{album}/...`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PathRule {
+    segments: Vec<Segment>,
+    /// Whether the rule ends with `...`, matching any remaining path segments.
+    rest: bool,
+}
+
+impl PathRule {
+    pub fn parse(rule: &str) -> Result<Self, PathRuleError> {
+        let trimmed = rule.trim_start_matches('/');
+        if trimmed.is_empty() {
+            return Err(PathRuleError::Empty(rule.to_owned()));
+        }
+
+        let parts = trimmed.split('/').collect::<Vec<_>>();
+        let mut segments = Vec::with_capacity(parts.len());
+        let mut rest = false;
+
+        for (index, part) in parts.iter().enumerate() {
+            if *part == "..." {
+                if index + 1 != parts.len() {
+                    return Err(PathRuleError::MisplacedRest(rule.to_owned()));
+                }
+
+                rest = true;
+                continue;
+            }
+
+            let segment = match part
+                .strip_prefix('{')
+                .and_then(|part| part.strip_suffix('}'))
+            {
+                Some("") => return Err(PathRuleError::EmptyTagTitle(rule.to_owned())),
+                Some(title) => {
+                    if is_system_tag_title(title) {
+                        return Err(PathRuleError::ReservedTagTitle {
+                            rule: rule.to_owned(),
+                            title: title.to_owned(),
+                        });
+                    }
+
+                    if segments.contains(&Segment::Tag(title.to_owned())) {
+                        return Err(PathRuleError::DuplicatedTagTitle {
+                            rule: rule.to_owned(),
+                            title: title.to_owned(),
+                        });
+                    }
+
+                    Segment::Tag(title.to_owned())
+                }
+                None if part.is_empty() => {
+                    return Err(PathRuleError::EmptySegment(rule.to_owned()))
+                }
+                None if *part == "*" => Segment::Any,
+                None => Segment::Literal(part.to_string()),
+            };
+            segments.push(segment);
+        }
+
+        Ok(Self { segments, rest })
+    }
+
+    /// Returns the tags of a file if its path, given as segments relative to the import root,
+    /// matches the rule.
+    pub fn apply(&self, path: &[&str]) -> Option<Vec<CreateFileTagDto>> {
+        if path.len() < self.segments.len() || (!self.rest && path.len() != self.segments.len()) {
+            return None;
+        }
+
+        let mut tags = Vec::new();
+        for (segment, part) in self.segments.iter().zip(path) {
+            match segment {
+                Segment::Tag(title) => tags.push(CreateFileTagDto {
+                    title: title.clone(),
+                    value: Some(part.to_string()),
+                }),
+                Segment::Any => {}
+                Segment::Literal(literal) if literal == part => {}
+                Segment::Literal(_) => return None,
+            }
+        }
+
+        Some(tags)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tag(title: &str, value: &str) -> CreateFileTagDto {
+        CreateFileTagDto {
+            title: title.to_owned(),
+            value: Some(value.to_owned()),
+        }
+    }
+
+    #[test]
+    fn parses_valid_rules() {
+        for rule in ["{artist}/{album}/...", "/music/*/{title}", "...", "{a}/{b}"] {
+            assert!(PathRule::parse(rule).is_ok(), "rule `{}`", rule);
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_rules() {
+        assert_eq!(
+            PathRule::parse("/"),
+            Err(PathRuleError::Empty("/".to_owned()))
+        );
+        assert_eq!(
+            PathRule::parse("a//b"),
+            Err(PathRuleError::EmptySegment("a//b".to_owned()))
+        );
+        assert_eq!(
+            PathRule::parse("{}/b"),
+            Err(PathRuleError::EmptyTagTitle("{}/b".to_owned()))
+        );
+        assert_eq!(
+            PathRule::parse(".../{a}"),
+            Err(PathRuleError::MisplacedRest(".../{a}".to_owned()))
+        );
+        assert_eq!(
+            PathRule::parse("{a}/{a}"),
+            Err(PathRuleError::DuplicatedTagTitle {
+                rule: "{a}/{a}".to_owned(),
+                title: "a".to_owned(),
+            })
+        );
+        assert_eq!(
+            PathRule::parse("{system:camera}"),
+            Err(PathRuleError::ReservedTagTitle {
+                rule: "{system:camera}".to_owned(),
+                title: "system:camera".to_owned(),
+            })
+        );
+    }
+
+    #[test]
+    fn tags_matching_paths() {
+        let rule = PathRule::parse("{artist}/*/{title}").unwrap();
+
+        assert_eq!(
+            rule.apply(&["Queen", "1975", "Bohemian Rhapsody.mp3"]),
+            Some(vec![
+                tag("artist", "Queen"),
+                tag("title", "Bohemian Rhapsody.mp3"),
+            ])
+        );
+    }
+
+    #[test]
+    fn matches_literal_segments() {
+        let rule = PathRule::parse("music/{artist}/...").unwrap();
+
+        assert_eq!(
+            rule.apply(&["music", "Queen", "a.mp3"]),
+            Some(vec![tag("artist", "Queen")])
+        );
+        assert_eq!(rule.apply(&["videos", "Queen", "a.mp4"]), None);
+    }
+
+    #[test]
+    fn matches_rest_of_paths() {
+        let rule = PathRule::parse("{artist}/...").unwrap();
+
+        assert_eq!(rule.apply(&["Queen"]), Some(vec![tag("artist", "Queen")]));
+        assert_eq!(
+            rule.apply(&["Queen", "1975", "a.mp3"]),
+            Some(vec![tag("artist", "Queen")])
+        );
+        assert_eq!(rule.apply(&[]), None);
+    }
+
+    #[test]
+    fn requires_exact_length_without_rest() {
+        let rule = PathRule::parse("{artist}/{title}").unwrap();
+
+        assert_eq!(rule.apply(&["Queen"]), None);
+        assert_eq!(rule.apply(&["Queen", "1975", "a.mp3"]), None);
+    }
+}
@@ -0,0 +1,57 @@
+use crate::{
+    db::{schema::files, DBPool},
+    search::{FileDocument, SearchBackend, SearchError},
+};
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Indexes a file in the search backend, under its current name.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexFileJob {
+    pub file_uuid: Uuid,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum IndexFileError {
+    #[error("{0}")]
+    PoolError(#[from] diesel_async::pooled_connection::deadpool::PoolError),
+    #[error("{0}")]
+    DieselError(#[from] diesel::result::Error),
+    #[error("failed to index file: {0:?}")]
+    IndexFile(#[from] SearchError),
+}
+
+pub async fn index_file(
+    db_pool: &DBPool,
+    search_backend: &dyn SearchBackend,
+    job: &IndexFileJob,
+) -> Result<(), IndexFileError> {
+    let name = {
+        let db_conn = &mut db_pool.get().await?;
+        files::table
+            .select(files::name)
+            .filter(files::uuid.eq(job.file_uuid))
+            .filter(files::uploaded_at.is_not_null())
+            .filter(files::deleted_at.is_null())
+            .get_result::<String>(db_conn)
+            .await
+            .optional()?
+    };
+    // Files in the trash are never listed, and are removed from the index when purged.
+    let name = match name {
+        Some(name) => name,
+        None => return Ok(()),
+    };
+
+    search_backend
+        .index_file(&FileDocument {
+            uuid: job.file_uuid,
+            name,
+        })
+        .await?;
+
+    Ok(())
+}
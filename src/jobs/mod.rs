@@ -0,0 +1,469 @@
+use crate::{
+    audit::enum_name,
+    db::{schema::jobs, DBPool},
+    file_driver::FileDriver,
+    schema::dto_in::{JobStatusDto, JobTypeDto},
+    search::SearchBackend,
+};
+use chrono::{Duration, Utc};
+use diesel::{
+    alias,
+    dsl::{now, sql},
+    prelude::*,
+    sql_types::{Integer, Nullable, Text, Timestamp},
+};
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+use uuid::Uuid;
+
+mod index_file;
+mod process_file;
+mod render_thumbnails;
+
+pub use index_file::*;
+pub use process_file::*;
+pub use render_thumbnails::*;
+
+/// Interval between two looks for due jobs.
+const POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// Time a claimed job stays hidden from other workers; a job claimed by a worker that died is
+/// attempted again once it elapses, unless that was its last attempt.
+const CLAIM_LEASE_SECONDS: i64 = 30 * 60;
+
+/// Delay before the first retry; it doubles with each further attempt.
+const BASE_RETRY_DELAY_SECONDS: i64 = 10;
+
+/// Maximum length of the error recorded for a failed attempt.
+const MAX_ERROR_LENGTH: usize = 1024;
+
+/// Error recorded for a job whose lease expired on its last attempt.
+const LEASE_EXPIRED_ERROR: &str = "the worker running the last attempt stopped before it finished";
+
+/// A unit of background work; its type and payload are stored in separate columns.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "jobType", content = "payload", rename_all = "camelCase")]
+pub enum Job {
+    ProcessFile(ProcessFileJob),
+    IndexFile(IndexFileJob),
+    RenderThumbnails(RenderThumbnailsJob),
+}
+
+impl Job {
+    pub fn job_type(&self) -> JobTypeDto {
+        match self {
+            Self::ProcessFile(_) => JobTypeDto::ProcessFile,
+            Self::IndexFile(_) => JobTypeDto::IndexFile,
+            Self::RenderThumbnails(_) => JobTypeDto::RenderThumbnails,
+        }
+    }
+
+    /// Returns the key identifying the work of the job among the jobs of its type; enqueuing a
+    /// job while another with the same key is pending only makes the pending one due now.
+    fn dedup_key(&self) -> Option<String> {
+        match self {
+            Self::ProcessFile(job) => Some(job.file_uuid.to_string()),
+            Self::IndexFile(job) => Some(job.file_uuid.to_string()),
+            Self::RenderThumbnails(job) => Some(job.file_uuid.to_string()),
+        }
+    }
+
+    fn payload(&self) -> serde_json::Value {
+        let mut value = serde_json::to_value(self).expect("failed to serialize job");
+        value["payload"].take()
+    }
+
+    fn parse(job_type: &str, payload: serde_json::Value) -> Result<Self, serde_json::Error> {
+        serde_json::from_value(serde_json::json!({
+            "jobType": job_type,
+            "payload": payload,
+        }))
+    }
+}
+
+impl JobTypeDto {
+    pub const ALL: [Self; 3] = [Self::ProcessFile, Self::IndexFile, Self::RenderThumbnails];
+
+    /// Number of attempts after which a job of this type is dead.
+    fn max_attempts(self) -> i32 {
+        match self {
+            Self::ProcessFile => 5,
+            Self::IndexFile => 8,
+            Self::RenderThumbnails => 3,
+        }
+    }
+
+    /// Returns the maximum number of jobs of this type run at once by a server, set by the env
+    /// var `JOB_CONCURRENCY_<TYPE>`.
+    fn concurrency(self) -> usize {
+        let (env_var, default) = match self {
+            Self::ProcessFile => ("JOB_CONCURRENCY_PROCESS_FILE", 4),
+            Self::IndexFile => ("JOB_CONCURRENCY_INDEX_FILE", 8),
+            Self::RenderThumbnails => ("JOB_CONCURRENCY_RENDER_THUMBNAILS", 2),
+        };
+
+        match std::env::var(env_var) {
+            Ok(concurrency) => match concurrency.parse::<usize>() {
+                Ok(concurrency) if concurrency != 0 => concurrency,
+                _ => {
+                    tracing::warn!(
+                        "env var `{}` is not a positive number; using {}",
+                        env_var,
+                        default
+                    );
+                    default
+                }
+            },
+            Err(_) => default,
+        }
+    }
+}
+
+/// Queues a job, due now.
+///
+/// It runs on the connection of the caller, so that a job is queued if and only if the
+/// mutation it follows is committed.
+pub async fn enqueue(db_conn: &mut AsyncPgConnection, job: &Job) -> QueryResult<()> {
+    let job_type = job.job_type();
+    let job_type_name = enum_name(&job_type);
+    let payload = job.payload();
+    let dedup_key = job.dedup_key();
+
+    if let Some(dedup_key) = &dedup_key {
+        let count = diesel::update(jobs::table)
+            .filter(jobs::job_type.eq(&job_type_name))
+            .filter(jobs::dedup_key.eq(dedup_key))
+            .filter(jobs::status.eq(enum_name(&JobStatusDto::Pending)))
+            .set((
+                jobs::payload.eq(&payload),
+                jobs::attempt_count.eq(0),
+                jobs::run_at.eq(now),
+                jobs::last_error.eq(None::<String>),
+            ))
+            .execute(db_conn)
+            .await?;
+
+        if count != 0 {
+            return Ok(());
+        }
+    }
+
+    // A job with the same key may have been queued since by a concurrent transaction.
+    diesel::insert_into(jobs::table)
+        .values((
+            jobs::job_type.eq(job_type_name),
+            jobs::payload.eq(payload),
+            jobs::dedup_key.eq(dedup_key),
+            jobs::max_attempts.eq(job_type.max_attempts()),
+        ))
+        .on_conflict_do_nothing()
+        .execute(db_conn)
+        .await?;
+
+    Ok(())
+}
+
+/// Runs queued jobs in the background.
+#[derive(Clone)]
+pub struct JobRunner {
+    db_pool: DBPool,
+    file_driver: FileDriver,
+    search_backend: Arc<dyn SearchBackend>,
+}
+
+impl JobRunner {
+    pub fn new(
+        db_pool: DBPool,
+        file_driver: FileDriver,
+        search_backend: Arc<dyn SearchBackend>,
+    ) -> Self {
+        Self {
+            db_pool,
+            file_driver,
+            search_backend,
+        }
+    }
+
+    /// Spawns a background task that runs due jobs, up to the concurrency limit of each type.
+    ///
+    /// A failed job is retried with an exponential backoff until it has been attempted as many
+    /// times as its type allows, and is then dead until retried through `/jobs`.
+    pub fn spawn_worker(self) {
+        let limits = JobTypeDto::ALL
+            .into_iter()
+            .map(|job_type| (job_type, Arc::new(Semaphore::new(job_type.concurrency()))))
+            .collect::<Vec<_>>();
+
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(POLL_INTERVAL);
+
+            loop {
+                interval.tick().await;
+
+                if let Err(err) = self.run_due(&limits).await {
+                    tracing::error!("failed to run jobs: {:#?}", err);
+                }
+            }
+        });
+    }
+
+    /// Claims as many due jobs of each type as there are free slots, and runs them in their own
+    /// tasks.
+    async fn run_due(&self, limits: &[(JobTypeDto, Arc<Semaphore>)]) -> Result<(), JobError> {
+        for (job_type, semaphore) in limits {
+            let available = semaphore.available_permits();
+            if available == 0 {
+                continue;
+            }
+
+            for job in self.claim_due(*job_type, available as i64).await? {
+                let permit = semaphore
+                    .clone()
+                    .acquire_owned()
+                    .await
+                    .expect("job semaphores are never closed");
+                let runner = self.clone();
+
+                tokio::spawn(async move {
+                    if let Err(err) = runner.run(&job).await {
+                        tracing::error!(
+                            "failed to record the outcome of job `{}`: {:#?}",
+                            job.uuid,
+                            err
+                        );
+                    }
+                    drop(permit);
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Claims a batch of due jobs of a type, including running jobs whose lease expired,
+    /// skipping those claimed by other workers.
+    ///
+    /// A job whose lease expired on its last attempt is not claimed but dead, as its worker
+    /// stopped before recording the outcome.
+    async fn claim_due(
+        &self,
+        job_type: JobTypeDto,
+        limit: i64,
+    ) -> Result<Vec<ClaimedJob>, JobError> {
+        let db_conn = &mut self.db_pool.get().await?;
+
+        let due = alias!(jobs as due);
+        let due_ids = due
+            .select(due.field(jobs::id))
+            .filter(due.field(jobs::job_type).eq(enum_name(&job_type)))
+            .filter(due.field(jobs::status).eq_any([
+                enum_name(&JobStatusDto::Pending),
+                enum_name(&JobStatusDto::Running),
+            ]))
+            .filter(due.field(jobs::run_at).le(now))
+            .order(due.field(jobs::run_at).asc())
+            .limit(limit)
+            .for_update()
+            .skip_locked();
+        // Every expression sees the row as it was before the update.
+        let has_attempts_left = "CASE WHEN jobs.attempt_count < jobs.max_attempts THEN ";
+        let claimed = diesel::update(jobs::table)
+            .filter(jobs::id.eq_any(due_ids))
+            .set((
+                jobs::status.eq(sql::<Text>(has_attempts_left)
+                    .bind::<Text, _>(enum_name(&JobStatusDto::Running))
+                    .sql(" ELSE ")
+                    .bind::<Text, _>(enum_name(&JobStatusDto::Dead))
+                    .sql(" END")),
+                jobs::attempt_count.eq(sql::<Integer>(has_attempts_left)
+                    .sql("jobs.attempt_count + 1 ELSE jobs.attempt_count END")),
+                jobs::run_at.eq((Utc::now() + Duration::seconds(CLAIM_LEASE_SECONDS)).naive_utc()),
+                jobs::last_error.eq(sql::<Nullable<Text>>(has_attempts_left)
+                    .sql("jobs.last_error ELSE ")
+                    .bind::<Text, _>(LEASE_EXPIRED_ERROR)
+                    .sql(" END")),
+                jobs::finished_at.eq(sql::<Nullable<Timestamp>>(has_attempts_left)
+                    .sql("jobs.finished_at ELSE now() END")),
+            ))
+            .returning((
+                (
+                    jobs::id,
+                    jobs::uuid,
+                    jobs::job_type,
+                    jobs::payload,
+                    jobs::dedup_key,
+                    jobs::attempt_count,
+                    jobs::max_attempts,
+                ),
+                jobs::status,
+            ))
+            .get_results::<(ClaimedJob, String)>(db_conn)
+            .await?;
+
+        let mut running = vec![];
+        for (claimed, status) in claimed {
+            if status == enum_name(&JobStatusDto::Running) {
+                running.push(claimed);
+                continue;
+            }
+
+            tracing::warn!("job `{}` is dead: {}", claimed.uuid, LEASE_EXPIRED_ERROR);
+            if let Ok(job) = Job::parse(&claimed.job_type, claimed.payload) {
+                self.give_up(&job, LEASE_EXPIRED_ERROR).await?;
+            }
+        }
+
+        Ok(running)
+    }
+
+    async fn run(&self, claimed: &ClaimedJob) -> Result<(), JobError> {
+        let job = Job::parse(&claimed.job_type, claimed.payload.clone());
+        let outcome = match &job {
+            Ok(job) => self.execute(job).await,
+            Err(err) => Err(format!("invalid payload: {}", err)),
+        };
+
+        let dead = self.record_outcome(claimed, outcome.as_ref().err()).await?;
+
+        if let (true, Ok(job), Err(error)) = (dead, &job, &outcome) {
+            self.give_up(job, error).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn execute(&self, job: &Job) -> Result<(), String> {
+        match job {
+            Job::ProcessFile(job) => process_file(&self.db_pool, &self.file_driver, job)
+                .await
+                .map_err(|err| err.to_string()),
+            Job::IndexFile(job) => index_file(&self.db_pool, self.search_backend.as_ref(), job)
+                .await
+                .map_err(|err| err.to_string()),
+            Job::RenderThumbnails(job) => render_thumbnails(&self.db_pool, &self.file_driver, job)
+                .await
+                .map_err(|err| err.to_string()),
+        }
+    }
+
+    /// Settles the work of a dead job, which is not attempted anymore.
+    async fn give_up(&self, job: &Job, error: &str) -> Result<(), JobError> {
+        match job {
+            Job::ProcessFile(job) => give_up_processing_file(&self.db_pool, job, error).await,
+            Job::IndexFile(_) | Job::RenderThumbnails(_) => Ok(()),
+        }
+    }
+
+    /// Records the outcome of an attempt, and returns whether the job is dead.
+    ///
+    /// Nothing is recorded if the lease of the attempt expired and the job was claimed again.
+    async fn record_outcome(
+        &self,
+        claimed: &ClaimedJob,
+        error: Option<&String>,
+    ) -> Result<bool, JobError> {
+        let db_conn = &mut self.db_pool.get().await?;
+        let attempt = diesel::update(jobs::table.find(claimed.id))
+            .filter(jobs::status.eq(enum_name(&JobStatusDto::Running)))
+            .filter(jobs::attempt_count.eq(claimed.attempt_count));
+
+        let error = match error {
+            Some(error) => error.chars().take(MAX_ERROR_LENGTH).collect::<String>(),
+            None => {
+                attempt
+                    .set((
+                        jobs::status.eq(enum_name(&JobStatusDto::Succeeded)),
+                        jobs::last_error.eq(None::<String>),
+                        jobs::finished_at.eq(now),
+                    ))
+                    .execute(db_conn)
+                    .await?;
+                return Ok(false);
+            }
+        };
+
+        tracing::warn!(
+            "job `{}` failed on attempt {}: {}",
+            claimed.uuid,
+            claimed.attempt_count,
+            error
+        );
+
+        // A pending job with the same key does the same work, so it replaces the retry.
+        let superseded = match &claimed.dedup_key {
+            Some(dedup_key) => {
+                diesel::select(diesel::dsl::exists(
+                    jobs::table
+                        .filter(jobs::job_type.eq(&claimed.job_type))
+                        .filter(jobs::dedup_key.eq(dedup_key))
+                        .filter(jobs::status.eq(enum_name(&JobStatusDto::Pending))),
+                ))
+                .get_result::<bool>(db_conn)
+                .await?
+            }
+            None => false,
+        };
+
+        if superseded {
+            attempt
+                .set((
+                    jobs::status.eq(enum_name(&JobStatusDto::Dead)),
+                    jobs::last_error.eq(format!("{}; superseded by a pending job", error)),
+                    jobs::finished_at.eq(now),
+                ))
+                .execute(db_conn)
+                .await?;
+            return Ok(false);
+        }
+
+        if claimed.attempt_count < claimed.max_attempts {
+            attempt
+                .set((
+                    jobs::status.eq(enum_name(&JobStatusDto::Pending)),
+                    jobs::run_at.eq((Utc::now() + retry_delay(claimed.attempt_count)).naive_utc()),
+                    jobs::last_error.eq(&error),
+                ))
+                .execute(db_conn)
+                .await?;
+            return Ok(false);
+        }
+
+        let count = attempt
+            .set((
+                jobs::status.eq(enum_name(&JobStatusDto::Dead)),
+                jobs::last_error.eq(&error),
+                jobs::finished_at.eq(now),
+            ))
+            .execute(db_conn)
+            .await?;
+
+        Ok(count != 0)
+    }
+}
+
+#[derive(Queryable, Debug)]
+struct ClaimedJob {
+    id: i64,
+    uuid: Uuid,
+    job_type: String,
+    payload: serde_json::Value,
+    dedup_key: Option<String>,
+    attempt_count: i32,
+    max_attempts: i32,
+}
+
+/// Returns the delay before attempting a job again after its n-th failed attempt.
+fn retry_delay(attempt_count: i32) -> Duration {
+    let exponent = (attempt_count - 1).clamp(0, 16) as u32;
+    Duration::seconds(BASE_RETRY_DELAY_SECONDS * 2i64.pow(exponent))
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum JobError {
+    #[error("{0}")]
+    PoolError(#[from] diesel_async::pooled_connection::deadpool::PoolError),
+    #[error("{0}")]
+    DieselError(#[from] diesel::result::Error),
+}
@@ -0,0 +1,115 @@
+use crate::{
+    audit::AuditContext,
+    db::{schema::files, DBPool},
+    file_driver::{FileDriver, ReadFileInfoError},
+    route_files::file_service::{fail_file_processing, store_processed_file},
+    schema::dto_in::FileStatusDto,
+};
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use diesel_async::{
+    scoped_futures::ScopedFutureExt, AsyncConnection, AsyncPgConnection, RunQueryDsl,
+};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Hashes the uploaded content of a file, and extracts its type and system tags.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ProcessFileJob {
+    pub file_uuid: Uuid,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum ProcessFileError {
+    #[error("{0}")]
+    PoolError(#[from] diesel_async::pooled_connection::deadpool::PoolError),
+    #[error("{0}")]
+    DieselError(#[from] diesel::result::Error),
+    #[error("failed to read file info: {0:?}")]
+    ReadFileInfo(#[from] ReadFileInfoError),
+}
+
+pub async fn process_file(
+    db_pool: &DBPool,
+    file_driver: &FileDriver,
+    job: &ProcessFileJob,
+) -> Result<(), ProcessFileError> {
+    let uploaded_at = {
+        let db_conn = &mut db_pool.get().await?;
+        find_pending_upload(db_conn, job.file_uuid).await?
+    };
+    // The file was removed, or its content was already processed by an earlier job.
+    let uploaded_at = match uploaded_at {
+        Some(uploaded_at) => uploaded_at,
+        None => return Ok(()),
+    };
+
+    let file_info = file_driver.read_file_info(job.file_uuid).await?;
+
+    let db_conn = &mut db_pool.get().await?;
+    db_conn
+        .transaction(|db_conn| {
+            async move {
+                store_processed_file(
+                    db_conn,
+                    job.file_uuid,
+                    uploaded_at,
+                    &file_info,
+                    &AuditContext::system(),
+                )
+                .await
+            }
+            .scope_boxed()
+        })
+        .await?;
+
+    Ok(())
+}
+
+/// Marks the content of a file as failed to be processed, once every attempt failed.
+pub async fn give_up_processing_file(
+    db_pool: &DBPool,
+    job: &ProcessFileJob,
+    error: &str,
+) -> Result<(), super::JobError> {
+    let db_conn = &mut db_pool.get().await?;
+    let uploaded_at = match find_pending_upload(db_conn, job.file_uuid).await? {
+        Some(uploaded_at) => uploaded_at,
+        None => return Ok(()),
+    };
+
+    db_conn
+        .transaction(|db_conn| {
+            async move {
+                fail_file_processing(
+                    db_conn,
+                    job.file_uuid,
+                    uploaded_at,
+                    error,
+                    &AuditContext::system(),
+                )
+                .await
+            }
+            .scope_boxed()
+        })
+        .await?;
+
+    Ok(())
+}
+
+/// Returns when the content of a file waiting to be processed was uploaded.
+async fn find_pending_upload(
+    db_conn: &mut AsyncPgConnection,
+    file_uuid: Uuid,
+) -> QueryResult<Option<NaiveDateTime>> {
+    files::table
+        .select(files::uploaded_at.assume_not_null())
+        .filter(files::uuid.eq(file_uuid))
+        .filter(files::deleted_at.is_null())
+        .filter(files::uploaded_at.is_not_null())
+        .filter(files::status.eq(FileStatusDto::Processing))
+        .get_result::<NaiveDateTime>(db_conn)
+        .await
+        .optional()
+}
@@ -0,0 +1,233 @@
+use super::{enqueue, Job};
+use crate::{
+    audit::{enum_name, parse_enum_name},
+    db::{
+        schema::{files, thumbnail_jobs},
+        DBPool,
+    },
+    file_driver::{FileDriver, GenerateThumbnailsError, RenderThumbnailsError},
+    schema::{
+        dto_in::{FileStatusDto, ThumbnailSizeDto},
+        dto_out::ThumbnailStatusDto,
+    },
+};
+use chrono::{NaiveDateTime, Utc};
+use diesel::{dsl::now, prelude::*};
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Maximum length of the error recorded for a thumbnail job.
+const MAX_ERROR_LENGTH: usize = 1024;
+
+/// Renders the thumbnails of a file.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct RenderThumbnailsJob {
+    pub file_uuid: Uuid,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum RenderThumbnailsJobError {
+    #[error("{0}")]
+    PoolError(#[from] diesel_async::pooled_connection::deadpool::PoolError),
+    #[error("{0}")]
+    DieselError(#[from] diesel::result::Error),
+    #[error("failed to generate thumbnails: {}", describe_failure(.0))]
+    GenerateThumbnails(#[from] GenerateThumbnailsError),
+}
+
+/// Queues the rendering of the thumbnails of a file, replacing any earlier request.
+///
+/// It runs on the connection of the caller, so that it can be part of the transaction storing
+/// new content. A job that is running when it is requested again is run once more after it.
+pub async fn request_thumbnails(
+    db_conn: &mut AsyncPgConnection,
+    file_id: i32,
+    file_uuid: Uuid,
+) -> QueryResult<()> {
+    let running = enum_name(&ThumbnailStatusDto::Running);
+
+    diesel::insert_into(thumbnail_jobs::table)
+        .values(thumbnail_jobs::file_id.eq(file_id))
+        .on_conflict(thumbnail_jobs::file_id)
+        .do_update()
+        .set((
+            thumbnail_jobs::requested_at.eq(now),
+            thumbnail_jobs::last_error.eq(None::<String>),
+        ))
+        .execute(db_conn)
+        .await?;
+    diesel::update(thumbnail_jobs::table)
+        .filter(thumbnail_jobs::file_id.eq(file_id))
+        .filter(thumbnail_jobs::status.ne(running))
+        .set(thumbnail_jobs::status.eq(enum_name(&ThumbnailStatusDto::Pending)))
+        .execute(db_conn)
+        .await?;
+
+    enqueue(
+        db_conn,
+        &Job::RenderThumbnails(RenderThumbnailsJob { file_uuid }),
+    )
+    .await
+}
+
+#[derive(Queryable, Debug)]
+struct ThumbnailRequest {
+    file_id: i32,
+    requested_at: NaiveDateTime,
+    mime: String,
+}
+
+/// Renders the thumbnails of a file as last requested.
+///
+/// A run that fails is recorded on the thumbnail job and retried; the thumbnails of the last run
+/// that completed stay available meanwhile.
+pub async fn render_thumbnails(
+    db_pool: &DBPool,
+    file_driver: &FileDriver,
+    job: &RenderThumbnailsJob,
+) -> Result<(), RenderThumbnailsJobError> {
+    let request = {
+        let db_conn = &mut db_pool.get().await?;
+        let request = thumbnail_jobs::table
+            .inner_join(files::table)
+            .select((
+                thumbnail_jobs::file_id,
+                thumbnail_jobs::requested_at,
+                files::mime.assume_not_null(),
+            ))
+            .filter(files::uuid.eq(job.file_uuid))
+            .filter(files::status.eq(FileStatusDto::Ready))
+            .filter(files::deleted_at.is_null())
+            .get_result::<ThumbnailRequest>(db_conn)
+            .await
+            .optional()?;
+
+        if let Some(request) = &request {
+            diesel::update(thumbnail_jobs::table.find(request.file_id))
+                .set(thumbnail_jobs::status.eq(enum_name(&ThumbnailStatusDto::Running)))
+                .execute(db_conn)
+                .await?;
+        }
+
+        request
+    };
+    // The file was removed, or its content was replaced and is not processed yet.
+    let request = match request {
+        Some(request) => request,
+        None => return Ok(()),
+    };
+
+    let outcome = file_driver
+        .generate_thumbnails(job.file_uuid, &request.mime)
+        .await;
+    record_outcome(db_pool, file_driver, job.file_uuid, &request, outcome).await
+}
+
+/// Records the outcome of a run; a job requested again while it ran is left pending instead.
+async fn record_outcome(
+    db_pool: &DBPool,
+    file_driver: &FileDriver,
+    file_uuid: Uuid,
+    request: &ThumbnailRequest,
+    outcome: Result<Option<Vec<ThumbnailSizeDto>>, GenerateThumbnailsError>,
+) -> Result<(), RenderThumbnailsJobError> {
+    // Thumbnails of earlier content of a file that has no preview anymore would be stale.
+    if let Ok(None) = &outcome {
+        if let Err(err) = file_driver.remove_thumbnails(file_uuid).await {
+            tracing::error!(
+                "failed to remove the thumbnails of file `{}`: {:#?}",
+                file_uuid,
+                err
+            );
+        }
+    }
+
+    let (status, sizes, error, failure) = match outcome {
+        Ok(Some(sizes)) => (ThumbnailStatusDto::Completed, Some(sizes), None, None),
+        Ok(None) => (
+            ThumbnailStatusDto::Unsupported,
+            Some(Vec::new()),
+            None,
+            None,
+        ),
+        Err(GenerateThumbnailsError::RenderThumbnails(
+            err @ RenderThumbnailsError::ToolNotFoundError(..),
+        )) => (
+            ThumbnailStatusDto::Unsupported,
+            None,
+            Some(err.to_string()),
+            None,
+        ),
+        Err(err) => (
+            ThumbnailStatusDto::Failed,
+            None,
+            Some(describe_failure(&err)),
+            Some(err),
+        ),
+    };
+
+    let db_conn = &mut db_pool.get().await?;
+    let error = error.map(|error| error.chars().take(MAX_ERROR_LENGTH).collect::<String>());
+    let update = diesel::update(thumbnail_jobs::table)
+        .filter(thumbnail_jobs::file_id.eq(request.file_id))
+        .filter(thumbnail_jobs::requested_at.eq(request.requested_at))
+        .filter(thumbnail_jobs::status.eq(enum_name(&ThumbnailStatusDto::Running)));
+    let count = match sizes {
+        Some(sizes) => {
+            let generated_at = (!sizes.is_empty()).then(|| Utc::now().naive_utc());
+            update
+                .set((
+                    thumbnail_jobs::status.eq(enum_name(&status)),
+                    thumbnail_jobs::sizes.eq(sizes.iter().map(enum_name).collect::<Vec<_>>()),
+                    thumbnail_jobs::last_error.eq(error),
+                    thumbnail_jobs::generated_at.eq(generated_at),
+                ))
+                .execute(db_conn)
+                .await?
+        }
+        // A failed run keeps the thumbnails of the last one that completed.
+        None => {
+            update
+                .set((
+                    thumbnail_jobs::status.eq(enum_name(&status)),
+                    thumbnail_jobs::last_error.eq(error),
+                ))
+                .execute(db_conn)
+                .await?
+        }
+    };
+
+    // The request was replaced while running; the job queued by the new request renders it.
+    if count == 0 {
+        diesel::update(thumbnail_jobs::table)
+            .filter(thumbnail_jobs::file_id.eq(request.file_id))
+            .filter(thumbnail_jobs::status.eq(enum_name(&ThumbnailStatusDto::Running)))
+            .set(thumbnail_jobs::status.eq(enum_name(&ThumbnailStatusDto::Pending)))
+            .execute(db_conn)
+            .await?;
+        return Ok(());
+    }
+
+    match failure {
+        Some(err) => Err(err.into()),
+        None => Ok(()),
+    }
+}
+
+/// Describes why thumbnails could not be generated; unlike the error answered to clients, it
+/// keeps the details for the job and the logs.
+fn describe_failure(err: &GenerateThumbnailsError) -> String {
+    match err {
+        GenerateThumbnailsError::RenderThumbnails(err) => err.to_string(),
+        GenerateThumbnailsError::WriteThumbnail(err) => {
+            format!("failed to write thumbnail: {}", err)
+        }
+    }
+}
+
+/// Parses the sizes stored for a job.
+pub fn parse_sizes(sizes: Vec<String>) -> Vec<ThumbnailSizeDto> {
+    sizes.into_iter().map(parse_enum_name).collect()
+}
@@ -1,12 +1,39 @@
 mod app_state;
+mod archive;
+mod audit;
+mod changes;
 mod db;
 mod docs;
+mod etag;
 mod file_driver;
+mod import;
+mod jobs;
+mod pagination;
+mod quota;
+mod reconcile;
 mod response;
+mod route_archives;
+mod route_audit;
 mod route_collections;
+mod route_events;
+mod route_files;
+mod route_imports;
+mod route_jobs;
+mod route_quotas;
+mod route_storage;
+mod route_thumbnails;
+mod route_webhooks;
 mod schema;
-
-use crate::{docs::ApiDoc, file_driver::FileDriver};
+mod scrub;
+mod search;
+mod trash;
+mod versions;
+mod webhooks;
+
+use crate::{
+    docs::ApiDoc, file_driver::FileDriver, import::ImportRunner, jobs::JobRunner,
+    reconcile::Reconciler, scrub::Scrubber,
+};
 use app_state::AppState;
 use axum::{http::StatusCode, response::IntoResponse, Router};
 use std::net::SocketAddr;
@@ -31,9 +58,36 @@ async fn main() {
     let mut file_driver = FileDriver::new("./files"); // TODO: make this configurable
     file_driver.create_dirs().await;
 
-    let meilisearch_client = db::init_meilisearch_client().await;
+    let search_backend = search::init_search_backend(db_pool.clone()).await;
+
+    let import_runner =
+        ImportRunner::new(db_pool.clone(), file_driver.clone(), search_backend.clone());
+
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    if args.first().map(String::as_str) == Some("import") {
+        import::run_command(&args[1..], import_runner).await;
+        return;
+    }
+
+    let cursor_codec = pagination::init_cursor_codec();
+
+    let change_broker = changes::init_change_broker();
 
-    let app_state = AppState::new(db_pool, file_driver, meilisearch_client);
+    trash::spawn_purge_job(db_pool.clone(), file_driver.clone(), search_backend.clone());
+    versions::spawn_prune_job(db_pool.clone(), file_driver.clone());
+    webhooks::spawn_delivery_worker(db_pool.clone());
+    JobRunner::new(db_pool.clone(), file_driver.clone(), search_backend.clone()).spawn_worker();
+    import_runner.spawn_worker();
+    Reconciler::new(db_pool.clone(), file_driver.clone()).spawn_worker();
+    Scrubber::new(db_pool.clone(), file_driver.clone()).spawn_worker();
+
+    let app_state = AppState::new(
+        db_pool,
+        file_driver,
+        search_backend,
+        cursor_codec,
+        change_broker.clone(),
+    );
     let app = Router::new();
 
     let port = 3000; // TODO: make this configurable
@@ -49,13 +103,26 @@ async fn main() {
 
     let app = app
         .merge(route_collections::router())
+        .merge(route_files::router())
+        .merge(route_audit::router())
+        .merge(route_events::router())
+        .merge(route_webhooks::router())
+        .merge(route_imports::router())
+        .merge(route_jobs::router())
+        .merge(route_storage::router())
+        .merge(route_quotas::router())
+        .merge(route_archives::router())
+        .merge(route_thumbnails::router())
         .fallback(handler_fallback)
         .with_state(app_state);
 
     tracing::info!("listening on {}", addr);
     let listener = TcpListener::bind(&addr).await.unwrap();
     axum::serve(listener, app.into_make_service())
-        .with_graceful_shutdown(shutdown_signal())
+        .with_graceful_shutdown(async move {
+            shutdown_signal().await;
+            change_broker.shutdown();
+        })
         .await
         .unwrap();
 }
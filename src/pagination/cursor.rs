@@ -0,0 +1,160 @@
+use axum::http::StatusCode;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use codegen::ErrorEnum;
+use hmac::{Hmac, Mac};
+use serde::{de::DeserializeOwned, Serialize};
+use sha2::Sha256;
+use thiserror::Error;
+use uuid::Uuid;
+
+#[derive(ErrorEnum, Error, Debug)]
+pub enum CursorError {
+    #[error("cursor is malformed")]
+    #[status(StatusCode::UNPROCESSABLE_ENTITY)]
+    Malformed,
+    #[error("cursor has an invalid signature")]
+    #[status(StatusCode::UNPROCESSABLE_ENTITY)]
+    InvalidSignature,
+}
+
+/// Encodes pagination cursors into opaque tokens, signed so that clients cannot forge positions.
+///
+/// A token is `<payload>.<signature>`, where the payload is the JSON-serialized cursor and the
+/// signature is its HMAC-SHA256, both base64url-encoded.
+#[derive(Clone)]
+pub struct CursorCodec {
+    mac: Hmac<Sha256>,
+}
+
+impl CursorCodec {
+    pub fn new(secret: &[u8]) -> Self {
+        Self {
+            mac: Hmac::new_from_slice(secret).expect("hmac accepts keys of any size"),
+        }
+    }
+
+    pub fn encode<T: Serialize>(&self, cursor: &T) -> String {
+        let payload = serde_json::to_vec(cursor).expect("failed to serialize cursor");
+        let mut mac = self.mac.clone();
+        mac.update(&payload);
+        let signature = mac.finalize().into_bytes();
+
+        format!(
+            "{}.{}",
+            URL_SAFE_NO_PAD.encode(payload),
+            URL_SAFE_NO_PAD.encode(signature)
+        )
+    }
+
+    pub fn decode<T: DeserializeOwned>(&self, token: &str) -> Result<T, CursorError> {
+        let (payload, signature) = token.split_once('.').ok_or(CursorError::Malformed)?;
+        let payload = URL_SAFE_NO_PAD
+            .decode(payload)
+            .map_err(|_| CursorError::Malformed)?;
+        let signature = URL_SAFE_NO_PAD
+            .decode(signature)
+            .map_err(|_| CursorError::Malformed)?;
+
+        let mut mac = self.mac.clone();
+        mac.update(&payload);
+        mac.verify_slice(&signature)
+            .map_err(|_| CursorError::InvalidSignature)?;
+
+        serde_json::from_slice(&payload).map_err(|_| CursorError::Malformed)
+    }
+}
+
+pub fn init_cursor_codec() -> CursorCodec {
+    match std::env::var("CURSOR_SECRET") {
+        Ok(secret) => CursorCodec::new(secret.as_bytes()),
+        Err(_) => {
+            tracing::warn!(
+                "env var `CURSOR_SECRET` is not set; using a random secret, so cursors will not survive restarts nor be shared across instances"
+            );
+
+            let secret = [Uuid::new_v4().into_bytes(), Uuid::new_v4().into_bytes()].concat();
+            CursorCodec::new(&secret)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Position {
+        name: String,
+        id: i32,
+    }
+
+    fn position() -> Position {
+        Position {
+            name: "Foo.txt".to_owned(),
+            id: 42,
+        }
+    }
+
+    #[test]
+    fn round_trips() {
+        let codec = CursorCodec::new(b"secret");
+        let token = codec.encode(&position());
+
+        assert_eq!(codec.decode::<Position>(&token).unwrap(), position());
+    }
+
+    #[test]
+    fn rejects_tampered_payload() {
+        let codec = CursorCodec::new(b"secret");
+        let token = codec.encode(&position());
+        let (_, signature) = token.split_once('.').unwrap();
+        let forged = Position {
+            name: "Foo.txt".to_owned(),
+            id: 7,
+        };
+        let payload = URL_SAFE_NO_PAD.encode(serde_json::to_vec(&forged).unwrap());
+
+        assert!(matches!(
+            codec.decode::<Position>(&format!("{}.{}", payload, signature)),
+            Err(CursorError::InvalidSignature)
+        ));
+    }
+
+    #[test]
+    fn rejects_tampered_signature() {
+        let codec = CursorCodec::new(b"secret");
+        let token = codec.encode(&position());
+        let (payload, signature) = token.split_once('.').unwrap();
+        let mut signature = URL_SAFE_NO_PAD.decode(signature).unwrap();
+        signature[0] ^= 1;
+        let signature = URL_SAFE_NO_PAD.encode(signature);
+
+        assert!(matches!(
+            codec.decode::<Position>(&format!("{}.{}", payload, signature)),
+            Err(CursorError::InvalidSignature)
+        ));
+    }
+
+    #[test]
+    fn rejects_token_signed_with_another_secret() {
+        let token = CursorCodec::new(b"another secret").encode(&position());
+
+        assert!(matches!(
+            CursorCodec::new(b"secret").decode::<Position>(&token),
+            Err(CursorError::InvalidSignature)
+        ));
+    }
+
+    #[test]
+    fn rejects_malformed_token() {
+        let codec = CursorCodec::new(b"secret");
+
+        for token in ["", "no-separator", "!!!.!!!"] {
+            assert!(matches!(
+                codec.decode::<Position>(token),
+                Err(CursorError::Malformed)
+            ));
+        }
+    }
+}
@@ -0,0 +1,188 @@
+use crate::schema::{dto_in::PaginationOrderDto, dto_out::PaginationMetadataDto};
+use axum::http::StatusCode;
+use codegen::ErrorEnum;
+use diesel::{
+    dsl::{exists, not, sql, BareSelect, IntoBoxed, Select},
+    expression::SqlLiteral,
+    pg::Pg,
+    prelude::*,
+    query_builder::{AsQuery, SelectQuery},
+    query_dsl::methods::{BoxedDsl, FilterDsl, LimitDsl, SelectDsl},
+    sql_types::{Bool, Integer},
+};
+use thiserror::Error;
+
+pub const MAX_PAGE_SIZE: u64 = 100;
+
+#[derive(ErrorEnum, Error, Debug)]
+pub enum PaginationError {
+    #[error("page size must be between 1 and {}, but was `{0}`", MAX_PAGE_SIZE)]
+    #[status(StatusCode::UNPROCESSABLE_ENTITY)]
+    InvalidPageSize(u64),
+    #[error("a page can be requested either before or after a position, but not both")]
+    #[status(StatusCode::UNPROCESSABLE_ENTITY)]
+    ConflictingCursors,
+    #[error("cursor was issued for a different sort")]
+    #[status(StatusCode::UNPROCESSABLE_ENTITY)]
+    CursorSortMismatch,
+}
+
+pub type BoxedPredicate<'a, T> = Box<dyn BoxableExpression<T, Pg, SqlType = Bool> + 'a>;
+
+/// Tells whether any row lies behind the cursor.
+pub type BehindQuery<'a, T> = BareSelect<exists<Select<IntoBoxed<'a, T, Pg>, SqlLiteral<Integer>>>>;
+
+/// Selects a page of rows.
+pub type PageQuery<'a, T, S> = Select<IntoBoxed<'a, T, Pg>, S>;
+
+/// A position in a listing of rows ordered by one or more columns, such as `(name, id)`.
+///
+/// Every sort must end with a unique column, so that no two rows share a position.
+pub trait Keyset: Sized {
+    type Table: Table + for<'a> BoxedDsl<'a, Pg>;
+    type Sort: Copy + PartialEq;
+    type Row;
+
+    /// Returns the position of the given row.
+    fn new(row: &Self::Row, sort: Self::Sort) -> Self;
+
+    fn sort(&self) -> Self::Sort;
+
+    /// Matches the rows strictly after this position when walking in the given direction.
+    fn beyond(&self, ascending: bool) -> BoxedPredicate<'_, Self::Table>;
+
+    /// Orders rows by the given sort, walking in the given direction.
+    fn order(
+        query: IntoBoxed<'_, Self::Table, Pg>,
+        sort: Self::Sort,
+        ascending: bool,
+    ) -> IntoBoxed<'_, Self::Table, Pg>;
+}
+
+/// A request for a page of rows next to a position, or the first page if there is none.
+pub struct PageRequest<K: Keyset> {
+    pub cursor: Option<K>,
+    pub backward: bool,
+    pub sort: K::Sort,
+    pub order: PaginationOrderDto,
+    pub page_size: u64,
+}
+
+pub struct Page<R> {
+    pub items: Vec<R>,
+    pub pagination: PaginationMetadataDto,
+}
+
+impl<K: Keyset> PageRequest<K> {
+    /// Requests the page before `first`, or the page after `last`.
+    pub fn new(
+        first: Option<K>,
+        last: Option<K>,
+        sort: K::Sort,
+        order: PaginationOrderDto,
+        page_size: u64,
+    ) -> Result<Self, PaginationError> {
+        if page_size == 0 || MAX_PAGE_SIZE < page_size {
+            return Err(PaginationError::InvalidPageSize(page_size));
+        }
+
+        let (cursor, backward) = match (first, last) {
+            (Some(_), Some(_)) => return Err(PaginationError::ConflictingCursors),
+            (Some(first), None) => (Some(first), true),
+            (None, Some(last)) => (Some(last), false),
+            (None, None) => (None, false),
+        };
+
+        if let Some(cursor) = &cursor {
+            if cursor.sort() != sort {
+                return Err(PaginationError::CursorSortMismatch);
+            }
+        }
+
+        Ok(Self {
+            cursor,
+            backward,
+            sort,
+            order,
+            page_size,
+        })
+    }
+
+    /// Selects `selection` from the rows of the requested page among those matched by `query`.
+    ///
+    /// One extra row tells whether there is more ahead. Whether there is anything behind the
+    /// cursor is asked apart by [`PageRequest::behind`], and both go to [`PageRequest::page`].
+    pub fn select<'a, S>(
+        &'a self,
+        query: impl Fn() -> IntoBoxed<'a, K::Table, Pg>,
+        selection: S,
+    ) -> PageQuery<'a, K::Table, S>
+    where
+        IntoBoxed<'a, K::Table, Pg>: FilterDsl<BoxedPredicate<'a, K::Table>, Output = IntoBoxed<'a, K::Table, Pg>>
+            + LimitDsl<Output = IntoBoxed<'a, K::Table, Pg>>
+            + SelectDsl<S>,
+        S: Expression,
+    {
+        let ascending = self.ascending();
+
+        let q = match &self.cursor {
+            Some(cursor) => query().filter(cursor.beyond(ascending)),
+            None => query(),
+        };
+
+        let q = K::order(q, self.sort, ascending).limit(self.page_size as i64 + 1);
+        SelectDsl::<S>::select(q, selection)
+    }
+
+    /// Tells whether any row matched by `query` lies behind the cursor.
+    ///
+    /// This is asked once for the cursor rather than alongside the rows, so that an empty page past
+    /// the end still reports what precedes it. Nothing lies behind the first page, so there is
+    /// nothing to ask without a cursor.
+    pub fn behind<'a>(
+        &'a self,
+        query: impl Fn() -> IntoBoxed<'a, K::Table, Pg>,
+    ) -> Option<BehindQuery<'a, K::Table>>
+    where
+        IntoBoxed<'a, K::Table, Pg>: FilterDsl<BoxedPredicate<'a, K::Table>, Output = IntoBoxed<'a, K::Table, Pg>>
+            + SelectDsl<SqlLiteral<Integer>>,
+        Select<IntoBoxed<'a, K::Table, Pg>, SqlLiteral<Integer>>: SelectQuery,
+        BehindQuery<'a, K::Table>: AsQuery,
+    {
+        let cursor = self.cursor.as_ref()?;
+        let behind: BoxedPredicate<'a, K::Table> = Box::new(not(cursor.beyond(self.ascending())));
+        // The boxed query can take either selection, so it is named explicitly.
+        let q =
+            SelectDsl::<SqlLiteral<Integer>>::select(query().filter(behind), sql::<Integer>("1"));
+        Some(diesel::select(exists(q)))
+    }
+
+    /// Turns the rows loaded by [`PageRequest::select`] into the page.
+    pub fn page<R>(&self, rows: Vec<R>, has_behind: bool) -> Page<R> {
+        let has_ahead = self.page_size < rows.len() as u64;
+        let mut items = rows
+            .into_iter()
+            .take(self.page_size as usize)
+            .collect::<Vec<_>>();
+
+        // A previous page is read backwards from the cursor, so it is reversed back.
+        if self.backward {
+            items.reverse();
+        }
+
+        let (has_prev, has_next) = if self.backward {
+            (has_ahead, has_behind)
+        } else {
+            (has_behind, has_ahead)
+        };
+
+        Page {
+            items,
+            pagination: PaginationMetadataDto { has_prev, has_next },
+        }
+    }
+
+    fn ascending(&self) -> bool {
+        (self.order == PaginationOrderDto::Asc) != self.backward
+    }
+}
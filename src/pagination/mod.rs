@@ -0,0 +1,5 @@
+mod cursor;
+mod keyset;
+
+pub use cursor::*;
+pub use keyset::*;
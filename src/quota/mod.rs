@@ -0,0 +1,201 @@
+use crate::db::schema::{
+    collection_file_pairs, collection_quotas, collections, file_versions, files, user_quotas,
+};
+use axum::http::StatusCode;
+use codegen::ErrorEnum;
+use diesel::prelude::*;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use thiserror::Error;
+use uuid::Uuid;
+
+/// Owner of the quota shared by the files created without an actor. No actor can claim it, as an
+/// empty `X-Actor` header is ignored.
+pub const ANONYMOUS_OWNER: &str = "";
+
+/// Whose storage quota is exhausted.
+#[derive(Debug)]
+pub enum QuotaSubject {
+    User(String),
+    Collection(Uuid),
+}
+
+impl QuotaSubject {
+    fn user(owner: &str) -> Self {
+        Self::User(owner.to_owned())
+    }
+}
+
+impl std::fmt::Display for QuotaSubject {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::User(owner) if owner == ANONYMOUS_OWNER => write!(f, "anonymous users"),
+            Self::User(owner) => write!(f, "user `{}`", owner),
+            Self::Collection(uuid) => write!(f, "collection `{}`", uuid),
+        }
+    }
+}
+
+#[derive(ErrorEnum, Error, Debug)]
+pub enum QuotaError {
+    #[error("internal server error")]
+    #[status(StatusCode::INTERNAL_SERVER_ERROR)]
+    DieselError(#[from] diesel::result::Error),
+    #[error("storage quota of {0} is exhausted; it allows at most `{1}` files")]
+    #[status(StatusCode::INSUFFICIENT_STORAGE)]
+    TooManyFiles(QuotaSubject, i64),
+    #[error("storage quota of {0} is exhausted; it allows at most `{1}` bytes")]
+    #[status(StatusCode::INSUFFICIENT_STORAGE)]
+    TooManyBytes(QuotaSubject, i64),
+}
+
+/// Limits and usage of a storage quota; the usage is kept up to date by the database.
+#[derive(Queryable, Debug)]
+pub struct RawQuotaDto {
+    pub max_bytes: Option<i64>,
+    pub max_files: Option<i64>,
+    pub used_bytes: i64,
+    pub used_files: i64,
+}
+
+/// Checks that a user, or anonymous users when there is no owner, may store one more file of
+/// `size` bytes. The quota is locked until the end of the transaction, so that concurrent
+/// creations cannot both take the last place.
+pub async fn ensure_user_file_room(
+    db_conn: &mut AsyncPgConnection,
+    owner: Option<&str>,
+    size: i64,
+) -> Result<(), QuotaError> {
+    let owner = owner.unwrap_or(ANONYMOUS_OWNER);
+
+    let quota = user_quotas::table
+        .select((
+            user_quotas::max_bytes,
+            user_quotas::max_files,
+            user_quotas::used_bytes,
+            user_quotas::used_files,
+        ))
+        .filter(user_quotas::owner.eq(owner))
+        .for_update()
+        .get_result::<RawQuotaDto>(db_conn)
+        .await
+        .optional()?;
+
+    let quota = match quota {
+        Some(quota) => quota,
+        None => return Ok(()),
+    };
+
+    if let Some(max_files) = quota.max_files.filter(|max| *max <= quota.used_files) {
+        return Err(QuotaError::TooManyFiles(
+            QuotaSubject::user(owner),
+            max_files,
+        ));
+    }
+
+    if let Some(max_bytes) = quota.max_bytes.filter(|max| *max < quota.used_bytes + size) {
+        return Err(QuotaError::TooManyBytes(
+            QuotaSubject::user(owner),
+            max_bytes,
+        ));
+    }
+
+    Ok(())
+}
+
+/// Checks that a file of `size` bytes may be added to a collection. The quota is locked until the
+/// end of the transaction.
+pub async fn ensure_collection_file_room(
+    db_conn: &mut AsyncPgConnection,
+    (collection_id, collection_uuid): (i32, Uuid),
+    size: i64,
+) -> Result<(), QuotaError> {
+    let quota = collection_quotas::table
+        .select((
+            collection_quotas::max_bytes,
+            collection_quotas::max_files,
+            collection_quotas::used_bytes,
+            collection_quotas::used_files,
+        ))
+        .filter(collection_quotas::collection_id.eq(collection_id))
+        .for_update()
+        .get_result::<RawQuotaDto>(db_conn)
+        .await
+        .optional()?;
+    let quota = match quota {
+        Some(quota) => quota,
+        None => return Ok(()),
+    };
+
+    if let Some(max_files) = quota.max_files.filter(|max| *max <= quota.used_files) {
+        return Err(QuotaError::TooManyFiles(
+            QuotaSubject::Collection(collection_uuid),
+            max_files,
+        ));
+    }
+
+    if let Some(max_bytes) = quota.max_bytes.filter(|max| *max < quota.used_bytes + size) {
+        return Err(QuotaError::TooManyBytes(
+            QuotaSubject::Collection(collection_uuid),
+            max_bytes,
+        ));
+    }
+
+    Ok(())
+}
+
+/// Finds the number of bytes the content of a file of `size` bytes may grow to, as the tightest of
+/// the quotas of its owner, or of anonymous users, and of the live collections it is in; `None`
+/// is unlimited.
+///
+/// The current content is replaced by the write, unless it is kept as the content of the current
+/// version; then it goes on counting toward the usage along with the new one.
+pub async fn find_size_budget(
+    db_conn: &mut AsyncPgConnection,
+    file_id: i32,
+    owner: Option<&str>,
+    size: i64,
+) -> QueryResult<Option<u64>> {
+    let mut max_bytes = collection_quotas::table
+        .select(collection_quotas::max_bytes.assume_not_null() - collection_quotas::used_bytes)
+        .filter(collection_quotas::max_bytes.is_not_null())
+        .filter(
+            collection_quotas::collection_id.eq_any(
+                collection_file_pairs::table
+                    .inner_join(collections::table)
+                    .select(collection_file_pairs::collection_id)
+                    .filter(collection_file_pairs::file_id.eq(file_id))
+                    // A trashed collection keeps its files, but no longer constrains them.
+                    .filter(collections::deleted_at.is_null()),
+            ),
+        )
+        .load::<i64>(db_conn)
+        .await?;
+
+    let user_max_bytes = user_quotas::table
+        .select(user_quotas::max_bytes.assume_not_null() - user_quotas::used_bytes)
+        .filter(user_quotas::max_bytes.is_not_null())
+        .filter(user_quotas::owner.eq(owner.unwrap_or(ANONYMOUS_OWNER)))
+        .get_result::<i64>(db_conn)
+        .await
+        .optional()?;
+    max_bytes.extend(user_max_bytes);
+
+    if max_bytes.is_empty() {
+        return Ok(None);
+    }
+
+    let kept_size = file_versions::table
+        .inner_join(files::table)
+        .select(file_versions::size)
+        .filter(file_versions::file_id.eq(file_id))
+        .filter(files::uploaded_at.eq(file_versions::uploaded_at.nullable()))
+        .get_result::<i64>(db_conn)
+        .await
+        .optional()?
+        .unwrap_or_default();
+
+    Ok(max_bytes
+        .into_iter()
+        .min()
+        .map(|remaining| (remaining + size - kept_size).max(0) as u64))
+}
@@ -0,0 +1,294 @@
+use crate::{
+    audit::AuditContext,
+    db::{schema::files, DBPool},
+    file_driver::{FileDriver, ListFilesError},
+    route_files::file_service::{expire_upload, fail_damaged_content, interrupt_upload},
+    schema::{dto_in::FileStatusDto, dto_out::ReconcileStorageResultDto},
+};
+use axum::http::StatusCode;
+use chrono::{Duration, NaiveDateTime, Utc};
+use codegen::ErrorEnum;
+use diesel::prelude::*;
+use diesel_async::{scoped_futures::ScopedFutureExt, AsyncConnection, RunQueryDsl};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::atomic::{AtomicBool, Ordering},
+};
+use thiserror::Error;
+use uuid::Uuid;
+
+/// Number of hours an upload is kept without progress when `UPLOAD_TTL_HOURS` is not set.
+const DEFAULT_UPLOAD_TTL_HOURS: i64 = 24;
+
+/// Interval between two reconciliations.
+const RECONCILE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60 * 60);
+
+/// Why a file whose content is missing from the storage failed.
+pub const MISSING_CONTENT: &str = "the content of the file is missing from the storage";
+
+/// Whether a reconciliation is running in this process.
+static RECONCILING: AtomicBool = AtomicBool::new(false);
+
+#[derive(ErrorEnum, Error, Debug)]
+pub enum ReconcileError {
+    #[error("internal server error")]
+    #[status(StatusCode::INTERNAL_SERVER_ERROR)]
+    PoolError(#[from] diesel_async::pooled_connection::deadpool::PoolError),
+    #[error("internal server error")]
+    #[status(StatusCode::INTERNAL_SERVER_ERROR)]
+    DieselError(#[from] diesel::result::Error),
+    #[error("{0}")]
+    #[status("0")]
+    ListFilesError(#[from] ListFilesError),
+    #[error("a reconciliation is already running")]
+    #[status(StatusCode::CONFLICT)]
+    AlreadyRunning,
+}
+
+/// Reconciles the stored contents with the files: it removes uploads abandoned for longer than
+/// `UPLOAD_TTL_HOURS` and contents that belong to no file, and marks as failed the files whose
+/// content is missing.
+#[derive(Clone)]
+pub struct Reconciler {
+    db_pool: DBPool,
+    file_driver: FileDriver,
+    upload_ttl: Duration,
+}
+
+impl Reconciler {
+    pub fn new(db_pool: DBPool, file_driver: FileDriver) -> Self {
+        let ttl_hours = match std::env::var("UPLOAD_TTL_HOURS") {
+            Ok(hours) => hours.parse::<i64>().unwrap_or_else(|_| {
+                tracing::warn!(
+                    "env var `UPLOAD_TTL_HOURS` is not a number; using {} hours",
+                    DEFAULT_UPLOAD_TTL_HOURS
+                );
+                DEFAULT_UPLOAD_TTL_HOURS
+            }),
+            Err(_) => DEFAULT_UPLOAD_TTL_HOURS,
+        };
+
+        Self {
+            db_pool,
+            file_driver,
+            upload_ttl: Duration::hours(ttl_hours),
+        }
+    }
+
+    /// Spawns a background task that reconciles the storage periodically.
+    pub fn spawn_worker(self) {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(RECONCILE_INTERVAL);
+
+            loop {
+                interval.tick().await;
+
+                match self.reconcile(false).await {
+                    Ok(_) | Err(ReconcileError::AlreadyRunning) => {}
+                    Err(err) => tracing::error!("failed to reconcile the storage: {:#?}", err),
+                }
+            }
+        });
+    }
+
+    /// Reconciles the storage, or only reports what would be done in a dry run.
+    ///
+    /// Anything that changed since it was looked at is left alone; a concurrent upload, import or
+    /// processing is never undone.
+    pub async fn reconcile(
+        &self,
+        dry_run: bool,
+    ) -> Result<ReconcileStorageResultDto, ReconcileError> {
+        let _guard = ReconcileGuard::acquire()?;
+
+        let started_at = Utc::now().naive_utc();
+        let idle_since = started_at - self.upload_ttl;
+
+        // Contents are listed before the files are loaded, so that a file created in between is
+        // not taken for missing its content.
+        let stored_files = self
+            .file_driver
+            .list_files()
+            .await?
+            .into_iter()
+            .map(|stored_file| (stored_file.uuid, stored_file.changed_at))
+            .collect::<HashMap<_, _>>();
+        let is_idle = |uuid: &Uuid| {
+            stored_files
+                .get(uuid)
+                .map_or(true, |changed_at| *changed_at < idle_since)
+        };
+
+        let db_conn = &mut self.db_pool.get().await?;
+        let rows = files::table
+            .select((
+                files::uuid,
+                files::status,
+                files::uploaded_at,
+                files::updated_at,
+            ))
+            .load::<(Uuid, FileStatusDto, Option<NaiveDateTime>, NaiveDateTime)>(db_conn)
+            .await?;
+
+        let mut expired_files = Vec::new();
+        let mut interrupted_files = Vec::new();
+        let mut missing_files = Vec::new();
+        for (uuid, status, uploaded_at, updated_at) in &rows {
+            let abandoned = *updated_at < idle_since && is_idle(uuid);
+
+            match (status, uploaded_at) {
+                (
+                    FileStatusDto::Prepared | FileStatusDto::Uploading | FileStatusDto::Failed,
+                    None,
+                ) if abandoned => expired_files.push(*uuid),
+                (FileStatusDto::Uploading, Some(_)) if abandoned => interrupted_files.push(*uuid),
+                (FileStatusDto::Processing | FileStatusDto::Ready, Some(uploaded_at))
+                    if *uploaded_at < started_at && !stored_files.contains_key(uuid) =>
+                {
+                    missing_files.push((*uuid, *uploaded_at))
+                }
+                _ => {}
+            }
+        }
+
+        let known_uuids = rows.iter().map(|row| row.0).collect::<HashSet<_>>();
+        let mut orphaned_contents = stored_files
+            .iter()
+            .filter(|(uuid, changed_at)| !known_uuids.contains(uuid) && **changed_at < idle_since)
+            .map(|(uuid, _)| *uuid)
+            .collect::<Vec<_>>();
+        drop(rows);
+
+        if dry_run {
+            return Ok(ReconcileStorageResultDto {
+                dry_run,
+                expired_files,
+                interrupted_files,
+                missing_files: missing_files.into_iter().map(|(uuid, _)| uuid).collect(),
+                orphaned_contents,
+            });
+        }
+
+        let context = AuditContext::system();
+
+        for uuid in std::mem::take(&mut expired_files) {
+            let expired = db_conn
+                .transaction(|db_conn| {
+                    expire_upload(db_conn, uuid, idle_since, &context).scope_boxed()
+                })
+                .await?;
+
+            if expired {
+                self.remove_content(uuid).await;
+                expired_files.push(uuid);
+            }
+        }
+
+        for uuid in std::mem::take(&mut interrupted_files) {
+            let interrupted = db_conn
+                .transaction(|db_conn| {
+                    interrupt_upload(db_conn, uuid, idle_since, &context).scope_boxed()
+                })
+                .await?;
+
+            if interrupted {
+                interrupted_files.push(uuid);
+            }
+        }
+
+        let mut failed_files = Vec::new();
+        for (uuid, uploaded_at) in missing_files {
+            // The content may have been restored by hand since it was listed.
+            match self.file_driver.read_file_size(uuid).await {
+                Ok(None) => {}
+                Ok(Some(_)) => continue,
+                Err(err) => {
+                    tracing::error!("failed to check the content of file `{}`: {:#?}", uuid, err);
+                    continue;
+                }
+            }
+
+            let failed = db_conn
+                .transaction(|db_conn| {
+                    fail_damaged_content(db_conn, uuid, uploaded_at, MISSING_CONTENT, &context)
+                        .scope_boxed()
+                })
+                .await?;
+
+            if failed {
+                failed_files.push(uuid);
+            }
+        }
+
+        // An import stores the content of a file before creating it.
+        let created_uuids = files::table
+            .select(files::uuid)
+            .filter(files::uuid.eq_any(&orphaned_contents))
+            .load::<Uuid>(db_conn)
+            .await?;
+        orphaned_contents.retain(|uuid| !created_uuids.contains(uuid));
+        for uuid in &orphaned_contents {
+            self.remove_content(*uuid).await;
+        }
+
+        let result = ReconcileStorageResultDto {
+            dry_run,
+            expired_files,
+            interrupted_files,
+            missing_files: failed_files,
+            orphaned_contents,
+        };
+
+        if !result.expired_files.is_empty()
+            || !result.interrupted_files.is_empty()
+            || !result.missing_files.is_empty()
+            || !result.orphaned_contents.is_empty()
+        {
+            tracing::info!(
+                "reconciled the storage: expired {} uploads, marked {} interrupted uploads and {} missing contents as failed, and removed {} orphaned contents",
+                result.expired_files.len(),
+                result.interrupted_files.len(),
+                result.missing_files.len(),
+                result.orphaned_contents.len()
+            );
+        }
+
+        Ok(result)
+    }
+
+    async fn remove_content(&self, uuid: Uuid) {
+        if let Err(err) = self.file_driver.remove_file(uuid).await {
+            tracing::error!(
+                "failed to remove the contents of file `{}`: {:#?}",
+                uuid,
+                err
+            );
+        }
+
+        if let Err(err) = self.file_driver.remove_thumbnails(uuid).await {
+            tracing::error!(
+                "failed to remove the thumbnails of file `{}`: {:#?}",
+                uuid,
+                err
+            );
+        }
+    }
+}
+
+/// Held while a reconciliation runs, so that the periodic one and a requested one never overlap.
+struct ReconcileGuard;
+
+impl ReconcileGuard {
+    fn acquire() -> Result<Self, ReconcileError> {
+        RECONCILING
+            .compare_exchange(false, true, Ordering::AcqRel, Ordering::Acquire)
+            .map(|_| Self)
+            .map_err(|_| ReconcileError::AlreadyRunning)
+    }
+}
+
+impl Drop for ReconcileGuard {
+    fn drop(&mut self) {
+        RECONCILING.store(false, Ordering::Release);
+    }
+}
@@ -1,6 +1,7 @@
 use axum::{extract::multipart::MultipartError, http::StatusCode};
 
 pub trait IntoStatus {
+    #[allow(clippy::wrong_self_convention)]
     fn into_status(&self) -> StatusCode;
 }
 
@@ -0,0 +1,690 @@
+use crate::{
+    archive::{
+        tar::{self, TarReader},
+        Manifest, ManifestCollection, ManifestFile, MANIFEST_PATH, MANIFEST_VERSION,
+    },
+    audit::AuditContext,
+    db::{
+        schema::{collection_file_pairs, collections, files, tags},
+        DBPool,
+    },
+    file_driver::{FileDriver, FileInfo, ReadFileInfoError, UploadPolicy, WriteFileError},
+    quota::QuotaError,
+    route_collections::collection_service::{
+        insert_collection, insert_collection_file, CollectionServiceError,
+    },
+    route_files::file_service::{find_duplicated_tag_title, insert_stored_file, merge_file_tags},
+    schema::{
+        dto_in::{
+            CreateCollectionBodyDto, CreateFileTagDto, ExportCollectionPathDto, FindFilesBodyDto,
+            ImportCollectionQueryDto,
+        },
+        dto_out::{CollectionDto, ImportCollectionResultDto},
+    },
+    search::{FileDocument, SearchBackend, SearchError},
+};
+use axum::{body::Bytes, http::StatusCode};
+use chrono::{NaiveDateTime, Utc};
+use codegen::ErrorEnum;
+use diesel::prelude::*;
+use diesel_async::{
+    scoped_futures::ScopedFutureExt, AsyncConnection, AsyncPgConnection, RunQueryDsl,
+};
+use futures::{stream::BoxStream, Stream, StreamExt, TryStreamExt};
+use std::{
+    collections::{HashMap, HashSet},
+    future::ready,
+    io,
+    sync::Arc,
+};
+use thiserror::Error;
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio_util::io::{ReaderStream, StreamReader};
+use uuid::Uuid;
+
+/// Largest manifest that is read into memory on import.
+const MAX_MANIFEST_SIZE: u64 = 64 * 1024 * 1024;
+
+const STREAM_BUFFER_SIZE: usize = 64 * 1024;
+
+#[derive(ErrorEnum, Error, Debug)]
+pub enum ArchiveServiceError {
+    #[error("internal server error")]
+    #[status(StatusCode::INTERNAL_SERVER_ERROR)]
+    PoolError(#[from] diesel_async::pooled_connection::deadpool::PoolError),
+    #[error("internal server error")]
+    #[status(StatusCode::INTERNAL_SERVER_ERROR)]
+    DieselError(#[from] diesel::result::Error),
+    #[error("{0}")]
+    #[status("0")]
+    CollectionServiceError(#[from] CollectionServiceError),
+    #[error("{0}")]
+    #[status("0")]
+    SearchError(#[from] SearchError),
+    #[error("{0}")]
+    #[status("0")]
+    WriteFileError(#[from] WriteFileError),
+    #[error("{0}")]
+    #[status("0")]
+    ReadFileInfoError(#[from] ReadFileInfoError),
+    #[error("{0}")]
+    #[status("0")]
+    QuotaError(#[from] QuotaError),
+    #[error("internal server error")]
+    #[status(StatusCode::INTERNAL_SERVER_ERROR)]
+    InvalidSmartQuery(serde_json::Error),
+    #[error("failed to read archive: {0}")]
+    #[status(StatusCode::UNPROCESSABLE_ENTITY)]
+    ReadArchive(io::Error),
+    #[error("archive must start with `{}`", MANIFEST_PATH)]
+    #[status(StatusCode::UNPROCESSABLE_ENTITY)]
+    MissingManifest,
+    #[error("manifest must not be larger than {} bytes", MAX_MANIFEST_SIZE)]
+    #[status(StatusCode::UNPROCESSABLE_ENTITY)]
+    ManifestTooLarge,
+    #[error("invalid manifest: {0}")]
+    #[status(StatusCode::UNPROCESSABLE_ENTITY)]
+    InvalidManifest(serde_json::Error),
+    #[error("manifest version `{0}` is not supported")]
+    #[status(StatusCode::UNPROCESSABLE_ENTITY)]
+    UnsupportedManifestVersion(u32),
+    #[error("files of a smart collection are defined by its query")]
+    #[status(StatusCode::UNPROCESSABLE_ENTITY)]
+    SmartCollectionFiles,
+    #[error("file name must not be empty")]
+    #[status(StatusCode::UNPROCESSABLE_ENTITY)]
+    EmptyFileName,
+    #[error("tag `{0}` is duplicated")]
+    #[status(StatusCode::UNPROCESSABLE_ENTITY)]
+    DuplicatedTag(String),
+    #[error("entry `{0}` appears more than once")]
+    #[status(StatusCode::UNPROCESSABLE_ENTITY)]
+    DuplicatedEntry(String),
+    #[error("entry `{0}` of the manifest is missing from the archive")]
+    #[status(StatusCode::UNPROCESSABLE_ENTITY)]
+    MissingEntry(String),
+    #[error("content of entry `{0}` does not match its size and hash in the manifest")]
+    #[status(StatusCode::UNPROCESSABLE_ENTITY)]
+    CorruptEntry(String),
+}
+
+/// A collection exported as a tar archive, streamed as it is read.
+pub struct CollectionArchive {
+    pub file_name: String,
+    pub stream: BoxStream<'static, io::Result<Bytes>>,
+}
+
+#[derive(Clone)]
+pub struct ArchiveService {
+    db_pool: DBPool,
+    file_driver: FileDriver,
+    search_backend: Arc<dyn SearchBackend>,
+    /// Server-wide policy of the uploads; an imported collection starts without overrides.
+    upload_policy: UploadPolicy,
+}
+
+impl ArchiveService {
+    pub fn new(
+        db_pool: DBPool,
+        file_driver: FileDriver,
+        search_backend: Arc<dyn SearchBackend>,
+    ) -> Self {
+        Self {
+            db_pool,
+            file_driver,
+            search_backend,
+            upload_policy: UploadPolicy::from_env(),
+        }
+    }
+
+    /// Exports the manifest of a collection and the content of its uploaded files.
+    ///
+    /// Only the metadata is loaded up front; the content of each file is read when the archive
+    /// reaches it.
+    pub async fn export_collection(
+        &self,
+        path: ExportCollectionPathDto,
+    ) -> Result<Option<CollectionArchive>, ArchiveServiceError> {
+        let db_conn = &mut self.db_pool.get().await?;
+        let collection = collections::table
+            .select((
+                collections::id,
+                collections::uuid,
+                collections::name,
+                collections::description,
+                collections::smart_query,
+                collections::created_at,
+                collections::updated_at,
+            ))
+            .filter(collections::uuid.eq(path.identifier))
+            .filter(collections::deleted_at.is_null())
+            .get_result::<RawArchiveCollection>(db_conn)
+            .await
+            .optional()?;
+        let collection = match collection {
+            Some(collection) => collection,
+            None => return Ok(None),
+        };
+        let smart_query = collection
+            .smart_query
+            .map(serde_json::from_value::<FindFilesBodyDto>)
+            .transpose()
+            .map_err(ArchiveServiceError::InvalidSmartQuery)?;
+
+        let files = if smart_query.is_some() {
+            Vec::new()
+        } else {
+            load_collection_files(db_conn, collection.id).await?
+        };
+
+        let manifest = Manifest {
+            version: MANIFEST_VERSION,
+            exported_at: Utc::now(),
+            collection: ManifestCollection {
+                uuid: collection.uuid,
+                name: collection.name,
+                description: collection.description,
+                smart_query,
+                created_at: collection.created_at.and_utc(),
+                updated_at: collection.updated_at.and_utc(),
+            },
+            files,
+        };
+        let manifest_content = Bytes::from(
+            serde_json::to_vec_pretty(&manifest).expect("failed to serialize manifest"),
+        );
+        let manifest_size = manifest_content.len() as u64;
+
+        let head = futures::stream::iter([
+            Ok(tar::entry_header(
+                MANIFEST_PATH,
+                manifest_size,
+                manifest.exported_at.timestamp(),
+            )),
+            Ok(manifest_content),
+            Ok(tar::entry_padding(manifest_size)),
+        ]);
+        let file_driver = self.file_driver.clone();
+        let entries = futures::stream::iter(manifest.files)
+            .then(move |file| file_entry(file_driver.clone(), file))
+            .try_flatten();
+        let tail = futures::stream::once(ready(Ok(tar::archive_end())));
+
+        Ok(Some(CollectionArchive {
+            file_name: format!("{}.tar", archive_file_name(&manifest.collection.name)),
+            stream: head.chain(entries).chain(tail).boxed(),
+        }))
+    }
+
+    /// Recreates a collection from an exported archive, reusing files whose content already
+    /// exists instead of storing it twice.
+    pub async fn import_collection(
+        &self,
+        query: ImportCollectionQueryDto,
+        stream: impl Stream<Item = Result<Bytes, axum::Error>>,
+        context: &AuditContext,
+    ) -> Result<ImportCollectionResultDto, ArchiveServiceError> {
+        let reader = StreamReader::new(Box::pin(
+            stream.map_err(|err| io::Error::new(io::ErrorKind::Other, err)),
+        ));
+        let mut archive = TarReader::new(reader);
+
+        let manifest = read_manifest(&mut archive).await?;
+        validate_manifest(&manifest)?;
+
+        // Contents are stored before anything is inserted, so that a broken archive leaves
+        // nothing behind.
+        let mut stored_files = Vec::with_capacity(manifest.files.len());
+        if let Err(err) = self
+            .store_files(&mut archive, &manifest.files, &mut stored_files)
+            .await
+        {
+            self.remove_stored_files(stored_files.iter().map(|stored| stored.uuid))
+                .await;
+            return Err(err);
+        }
+
+        let result = {
+            let db_conn = &mut self.db_pool.get().await?;
+            let manifest = &manifest;
+            let stored_files = &stored_files;
+            db_conn
+                .transaction(|db_conn| {
+                    async move {
+                        self.insert_archive(db_conn, query, manifest, stored_files, context)
+                            .await
+                    }
+                    .scope_boxed()
+                })
+                .await
+        };
+        let inserted = match result {
+            Ok(inserted) => inserted,
+            Err(err) => {
+                self.remove_stored_files(stored_files.iter().map(|stored| stored.uuid))
+                    .await;
+                return Err(err);
+            }
+        };
+
+        self.remove_stored_files(inserted.reused_files.iter().copied())
+            .await;
+
+        for document in &inserted.created_files {
+            self.search_backend.index_file(document).await?;
+        }
+
+        Ok(ImportCollectionResultDto {
+            collection: inserted.collection,
+            created_file_count: inserted.created_files.len() as u64,
+            reused_file_count: inserted.reused_files.len() as u64,
+        })
+    }
+
+    /// Stores the content of every file of the manifest, in the order of the archive.
+    async fn store_files<R: AsyncRead + Unpin>(
+        &self,
+        archive: &mut TarReader<R>,
+        files: &[ManifestFile],
+        stored_files: &mut Vec<StoredFile>,
+    ) -> Result<(), ArchiveServiceError> {
+        let mut indices = files
+            .iter()
+            .enumerate()
+            .map(|(index, file)| (file.path.as_str(), (index, false)))
+            .collect::<HashMap<_, _>>();
+
+        while let Some(entry) = archive
+            .next_entry()
+            .await
+            .map_err(ArchiveServiceError::ReadArchive)?
+        {
+            let (index, seen) = match indices.get_mut(entry.path.as_str()) {
+                Some(index) => index,
+                None => continue,
+            };
+
+            if *seen {
+                return Err(ArchiveServiceError::DuplicatedEntry(entry.path));
+            }
+            *seen = true;
+
+            let file = &files[*index];
+            let uuid = Uuid::new_v4();
+            stored_files.push(StoredFile {
+                index: *index,
+                uuid,
+                info: None,
+            });
+
+            let content = ReaderStream::with_capacity(&mut *archive, STREAM_BUFFER_SIZE)
+                .map_err(axum::Error::new);
+            let size = self
+                .file_driver
+                .write_file(
+                    uuid,
+                    None,
+                    Some(file.size),
+                    None,
+                    std::slice::from_ref(&self.upload_policy),
+                    content,
+                )
+                .await?;
+            let info = self.file_driver.read_file_info(uuid).await?;
+
+            if size != file.size || info.hash != file.hash {
+                return Err(ArchiveServiceError::CorruptEntry(entry.path));
+            }
+
+            stored_files
+                .last_mut()
+                .expect("stored file must exist")
+                .info = Some(info);
+        }
+
+        match indices.into_iter().find(|(_, (_, seen))| !seen) {
+            Some((path, _)) => Err(ArchiveServiceError::MissingEntry(path.to_owned())),
+            None => Ok(()),
+        }
+    }
+
+    async fn insert_archive(
+        &self,
+        db_conn: &mut AsyncPgConnection,
+        query: ImportCollectionQueryDto,
+        manifest: &Manifest,
+        stored_files: &[StoredFile],
+        context: &AuditContext,
+    ) -> Result<InsertedArchive, ArchiveServiceError> {
+        let mut collection = insert_collection(
+            db_conn,
+            CreateCollectionBodyDto {
+                name: manifest.collection.name.clone(),
+                description: manifest.collection.description.clone(),
+                parent_uuid: query.parent_uuid,
+                smart_query: manifest.collection.smart_query.clone(),
+            },
+            context,
+        )
+        .await?;
+        let collection_id = find_id(db_conn, collection.uuid).await?;
+
+        let mut created_files = Vec::new();
+        let mut reused_files = Vec::new();
+
+        for stored in stored_files {
+            let file = &manifest.files[stored.index];
+            let info = stored.info.as_ref().expect("stored file must be complete");
+
+            let (file_id, file_uuid) = match self
+                .find_same_file(db_conn, stored.uuid, info, file.size)
+                .await?
+            {
+                Some((file_id, file_uuid)) => {
+                    merge_file_tags(db_conn, file_id, &file.tags, context).await?;
+                    reused_files.push(stored.uuid);
+                    (file_id, file_uuid)
+                }
+                None => {
+                    let item = insert_stored_file(
+                        db_conn,
+                        stored.uuid,
+                        &file.name,
+                        info,
+                        file.size,
+                        &file.tags,
+                        context,
+                    )
+                    .await?;
+                    let file_id = files::table
+                        .select(files::id)
+                        .filter(files::uuid.eq(item.uuid))
+                        .get_result::<i32>(db_conn)
+                        .await?;
+                    created_files.push(FileDocument {
+                        uuid: item.uuid,
+                        name: item.name,
+                    });
+                    (file_id, item.uuid)
+                }
+            };
+
+            if insert_collection_file(
+                db_conn,
+                (collection_id, collection.uuid),
+                (file_id, file_uuid),
+                context,
+            )
+            .await?
+            {
+                collection.file_count += 1;
+            }
+        }
+
+        Ok(InsertedArchive {
+            collection,
+            created_files,
+            reused_files,
+        })
+    }
+
+    /// Finds an existing file with the same content as a stored one.
+    ///
+    /// Hashes are only CRC-32, so candidates with the same hash and size are compared byte by
+    /// byte.
+    async fn find_same_file(
+        &self,
+        db_conn: &mut AsyncPgConnection,
+        stored_uuid: Uuid,
+        info: &FileInfo,
+        size: u64,
+    ) -> Result<Option<(i32, Uuid)>, ArchiveServiceError> {
+        let candidates = files::table
+            .select((files::id, files::uuid))
+            .filter(files::hash.eq(info.hash as i64))
+            .filter(files::size.eq(size as i64))
+            .filter(files::uploaded_at.is_not_null())
+            .filter(files::deleted_at.is_null())
+            .filter(files::uuid.ne(stored_uuid))
+            .order(files::id)
+            .load::<(i32, Uuid)>(db_conn)
+            .await?;
+
+        for (file_id, file_uuid) in candidates {
+            match self.file_driver.compare_files(file_uuid, stored_uuid).await {
+                Ok(true) => return Ok(Some((file_id, file_uuid))),
+                Ok(false) => {}
+                Err(err) => {
+                    tracing::warn!("failed to compare file `{}`: {:#?}", file_uuid, err);
+                }
+            }
+        }
+
+        Ok(None)
+    }
+
+    async fn remove_stored_files(&self, uuids: impl Iterator<Item = Uuid>) {
+        for uuid in uuids {
+            if let Err(err) = self.file_driver.remove_file(uuid).await {
+                tracing::warn!("failed to remove stored file `{}`: {:#?}", uuid, err);
+            }
+        }
+    }
+}
+
+async fn load_collection_files(
+    db_conn: &mut AsyncPgConnection,
+    collection_id: i32,
+) -> QueryResult<Vec<ManifestFile>> {
+    let member_ids = collection_file_pairs::table
+        .select(collection_file_pairs::file_id)
+        .filter(collection_file_pairs::collection_id.eq(collection_id));
+
+    let raw_files = files::table
+        .select((
+            files::id,
+            files::uuid,
+            files::name,
+            files::mime.assume_not_null(),
+            files::size.assume_not_null(),
+            files::hash.assume_not_null(),
+            files::created_at,
+            files::uploaded_at.assume_not_null(),
+        ))
+        .filter(files::id.eq_any(member_ids))
+        .filter(
+            files::mime
+                .is_not_null()
+                .and(files::size.is_not_null())
+                .and(files::hash.is_not_null())
+                .and(files::uploaded_at.is_not_null())
+                .and(files::deleted_at.is_null()),
+        )
+        .order(files::id)
+        .load::<RawArchiveFile>(db_conn)
+        .await?;
+
+    let mut file_tags = HashMap::<i32, Vec<CreateFileTagDto>>::new();
+    for (file_id, title, value) in tags::table
+        .select((tags::file_id, tags::title, tags::value))
+        .filter(tags::file_id.eq_any(member_ids))
+        .order(tags::id)
+        .load::<(i32, String, Option<String>)>(db_conn)
+        .await?
+    {
+        file_tags
+            .entry(file_id)
+            .or_default()
+            .push(CreateFileTagDto { title, value });
+    }
+
+    Ok(raw_files
+        .into_iter()
+        .map(|file| ManifestFile {
+            path: ManifestFile::entry_path(file.uuid),
+            uuid: file.uuid,
+            name: file.name,
+            mime: file.mime,
+            size: file.size as u64,
+            hash: file.hash as u32,
+            tags: file_tags.remove(&file.id).unwrap_or_default(),
+            created_at: file.created_at.and_utc(),
+            uploaded_at: file.uploaded_at.and_utc(),
+        })
+        .collect())
+}
+
+/// Streams the entry of a file; it fails if the content became shorter than when it was listed.
+async fn file_entry(
+    file_driver: FileDriver,
+    file: ManifestFile,
+) -> io::Result<BoxStream<'static, io::Result<Bytes>>> {
+    let content = file_driver.open_file(file.uuid).await.map_err(|err| {
+        tracing::error!("failed to export file `{}`: {:#?}", file.uuid, err);
+        io::Error::new(io::ErrorKind::Other, err)
+    })?;
+    let length = content.metadata().await?.len();
+
+    if length < file.size {
+        tracing::error!(
+            "failed to export file `{}`: its content is shorter than its size",
+            file.uuid
+        );
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "file content is shorter than its size",
+        ));
+    }
+
+    let header = tar::entry_header(&file.path, file.size, file.uploaded_at.timestamp());
+    let padding = tar::entry_padding(file.size);
+
+    Ok(futures::stream::once(ready(Ok(header)))
+        .chain(ReaderStream::with_capacity(
+            content.take(file.size),
+            STREAM_BUFFER_SIZE,
+        ))
+        .chain(futures::stream::once(ready(Ok(padding))))
+        .boxed())
+}
+
+async fn read_manifest<R: AsyncRead + Unpin>(
+    archive: &mut TarReader<R>,
+) -> Result<Manifest, ArchiveServiceError> {
+    let entry = archive
+        .next_entry()
+        .await
+        .map_err(ArchiveServiceError::ReadArchive)?;
+    let entry = match entry {
+        Some(entry) if entry.path == MANIFEST_PATH => entry,
+        _ => return Err(ArchiveServiceError::MissingManifest),
+    };
+
+    if entry.size > MAX_MANIFEST_SIZE {
+        return Err(ArchiveServiceError::ManifestTooLarge);
+    }
+
+    let mut content = Vec::with_capacity(entry.size as usize);
+    archive
+        .read_to_end(&mut content)
+        .await
+        .map_err(ArchiveServiceError::ReadArchive)?;
+
+    let manifest = serde_json::from_slice::<Manifest>(&content)
+        .map_err(ArchiveServiceError::InvalidManifest)?;
+
+    if manifest.version != MANIFEST_VERSION {
+        return Err(ArchiveServiceError::UnsupportedManifestVersion(
+            manifest.version,
+        ));
+    }
+
+    Ok(manifest)
+}
+
+fn validate_manifest(manifest: &Manifest) -> Result<(), ArchiveServiceError> {
+    if manifest.collection.smart_query.is_some() && !manifest.files.is_empty() {
+        return Err(ArchiveServiceError::SmartCollectionFiles);
+    }
+
+    for file in &manifest.files {
+        if file.name.is_empty() {
+            return Err(ArchiveServiceError::EmptyFileName);
+        }
+
+        if let Some(title) =
+            find_duplicated_tag_title(file.tags.iter().map(|tag| tag.title.as_str()))
+        {
+            return Err(ArchiveServiceError::DuplicatedTag(title.to_owned()));
+        }
+    }
+
+    let mut paths = HashSet::with_capacity(manifest.files.len());
+    if let Some(file) = manifest.files.iter().find(|file| !paths.insert(&file.path)) {
+        return Err(ArchiveServiceError::DuplicatedEntry(file.path.clone()));
+    }
+
+    Ok(())
+}
+
+/// Name of the archive, keeping the characters of the collection name that are safe in headers.
+fn archive_file_name(name: &str) -> String {
+    let name = name
+        .chars()
+        .map(|char| match char {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_' | '.' | ' ' => char,
+            _ => '_',
+        })
+        .collect::<String>();
+
+    match name.trim() {
+        "" => "collection".to_owned(),
+        name => name.to_owned(),
+    }
+}
+
+async fn find_id(db_conn: &mut AsyncPgConnection, uuid: Uuid) -> QueryResult<i32> {
+    collections::table
+        .select(collections::id)
+        .filter(collections::uuid.eq(uuid))
+        .get_result(db_conn)
+        .await
+}
+
+struct StoredFile {
+    /// Index of the file in the manifest.
+    index: usize,
+    uuid: Uuid,
+    /// Set once the content is completely stored.
+    info: Option<FileInfo>,
+}
+
+struct InsertedArchive {
+    collection: CollectionDto,
+    created_files: Vec<FileDocument>,
+    /// Stored contents that turned out to be duplicates of existing files.
+    reused_files: Vec<Uuid>,
+}
+
+#[derive(Queryable, Debug)]
+struct RawArchiveCollection {
+    id: i32,
+    uuid: Uuid,
+    name: String,
+    description: Option<String>,
+    smart_query: Option<serde_json::Value>,
+    created_at: NaiveDateTime,
+    updated_at: NaiveDateTime,
+}
+
+#[derive(Queryable, Debug)]
+struct RawArchiveFile {
+    id: i32,
+    uuid: Uuid,
+    name: String,
+    mime: String,
+    size: i64,
+    hash: i64,
+    created_at: NaiveDateTime,
+    uploaded_at: NaiveDateTime,
+}
@@ -0,0 +1,112 @@
+use crate::app_state::AppState;
+use axum::{
+    routing::{get, post},
+    Router,
+};
+
+pub mod archive_service;
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route(
+            "/collections/:identifier/export",
+            get(handlers::export_collection),
+        )
+        .route("/collections/import", post(handlers::import_collection))
+}
+
+pub mod handlers {
+    use super::archive_service::{ArchiveService, ArchiveServiceError};
+    use crate::{
+        app_state::AppState,
+        audit::AuditContext,
+        schema::{
+            dto_in::{ExportCollectionPathDto, ImportCollectionQueryDto},
+            dto_out::ImportCollectionResultDto,
+        },
+    };
+    use axum::{
+        body::Body,
+        debug_handler,
+        extract::{Path, Query, State},
+        http::{header, StatusCode},
+        response::{IntoResponse, Response},
+        Json,
+    };
+
+    /// Export a collection as a tar archive of its files, led by a `manifest.json` of their
+    /// names, tags, hashes and timestamps.
+    ///
+    /// The archive is streamed; smart collections are exported as their query, without files.
+    #[utoipa::path(
+        get,
+        operation_id = "export-collection",
+        tag = "collection",
+        path = "/collections/{identifier}/export",
+        params(
+            ExportCollectionPathDto
+        ),
+        responses(
+            (status = OK, description = "the archive of the collection", body = Vec<u8>, content_type = "application/x-tar"),
+            (status = NOT_FOUND, description = "the collection does not exist"),
+            (status = INTERNAL_SERVER_ERROR, description = "an error has occurred", body = ErrorBody),
+        ),
+    )]
+    #[debug_handler(state = AppState)]
+    pub async fn export_collection(
+        State(archive_service): State<ArchiveService>,
+        Path(path): Path<ExportCollectionPathDto>,
+    ) -> Result<Response, ArchiveServiceError> {
+        match archive_service.export_collection(path).await? {
+            Some(archive) => Ok((
+                StatusCode::OK,
+                [
+                    (header::CONTENT_TYPE, "application/x-tar".to_owned()),
+                    (
+                        header::CONTENT_DISPOSITION,
+                        format!("attachment; filename=\"{}\"", archive.file_name),
+                    ),
+                ],
+                Body::from_stream(archive.stream),
+            )
+                .into_response()),
+            None => Ok(StatusCode::NOT_FOUND.into_response()),
+        }
+    }
+
+    /// Recreate a collection, its files and their tags from an exported archive.
+    ///
+    /// Files whose content already exists are reused instead of being stored again; the tags
+    /// they lack are added to them.
+    #[utoipa::path(
+        post,
+        operation_id = "import-collection",
+        tag = "collection",
+        path = "/collections/import",
+        params(
+            ImportCollectionQueryDto
+        ),
+        request_body(content = Vec<u8>, content_type = "application/x-tar"),
+        responses(
+            (status = CREATED, body = ImportCollectionResultDto),
+            (status = UNPROCESSABLE_ENTITY, description = "the archive or the parent collection is invalid", body = ErrorBody),
+            (status = INTERNAL_SERVER_ERROR, description = "an error has occurred", body = ErrorBody),
+            (status = PAYLOAD_TOO_LARGE, description = "a file exceeds the maximum file size", body = ErrorBody),
+            (status = UNSUPPORTED_MEDIA_TYPE, description = "the type of a file is not allowed", body = ErrorBody),
+            (status = INSUFFICIENT_STORAGE, description = "the storage quota of the actor has no room for the files", body = ErrorBody),
+        ),
+    )]
+    #[debug_handler(state = AppState)]
+    pub async fn import_collection(
+        State(archive_service): State<ArchiveService>,
+        context: AuditContext,
+        Query(query): Query<ImportCollectionQueryDto>,
+        body: Body,
+    ) -> Result<(StatusCode, Json<ImportCollectionResultDto>), ArchiveServiceError> {
+        let result = archive_service
+            .import_collection(query, body.into_data_stream(), &context)
+            .await?;
+
+        Ok((StatusCode::CREATED, Json(result)))
+    }
+}
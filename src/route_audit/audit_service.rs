@@ -0,0 +1,231 @@
+use crate::{
+    audit::{enum_name, parse_enum_name},
+    db::{schema::audit_events, DBPool},
+    pagination::{BoxedPredicate, CursorCodec, CursorError, Keyset, PageRequest, PaginationError},
+    schema::{
+        dto_in::FindAuditEventsQueryDto,
+        dto_out::{AuditEventDto, CursorPaginationMetadataDto, FindAuditEventsResultDto},
+    },
+};
+use axum::http::StatusCode;
+use chrono::NaiveDateTime;
+use codegen::ErrorEnum;
+use diesel::{dsl::IntoBoxed, pg::Pg, prelude::*};
+use diesel_async::RunQueryDsl;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use uuid::Uuid;
+
+#[derive(ErrorEnum, Error, Debug)]
+#[allow(clippy::enum_variant_names)]
+pub enum AuditServiceError {
+    #[error("internal server error")]
+    #[status(StatusCode::INTERNAL_SERVER_ERROR)]
+    PoolError(#[from] diesel_async::pooled_connection::deadpool::PoolError),
+    #[error("internal server error")]
+    #[status(StatusCode::INTERNAL_SERVER_ERROR)]
+    DieselError(#[from] diesel::result::Error),
+    #[error("{0}")]
+    #[status("0")]
+    PaginationError(#[from] PaginationError),
+    #[error("{0}")]
+    #[status("0")]
+    CursorError(#[from] CursorError),
+}
+
+#[derive(Clone)]
+pub struct AuditService {
+    db_pool: DBPool,
+    cursor_codec: CursorCodec,
+}
+
+impl AuditService {
+    pub fn new(db_pool: DBPool, cursor_codec: CursorCodec) -> Self {
+        Self {
+            db_pool,
+            cursor_codec,
+        }
+    }
+
+    pub async fn find_audit_events(
+        &self,
+        query: FindAuditEventsQueryDto,
+    ) -> Result<FindAuditEventsResultDto, AuditServiceError> {
+        let first = query
+            .first_cursor
+            .as_deref()
+            .map(|cursor| self.cursor_codec.decode::<AuditEventKey>(cursor))
+            .transpose()?;
+        let last = query
+            .last_cursor
+            .as_deref()
+            .map(|cursor| self.cursor_codec.decode::<AuditEventKey>(cursor))
+            .transpose()?;
+        let request = PageRequest::new(first, last, (), query.order, query.page_size)?;
+
+        let action = query.action.map(|action| enum_name(&action));
+        let resource_type = query
+            .resource_type
+            .map(|resource_type| enum_name(&resource_type));
+        let occurred_after = query.occurred_after.map(|time| time.naive_utc());
+        let occurred_before = query.occurred_before.map(|time| time.naive_utc());
+
+        let db_conn = &mut self.db_pool.get().await?;
+        let matching = || {
+            let mut select = audit_events::table.into_boxed();
+
+            if let Some(action) = &action {
+                select = select.filter(audit_events::action.eq(action));
+            }
+
+            if let Some(resource_type) = &resource_type {
+                select = select.filter(audit_events::resource_type.eq(resource_type));
+            }
+
+            if let Some(resource_uuid) = query.resource_uuid {
+                select = select.filter(audit_events::resource_uuid.eq(resource_uuid));
+            }
+
+            if let Some(request_id) = &query.request_id {
+                select = select.filter(audit_events::request_id.eq(request_id));
+            }
+
+            if let Some(actor) = &query.actor {
+                select = select.filter(audit_events::actor.eq(actor));
+            }
+
+            if let Some(occurred_after) = occurred_after {
+                select = select.filter(audit_events::occurred_at.ge(occurred_after));
+            }
+
+            if let Some(occurred_before) = occurred_before {
+                select = select.filter(audit_events::occurred_at.lt(occurred_before));
+            }
+
+            select
+        };
+        let rows = request
+            .select(
+                &matching,
+                (
+                    audit_events::uuid,
+                    audit_events::occurred_at,
+                    audit_events::request_id,
+                    audit_events::actor,
+                    audit_events::action,
+                    audit_events::resource_type,
+                    audit_events::resource_uuid,
+                    audit_events::before,
+                    audit_events::after,
+                ),
+            )
+            .load::<RawAuditEventDto>(db_conn)
+            .await?;
+        let has_behind = match request.behind(&matching) {
+            Some(behind) => behind.get_result(db_conn).await?,
+            None => false,
+        };
+        let page = request.page(rows, has_behind);
+
+        let pagination = CursorPaginationMetadataDto {
+            has_prev: page.pagination.has_prev,
+            has_next: page.pagination.has_next,
+            first_cursor: page
+                .items
+                .as_slice()
+                .first()
+                .map(|item| self.cursor_codec.encode(&AuditEventKey::new(item, ()))),
+            last_cursor: page
+                .items
+                .as_slice()
+                .last()
+                .map(|item| self.cursor_codec.encode(&AuditEventKey::new(item, ()))),
+        };
+        let items = page
+            .items
+            .into_iter()
+            .map(|raw_item| raw_item.into())
+            .collect();
+
+        Ok(FindAuditEventsResultDto { pagination, items })
+    }
+}
+
+/// Position of an event in the audit log, which is ordered by occurrence time.
+#[derive(Serialize, Deserialize, Debug)]
+struct AuditEventKey(NaiveDateTime, Uuid);
+
+impl Keyset for AuditEventKey {
+    type Table = audit_events::table;
+    type Sort = ();
+    type Row = RawAuditEventDto;
+
+    fn new(row: &RawAuditEventDto, _sort: ()) -> Self {
+        Self(row.occurred_at, row.uuid)
+    }
+
+    fn sort(&self) {}
+
+    fn beyond(&self, ascending: bool) -> BoxedPredicate<'_, audit_events::table> {
+        let Self(occurred_at, uuid) = self;
+
+        if ascending {
+            Box::new(
+                audit_events::occurred_at
+                    .gt(occurred_at)
+                    .or(audit_events::occurred_at
+                        .eq(occurred_at)
+                        .and(audit_events::uuid.gt(uuid))),
+            )
+        } else {
+            Box::new(
+                audit_events::occurred_at
+                    .lt(occurred_at)
+                    .or(audit_events::occurred_at
+                        .eq(occurred_at)
+                        .and(audit_events::uuid.lt(uuid))),
+            )
+        }
+    }
+
+    fn order(
+        query: IntoBoxed<'_, audit_events::table, Pg>,
+        _sort: (),
+        ascending: bool,
+    ) -> IntoBoxed<'_, audit_events::table, Pg> {
+        if ascending {
+            query.order((audit_events::occurred_at.asc(), audit_events::uuid.asc()))
+        } else {
+            query.order((audit_events::occurred_at.desc(), audit_events::uuid.desc()))
+        }
+    }
+}
+
+#[derive(Queryable, Debug)]
+struct RawAuditEventDto {
+    uuid: Uuid,
+    occurred_at: NaiveDateTime,
+    request_id: String,
+    actor: Option<String>,
+    action: String,
+    resource_type: String,
+    resource_uuid: Uuid,
+    before: Option<serde_json::Value>,
+    after: Option<serde_json::Value>,
+}
+
+impl From<RawAuditEventDto> for AuditEventDto {
+    fn from(item: RawAuditEventDto) -> Self {
+        Self {
+            uuid: item.uuid,
+            occurred_at: item.occurred_at.and_utc(),
+            request_id: item.request_id,
+            actor: item.actor,
+            action: parse_enum_name(item.action),
+            resource_type: parse_enum_name(item.resource_type),
+            resource_uuid: item.resource_uuid,
+            before: item.before,
+            after: item.after,
+        }
+    }
+}
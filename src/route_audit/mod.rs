@@ -0,0 +1,47 @@
+use crate::app_state::AppState;
+use axum::{routing::get, Router};
+
+pub mod audit_service;
+
+pub fn router() -> Router<AppState> {
+    Router::new().route("/audit", get(handlers::find_audit_events))
+}
+
+pub mod handlers {
+    use super::audit_service::{AuditService, AuditServiceError};
+    use crate::{
+        app_state::AppState,
+        schema::{dto_in::FindAuditEventsQueryDto, dto_out::FindAuditEventsResultDto},
+    };
+    use axum::{
+        debug_handler,
+        extract::{Query, State},
+        http::StatusCode,
+        Json,
+    };
+
+    /// Finds events of the audit log, most recent first by default.
+    #[utoipa::path(
+        get,
+        operation_id = "find-audit-events",
+        tag = "audit",
+        path = "/audit",
+        params(
+            FindAuditEventsQueryDto
+        ),
+        responses(
+            (status = OK, body = FindAuditEventsResultDto),
+            (status = UNPROCESSABLE_ENTITY, description = "the pagination parameters are invalid", body = ErrorBody),
+            (status = INTERNAL_SERVER_ERROR, description = "an error has occurred", body = ErrorBody),
+        ),
+    )]
+    #[debug_handler(state = AppState)]
+    pub async fn find_audit_events(
+        State(audit_service): State<AuditService>,
+        Query(query): Query<FindAuditEventsQueryDto>,
+    ) -> Result<(StatusCode, Json<FindAuditEventsResultDto>), AuditServiceError> {
+        let result = audit_service.find_audit_events(query).await?;
+
+        Ok((StatusCode::OK, Json(result)))
+    }
+}
@@ -1,26 +1,56 @@
 use crate::{
-    db::DBPool,
+    audit::{record_event, snapshot, AuditContext},
+    db::{
+        escape_like_pattern,
+        schema::{collection_file_pairs, collection_upload_policies, collections, files},
+        DBPool,
+    },
+    etag::{ETag, IfMatch},
+    file_driver::{is_mime_type_pattern, UploadPolicy, WriteFileError},
+    pagination::{BoxedPredicate, CursorCodec, CursorError, Keyset, PageRequest, PaginationError},
+    quota::{ensure_collection_file_room, QuotaError},
+    route_files::file_service::find_duplicated_tag_title,
     schema::{
         dto_in::{
-            CreateCollectionBodyDto, FindCollectionPathDto, FindCollectionsQueryDto,
-            PaginationOrderDto, RemoveCollectionPathDto, UpdateCollectionBodyDto,
-            UpdateCollectionPathDto,
+            AddCollectionFilePathDto, AuditActionDto, CollectionSortDto, CreateCollectionBodyDto,
+            FindCollectionPathDto, FindCollectionUploadPolicyPathDto, FindCollectionsQueryDto,
+            FindFilesBodyDto, FindTrashQueryDto, MoveCollectionBodyDto, MoveCollectionPathDto,
+            NameMatchDto, PatchCollectionPathDto, RemoveCollectionFilePathDto,
+            RemoveCollectionModeDto, RemoveCollectionPathDto, RemoveCollectionQueryDto,
+            RestoreCollectionPathDto, UpdateCollectionBodyDto, UpdateCollectionPathDto,
+            UpdateCollectionUploadPolicyPathDto, UpdateUploadPolicyBodyDto,
+        },
+        dto_out::{
+            CollectionDto, CollectionKindDto, CursorPaginationMetadataDto,
+            FindCollectionsResultDto, RemoveCollectionResultDto, UploadPolicyDto,
         },
-        dto_out::{CollectionDto, FindCollectionsResultDto, PaginationMetadataDto},
+        merge_patch::merge_patch,
     },
 };
 use axum::http::StatusCode;
 use chrono::NaiveDateTime;
 use codegen::ErrorEnum;
 use diesel::{
+    alias,
+    dsl::{exists, not, now, sql, Filter, IntoBoxed, IsNull, Select},
+    expression::SqlLiteral,
+    pg::Pg,
     prelude::*,
-    sql_types::{Bool, Integer},
+    sql_types::{BigInt, Bool, Nullable},
+    upsert::excluded,
+};
+use diesel_async::{
+    scoped_futures::ScopedFutureExt, AsyncConnection, AsyncPgConnection, RunQueryDsl,
 };
-use diesel_async::{scoped_futures::ScopedFutureExt, AsyncConnection, RunQueryDsl};
 use serde::{Deserialize, Serialize};
+use serde_json::json;
 use thiserror::Error;
 use uuid::Uuid;
 
+/// Key of the advisory lock that serializes changes to the collection tree, so that concurrent
+/// moves cannot form a cycle together.
+const COLLECTION_TREE_LOCK_KEY: i64 = 0x636f6c6c656374;
+
 #[derive(ErrorEnum, Error, Debug)]
 pub enum CollectionServiceError {
     #[error("internal server error")]
@@ -29,223 +59,1276 @@ pub enum CollectionServiceError {
     #[error("internal server error")]
     #[status(StatusCode::INTERNAL_SERVER_ERROR)]
     DieselError(#[from] diesel::result::Error),
+    #[error("{0}")]
+    #[status("0")]
+    PaginationError(#[from] PaginationError),
+    #[error("{0}")]
+    #[status("0")]
+    CursorError(#[from] CursorError),
+    #[error("{0}")]
+    #[status("0")]
+    QuotaError(#[from] QuotaError),
+    #[error("{0}")]
+    #[status("0")]
+    WriteFileError(#[from] WriteFileError),
+    #[error("parent collection `{0}` does not exist")]
+    #[status(StatusCode::UNPROCESSABLE_ENTITY)]
+    ParentNotFound(Uuid),
+    #[error("a collection cannot be moved into itself or one of its descendants")]
+    #[status(StatusCode::UNPROCESSABLE_ENTITY)]
+    CyclicParent,
+    #[error("tag `{0}` is duplicated")]
+    #[status(StatusCode::UNPROCESSABLE_ENTITY)]
+    DuplicatedTag(String),
+    #[error("the kind of a collection cannot be changed")]
+    #[status(StatusCode::UNPROCESSABLE_ENTITY)]
+    KindMismatch,
+    #[error("files of a smart collection are defined by its query")]
+    #[status(StatusCode::CONFLICT)]
+    SmartCollectionFiles,
+    #[error("collection has {0} sub-collections; move or remove them first")]
+    #[status(StatusCode::CONFLICT)]
+    HasChildren(i64),
+    #[error(
+        "collection has {0} files; remove them first, or use the `detach` or `deleteOrphans` mode"
+    )]
+    #[status(StatusCode::CONFLICT)]
+    NotEmpty(i64),
+    #[error("parent collection `{0}` is in the trash; restore it first")]
+    #[status(StatusCode::CONFLICT)]
+    ParentTrashed(Uuid),
+    #[error("collection was modified since it was read")]
+    #[status(StatusCode::PRECONDITION_FAILED)]
+    PreconditionFailed,
+    #[error("invalid patch: {0}")]
+    #[status(StatusCode::UNPROCESSABLE_ENTITY)]
+    InvalidPatch(serde_json::Error),
+    #[error("maximum file size must not be negative")]
+    #[status(StatusCode::UNPROCESSABLE_ENTITY)]
+    NegativeMaxFileSize,
+    #[error("`{0}` is not a type such as `image/png` or `image/*`")]
+    #[status(StatusCode::UNPROCESSABLE_ENTITY)]
+    InvalidMimeType(String),
 }
 
 #[derive(Clone)]
 pub struct CollectionService {
     db_pool: DBPool,
+    cursor_codec: CursorCodec,
+    /// Server-wide policy of the uploads, which collections may override.
+    upload_policy: UploadPolicy,
 }
 
 impl CollectionService {
-    pub fn new(db_pool: DBPool) -> Self {
-        Self { db_pool }
+    pub fn new(db_pool: DBPool, cursor_codec: CursorCodec) -> Self {
+        Self {
+            db_pool,
+            cursor_codec,
+            upload_policy: UploadPolicy::from_env(),
+        }
     }
 
     pub async fn find_collections(
         &self,
         query: FindCollectionsQueryDto,
     ) -> Result<FindCollectionsResultDto, CollectionServiceError> {
+        let first = query
+            .first_cursor
+            .as_deref()
+            .map(|cursor| self.cursor_codec.decode::<CollectionKey>(cursor))
+            .transpose()?;
+        let last = query
+            .last_cursor
+            .as_deref()
+            .map(|cursor| self.cursor_codec.decode::<CollectionKey>(cursor))
+            .transpose()?;
+        let request = PageRequest::new(first, last, query.sort, query.order, query.page_size)?;
+
+        let db_conn = &mut self.db_pool.get().await?;
+        let matching = || filter_collections(&query);
+        let rows = request
+            .select(&matching, collection_columns())
+            .load::<RawCollectionDto>(db_conn)
+            .await?;
+        let has_behind = match request.behind(&matching) {
+            Some(behind) => behind.get_result(db_conn).await?,
+            None => false,
+        };
+        let page = request.page(rows, has_behind);
+
+        let pagination = CursorPaginationMetadataDto {
+            has_prev: page.pagination.has_prev,
+            has_next: page.pagination.has_next,
+            first_cursor: page.items.as_slice().first().map(|item| {
+                self.cursor_codec
+                    .encode(&CollectionKey::new(item, query.sort))
+            }),
+            last_cursor: page.items.as_slice().last().map(|item| {
+                self.cursor_codec
+                    .encode(&CollectionKey::new(item, query.sort))
+            }),
+        };
+        let items = page
+            .items
+            .into_iter()
+            .map(|raw_item| raw_item.into())
+            .collect();
+
+        Ok(FindCollectionsResultDto { pagination, items })
+    }
+
+    pub async fn find_collection(
+        &self,
+        path: FindCollectionPathDto,
+    ) -> Result<Option<CollectionDto>, CollectionServiceError> {
+        use crate::db::schema::collections::dsl::*;
+
+        let db_conn = &mut self.db_pool.get().await?;
+        let raw_item = collections
+            .select(collection_columns())
+            .filter(uuid.eq(path.identifier))
+            .filter(deleted_at.is_null())
+            .get_result::<RawCollectionDto>(db_conn)
+            .await
+            .optional()?;
+
+        Ok(raw_item.map(|item| item.into()))
+    }
+
+    pub async fn create_collection(
+        &self,
+        body: CreateCollectionBodyDto,
+        context: &AuditContext,
+    ) -> Result<CollectionDto, CollectionServiceError> {
+        let db_conn = &mut self.db_pool.get().await?;
+        db_conn
+            .transaction(|db_conn| insert_collection(db_conn, body, context).scope_boxed())
+            .await
+    }
+
+    pub async fn update_collection(
+        &self,
+        path: UpdateCollectionPathDto,
+        body: UpdateCollectionBodyDto,
+        if_match: IfMatch,
+        context: &AuditContext,
+    ) -> Result<Option<CollectionDto>, CollectionServiceError> {
         use crate::db::schema::collections::dsl::*;
 
+        let smart_query_value = body
+            .smart_query
+            .as_ref()
+            .map(serialize_smart_query)
+            .transpose()?;
+
         let db_conn = &mut self.db_pool.get().await?;
         db_conn
             .transaction(|db_conn| {
                 async move {
-                    let mut q = collections
-                        .select((id, uuid, name, description, created_at))
-                        .limit(query.page_size as i64)
-                        .into_boxed();
-
-                    if let Some(first_id) = query.first_id {
-                        match query.order {
-                            PaginationOrderDto::Asc => {
-                                q = q.filter(id.lt(first_id));
-                            }
-                            PaginationOrderDto::Desc => {
-                                q = q.filter(id.gt(first_id));
-                            }
-                        }
-                    }
+                    let current = collections
+                        .select(collection_columns())
+                        .filter(uuid.eq(path.identifier))
+                        .filter(deleted_at.is_null())
+                        .for_update()
+                        .get_result::<RawCollectionDto>(db_conn)
+                        .await
+                        .optional()?;
+                    let (collection_id, current) = match current {
+                        Some(current) => (current.id, CollectionDto::from(current)),
+                        None => return Ok(None),
+                    };
 
-                    if let Some(last_id) = query.last_id {
-                        match query.order {
-                            PaginationOrderDto::Asc => {
-                                q = q.filter(id.gt(last_id));
-                            }
-                            PaginationOrderDto::Desc => {
-                                q = q.filter(id.lt(last_id));
-                            }
-                        }
+                    if !if_match.matches(&ETag::from_updated_at(current.updated_at)) {
+                        return Err(CollectionServiceError::PreconditionFailed);
                     }
 
-                    match query.order {
-                        PaginationOrderDto::Asc => {
-                            q = q.order(id.asc());
-                        }
-                        PaginationOrderDto::Desc => {
-                            q = q.order(id.desc());
-                        }
+                    let is_smart = current.kind == CollectionKindDto::Smart;
+
+                    if is_smart != smart_query_value.is_some() {
+                        return Err(CollectionServiceError::KindMismatch);
                     }
 
-                    if let Some(filter_name) = &query.filter_name {
-                        q = q.filter(name.eq(filter_name));
+                    let raw_item = diesel::update(collections.filter(id.eq(collection_id)))
+                        .set((
+                            name.eq(body.name),
+                            description.eq(body.description),
+                            smart_query.eq(smart_query_value),
+                        ))
+                        .returning(collection_columns())
+                        .get_result::<RawCollectionDto>(db_conn)
+                        .await?;
+                    let item = CollectionDto::from(raw_item);
+
+                    record_event(
+                        db_conn,
+                        context,
+                        AuditActionDto::UpdateCollection,
+                        item.uuid,
+                        Some(snapshot(&current)),
+                        Some(snapshot(&item)),
+                    )
+                    .await?;
+
+                    Ok(Some(item))
+                }
+                .scope_boxed()
+            })
+            .await
+    }
+
+    pub async fn patch_collection(
+        &self,
+        path: PatchCollectionPathDto,
+        patch: serde_json::Value,
+        if_match: IfMatch,
+        context: &AuditContext,
+    ) -> Result<Option<CollectionDto>, CollectionServiceError> {
+        use crate::db::schema::collections::dsl::*;
+
+        let db_conn = &mut self.db_pool.get().await?;
+        db_conn
+            .transaction(|db_conn| {
+                async move {
+                    let current = collections
+                        .select(collection_columns())
+                        .filter(uuid.eq(path.identifier))
+                        .filter(deleted_at.is_null())
+                        .for_update()
+                        .get_result::<RawCollectionDto>(db_conn)
+                        .await
+                        .optional()?;
+                    let (collection_id, current) = match current {
+                        Some(current) => (current.id, CollectionDto::from(current)),
+                        None => return Ok(None),
+                    };
+
+                    if !if_match.matches(&ETag::from_updated_at(current.updated_at)) {
+                        return Err(CollectionServiceError::PreconditionFailed);
                     }
 
-                    let raw_items = q.load::<RawCollectionDto>(db_conn).await?;
-                    let has_prev_q = match query.order {
-                        PaginationOrderDto::Asc => {
-                            let mut q = collections
-                                .select(diesel::dsl::sql::<Integer>("1"))
-                                .into_boxed();
-
-                            if let Some(first) = raw_items.as_slice().first() {
-                                q = q.filter(id.lt(first.id));
-                            } else {
-                                q = q.filter(diesel::dsl::sql::<Bool>("false"));
-                            }
+                    let is_smart = current.kind == CollectionKindDto::Smart;
+                    let mut document = serde_json::to_value(UpdateCollectionBodyDto {
+                        name: current.name.clone(),
+                        description: current.description.clone(),
+                        smart_query: current.smart_query.clone(),
+                    })
+                    .expect("failed to serialize collection");
+                    merge_patch(&mut document, &patch);
+                    let body = serde_json::from_value::<UpdateCollectionBodyDto>(document)
+                        .map_err(CollectionServiceError::InvalidPatch)?;
 
-                            if let Some(filter_name) = &query.filter_name {
-                                q = q.filter(name.eq(filter_name));
-                            }
+                    let smart_query_value = body
+                        .smart_query
+                        .as_ref()
+                        .map(serialize_smart_query)
+                        .transpose()?;
 
-                            q
-                        }
-                        PaginationOrderDto::Desc => {
-                            let mut q = collections
-                                .select(diesel::dsl::sql::<Integer>("1"))
-                                .into_boxed();
-
-                            if let Some(last) = raw_items.as_slice().last() {
-                                q = q.filter(id.gt(last.id));
-                            } else {
-                                q = q.filter(diesel::dsl::sql::<Bool>("false"));
-                            }
+                    if is_smart != smart_query_value.is_some() {
+                        return Err(CollectionServiceError::KindMismatch);
+                    }
 
-                            if let Some(filter_name) = &query.filter_name {
-                                q = q.filter(name.eq(filter_name));
-                            }
+                    let raw_item = diesel::update(collections.filter(id.eq(collection_id)))
+                        .set((
+                            name.eq(body.name),
+                            description.eq(body.description),
+                            smart_query.eq(smart_query_value),
+                        ))
+                        .returning(collection_columns())
+                        .get_result::<RawCollectionDto>(db_conn)
+                        .await?;
+                    let item = CollectionDto::from(raw_item);
 
-                            q
-                        }
+                    record_event(
+                        db_conn,
+                        context,
+                        AuditActionDto::UpdateCollection,
+                        item.uuid,
+                        Some(snapshot(&current)),
+                        Some(snapshot(&item)),
+                    )
+                    .await?;
+
+                    Ok(Some(item))
+                }
+                .scope_boxed()
+            })
+            .await
+    }
+
+    pub async fn remove_collection(
+        &self,
+        path: RemoveCollectionPathDto,
+        query: RemoveCollectionQueryDto,
+        if_match: IfMatch,
+        context: &AuditContext,
+    ) -> Result<Option<RemoveCollectionResultDto>, CollectionServiceError> {
+        let db_conn = &mut self.db_pool.get().await?;
+        db_conn
+            .transaction(|db_conn| {
+                async move {
+                    let current = collections::table
+                        .select(collection_columns())
+                        .filter(collections::uuid.eq(path.identifier))
+                        .filter(collections::deleted_at.is_null())
+                        .for_update()
+                        .get_result::<RawCollectionDto>(db_conn)
+                        .await
+                        .optional()?;
+                    let (collection_id, current) = match current {
+                        Some(current) => (current.id, CollectionDto::from(current)),
+                        None => return Ok(None),
                     };
-                    let has_next_q = match query.order {
-                        PaginationOrderDto::Asc => {
-                            let mut q = collections
-                                .select(diesel::dsl::sql::<Integer>("1"))
-                                .into_boxed();
-
-                            if let Some(last) = raw_items.as_slice().last() {
-                                q = q.filter(id.gt(last.id));
-                            } else {
-                                q = q.filter(diesel::dsl::sql::<Bool>("false"));
-                            }
 
-                            if let Some(filter_name) = &query.filter_name {
-                                q = q.filter(name.eq(filter_name));
-                            }
+                    if !if_match.matches(&ETag::from_updated_at(current.updated_at)) {
+                        return Err(CollectionServiceError::PreconditionFailed);
+                    }
 
-                            q
-                        }
-                        PaginationOrderDto::Desc => {
-                            let mut q = collections
-                                .select(diesel::dsl::sql::<Integer>("1"))
-                                .into_boxed();
-
-                            if let Some(first) = raw_items.as_slice().first() {
-                                q = q.filter(id.lt(first.id));
-                            } else {
-                                q = q.filter(diesel::dsl::sql::<Bool>("false"));
-                            }
+                    let member_count = current.file_count;
 
-                            if let Some(filter_name) = &query.filter_name {
-                                q = q.filter(name.eq(filter_name));
-                            }
+                    let child_count = collections::table
+                        .filter(collections::parent_id.eq(collection_id))
+                        .filter(collections::deleted_at.is_null())
+                        .count()
+                        .get_result::<i64>(db_conn)
+                        .await?;
 
-                            q
-                        }
+                    if child_count != 0 {
+                        return Err(CollectionServiceError::HasChildren(child_count));
+                    }
+
+                    if query.mode == RemoveCollectionModeDto::Refuse && member_count != 0 {
+                        return Err(CollectionServiceError::NotEmpty(member_count));
+                    }
+
+                    // Orphans are the members that no other live collection holds.
+                    let orphan_ids = if query.mode == RemoveCollectionModeDto::DeleteOrphans {
+                        let other_pairs = alias!(collection_file_pairs as other_pairs);
+                        collection_file_pairs::table
+                            .select(collection_file_pairs::file_id)
+                            .filter(collection_file_pairs::collection_id.eq(collection_id))
+                            .filter(not(exists(
+                                other_pairs
+                                    .filter(
+                                        other_pairs
+                                            .field(collection_file_pairs::file_id)
+                                            .eq(collection_file_pairs::file_id),
+                                    )
+                                    .filter(
+                                        other_pairs
+                                            .field(collection_file_pairs::collection_id)
+                                            .ne(collection_id),
+                                    )
+                                    .filter(
+                                        other_pairs
+                                            .field(collection_file_pairs::collection_id)
+                                            .eq_any(live_collection_ids()),
+                                    ),
+                            )))
+                            .load::<i32>(db_conn)
+                            .await?
+                    } else {
+                        vec![]
                     };
 
-                    let has_prev_q = diesel::select(diesel::dsl::exists(has_prev_q));
-                    let has_next_q = diesel::select(diesel::dsl::exists(has_next_q));
+                    // The members stay paired with the collection while it is in the trash, so
+                    // that restoring it brings them back; the pairs go when the trash is purged.
+                    let detached_file_count = member_count;
+
+                    let trashed_files = diesel::update(
+                        files::table
+                            .filter(files::id.eq_any(&orphan_ids))
+                            .filter(files::deleted_at.is_null()),
+                    )
+                    .set(files::deleted_at.eq(now))
+                    .returning(files::uuid)
+                    .get_results::<Uuid>(db_conn)
+                    .await?;
 
-                    let has_prev = has_prev_q.get_result::<bool>(db_conn).await?;
-                    let has_next = has_next_q.get_result::<bool>(db_conn).await?;
-                    let pagination = PaginationMetadataDto { has_prev, has_next };
+                    let raw_item = diesel::update(
+                        collections::table.filter(collections::id.eq(collection_id)),
+                    )
+                    .set(collections::deleted_at.eq(now))
+                    .returning(collection_columns())
+                    .get_result::<RawCollectionDto>(db_conn)
+                    .await?;
+
+                    let result = RemoveCollectionResultDto {
+                        mode: query.mode,
+                        collection: raw_item.into(),
+                        detached_file_count,
+                        trashed_files,
+                    };
 
-                    let items = raw_items
-                        .into_iter()
-                        .map(|raw_item| raw_item.into())
-                        .collect();
+                    // The whole result is kept, so the detached and trashed files are traceable.
+                    record_event(
+                        db_conn,
+                        context,
+                        AuditActionDto::RemoveCollection,
+                        result.collection.uuid,
+                        Some(snapshot(&current)),
+                        Some(snapshot(&result)),
+                    )
+                    .await?;
 
-                    Ok(FindCollectionsResultDto { pagination, items })
+                    Ok(Some(result))
                 }
                 .scope_boxed()
             })
             .await
     }
 
-    pub async fn find_collection(
+    pub async fn find_trashed_collections(
         &self,
-        path: FindCollectionPathDto,
-    ) -> Result<Option<CollectionDto>, CollectionServiceError> {
-        use crate::db::schema::collections::dsl::*;
+        query: FindTrashQueryDto,
+    ) -> Result<FindCollectionsResultDto, CollectionServiceError> {
+        let first = query
+            .first_cursor
+            .as_deref()
+            .map(|cursor| self.cursor_codec.decode::<TrashedCollectionKey>(cursor))
+            .transpose()?;
+        let last = query
+            .last_cursor
+            .as_deref()
+            .map(|cursor| self.cursor_codec.decode::<TrashedCollectionKey>(cursor))
+            .transpose()?;
+        let request = PageRequest::new(first, last, (), query.order, query.page_size)?;
 
         let db_conn = &mut self.db_pool.get().await?;
-        let raw_item = collections
-            .filter(uuid.eq(path.identifier))
-            .get_result::<RawCollectionDto>(db_conn)
-            .await
-            .optional()?;
+        let matching = || {
+            collections::table
+                .filter(collections::deleted_at.is_not_null())
+                .into_boxed()
+        };
+        let rows = request
+            .select(&matching, collection_columns())
+            .load::<RawCollectionDto>(db_conn)
+            .await?;
+        let has_behind = match request.behind(&matching) {
+            Some(behind) => behind.get_result(db_conn).await?,
+            None => false,
+        };
+        let page = request.page(rows, has_behind);
 
-        Ok(raw_item.map(|item| item.into()))
+        let pagination = CursorPaginationMetadataDto {
+            has_prev: page.pagination.has_prev,
+            has_next: page.pagination.has_next,
+            first_cursor: page.items.as_slice().first().map(|item| {
+                self.cursor_codec
+                    .encode(&TrashedCollectionKey::new(item, ()))
+            }),
+            last_cursor: page.items.as_slice().last().map(|item| {
+                self.cursor_codec
+                    .encode(&TrashedCollectionKey::new(item, ()))
+            }),
+        };
+        let items = page
+            .items
+            .into_iter()
+            .map(|raw_item| raw_item.into())
+            .collect();
+
+        Ok(FindCollectionsResultDto { pagination, items })
     }
 
-    pub async fn create_collection(
+    pub async fn restore_collection(
         &self,
-        body: CreateCollectionBodyDto,
-    ) -> Result<CollectionDto, CollectionServiceError> {
+        path: RestoreCollectionPathDto,
+        context: &AuditContext,
+    ) -> Result<Option<CollectionDto>, CollectionServiceError> {
         use crate::db::schema::collections::dsl::*;
 
         let db_conn = &mut self.db_pool.get().await?;
-        let raw_item = diesel::insert_into(collections)
-            .values((name.eq(body.name), description.eq(body.description)))
-            .get_result::<RawCollectionDto>(db_conn)
-            .await?;
+        db_conn
+            .transaction(|db_conn| {
+                async move {
+                    lock_collection_tree(db_conn).await?;
+
+                    let current = collections
+                        .select((collection_columns(), parent_id))
+                        .filter(uuid.eq(path.identifier))
+                        .filter(deleted_at.is_not_null())
+                        .get_result::<(RawCollectionDto, Option<i32>)>(db_conn)
+                        .await
+                        .optional()?;
+                    let (collection_id, current, parent) = match current {
+                        Some((current, parent)) => {
+                            (current.id, CollectionDto::from(current), parent)
+                        }
+                        None => return Ok(None),
+                    };
+
+                    if let Some(parent) = parent {
+                        let (parent_uuid, is_parent_trashed) = collections
+                            .select((uuid, deleted_at.is_not_null()))
+                            .filter(id.eq(parent))
+                            .get_result::<(Uuid, bool)>(db_conn)
+                            .await?;
 
-        Ok(raw_item.into())
+                        if is_parent_trashed {
+                            return Err(CollectionServiceError::ParentTrashed(parent_uuid));
+                        }
+                    }
+
+                    let raw_item = diesel::update(collections.filter(id.eq(collection_id)))
+                        .set(deleted_at.eq(None::<NaiveDateTime>))
+                        .returning(collection_columns())
+                        .get_result::<RawCollectionDto>(db_conn)
+                        .await?;
+                    let item = CollectionDto::from(raw_item);
+
+                    record_event(
+                        db_conn,
+                        context,
+                        AuditActionDto::RestoreCollection,
+                        item.uuid,
+                        Some(snapshot(&current)),
+                        Some(snapshot(&item)),
+                    )
+                    .await?;
+
+                    Ok(Some(item))
+                }
+                .scope_boxed()
+            })
+            .await
     }
 
-    pub async fn update_collection(
+    pub async fn move_collection(
         &self,
-        path: UpdateCollectionPathDto,
-        body: UpdateCollectionBodyDto,
+        path: MoveCollectionPathDto,
+        body: MoveCollectionBodyDto,
+        context: &AuditContext,
     ) -> Result<Option<CollectionDto>, CollectionServiceError> {
         use crate::db::schema::collections::dsl::*;
 
         let db_conn = &mut self.db_pool.get().await?;
-        let raw_item = diesel::update(collections.filter(id.eq(path.identifier)))
-            .set((name.eq(body.name), description.eq(body.description)))
-            .get_result::<RawCollectionDto>(db_conn)
+        db_conn
+            .transaction(|db_conn| {
+                async move {
+                    lock_collection_tree(db_conn).await?;
+
+                    let current = collections
+                        .select(collection_columns())
+                        .filter(uuid.eq(path.identifier))
+                        .filter(deleted_at.is_null())
+                        .get_result::<RawCollectionDto>(db_conn)
+                        .await
+                        .optional()?;
+                    let (collection_id, current) = match current {
+                        Some(current) => (current.id, CollectionDto::from(current)),
+                        None => return Ok(None),
+                    };
+
+                    let parent = match body.parent_uuid {
+                        Some(parent_uuid) => {
+                            let parent = match find_collection_id(db_conn, parent_uuid).await? {
+                                Some(parent) => parent,
+                                None => {
+                                    return Err(CollectionServiceError::ParentNotFound(parent_uuid))
+                                }
+                            };
+
+                            if is_in_subtree(db_conn, parent, path.identifier).await? {
+                                return Err(CollectionServiceError::CyclicParent);
+                            }
+
+                            Some(parent)
+                        }
+                        None => None,
+                    };
+
+                    let raw_item = diesel::update(collections.filter(id.eq(collection_id)))
+                        .set(parent_id.eq(parent))
+                        .returning(collection_columns())
+                        .get_result::<RawCollectionDto>(db_conn)
+                        .await?;
+                    let item = CollectionDto::from(raw_item);
+
+                    record_event(
+                        db_conn,
+                        context,
+                        AuditActionDto::MoveCollection,
+                        item.uuid,
+                        Some(snapshot(&current)),
+                        Some(snapshot(&item)),
+                    )
+                    .await?;
+
+                    Ok(Some(item))
+                }
+                .scope_boxed()
+            })
             .await
-            .optional()?;
+    }
 
-        Ok(raw_item.map(|item| item.into()))
+    pub async fn add_collection_file(
+        &self,
+        path: AddCollectionFilePathDto,
+        context: &AuditContext,
+    ) -> Result<Option<()>, CollectionServiceError> {
+        let db_conn = &mut self.db_pool.get().await?;
+        db_conn
+            .transaction(|db_conn| {
+                async move {
+                    let collection = collections::table
+                        .select((collections::id, collections::smart_query.is_not_null()))
+                        .filter(collections::uuid.eq(path.identifier))
+                        .filter(collections::deleted_at.is_null())
+                        .get_result::<(i32, bool)>(db_conn)
+                        .await
+                        .optional()?;
+
+                    let collection_id = match collection {
+                        Some((_, true)) => {
+                            return Err(CollectionServiceError::SmartCollectionFiles)
+                        }
+                        Some((collection_id, false)) => collection_id,
+                        None => return Ok(None),
+                    };
+
+                    let file = files::table
+                        .select((files::id, files::mime, files::size))
+                        .filter(files::uuid.eq(path.file))
+                        .filter(files::deleted_at.is_null())
+                        .get_result::<(i32, Option<String>, Option<i64>)>(db_conn)
+                        .await
+                        .optional()?;
+                    let (file_id, file_mime, file_size) = match file {
+                        Some(file) => file,
+                        None => return Ok(None),
+                    };
+
+                    // Files join a collection as if they were uploaded to it.
+                    let policy = collection_upload_policies::table
+                        .select((
+                            collection_upload_policies::max_file_size,
+                            collection_upload_policies::allowed_mime_types,
+                            collection_upload_policies::denied_mime_types,
+                        ))
+                        .filter(collection_upload_policies::collection_id.eq(collection_id))
+                        .get_result::<RawUploadPolicyDto>(db_conn)
+                        .await
+                        .optional()?;
+                    let policy = match policy {
+                        Some(policy) => self.upload_policy.override_with(
+                            policy
+                                .max_file_size
+                                .map(|max_file_size| max_file_size as u64),
+                            policy.allowed_mime_types,
+                            policy.denied_mime_types,
+                        ),
+                        None => self.upload_policy.clone(),
+                    };
+                    policy.check_stored_content(
+                        file_mime.as_deref(),
+                        file_size.unwrap_or_default() as u64,
+                    )?;
+
+                    insert_collection_file(
+                        db_conn,
+                        (collection_id, path.identifier),
+                        (file_id, path.file),
+                        context,
+                    )
+                    .await?;
+
+                    Ok(Some(()))
+                }
+                .scope_boxed()
+            })
+            .await
     }
 
-    pub async fn remove_collection(
+    pub async fn remove_collection_file(
         &self,
-        path: RemoveCollectionPathDto,
-    ) -> Result<Option<CollectionDto>, CollectionServiceError> {
-        use crate::db::schema::collections::dsl::*;
+        path: RemoveCollectionFilePathDto,
+        context: &AuditContext,
+    ) -> Result<Option<()>, CollectionServiceError> {
+        let db_conn = &mut self.db_pool.get().await?;
+        db_conn
+            .transaction(|db_conn| {
+                async move {
+                    let collection = collections::table
+                        .select((collections::id, collections::smart_query.is_not_null()))
+                        .filter(collections::uuid.eq(path.identifier))
+                        .filter(collections::deleted_at.is_null())
+                        .get_result::<(i32, bool)>(db_conn)
+                        .await
+                        .optional()?;
+
+                    let collection_id = match collection {
+                        Some((_, true)) => {
+                            return Err(CollectionServiceError::SmartCollectionFiles)
+                        }
+                        Some((collection_id, false)) => collection_id,
+                        None => return Ok(None),
+                    };
+
+                    let removed = diesel::delete(
+                        collection_file_pairs::table.filter(
+                            collection_file_pairs::collection_id.eq(collection_id).and(
+                                collection_file_pairs::file_id.eq_any(
+                                    files::table
+                                        .select(files::id)
+                                        .filter(files::uuid.eq(path.file)),
+                                ),
+                            ),
+                        ),
+                    )
+                    .execute(db_conn)
+                    .await?;
+
+                    if removed == 0 {
+                        return Ok(None);
+                    }
+
+                    record_event(
+                        db_conn,
+                        context,
+                        AuditActionDto::RemoveCollectionFile,
+                        path.identifier,
+                        Some(json!({ "file": path.file })),
+                        None,
+                    )
+                    .await?;
+
+                    Ok(Some(()))
+                }
+                .scope_boxed()
+            })
+            .await
+    }
 
+    pub async fn find_collection_upload_policy(
+        &self,
+        path: FindCollectionUploadPolicyPathDto,
+    ) -> Result<Option<UploadPolicyDto>, CollectionServiceError> {
         let db_conn = &mut self.db_pool.get().await?;
-        let raw_item = diesel::delete(collections.filter(id.eq(path.identifier)))
-            .get_result::<RawCollectionDto>(db_conn)
+        let policy = collections::table
+            .left_join(collection_upload_policies::table)
+            .select((
+                collection_upload_policies::max_file_size.nullable(),
+                collection_upload_policies::allowed_mime_types.nullable(),
+                collection_upload_policies::denied_mime_types.nullable(),
+            ))
+            .filter(collections::uuid.eq(path.identifier))
+            .filter(collections::deleted_at.is_null())
+            .get_result::<RawUploadPolicyDto>(db_conn)
             .await
             .optional()?;
 
-        Ok(raw_item.map(|item| item.into()))
+        Ok(policy.map(|policy| policy.into()))
     }
+
+    pub async fn update_collection_upload_policy(
+        &self,
+        path: UpdateCollectionUploadPolicyPathDto,
+        mut body: UpdateUploadPolicyBodyDto,
+        context: &AuditContext,
+    ) -> Result<Option<UploadPolicyDto>, CollectionServiceError> {
+        if body
+            .max_file_size
+            .map_or(false, |max_file_size| max_file_size < 0)
+        {
+            return Err(CollectionServiceError::NegativeMaxFileSize);
+        }
+
+        for mime_types in [&mut body.allowed_mime_types, &mut body.denied_mime_types]
+            .into_iter()
+            .flatten()
+        {
+            for mime_type in mime_types.iter_mut() {
+                if !is_mime_type_pattern(mime_type) {
+                    return Err(CollectionServiceError::InvalidMimeType(mime_type.clone()));
+                }
+
+                mime_type.make_ascii_lowercase();
+            }
+        }
+
+        let db_conn = &mut self.db_pool.get().await?;
+        db_conn
+            .transaction(|db_conn| {
+                async move {
+                    let collection = collections::table
+                        .select((collections::id, collections::smart_query.is_not_null()))
+                        .filter(collections::uuid.eq(path.identifier))
+                        .filter(collections::deleted_at.is_null())
+                        .get_result::<(i32, bool)>(db_conn)
+                        .await
+                        .optional()?;
+
+                    // The policy applies to the files added to a collection.
+                    let collection_id = match collection {
+                        Some((_, true)) => {
+                            return Err(CollectionServiceError::SmartCollectionFiles)
+                        }
+                        Some((collection_id, false)) => collection_id,
+                        None => return Ok(None),
+                    };
+
+                    let before = collection_upload_policies::table
+                        .select((
+                            collection_upload_policies::max_file_size,
+                            collection_upload_policies::allowed_mime_types,
+                            collection_upload_policies::denied_mime_types,
+                        ))
+                        .filter(collection_upload_policies::collection_id.eq(collection_id))
+                        .for_update()
+                        .get_result::<RawUploadPolicyDto>(db_conn)
+                        .await
+                        .optional()?
+                        .map(UploadPolicyDto::from);
+
+                    let policy = diesel::insert_into(collection_upload_policies::table)
+                        .values((
+                            collection_upload_policies::collection_id.eq(collection_id),
+                            collection_upload_policies::max_file_size.eq(body.max_file_size),
+                            collection_upload_policies::allowed_mime_types
+                                .eq(&body.allowed_mime_types),
+                            collection_upload_policies::denied_mime_types
+                                .eq(&body.denied_mime_types),
+                        ))
+                        .on_conflict(collection_upload_policies::collection_id)
+                        .do_update()
+                        .set((
+                            collection_upload_policies::max_file_size
+                                .eq(excluded(collection_upload_policies::max_file_size)),
+                            collection_upload_policies::allowed_mime_types
+                                .eq(excluded(collection_upload_policies::allowed_mime_types)),
+                            collection_upload_policies::denied_mime_types
+                                .eq(excluded(collection_upload_policies::denied_mime_types)),
+                        ))
+                        .returning((
+                            collection_upload_policies::max_file_size,
+                            collection_upload_policies::allowed_mime_types,
+                            collection_upload_policies::denied_mime_types,
+                        ))
+                        .get_result::<RawUploadPolicyDto>(db_conn)
+                        .await?;
+                    let policy = UploadPolicyDto::from(policy);
+
+                    record_event(
+                        db_conn,
+                        context,
+                        AuditActionDto::UpdateCollectionUploadPolicy,
+                        path.identifier,
+                        before.as_ref().map(snapshot),
+                        Some(snapshot(&policy)),
+                    )
+                    .await?;
+
+                    Ok(Some(policy))
+                }
+                .scope_boxed()
+            })
+            .await
+    }
+}
+
+/// Inserts a collection and records its creation.
+///
+/// It runs on the connection of the caller, which must be in a transaction, so that the tree
+/// lock is held until the collection is committed.
+pub async fn insert_collection(
+    db_conn: &mut AsyncPgConnection,
+    body: CreateCollectionBodyDto,
+    context: &AuditContext,
+) -> Result<CollectionDto, CollectionServiceError> {
+    use crate::db::schema::collections::dsl::*;
+
+    let smart_query_value = body
+        .smart_query
+        .as_ref()
+        .map(serialize_smart_query)
+        .transpose()?;
+
+    let parent = match body.parent_uuid {
+        Some(parent_uuid) => {
+            lock_collection_tree(db_conn).await?;
+
+            match find_collection_id(db_conn, parent_uuid).await? {
+                Some(parent) => Some(parent),
+                None => return Err(CollectionServiceError::ParentNotFound(parent_uuid)),
+            }
+        }
+        None => None,
+    };
+
+    let raw_item = diesel::insert_into(collections)
+        .values((
+            name.eq(body.name),
+            description.eq(body.description),
+            parent_id.eq(parent),
+            smart_query.eq(smart_query_value),
+        ))
+        .returning(collection_columns())
+        .get_result::<RawCollectionDto>(db_conn)
+        .await?;
+    let item = CollectionDto::from(raw_item);
+
+    record_event(
+        db_conn,
+        context,
+        AuditActionDto::CreateCollection,
+        item.uuid,
+        None,
+        Some(snapshot(&item)),
+    )
+    .await?;
+
+    Ok(item)
+}
+
+/// Adds a file to a manual collection, given as `(id, uuid)` pairs, and records it; returns
+/// whether the file was not in the collection yet. It fails if the quota of the collection has no
+/// room for a file it does not have yet.
+pub async fn insert_collection_file(
+    db_conn: &mut AsyncPgConnection,
+    (collection_id, collection_uuid): (i32, Uuid),
+    (file_id, file_uuid): (i32, Uuid),
+    context: &AuditContext,
+) -> Result<bool, QuotaError> {
+    let present = diesel::select(exists(
+        collection_file_pairs::table
+            .filter(collection_file_pairs::collection_id.eq(collection_id))
+            .filter(collection_file_pairs::file_id.eq(file_id)),
+    ))
+    .get_result::<bool>(db_conn)
+    .await?;
+
+    if present {
+        return Ok(false);
+    }
+
+    let (file_size, kept_bytes) = files::table
+        .select((files::size, files::kept_bytes))
+        .filter(files::id.eq(file_id))
+        .get_result::<(Option<i64>, i64)>(db_conn)
+        .await?;
+    ensure_collection_file_room(
+        db_conn,
+        (collection_id, collection_uuid),
+        file_size.unwrap_or_default() + kept_bytes,
+    )
+    .await?;
+
+    let added = diesel::insert_into(collection_file_pairs::table)
+        .values((
+            collection_file_pairs::collection_id.eq(collection_id),
+            collection_file_pairs::file_id.eq(file_id),
+        ))
+        .on_conflict_do_nothing()
+        .execute(db_conn)
+        .await?;
+
+    if added != 0 {
+        record_event(
+            db_conn,
+            context,
+            AuditActionDto::AddCollectionFile,
+            collection_uuid,
+            None,
+            Some(json!({ "file": file_uuid })),
+        )
+        .await?;
+    }
+
+    Ok(added != 0)
+}
+
+fn serialize_smart_query(
+    smart_query: &FindFilesBodyDto,
+) -> Result<serde_json::Value, CollectionServiceError> {
+    let tags = smart_query.tags.as_deref().unwrap_or_default();
+
+    if let Some(title) = find_duplicated_tag_title(tags.iter().map(|tag| tag.title.as_str())) {
+        return Err(CollectionServiceError::DuplicatedTag(title.to_owned()));
+    }
+
+    Ok(serde_json::to_value(smart_query).expect("failed to serialize smart query"))
+}
+
+/// Matches the rows whose `column` is the id of `root` or of one of its descendants.
+pub fn in_collection_subtree<QS>(column: &'static str, root: Uuid) -> BoxedPredicate<'static, QS> {
+    Box::new(
+        sql::<Bool>(column)
+            .sql(" IN (WITH RECURSIVE subtree(id) AS (SELECT id FROM collections WHERE uuid = ")
+            .bind::<diesel::sql_types::Uuid, _>(root)
+            .sql(" UNION SELECT collections.id FROM collections JOIN subtree ON collections.parent_id = subtree.id) SELECT id FROM subtree)"),
+    )
+}
+
+async fn lock_collection_tree(db_conn: &mut AsyncPgConnection) -> QueryResult<()> {
+    diesel::sql_query("SELECT pg_advisory_xact_lock($1)")
+        .bind::<BigInt, _>(COLLECTION_TREE_LOCK_KEY)
+        .execute(db_conn)
+        .await?;
+
+    Ok(())
+}
+
+/// Returns the id of the collection, unless it does not exist or is in the trash.
+async fn find_collection_id(
+    db_conn: &mut AsyncPgConnection,
+    uuid: Uuid,
+) -> QueryResult<Option<i32>> {
+    collections::table
+        .select(collections::id)
+        .filter(collections::uuid.eq(uuid))
+        .filter(collections::deleted_at.is_null())
+        .get_result(db_conn)
+        .await
+        .optional()
+}
+
+/// Selects the ids of the collections that are not in the trash.
+///
+/// A trashed collection keeps its files, which must not count toward it until it is restored.
+pub fn live_collection_ids(
+) -> Filter<Select<collections::table, collections::id>, IsNull<collections::deleted_at>> {
+    collections::table
+        .select(collections::id)
+        .filter(collections::deleted_at.is_null())
+}
+
+/// Tells whether `id` is `root` or one of its descendants.
+async fn is_in_subtree(db_conn: &mut AsyncPgConnection, id: i32, root: Uuid) -> QueryResult<bool> {
+    diesel::select(exists(
+        collections::table
+            .filter(collections::id.eq(id))
+            .filter(in_collection_subtree("collections.id", root)),
+    ))
+    .get_result(db_conn)
+    .await
+}
+
+fn filter_collections(query: &FindCollectionsQueryDto) -> collections::BoxedQuery<'_, Pg> {
+    let mut q = collections::table
+        .filter(collections::deleted_at.is_null())
+        .into_boxed();
+
+    if let Some(filter_name) = &query.filter_name {
+        let filter_name = escape_like_pattern(filter_name);
+        let pattern = match query.name_match {
+            NameMatchDto::Contains => format!("%{}%", filter_name),
+            NameMatchDto::Prefix => format!("{}%", filter_name),
+        };
+        q = q.filter(collections::name.ilike(pattern));
+    }
+
+    if let Some(filter_description) = &query.filter_description {
+        q = q.filter(
+            collections::description
+                .ilike(format!("%{}%", escape_like_pattern(filter_description))),
+        );
+    }
+
+    if let Some(created_after) = query.created_after {
+        q = q.filter(collections::created_at.ge(created_after.naive_utc()));
+    }
+
+    if let Some(created_before) = query.created_before {
+        q = q.filter(collections::created_at.lt(created_before.naive_utc()));
+    }
+
+    if let Some(parent) = query.parent_uuid {
+        let parents = alias!(collections as parents);
+        q = q.filter(
+            collections::parent_id.eq_any(
+                parents
+                    .select(parents.field(collections::id).nullable())
+                    .filter(parents.field(collections::uuid).eq(parent)),
+            ),
+        );
+    }
+
+    if let Some(ancestor) = query.ancestor_uuid {
+        q = q
+            .filter(collections::uuid.ne(ancestor))
+            .filter(in_collection_subtree("collections.id", ancestor));
+    }
+
+    q
+}
+
+/// Position of a collection in a listing sorted by one of its columns, using `uuid` as a tie-breaker.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+enum CollectionKey {
+    Name(String, Uuid),
+    CreatedAt(NaiveDateTime, Uuid),
+    FileCount(i64, Uuid),
+}
+
+impl Keyset for CollectionKey {
+    type Table = collections::table;
+    type Sort = CollectionSortDto;
+    type Row = RawCollectionDto;
+
+    fn new(row: &RawCollectionDto, sort: CollectionSortDto) -> Self {
+        match sort {
+            CollectionSortDto::Name => Self::Name(row.name.clone(), row.uuid),
+            CollectionSortDto::CreatedAt => Self::CreatedAt(row.created_at, row.uuid),
+            CollectionSortDto::FileCount => Self::FileCount(row.file_count, row.uuid),
+        }
+    }
+
+    fn sort(&self) -> CollectionSortDto {
+        match self {
+            Self::Name(..) => CollectionSortDto::Name,
+            Self::CreatedAt(..) => CollectionSortDto::CreatedAt,
+            Self::FileCount(..) => CollectionSortDto::FileCount,
+        }
+    }
+
+    fn beyond(&self, ascending: bool) -> BoxedPredicate<'_, collections::table> {
+        match (self, ascending) {
+            (Self::Name(name, uuid), true) => Box::new(
+                collections::name
+                    .gt(name)
+                    .or(collections::name.eq(name).and(collections::uuid.gt(uuid))),
+            ),
+            (Self::Name(name, uuid), false) => Box::new(
+                collections::name
+                    .lt(name)
+                    .or(collections::name.eq(name).and(collections::uuid.lt(uuid))),
+            ),
+            (Self::CreatedAt(created_at, uuid), true) => Box::new(
+                collections::created_at
+                    .gt(created_at)
+                    .or(collections::created_at
+                        .eq(created_at)
+                        .and(collections::uuid.gt(uuid))),
+            ),
+            (Self::CreatedAt(created_at, uuid), false) => Box::new(
+                collections::created_at
+                    .lt(created_at)
+                    .or(collections::created_at
+                        .eq(created_at)
+                        .and(collections::uuid.lt(uuid))),
+            ),
+            (Self::FileCount(file_count, uuid), true) => Box::new(
+                collections::file_count
+                    .gt(file_count)
+                    .or(collections::file_count
+                        .eq(file_count)
+                        .and(collections::uuid.gt(uuid))),
+            ),
+            (Self::FileCount(file_count, uuid), false) => Box::new(
+                collections::file_count
+                    .lt(file_count)
+                    .or(collections::file_count
+                        .eq(file_count)
+                        .and(collections::uuid.lt(uuid))),
+            ),
+        }
+    }
+
+    fn order(
+        query: IntoBoxed<'_, collections::table, Pg>,
+        sort: CollectionSortDto,
+        ascending: bool,
+    ) -> IntoBoxed<'_, collections::table, Pg> {
+        match (sort, ascending) {
+            (CollectionSortDto::Name, true) => {
+                query.order((collections::name.asc(), collections::uuid.asc()))
+            }
+            (CollectionSortDto::Name, false) => {
+                query.order((collections::name.desc(), collections::uuid.desc()))
+            }
+            (CollectionSortDto::CreatedAt, true) => {
+                query.order((collections::created_at.asc(), collections::uuid.asc()))
+            }
+            (CollectionSortDto::CreatedAt, false) => {
+                query.order((collections::created_at.desc(), collections::uuid.desc()))
+            }
+            (CollectionSortDto::FileCount, true) => {
+                query.order((collections::file_count.asc(), collections::uuid.asc()))
+            }
+            (CollectionSortDto::FileCount, false) => {
+                query.order((collections::file_count.desc(), collections::uuid.desc()))
+            }
+        }
+    }
+}
+
+/// Position of a collection in the trash, which is ordered by deletion time.
+#[derive(Serialize, Deserialize, Debug)]
+struct TrashedCollectionKey(NaiveDateTime, Uuid);
+
+impl Keyset for TrashedCollectionKey {
+    type Table = collections::table;
+    type Sort = ();
+    type Row = RawCollectionDto;
+
+    fn new(row: &RawCollectionDto, _sort: ()) -> Self {
+        Self(row.deleted_at.unwrap_or_default(), row.uuid)
+    }
+
+    fn sort(&self) {}
+
+    fn beyond(&self, ascending: bool) -> BoxedPredicate<'_, collections::table> {
+        let Self(deleted_at, uuid) = self;
+        let deleted_at_column = collections::deleted_at.assume_not_null();
+
+        if ascending {
+            Box::new(
+                deleted_at_column.gt(deleted_at).or(deleted_at_column
+                    .eq(deleted_at)
+                    .and(collections::uuid.gt(uuid))),
+            )
+        } else {
+            Box::new(
+                deleted_at_column.lt(deleted_at).or(deleted_at_column
+                    .eq(deleted_at)
+                    .and(collections::uuid.lt(uuid))),
+            )
+        }
+    }
+
+    fn order(
+        query: IntoBoxed<'_, collections::table, Pg>,
+        _sort: (),
+        ascending: bool,
+    ) -> IntoBoxed<'_, collections::table, Pg> {
+        if ascending {
+            query.order((collections::deleted_at.asc(), collections::uuid.asc()))
+        } else {
+            query.order((collections::deleted_at.desc(), collections::uuid.desc()))
+        }
+    }
+}
+
+/// Columns of a `RawCollectionDto`, with the parent referred to by its uuid.
+type CollectionColumns = (
+    collections::id,
+    collections::uuid,
+    collections::name,
+    collections::description,
+    collections::created_at,
+    collections::file_count,
+    SqlLiteral<Nullable<diesel::sql_types::Uuid>>,
+    collections::smart_query,
+    collections::deleted_at,
+    collections::updated_at,
+);
+
+fn collection_columns() -> CollectionColumns {
+    (
+        collections::id,
+        collections::uuid,
+        collections::name,
+        collections::description,
+        collections::created_at,
+        collections::file_count,
+        sql::<Nullable<diesel::sql_types::Uuid>>(
+            "(SELECT parents.uuid FROM collections parents WHERE parents.id = collections.parent_id)",
+        ),
+        collections::smart_query,
+        collections::deleted_at,
+        collections::updated_at,
+    )
 }
 
 #[derive(Queryable, Serialize, Deserialize, Debug)]
@@ -256,28 +1339,49 @@ struct RawCollectionDto {
     name: String,
     description: Option<String>,
     created_at: NaiveDateTime,
+    file_count: i64,
+    parent_uuid: Option<Uuid>,
+    smart_query: Option<serde_json::Value>,
+    deleted_at: Option<NaiveDateTime>,
+    updated_at: NaiveDateTime,
 }
 
-impl From<CollectionDto> for RawCollectionDto {
-    fn from(item: CollectionDto) -> Self {
+impl From<RawCollectionDto> for CollectionDto {
+    fn from(item: RawCollectionDto) -> Self {
         Self {
-            id: item.id,
             uuid: item.uuid,
             name: item.name,
             description: item.description,
-            created_at: item.created_at.naive_utc(),
+            created_at: item.created_at.and_utc(),
+            file_count: item.file_count,
+            parent_uuid: item.parent_uuid,
+            kind: match item.smart_query {
+                Some(_) => CollectionKindDto::Smart,
+                None => CollectionKindDto::Manual,
+            },
+            // Smart queries are validated before being stored.
+            smart_query: item.smart_query.map(|smart_query| {
+                serde_json::from_value(smart_query).expect("failed to deserialize smart query")
+            }),
+            deleted_at: item.deleted_at.map(|deleted_at| deleted_at.and_utc()),
+            updated_at: item.updated_at.and_utc(),
         }
     }
 }
 
-impl From<RawCollectionDto> for CollectionDto {
-    fn from(item: RawCollectionDto) -> Self {
+#[derive(Queryable, Debug)]
+struct RawUploadPolicyDto {
+    max_file_size: Option<i64>,
+    allowed_mime_types: Option<Vec<String>>,
+    denied_mime_types: Option<Vec<String>>,
+}
+
+impl From<RawUploadPolicyDto> for UploadPolicyDto {
+    fn from(policy: RawUploadPolicyDto) -> Self {
         Self {
-            id: item.id,
-            uuid: item.uuid,
-            name: item.name,
-            description: item.description,
-            created_at: item.created_at.and_utc(),
+            max_file_size: policy.max_file_size,
+            allowed_mime_types: policy.allowed_mime_types,
+            denied_mime_types: policy.denied_mime_types,
         }
     }
 }
@@ -1,6 +1,6 @@
 use crate::app_state::AppState;
 use axum::{
-    routing::{delete, get, post, put},
+    routing::{delete, get, patch, post, put},
     Router,
 };
 
@@ -9,23 +9,68 @@ pub mod collection_service;
 pub fn router() -> Router<AppState> {
     Router::new()
         .route("/collections", get(handlers::find_collections))
+        .route(
+            "/collections/trash",
+            get(handlers::find_trashed_collections),
+        )
         .route("/collections/:identifier", get(handlers::find_collection))
         .route("/collections", post(handlers::create_collection))
         .route("/collections/:identifier", put(handlers::update_collection))
+        .route(
+            "/collections/:identifier",
+            patch(handlers::patch_collection),
+        )
         .route(
             "/collections/:identifier",
             delete(handlers::remove_collection),
         )
+        .route(
+            "/collections/:identifier/restore",
+            post(handlers::restore_collection),
+        )
+        .route(
+            "/collections/:identifier/parent",
+            put(handlers::move_collection),
+        )
+        .route(
+            "/collections/:identifier/files",
+            get(handlers::find_collection_files),
+        )
+        .route(
+            "/collections/:identifier/files/:file",
+            put(handlers::add_collection_file),
+        )
+        .route(
+            "/collections/:identifier/files/:file",
+            delete(handlers::remove_collection_file),
+        )
+        .route(
+            "/collections/:identifier/upload-policy",
+            get(handlers::find_collection_upload_policy),
+        )
+        .route(
+            "/collections/:identifier/upload-policy",
+            put(handlers::update_collection_upload_policy),
+        )
 }
 
 pub mod handlers {
     use super::collection_service::{CollectionService, CollectionServiceError};
     use crate::{
         app_state::AppState,
+        audit::AuditContext,
+        etag::{ETag, IfMatch},
+        route_files::file_service::{FileService, FileServiceError},
         schema::{
             dto_in::{
-                CreateCollectionBodyDto, FindCollectionPathDto, FindCollectionsQueryDto,
-                RemoveCollectionPathDto, UpdateCollectionBodyDto, UpdateCollectionPathDto,
+                AddCollectionFilePathDto, CreateCollectionBodyDto, FindCollectionFilesPathDto,
+                FindCollectionFilesQueryDto, FindCollectionPathDto,
+                FindCollectionUploadPolicyPathDto, FindCollectionsQueryDto, FindFilesQueryDto,
+                FindTrashQueryDto, MoveCollectionBodyDto, MoveCollectionPathDto,
+                PatchCollectionPathDto, RemoveCollectionFilePathDto, RemoveCollectionPathDto,
+                RemoveCollectionQueryDto, RestoreCollectionPathDto, UpdateCollectionBodyDto,
+                UpdateCollectionPathDto, UpdateCollectionUploadPolicyPathDto,
+                UpdateUploadPolicyBodyDto,
             },
             dto_out::{CollectionDto, FindCollectionsResultDto},
         },
@@ -49,6 +94,7 @@ pub mod handlers {
         ),
         responses(
             (status = OK, body = FindCollectionsResultDto),
+            (status = UNPROCESSABLE_ENTITY, description = "the pagination parameters are invalid", body = ErrorBody),
             (status = INTERNAL_SERVER_ERROR, description = "an error has occurred", body = ErrorBody),
         ),
     )]
@@ -72,7 +118,9 @@ pub mod handlers {
             FindCollectionPathDto
         ),
         responses(
-            (status = OK, body = CollectionDto),
+            (status = OK, body = CollectionDto, headers(
+                ("ETag" = String, description = "entity tag of the collection, for use in `If-Match`")
+            )),
             (status = NOT_FOUND, description = "the collection does not exist"),
             (status = INTERNAL_SERVER_ERROR, description = "an error has occurred", body = ErrorBody),
         ),
@@ -83,7 +131,10 @@ pub mod handlers {
         Path(path): Path<FindCollectionPathDto>,
     ) -> Result<Response, CollectionServiceError> {
         match collection_service.find_collection(path).await? {
-            Some(result) => Ok((StatusCode::OK, Json(result)).into_response()),
+            Some(result) => {
+                let etag = ETag::from_updated_at(result.updated_at);
+                Ok((StatusCode::OK, etag.header(), Json(result)).into_response())
+            }
             None => Ok(StatusCode::NOT_FOUND.into_response()),
         }
     }
@@ -94,17 +145,20 @@ pub mod handlers {
         operation_id = "create-collection",
         tag = "collection",
         path = "/collections",
+        request_body = CreateCollectionBodyDto,
         responses(
             (status = CREATED, body = CollectionDto),
+            (status = UNPROCESSABLE_ENTITY, description = "the parent collection does not exist", body = ErrorBody),
             (status = INTERNAL_SERVER_ERROR, description = "an error has occurred", body = ErrorBody),
         ),
     )]
     #[debug_handler(state = AppState)]
     pub async fn create_collection(
         State(collection_service): State<CollectionService>,
+        context: AuditContext,
         Json(body): Json<CreateCollectionBodyDto>,
     ) -> Result<(StatusCode, Json<CollectionDto>), CollectionServiceError> {
-        let result = collection_service.create_collection(body).await?;
+        let result = collection_service.create_collection(body, &context).await?;
 
         Ok((StatusCode::CREATED, Json(result)))
     }
@@ -116,47 +170,370 @@ pub mod handlers {
         tag = "collection",
         path = "/collections/{identifier}",
         params(
-            UpdateCollectionPathDto
+            UpdateCollectionPathDto,
+            ("If-Match" = Option<String>, Header, description = "only update the collection if its entity tag matches one of these"),
         ),
         responses(
-            (status = OK, body = CollectionDto),
+            (status = OK, body = CollectionDto, headers(
+                ("ETag" = String, description = "entity tag of the updated collection")
+            )),
             (status = NOT_FOUND, description = "the collection does not exist"),
+            (status = PRECONDITION_FAILED, description = "the collection was modified since it was read", body = ErrorBody),
             (status = INTERNAL_SERVER_ERROR, description = "an error has occurred", body = ErrorBody),
         ),
     )]
     #[debug_handler(state = AppState)]
     pub async fn update_collection(
         State(collection_service): State<CollectionService>,
+        context: AuditContext,
         Path(path): Path<UpdateCollectionPathDto>,
+        if_match: IfMatch,
         Json(body): Json<UpdateCollectionBodyDto>,
     ) -> Result<Response, CollectionServiceError> {
-        match collection_service.update_collection(path, body).await? {
-            Some(result) => Ok((StatusCode::OK, Json(result)).into_response()),
+        match collection_service
+            .update_collection(path, body, if_match, &context)
+            .await?
+        {
+            Some(result) => {
+                let etag = ETag::from_updated_at(result.updated_at);
+                Ok((StatusCode::OK, etag.header(), Json(result)).into_response())
+            }
+            None => Ok(StatusCode::NOT_FOUND.into_response()),
+        }
+    }
+
+    /// Update a collection with a JSON Merge Patch.
+    #[utoipa::path(
+        patch,
+        operation_id = "patch-collection",
+        tag = "collection",
+        path = "/collections/{identifier}",
+        params(
+            PatchCollectionPathDto,
+            ("If-Match" = Option<String>, Header, description = "only update the collection if its entity tag matches one of these"),
+        ),
+        request_body(content = PatchCollectionBodyDto, content_type = "application/merge-patch+json"),
+        responses(
+            (status = OK, body = CollectionDto, headers(
+                ("ETag" = String, description = "entity tag of the updated collection")
+            )),
+            (status = NOT_FOUND, description = "the collection does not exist"),
+            (status = PRECONDITION_FAILED, description = "the collection was modified since it was read", body = ErrorBody),
+            (status = UNPROCESSABLE_ENTITY, description = "the patched collection is invalid", body = ErrorBody),
+            (status = INTERNAL_SERVER_ERROR, description = "an error has occurred", body = ErrorBody),
+        ),
+    )]
+    #[debug_handler(state = AppState)]
+    pub async fn patch_collection(
+        State(collection_service): State<CollectionService>,
+        context: AuditContext,
+        Path(path): Path<PatchCollectionPathDto>,
+        if_match: IfMatch,
+        Json(patch): Json<serde_json::Value>,
+    ) -> Result<Response, CollectionServiceError> {
+        match collection_service
+            .patch_collection(path, patch, if_match, &context)
+            .await?
+        {
+            Some(result) => {
+                let etag = ETag::from_updated_at(result.updated_at);
+                Ok((StatusCode::OK, etag.header(), Json(result)).into_response())
+            }
             None => Ok(StatusCode::NOT_FOUND.into_response()),
         }
     }
 
-    /// Remove a collection.
+    /// Move a collection to the trash.
+    ///
+    /// The collection keeps its files while it is in the trash, so that restoring it brings them
+    /// back; its quota and upload policy no longer apply to them meanwhile.
     #[utoipa::path(
         delete,
         operation_id = "delete-collection",
         tag = "collection",
         path = "/collections/{identifier}",
         params(
-            RemoveCollectionPathDto
+            RemoveCollectionPathDto,
+            RemoveCollectionQueryDto,
+            ("If-Match" = Option<String>, Header, description = "only remove the collection if its entity tag matches one of these"),
         ),
         responses(
-            (status = OK, body = CollectionDto),
+            (status = OK, body = RemoveCollectionResultDto),
             (status = NOT_FOUND, description = "the collection does not exist"),
+            (status = PRECONDITION_FAILED, description = "the collection was modified since it was read", body = ErrorBody),
+            (status = CONFLICT, description = "the collection has sub-collections, or has files and the mode is `refuse`", body = ErrorBody, example = json!({
+                "error": "collection has 3 files; remove them first, or use the `detach` or `deleteOrphans` mode"
+            })),
             (status = INTERNAL_SERVER_ERROR, description = "an error has occurred", body = ErrorBody),
         ),
     )]
     #[debug_handler(state = AppState)]
     pub async fn remove_collection(
         State(collection_service): State<CollectionService>,
+        context: AuditContext,
         Path(path): Path<RemoveCollectionPathDto>,
+        Query(query): Query<RemoveCollectionQueryDto>,
+        if_match: IfMatch,
+    ) -> Result<Response, CollectionServiceError> {
+        match collection_service
+            .remove_collection(path, query, if_match, &context)
+            .await?
+        {
+            Some(result) => Ok((StatusCode::OK, Json(result)).into_response()),
+            None => Ok(StatusCode::NOT_FOUND.into_response()),
+        }
+    }
+
+    /// Finds collections in the trash, most recently deleted first by default.
+    #[utoipa::path(
+        get,
+        operation_id = "find-trashed-collections",
+        tag = "collection",
+        path = "/collections/trash",
+        params(
+            FindTrashQueryDto
+        ),
+        responses(
+            (status = OK, body = FindCollectionsResultDto),
+            (status = UNPROCESSABLE_ENTITY, description = "the pagination parameters are invalid", body = ErrorBody),
+            (status = INTERNAL_SERVER_ERROR, description = "an error has occurred", body = ErrorBody),
+        ),
+    )]
+    #[debug_handler(state = AppState)]
+    pub async fn find_trashed_collections(
+        State(collection_service): State<CollectionService>,
+        Query(query): Query<FindTrashQueryDto>,
+    ) -> Result<(StatusCode, Json<FindCollectionsResultDto>), CollectionServiceError> {
+        let result = collection_service.find_trashed_collections(query).await?;
+
+        Ok((StatusCode::OK, Json(result)))
+    }
+
+    /// Restore a collection from the trash, along with the files it held.
+    #[utoipa::path(
+        post,
+        operation_id = "restore-collection",
+        tag = "collection",
+        path = "/collections/{identifier}/restore",
+        params(
+            RestoreCollectionPathDto
+        ),
+        responses(
+            (status = OK, body = CollectionDto),
+            (status = NOT_FOUND, description = "the collection does not exist or is not in the trash"),
+            (status = CONFLICT, description = "the parent collection is in the trash", body = ErrorBody),
+            (status = INTERNAL_SERVER_ERROR, description = "an error has occurred", body = ErrorBody),
+        ),
+    )]
+    #[debug_handler(state = AppState)]
+    pub async fn restore_collection(
+        State(collection_service): State<CollectionService>,
+        context: AuditContext,
+        Path(path): Path<RestoreCollectionPathDto>,
+    ) -> Result<Response, CollectionServiceError> {
+        match collection_service
+            .restore_collection(path, &context)
+            .await?
+        {
+            Some(result) => Ok((StatusCode::OK, Json(result)).into_response()),
+            None => Ok(StatusCode::NOT_FOUND.into_response()),
+        }
+    }
+
+    /// Move a collection under another collection, or to the root.
+    #[utoipa::path(
+        put,
+        operation_id = "move-collection",
+        tag = "collection",
+        path = "/collections/{identifier}/parent",
+        params(
+            MoveCollectionPathDto
+        ),
+        request_body = MoveCollectionBodyDto,
+        responses(
+            (status = OK, body = CollectionDto),
+            (status = NOT_FOUND, description = "the collection does not exist"),
+            (status = UNPROCESSABLE_ENTITY, description = "the parent collection does not exist, or is the collection itself or one of its descendants", body = ErrorBody),
+            (status = INTERNAL_SERVER_ERROR, description = "an error has occurred", body = ErrorBody),
+        ),
+    )]
+    #[debug_handler(state = AppState)]
+    pub async fn move_collection(
+        State(collection_service): State<CollectionService>,
+        context: AuditContext,
+        Path(path): Path<MoveCollectionPathDto>,
+        Json(body): Json<MoveCollectionBodyDto>,
+    ) -> Result<Response, CollectionServiceError> {
+        match collection_service
+            .move_collection(path, body, &context)
+            .await?
+        {
+            Some(result) => Ok((StatusCode::OK, Json(result)).into_response()),
+            None => Ok(StatusCode::NOT_FOUND.into_response()),
+        }
+    }
+
+    /// Finds the files of a collection.
+    #[utoipa::path(
+        get,
+        operation_id = "find-collection-files",
+        tag = "collection",
+        path = "/collections/{identifier}/files",
+        params(
+            FindCollectionFilesPathDto,
+            FindFilesQueryDto,
+            FindCollectionFilesQueryDto,
+        ),
+        responses(
+            (status = OK, body = FindFilesResultDto),
+            (status = NOT_FOUND, description = "the collection does not exist"),
+            (status = UNPROCESSABLE_ENTITY, description = "the pagination parameters are invalid", body = ErrorBody),
+            (status = INTERNAL_SERVER_ERROR, description = "an error has occurred", body = ErrorBody),
+        ),
+    )]
+    #[debug_handler(state = AppState)]
+    pub async fn find_collection_files(
+        State(file_service): State<FileService>,
+        Path(path): Path<FindCollectionFilesPathDto>,
+        Query(query): Query<FindFilesQueryDto>,
+        Query(scope): Query<FindCollectionFilesQueryDto>,
+    ) -> Result<Response, FileServiceError> {
+        match file_service
+            .find_collection_files(path, query, scope)
+            .await?
+        {
+            Some(result) => Ok((StatusCode::OK, Json(result)).into_response()),
+            None => Ok(StatusCode::NOT_FOUND.into_response()),
+        }
+    }
+
+    /// Add a file to a collection.
+    #[utoipa::path(
+        put,
+        operation_id = "add-collection-file",
+        tag = "collection",
+        path = "/collections/{identifier}/files/{file}",
+        params(
+            AddCollectionFilePathDto
+        ),
+        responses(
+            (status = NO_CONTENT, description = "the file is in the collection"),
+            (status = NOT_FOUND, description = "the collection or the file does not exist"),
+            (status = CONFLICT, description = "the collection is a smart collection", body = ErrorBody),
+            (status = PAYLOAD_TOO_LARGE, description = "the file exceeds the maximum file size of the collection", body = ErrorBody),
+            (status = UNSUPPORTED_MEDIA_TYPE, description = "the type of the file is not allowed in the collection", body = ErrorBody),
+            (status = INTERNAL_SERVER_ERROR, description = "an error has occurred", body = ErrorBody),
+            (status = INSUFFICIENT_STORAGE, description = "the storage quota of the collection has no room for the file", body = ErrorBody),
+        ),
+    )]
+    #[debug_handler(state = AppState)]
+    pub async fn add_collection_file(
+        State(collection_service): State<CollectionService>,
+        context: AuditContext,
+        Path(path): Path<AddCollectionFilePathDto>,
+    ) -> Result<StatusCode, CollectionServiceError> {
+        match collection_service
+            .add_collection_file(path, &context)
+            .await?
+        {
+            Some(()) => Ok(StatusCode::NO_CONTENT),
+            None => Ok(StatusCode::NOT_FOUND),
+        }
+    }
+
+    /// Remove a file from a collection.
+    #[utoipa::path(
+        delete,
+        operation_id = "remove-collection-file",
+        tag = "collection",
+        path = "/collections/{identifier}/files/{file}",
+        params(
+            RemoveCollectionFilePathDto
+        ),
+        responses(
+            (status = NO_CONTENT, description = "the file was removed from the collection"),
+            (status = NOT_FOUND, description = "the file is not in the collection or the collection is in the trash"),
+            (status = CONFLICT, description = "the collection is a smart collection", body = ErrorBody),
+            (status = INTERNAL_SERVER_ERROR, description = "an error has occurred", body = ErrorBody),
+        ),
+    )]
+    #[debug_handler(state = AppState)]
+    pub async fn remove_collection_file(
+        State(collection_service): State<CollectionService>,
+        context: AuditContext,
+        Path(path): Path<RemoveCollectionFilePathDto>,
+    ) -> Result<StatusCode, CollectionServiceError> {
+        match collection_service
+            .remove_collection_file(path, &context)
+            .await?
+        {
+            Some(()) => Ok(StatusCode::NO_CONTENT),
+            None => Ok(StatusCode::NOT_FOUND),
+        }
+    }
+
+    /// Finds the settings of the server-wide upload policy a collection overrides.
+    #[utoipa::path(
+        get,
+        operation_id = "find-collection-upload-policy",
+        tag = "collection",
+        path = "/collections/{identifier}/upload-policy",
+        params(
+            FindCollectionUploadPolicyPathDto
+        ),
+        responses(
+            (status = OK, body = UploadPolicyDto),
+            (status = NOT_FOUND, description = "the collection does not exist"),
+            (status = INTERNAL_SERVER_ERROR, description = "an error has occurred", body = ErrorBody),
+        ),
+    )]
+    #[debug_handler(state = AppState)]
+    pub async fn find_collection_upload_policy(
+        State(collection_service): State<CollectionService>,
+        Path(path): Path<FindCollectionUploadPolicyPathDto>,
+    ) -> Result<Response, CollectionServiceError> {
+        match collection_service
+            .find_collection_upload_policy(path)
+            .await?
+        {
+            Some(result) => Ok((StatusCode::OK, Json(result)).into_response()),
+            None => Ok(StatusCode::NOT_FOUND.into_response()),
+        }
+    }
+
+    /// Override the server-wide upload policy for the files of a collection.
+    ///
+    /// An upload must satisfy the policy of every collection its file is in, and the server-wide
+    /// one when it is in none. Only the type and size of new uploads are checked; stored contents
+    /// are kept.
+    #[utoipa::path(
+        put,
+        operation_id = "update-collection-upload-policy",
+        tag = "collection",
+        path = "/collections/{identifier}/upload-policy",
+        params(
+            UpdateCollectionUploadPolicyPathDto
+        ),
+        request_body = UpdateUploadPolicyBodyDto,
+        responses(
+            (status = OK, body = UploadPolicyDto),
+            (status = NOT_FOUND, description = "the collection does not exist"),
+            (status = CONFLICT, description = "the collection is smart", body = ErrorBody),
+            (status = UNPROCESSABLE_ENTITY, description = "the policy is invalid", body = ErrorBody),
+            (status = INTERNAL_SERVER_ERROR, description = "an error has occurred", body = ErrorBody),
+        ),
+    )]
+    #[debug_handler(state = AppState)]
+    pub async fn update_collection_upload_policy(
+        State(collection_service): State<CollectionService>,
+        context: AuditContext,
+        Path(path): Path<UpdateCollectionUploadPolicyPathDto>,
+        Json(body): Json<UpdateUploadPolicyBodyDto>,
     ) -> Result<Response, CollectionServiceError> {
-        match collection_service.remove_collection(path).await? {
+        match collection_service
+            .update_collection_upload_policy(path, body, &context)
+            .await?
+        {
             Some(result) => Ok((StatusCode::OK, Json(result)).into_response()),
             None => Ok(StatusCode::NOT_FOUND.into_response()),
         }
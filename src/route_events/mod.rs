@@ -0,0 +1,169 @@
+use crate::app_state::AppState;
+use axum::{routing::get, Router};
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/events", get(handlers::subscribe_changes))
+        .route("/events/ws", get(handlers::subscribe_changes_ws))
+}
+
+pub mod handlers {
+    use crate::{
+        app_state::AppState, changes::ChangeBroker, schema::dto_in::SubscribeChangesQueryDto,
+    };
+    use axum::{
+        debug_handler,
+        extract::{
+            ws::{close_code, CloseFrame, Message, WebSocket},
+            Query, State, WebSocketUpgrade,
+        },
+        response::{
+            sse::{Event, KeepAlive},
+            Response, Sse,
+        },
+    };
+    use futures::Stream;
+    use std::convert::Infallible;
+    use tokio::sync::broadcast::error::RecvError;
+
+    /// Streams changes of collections, files and tags as Server-Sent Events.
+    ///
+    /// Each change is sent as a `change` event. A subscriber that falls too far behind is sent a
+    /// `lagged` event and the stream ends; it should reload what it displays and subscribe again.
+    /// The stream also ends when the server shuts down.
+    #[utoipa::path(
+        get,
+        operation_id = "subscribe-changes",
+        tag = "event",
+        path = "/events",
+        params(
+            SubscribeChangesQueryDto
+        ),
+        responses(
+            (status = OK, description = "a stream of `change` events", content_type = "text/event-stream", body = ChangeEventDto),
+        ),
+    )]
+    #[debug_handler(state = AppState)]
+    pub async fn subscribe_changes(
+        State(change_broker): State<ChangeBroker>,
+        Query(query): Query<SubscribeChangesQueryDto>,
+    ) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+        let changes = change_broker.subscribe();
+        let stream = futures::stream::unfold(Some(changes), move |changes| {
+            let query = query.clone();
+            let change_broker = change_broker.clone();
+
+            async move {
+                let mut changes = changes?;
+
+                loop {
+                    let change = tokio::select! {
+                        change = changes.recv() => change,
+                        _ = change_broker.shutting_down() => break None,
+                    };
+
+                    match change {
+                        Ok(change) if query.matches(&change) => {
+                            let event = Event::default()
+                                .event("change")
+                                .json_data(change.event)
+                                .expect("failed to serialize change event");
+                            break Some((Ok(event), Some(changes)));
+                        }
+                        Ok(_) => {}
+                        Err(RecvError::Lagged(skipped)) => {
+                            let event = Event::default().event("lagged").data(skipped.to_string());
+                            break Some((Ok(event), None));
+                        }
+                        Err(RecvError::Closed) => break None,
+                    }
+                }
+            }
+        });
+
+        Sse::new(stream).keep_alive(KeepAlive::default())
+    }
+
+    /// Streams changes of collections, files and tags over a WebSocket.
+    ///
+    /// Each change is sent as a JSON text message. The client may replace the filters of the
+    /// subscription at any time by sending them as a JSON text message. A subscriber that falls
+    /// too far behind is disconnected with close code 1013, and every subscriber is disconnected
+    /// with close code 1001 when the server shuts down.
+    #[utoipa::path(
+        get,
+        operation_id = "subscribe-changes-ws",
+        tag = "event",
+        path = "/events/ws",
+        params(
+            SubscribeChangesQueryDto
+        ),
+        responses(
+            (status = SWITCHING_PROTOCOLS, description = "the connection is upgraded to a WebSocket carrying changes", body = ChangeEventDto),
+        ),
+    )]
+    #[debug_handler(state = AppState)]
+    pub async fn subscribe_changes_ws(
+        State(change_broker): State<ChangeBroker>,
+        Query(query): Query<SubscribeChangesQueryDto>,
+        upgrade: WebSocketUpgrade,
+    ) -> Response {
+        upgrade.on_upgrade(move |socket| stream_changes(socket, change_broker, query))
+    }
+
+    async fn stream_changes(
+        mut socket: WebSocket,
+        change_broker: ChangeBroker,
+        mut query: SubscribeChangesQueryDto,
+    ) {
+        let mut changes = change_broker.subscribe();
+
+        loop {
+            tokio::select! {
+                change = changes.recv() => match change {
+                    Ok(change) if query.matches(&change) => {
+                        let text = serde_json::to_string(&change.event)
+                            .expect("failed to serialize change event");
+                        if socket.send(Message::Text(text)).await.is_err() {
+                            return;
+                        }
+                    }
+                    Ok(_) => {}
+                    Err(RecvError::Lagged(_)) => {
+                        close(socket, close_code::AGAIN, "subscriber lagged behind").await;
+                        return;
+                    }
+                    Err(RecvError::Closed) => {
+                        close(socket, close_code::AWAY, "server is shutting down").await;
+                        return;
+                    }
+                },
+                _ = change_broker.shutting_down() => {
+                    close(socket, close_code::AWAY, "server is shutting down").await;
+                    return;
+                },
+                message = socket.recv() => match message {
+                    Some(Ok(Message::Text(text))) => match serde_json::from_str(&text) {
+                        Ok(filters) => query = filters,
+                        Err(_) => {
+                            close(socket, close_code::INVALID, "invalid subscription filters").await;
+                            return;
+                        }
+                    },
+                    Some(Ok(Message::Close(_))) | Some(Err(_)) | None => return,
+                    // Pings are answered by the socket itself.
+                    Some(Ok(_)) => {}
+                },
+            }
+        }
+    }
+
+    async fn close(mut socket: WebSocket, code: u16, reason: &'static str) {
+        let _ = socket
+            .send(Message::Close(Some(CloseFrame {
+                code,
+                reason: reason.into(),
+            })))
+            .await;
+    }
+}
@@ -0,0 +1,1751 @@
+use crate::{
+    audit::{record_event, snapshot, AuditContext},
+    db::{
+        escape_like_pattern,
+        schema::{
+            collection_file_pairs, collection_upload_policies, collections, file_versions, files,
+            tags,
+        },
+        DBPool,
+    },
+    file_driver::{
+        is_system_tag_title, FileDriver, FileInfo, OpenFileError, UploadPolicy, VersionError,
+        WriteFileError, SYSTEM_TAG_PREFIX,
+    },
+    jobs::{enqueue, request_thumbnails, IndexFileJob, Job, ProcessFileJob},
+    pagination::{BoxedPredicate, CursorCodec, CursorError, Keyset, PageRequest, PaginationError},
+    quota::{ensure_user_file_room, find_size_budget, QuotaError},
+    route_collections::collection_service::{in_collection_subtree, live_collection_ids},
+    schema::{
+        dto_in::{
+            AuditActionDto, CreateFileBodyDto, CreateFileTagDto, DownloadFileVersionPathDto,
+            FileSortDto, FileStatusDto, FindCollectionFilesPathDto, FindCollectionFilesQueryDto,
+            FindFileVersionsPathDto, FindFilesBodyDto, FindFilesQueryDto, FindFilesTagFilterDto,
+            FindTrashQueryDto, PatchFilePathDto, PruneFileVersionsBodyDto,
+            PruneFileVersionsPathDto, RemoveFilePathDto, RestoreFilePathDto,
+            RestoreFileVersionPathDto, UploadFilePathDto, UploadFileQueryDto,
+        },
+        dto_out::{CursorPaginationMetadataDto, FileDto, FileVersionDto, FindFilesResultDto},
+        merge_patch::merge_patch,
+    },
+    search::{search_all_files, SearchBackend, SearchError},
+    versions::{
+        file_version_columns, find_current_version, insert_file_version, prune_file_versions,
+        remove_version_contents, RawFileVersionDto, VersionRetention,
+    },
+};
+use axum::{body::Bytes, http::StatusCode};
+use chrono::{NaiveDateTime, Utc};
+use codegen::ErrorEnum;
+use diesel::{
+    dsl::{now, IntoBoxed},
+    pg::Pg,
+    prelude::*,
+};
+use diesel_async::{
+    scoped_futures::ScopedFutureExt, AsyncConnection, AsyncPgConnection, RunQueryDsl,
+};
+use futures::Stream;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use thiserror::Error;
+use tokio::fs::File;
+use uuid::Uuid;
+
+#[derive(ErrorEnum, Error, Debug)]
+pub enum FileServiceError {
+    #[error("internal server error")]
+    #[status(StatusCode::INTERNAL_SERVER_ERROR)]
+    PoolError(#[from] diesel_async::pooled_connection::deadpool::PoolError),
+    #[error("internal server error")]
+    #[status(StatusCode::INTERNAL_SERVER_ERROR)]
+    DieselError(#[from] diesel::result::Error),
+    #[error("{0}")]
+    #[status("0")]
+    SearchError(#[from] SearchError),
+    #[error("{0}")]
+    #[status("0")]
+    WriteFileError(#[from] WriteFileError),
+    #[error("{0}")]
+    #[status("0")]
+    QuotaError(#[from] QuotaError),
+    #[error("{0}")]
+    #[status("0")]
+    VersionError(#[from] VersionError),
+    #[error("{0}")]
+    #[status("0")]
+    OpenFileError(#[from] OpenFileError),
+    #[error("internal server error")]
+    #[status(StatusCode::INTERNAL_SERVER_ERROR)]
+    MissingVersionContent,
+    #[error("file name must not be empty")]
+    #[status(StatusCode::UNPROCESSABLE_ENTITY)]
+    EmptyFileName,
+    #[error("tag `{0}` is duplicated")]
+    #[status(StatusCode::UNPROCESSABLE_ENTITY)]
+    DuplicatedTag(String),
+    #[error(
+        "tag `{0}` is reserved; tags starting with `{}` are set from file metadata",
+        SYSTEM_TAG_PREFIX
+    )]
+    #[status(StatusCode::UNPROCESSABLE_ENTITY)]
+    ReservedTag(String),
+    #[error("{0}")]
+    #[status("0")]
+    PaginationError(#[from] PaginationError),
+    #[error("{0}")]
+    #[status("0")]
+    CursorError(#[from] CursorError),
+    #[error("internal server error")]
+    #[status(StatusCode::INTERNAL_SERVER_ERROR)]
+    InvalidSmartQuery(serde_json::Error),
+    #[error("invalid patch: {0}")]
+    #[status(StatusCode::UNPROCESSABLE_ENTITY)]
+    InvalidPatch(serde_json::Error),
+    #[error("file is being uploaded")]
+    #[status(StatusCode::CONFLICT)]
+    UploadInProgress,
+    #[error("upload was interrupted; the file was trashed or its upload abandoned meanwhile")]
+    #[status(StatusCode::CONFLICT)]
+    UploadInterrupted,
+    #[error("settings of a retention must not be negative")]
+    #[status(StatusCode::UNPROCESSABLE_ENTITY)]
+    NegativeRetention,
+}
+
+/// The content of a version of a file, opened for reading.
+pub struct VersionContent {
+    pub file: File,
+    pub size: i64,
+    pub mime: Option<String>,
+}
+
+#[derive(Clone)]
+pub struct FileService {
+    db_pool: DBPool,
+    file_driver: FileDriver,
+    search_backend: Arc<dyn SearchBackend>,
+    cursor_codec: CursorCodec,
+    /// Server-wide policy of the uploads, which collections may override.
+    upload_policy: UploadPolicy,
+    /// Server-wide retention of the versions, which prunings may override.
+    version_retention: VersionRetention,
+}
+
+impl FileService {
+    pub fn new(
+        db_pool: DBPool,
+        file_driver: FileDriver,
+        search_backend: Arc<dyn SearchBackend>,
+        cursor_codec: CursorCodec,
+    ) -> Self {
+        Self {
+            db_pool,
+            file_driver,
+            search_backend,
+            cursor_codec,
+            upload_policy: UploadPolicy::from_env(),
+            version_retention: VersionRetention::from_env(),
+        }
+    }
+
+    pub async fn find_files(
+        &self,
+        query: FindFilesQueryDto,
+        mut body: FindFilesBodyDto,
+    ) -> Result<FindFilesResultDto, FileServiceError> {
+        let tag_filters = body.tags.take().unwrap_or_default();
+        ensure_unique_tag_titles(tag_filters.iter().map(|tag| tag.title.as_str()))?;
+
+        let (hits, truncated) = match body.query.as_deref().map(str::trim) {
+            Some(search_query) if !search_query.is_empty() => {
+                let (hits, truncated) =
+                    search_all_files(self.search_backend.as_ref(), search_query).await?;
+                (Some(hits), truncated)
+            }
+            _ => (None, false),
+        };
+        let hits = hits.as_deref();
+
+        let result = self
+            .find_page(&query, || filter_files(query.status, hits, &tag_filters))
+            .await?;
+
+        Ok(FindFilesResultDto {
+            truncated,
+            ..result
+        })
+    }
+
+    pub async fn find_collection_files(
+        &self,
+        path: FindCollectionFilesPathDto,
+        query: FindFilesQueryDto,
+        scope: FindCollectionFilesQueryDto,
+    ) -> Result<Option<FindFilesResultDto>, FileServiceError> {
+        let collection = {
+            let db_conn = &mut self.db_pool.get().await?;
+            collections::table
+                .select((collections::id, collections::smart_query))
+                .filter(collections::uuid.eq(path.identifier))
+                .filter(collections::deleted_at.is_null())
+                .get_result::<(i32, Option<serde_json::Value>)>(db_conn)
+                .await
+                .optional()?
+        };
+        let (collection_id, smart_query) = match collection {
+            Some(collection) => collection,
+            None => return Ok(None),
+        };
+
+        // Smart collections are evaluated on every listing, so they follow tag changes.
+        if let Some(smart_query) = smart_query {
+            let body = serde_json::from_value::<FindFilesBodyDto>(smart_query)
+                .map_err(FileServiceError::InvalidSmartQuery)?;
+            return self.find_files(query, body).await.map(Some);
+        }
+
+        let result = self
+            .find_page(&query, || {
+                let mut pair_q = collection_file_pairs::table
+                    .select(collection_file_pairs::file_id)
+                    .into_boxed();
+
+                if scope.recursive {
+                    // Trashed subcollections keep their files, which are not listed.
+                    pair_q = pair_q
+                        .filter(in_collection_subtree(
+                            "collection_file_pairs.collection_id",
+                            path.identifier,
+                        ))
+                        .filter(collection_file_pairs::collection_id.eq_any(live_collection_ids()));
+                } else {
+                    pair_q = pair_q.filter(collection_file_pairs::collection_id.eq(collection_id));
+                }
+
+                filter_files(query.status, None, &[]).filter(files::id.eq_any(pair_q))
+            })
+            .await?;
+
+        Ok(Some(result))
+    }
+
+    /// Loads the requested page of the files matched by `filter`.
+    async fn find_page<'a>(
+        &self,
+        query: &FindFilesQueryDto,
+        filter: impl Fn() -> files::BoxedQuery<'a, Pg>,
+    ) -> Result<FindFilesResultDto, FileServiceError> {
+        let first = query
+            .first_cursor
+            .as_deref()
+            .map(|cursor| self.cursor_codec.decode::<FileKey>(cursor))
+            .transpose()?;
+        let last = query
+            .last_cursor
+            .as_deref()
+            .map(|cursor| self.cursor_codec.decode::<FileKey>(cursor))
+            .transpose()?;
+        let request = PageRequest::new(first, last, query.sort, query.order, query.page_size)?;
+
+        let db_conn = &mut self.db_pool.get().await?;
+        // The closure shortens the lifetime of the query to the borrow of the request.
+        #[allow(clippy::redundant_closure)]
+        let matching = || filter();
+        let rows = request
+            .select(&matching, files::all_columns)
+            .load::<RawFileDto>(db_conn)
+            .await?;
+        let has_behind = match request.behind(&matching) {
+            Some(behind) => behind.get_result(db_conn).await?,
+            None => false,
+        };
+        let page = request.page(rows, has_behind);
+
+        let pagination = CursorPaginationMetadataDto {
+            has_prev: page.pagination.has_prev,
+            has_next: page.pagination.has_next,
+            first_cursor: page
+                .items
+                .as_slice()
+                .first()
+                .map(|item| self.cursor_codec.encode(&FileKey::new(item, query.sort))),
+            last_cursor: page
+                .items
+                .as_slice()
+                .last()
+                .map(|item| self.cursor_codec.encode(&FileKey::new(item, query.sort))),
+        };
+        let items = page
+            .items
+            .into_iter()
+            .map(|raw_item| raw_item.into())
+            .collect();
+
+        Ok(FindFilesResultDto {
+            pagination,
+            items,
+            truncated: false,
+        })
+    }
+
+    pub async fn create_file(
+        &self,
+        body: CreateFileBodyDto,
+        context: &AuditContext,
+    ) -> Result<FileDto, FileServiceError> {
+        if body.name.is_empty() {
+            return Err(FileServiceError::EmptyFileName);
+        }
+
+        ensure_unique_tag_titles(body.tags.iter().map(|tag| tag.title.as_str()))?;
+
+        if let Some(tag) = body.tags.iter().find(|tag| is_system_tag_title(&tag.title)) {
+            return Err(FileServiceError::ReservedTag(tag.title.clone()));
+        }
+
+        let db_conn = &mut self.db_pool.get().await?;
+        db_conn
+            .transaction(|db_conn| {
+                async move {
+                    ensure_user_file_room(db_conn, context.actor.as_deref(), 0).await?;
+
+                    let raw_item = diesel::insert_into(files::table)
+                        .values((files::name.eq(&body.name), files::owner.eq(&context.actor)))
+                        .get_result::<RawFileDto>(db_conn)
+                        .await?;
+
+                    insert_tags(db_conn, raw_item.id, &body.tags).await?;
+
+                    let item = FileDto::from(raw_item);
+                    let mut after = snapshot(&item);
+                    after["tags"] = snapshot(&body.tags);
+
+                    record_event(
+                        db_conn,
+                        context,
+                        AuditActionDto::CreateFile,
+                        item.uuid,
+                        None,
+                        Some(after),
+                    )
+                    .await?;
+
+                    Ok(item)
+                }
+                .scope_boxed()
+            })
+            .await
+    }
+
+    pub async fn upload_file(
+        &self,
+        path: UploadFilePathDto,
+        query: UploadFileQueryDto,
+        content_length: Option<u64>,
+        stream: impl Stream<Item = Result<Bytes, axum::Error>>,
+        context: &AuditContext,
+    ) -> Result<Option<FileDto>, FileServiceError> {
+        use crate::db::schema::files::dsl::*;
+
+        let previous = {
+            let db_conn = &mut self.db_pool.get().await?;
+            db_conn
+                .transaction(|db_conn| {
+                    async move {
+                        let previous = files
+                            .select((id, uuid, status, failure_reason, size, owner, uploaded_at))
+                            .filter(uuid.eq(path.identifier))
+                            .filter(deleted_at.is_null())
+                            .for_update()
+                            .get_result::<(
+                                i32,
+                                Uuid,
+                                FileStatusDto,
+                                Option<String>,
+                                Option<i64>,
+                                Option<String>,
+                                Option<NaiveDateTime>,
+                            )>(db_conn)
+                            .await
+                            .optional()?;
+                        let (
+                            file_id,
+                            file_uuid,
+                            previous_status,
+                            previous_failure_reason,
+                            file_size,
+                            file_owner,
+                            file_uploaded_at,
+                        ) = match previous {
+                            Some(previous) => previous,
+                            None => return Ok(None),
+                        };
+
+                        if previous_status == FileStatusDto::Uploading {
+                            return Err(FileServiceError::UploadInProgress);
+                        }
+
+                        // The content about to be replaced stays the one of its version; an
+                        // upload resumed past the start extends the content of its version instead.
+                        if query.offset.unwrap_or_default() == 0 {
+                            if let Some(version) =
+                                find_current_version(db_conn, file_id, file_uploaded_at).await?
+                            {
+                                self.file_driver
+                                    .keep_version(file_uuid, version.uuid)
+                                    .await?;
+                            }
+                        }
+
+                        // Uploads of different files may take from the same quota at once, so it
+                        // may be exceeded by what they write concurrently.
+                        let max_size = find_size_budget(
+                            db_conn,
+                            file_id,
+                            file_owner.as_deref(),
+                            file_size.unwrap_or_default(),
+                        )
+                        .await?;
+                        let policies =
+                            find_upload_policies(db_conn, &self.upload_policy, file_id).await?;
+
+                        diesel::update(files.filter(uuid.eq(path.identifier)))
+                            .set((
+                                status.eq(FileStatusDto::Uploading),
+                                failure_reason.eq(None::<String>),
+                            ))
+                            .execute(db_conn)
+                            .await?;
+
+                        Ok::<_, FileServiceError>(Some((
+                            file_uuid,
+                            previous_status,
+                            previous_failure_reason,
+                            file_size,
+                            max_size,
+                            policies,
+                        )))
+                    }
+                    .scope_boxed()
+                })
+                .await?
+        };
+        let (
+            file_uuid,
+            previous_status,
+            previous_failure_reason,
+            previous_size,
+            max_size,
+            policies,
+        ) = match previous {
+            Some(previous) => previous,
+            None => return Ok(None),
+        };
+
+        let file_size = match self
+            .file_driver
+            .write_file(
+                file_uuid,
+                query.offset,
+                content_length,
+                max_size,
+                &policies,
+                stream,
+            )
+            .await
+        {
+            Ok(file_size) => file_size,
+            Err(err) => {
+                // A rejected offset, length or type leaves the content as it was; anything else
+                // may have truncated it, and what was written is counted toward the quotas.
+                let (next_status, next_failure_reason, next_size) = match &err {
+                    WriteFileError::InvalidOffset { .. }
+                    | WriteFileError::ContentTooLarge { .. }
+                    | WriteFileError::FileTooLarge { .. }
+                    | WriteFileError::MimeTypeNotAllowed(_) => {
+                        (previous_status, previous_failure_reason, previous_size)
+                    }
+                    err => {
+                        tracing::error!(
+                            "failed to upload the content of file `{}`: {:#?}",
+                            file_uuid,
+                            err
+                        );
+                        (
+                            FileStatusDto::Failed,
+                            Some("failed to upload the content".to_owned()),
+                            match self.file_driver.read_file_size(file_uuid).await {
+                                Ok(Some(written_size)) => Some(written_size as i64),
+                                _ => previous_size,
+                            },
+                        )
+                    }
+                };
+
+                let db_conn = &mut self.db_pool.get().await?;
+                diesel::update(files.filter(uuid.eq(file_uuid)))
+                    .filter(status.eq(FileStatusDto::Uploading))
+                    .set((
+                        status.eq(next_status),
+                        failure_reason.eq(next_failure_reason),
+                        size.eq(next_size),
+                    ))
+                    .execute(db_conn)
+                    .await?;
+
+                return Err(err.into());
+            }
+        };
+
+        let db_conn = &mut self.db_pool.get().await?;
+        let raw_item = db_conn
+            .transaction(|db_conn| {
+                async move {
+                    // The file may have been trashed, or its upload marked as interrupted, while
+                    // the content was written.
+                    let current = files
+                        .filter(uuid.eq(file_uuid))
+                        .filter(status.eq(FileStatusDto::Uploading))
+                        .filter(deleted_at.is_null())
+                        .for_update()
+                        .get_result::<RawFileDto>(db_conn)
+                        .await
+                        .optional()?
+                        .ok_or(FileServiceError::UploadInterrupted)?;
+
+                    // The type, hash and system tags of the new content are set once processed.
+                    let raw_item = diesel::update(files.filter(uuid.eq(file_uuid)))
+                        .set((
+                            mime.eq(None::<String>),
+                            size.eq(file_size as i64),
+                            hash.eq(None::<i64>),
+                            uploaded_at.eq(now),
+                            status.eq(FileStatusDto::Processing),
+                            failure_reason.eq(None::<String>),
+                        ))
+                        .get_result::<RawFileDto>(db_conn)
+                        .await?;
+                    let uploaded = raw_item
+                        .uploaded_at
+                        .expect("uploaded file must have an upload time");
+                    // Its upload time moves along, so that a processing of the content as it was
+                    // before is left without effect.
+                    let extended = match query.offset.unwrap_or_default() {
+                        0 => None,
+                        _ => find_current_version(db_conn, current.id, current.uploaded_at).await?,
+                    };
+                    match extended {
+                        Some(version) => {
+                            diesel::update(file_versions::table.find(version.id))
+                                .set((
+                                    file_versions::size.eq(file_size as i64),
+                                    file_versions::hash.eq(None::<i64>),
+                                    file_versions::mime.eq(None::<String>),
+                                    file_versions::uploaded_at.eq(uploaded),
+                                ))
+                                .execute(db_conn)
+                                .await?;
+                        }
+                        None => {
+                            insert_file_version(
+                                db_conn,
+                                raw_item.id,
+                                uploaded,
+                                file_size as i64,
+                                None,
+                                None,
+                            )
+                            .await?;
+                        }
+                    }
+                    replace_system_tags(db_conn, raw_item.id, &[]).await?;
+                    // A job still queued for the file is reused for the content as it is now.
+                    enqueue(db_conn, &Job::ProcessFile(ProcessFileJob { file_uuid })).await?;
+
+                    record_event(
+                        db_conn,
+                        context,
+                        AuditActionDto::UploadFile,
+                        file_uuid,
+                        Some(snapshot(&FileDto::from(current))),
+                        Some(snapshot(&FileDto::from(raw_item.clone()))),
+                    )
+                    .await?;
+
+                    Ok::<_, FileServiceError>(raw_item)
+                }
+                .scope_boxed()
+            })
+            .await?;
+
+        Ok(Some(raw_item.into()))
+    }
+
+    pub async fn patch_file(
+        &self,
+        path: PatchFilePathDto,
+        patch: serde_json::Value,
+        context: &AuditContext,
+    ) -> Result<Option<FileDto>, FileServiceError> {
+        use crate::db::schema::files::dsl::*;
+
+        let db_conn = &mut self.db_pool.get().await?;
+        let raw_item = db_conn
+            .transaction(|db_conn| {
+                async move {
+                    let current = files
+                        .filter(uuid.eq(path.identifier))
+                        .filter(deleted_at.is_null())
+                        .for_update()
+                        .get_result::<RawFileDto>(db_conn)
+                        .await
+                        .optional()?;
+                    let current = match current {
+                        Some(current) => current,
+                        None => return Ok(None),
+                    };
+
+                    let mut document = serde_json::to_value(FileMetadata {
+                        name: current.name.clone(),
+                    })
+                    .expect("failed to serialize file metadata");
+                    merge_patch(&mut document, &patch);
+                    let metadata = serde_json::from_value::<FileMetadata>(document)
+                        .map_err(FileServiceError::InvalidPatch)?;
+
+                    if metadata.name.is_empty() {
+                        return Err(FileServiceError::EmptyFileName);
+                    }
+
+                    let raw_item = diesel::update(files.filter(id.eq(current.id)))
+                        .set(name.eq(metadata.name))
+                        .get_result::<RawFileDto>(db_conn)
+                        .await?;
+
+                    // Only uploaded files are searchable.
+                    if raw_item.uploaded_at.is_some() {
+                        let job = Job::IndexFile(IndexFileJob {
+                            file_uuid: raw_item.uuid,
+                        });
+                        enqueue(db_conn, &job).await?;
+                    }
+
+                    record_event(
+                        db_conn,
+                        context,
+                        AuditActionDto::UpdateFile,
+                        raw_item.uuid,
+                        Some(snapshot(&FileDto::from(current))),
+                        Some(snapshot(&FileDto::from(raw_item.clone()))),
+                    )
+                    .await?;
+
+                    Ok(Some(raw_item))
+                }
+                .scope_boxed()
+            })
+            .await?;
+
+        Ok(raw_item.map(|raw_item| raw_item.into()))
+    }
+
+    pub async fn remove_file(
+        &self,
+        path: RemoveFilePathDto,
+        context: &AuditContext,
+    ) -> Result<Option<FileDto>, FileServiceError> {
+        self.set_file_deleted_at(path.identifier, true, AuditActionDto::RemoveFile, context)
+            .await
+    }
+
+    pub async fn find_trashed_files(
+        &self,
+        query: FindTrashQueryDto,
+    ) -> Result<FindFilesResultDto, FileServiceError> {
+        let first = query
+            .first_cursor
+            .as_deref()
+            .map(|cursor| self.cursor_codec.decode::<TrashedFileKey>(cursor))
+            .transpose()?;
+        let last = query
+            .last_cursor
+            .as_deref()
+            .map(|cursor| self.cursor_codec.decode::<TrashedFileKey>(cursor))
+            .transpose()?;
+        let request = PageRequest::new(first, last, (), query.order, query.page_size)?;
+
+        let db_conn = &mut self.db_pool.get().await?;
+        let matching = || {
+            files::table
+                .filter(files::deleted_at.is_not_null())
+                .into_boxed()
+        };
+        let rows = request
+            .select(&matching, files::all_columns)
+            .load::<RawFileDto>(db_conn)
+            .await?;
+        let has_behind = match request.behind(&matching) {
+            Some(behind) => behind.get_result(db_conn).await?,
+            None => false,
+        };
+        let page = request.page(rows, has_behind);
+
+        let pagination = CursorPaginationMetadataDto {
+            has_prev: page.pagination.has_prev,
+            has_next: page.pagination.has_next,
+            first_cursor: page
+                .items
+                .as_slice()
+                .first()
+                .map(|item| self.cursor_codec.encode(&TrashedFileKey::new(item, ()))),
+            last_cursor: page
+                .items
+                .as_slice()
+                .last()
+                .map(|item| self.cursor_codec.encode(&TrashedFileKey::new(item, ()))),
+        };
+        let items = page
+            .items
+            .into_iter()
+            .map(|raw_item| raw_item.into())
+            .collect();
+
+        Ok(FindFilesResultDto {
+            pagination,
+            items,
+            truncated: false,
+        })
+    }
+
+    pub async fn restore_file(
+        &self,
+        path: RestoreFilePathDto,
+        context: &AuditContext,
+    ) -> Result<Option<FileDto>, FileServiceError> {
+        self.set_file_deleted_at(path.identifier, false, AuditActionDto::RestoreFile, context)
+            .await
+    }
+
+    /// Finds the versions of a file, most recent first.
+    pub async fn find_file_versions(
+        &self,
+        path: FindFileVersionsPathDto,
+    ) -> Result<Option<Vec<FileVersionDto>>, FileServiceError> {
+        let db_conn = &mut self.db_pool.get().await?;
+        let file = files::table
+            .select((files::id, files::uploaded_at))
+            .filter(files::uuid.eq(path.identifier))
+            .filter(files::deleted_at.is_null())
+            .get_result::<(i32, Option<NaiveDateTime>)>(db_conn)
+            .await
+            .optional()?;
+        let (file_id, current) = match file {
+            Some(file) => file,
+            None => return Ok(None),
+        };
+
+        let versions = file_versions::table
+            .select(file_version_columns())
+            .filter(file_versions::file_id.eq(file_id))
+            .order(file_versions::number.desc())
+            .load::<RawFileVersionDto>(db_conn)
+            .await?;
+
+        Ok(Some(
+            versions
+                .into_iter()
+                .map(|version| version.into_dto(current))
+                .collect(),
+        ))
+    }
+
+    /// Opens the content of a version of a file for reading.
+    pub async fn download_file_version(
+        &self,
+        path: DownloadFileVersionPathDto,
+    ) -> Result<Option<VersionContent>, FileServiceError> {
+        let db_conn = &mut self.db_pool.get().await?;
+        let version = file_versions::table
+            .inner_join(files::table)
+            .select((file_version_columns(), files::uploaded_at))
+            .filter(files::uuid.eq(path.identifier))
+            .filter(files::deleted_at.is_null())
+            .filter(file_versions::uuid.eq(path.version))
+            .get_result::<(RawFileVersionDto, Option<NaiveDateTime>)>(db_conn)
+            .await
+            .optional()?;
+        let (version, current) = match version {
+            Some(version) => version,
+            None => return Ok(None),
+        };
+
+        let file = match self.file_driver.open_version(version.uuid).await? {
+            Some(file) => file,
+            // The content of a version is kept apart from the one of its file only once replaced.
+            None if current == Some(version.uploaded_at) => {
+                self.file_driver.open_file(path.identifier).await?
+            }
+            None => return Err(FileServiceError::MissingVersionContent),
+        };
+
+        Ok(Some(VersionContent {
+            file,
+            size: version.size,
+            mime: version.mime,
+        }))
+    }
+
+    /// Brings a file back to the content of one of its versions, as a new version that is
+    /// processed like an upload.
+    pub async fn restore_file_version(
+        &self,
+        path: RestoreFileVersionPathDto,
+        context: &AuditContext,
+    ) -> Result<Option<FileDto>, FileServiceError> {
+        let db_conn = &mut self.db_pool.get().await?;
+        db_conn
+            .transaction(|db_conn| {
+                async move {
+                    let current = files::table
+                        .filter(files::uuid.eq(path.identifier))
+                        .filter(files::deleted_at.is_null())
+                        .for_update()
+                        .get_result::<RawFileDto>(db_conn)
+                        .await
+                        .optional()?;
+                    let current = match current {
+                        Some(current) => current,
+                        None => return Ok(None),
+                    };
+
+                    if current.status == FileStatusDto::Uploading {
+                        return Err(FileServiceError::UploadInProgress);
+                    }
+
+                    let version = file_versions::table
+                        .select(file_version_columns())
+                        .filter(file_versions::file_id.eq(current.id))
+                        .filter(file_versions::uuid.eq(path.version))
+                        .get_result::<RawFileVersionDto>(db_conn)
+                        .await
+                        .optional()?;
+                    let version = match version {
+                        Some(version) => version,
+                        None => return Ok(None),
+                    };
+
+                    let max_size = find_size_budget(
+                        db_conn,
+                        current.id,
+                        current.owner.as_deref(),
+                        current.size.unwrap_or_default(),
+                    )
+                    .await?;
+                    if let Some(max_size) =
+                        max_size.filter(|max_size| *max_size < version.size as u64)
+                    {
+                        return Err(WriteFileError::ContentTooLarge {
+                            size: version.size as u64,
+                            max_size,
+                        }
+                        .into());
+                    }
+
+                    if let Some(replaced) =
+                        find_current_version(db_conn, current.id, current.uploaded_at).await?
+                    {
+                        self.file_driver
+                            .keep_version(current.uuid, replaced.uuid)
+                            .await?;
+                    }
+                    self.file_driver
+                        .restore_version(current.uuid, version.uuid)
+                        .await?;
+
+                    // The type, hash and system tags of the restored content are set once
+                    // processed again.
+                    let raw_item = diesel::update(files::table.find(current.id))
+                        .set((
+                            files::mime.eq(None::<String>),
+                            files::size.eq(version.size),
+                            files::hash.eq(None::<i64>),
+                            files::uploaded_at.eq(now),
+                            files::status.eq(FileStatusDto::Processing),
+                            files::failure_reason.eq(None::<String>),
+                        ))
+                        .get_result::<RawFileDto>(db_conn)
+                        .await?;
+                    let restored_at = raw_item
+                        .uploaded_at
+                        .expect("restored file must have an upload time");
+                    let restored = insert_file_version(
+                        db_conn,
+                        raw_item.id,
+                        restored_at,
+                        version.size,
+                        version.hash,
+                        version.mime.as_deref(),
+                    )
+                    .await?;
+                    replace_system_tags(db_conn, raw_item.id, &[]).await?;
+                    enqueue(
+                        db_conn,
+                        &Job::ProcessFile(ProcessFileJob {
+                            file_uuid: raw_item.uuid,
+                        }),
+                    )
+                    .await?;
+
+                    let item = FileDto::from(raw_item);
+                    let mut after = snapshot(&item);
+                    after["version"] = snapshot(&restored.into_dto(Some(restored_at)));
+                    after["restoredVersion"] = snapshot(&version.into_dto(Some(restored_at)));
+
+                    record_event(
+                        db_conn,
+                        context,
+                        AuditActionDto::RestoreFileVersion,
+                        item.uuid,
+                        Some(snapshot(&FileDto::from(current))),
+                        Some(after),
+                    )
+                    .await?;
+
+                    Ok(Some(item))
+                }
+                .scope_boxed()
+            })
+            .await
+    }
+
+    /// Removes the versions of a file the retention does not keep; returns the removed versions.
+    pub async fn prune_file_versions(
+        &self,
+        path: PruneFileVersionsPathDto,
+        body: PruneFileVersionsBodyDto,
+        context: &AuditContext,
+    ) -> Result<Option<Vec<FileVersionDto>>, FileServiceError> {
+        if body.keep_last.map_or(false, |keep_last| keep_last < 0)
+            || body.keep_days.map_or(false, |keep_days| keep_days < 0)
+        {
+            return Err(FileServiceError::NegativeRetention);
+        }
+
+        let retention = self
+            .version_retention
+            .override_with(body.keep_last, body.keep_days);
+
+        let db_conn = &mut self.db_pool.get().await?;
+        let removed = db_conn
+            .transaction(|db_conn| {
+                async move {
+                    let file = files::table
+                        .select((files::id, files::uploaded_at))
+                        .filter(files::uuid.eq(path.identifier))
+                        .filter(files::deleted_at.is_null())
+                        .for_update()
+                        .get_result::<(i32, Option<NaiveDateTime>)>(db_conn)
+                        .await
+                        .optional()?;
+                    let (file_id, current) = match file {
+                        Some(file) => file,
+                        None => return Ok(None),
+                    };
+
+                    let removed = prune_file_versions(
+                        db_conn,
+                        file_id,
+                        path.identifier,
+                        current,
+                        &retention,
+                        context,
+                    )
+                    .await?;
+
+                    Ok::<_, diesel::result::Error>(Some((removed, current)))
+                }
+                .scope_boxed()
+            })
+            .await?;
+        let (removed, current) = match removed {
+            Some(removed) => removed,
+            None => return Ok(None),
+        };
+
+        remove_version_contents(&self.file_driver, &removed).await;
+
+        Ok(Some(
+            removed
+                .into_iter()
+                .map(|version| version.into_dto(current))
+                .collect(),
+        ))
+    }
+
+    /// Moves a file to or out of the trash; does nothing if it is already there.
+    async fn set_file_deleted_at(
+        &self,
+        file_uuid: Uuid,
+        deleted: bool,
+        action: AuditActionDto,
+        context: &AuditContext,
+    ) -> Result<Option<FileDto>, FileServiceError> {
+        use crate::db::schema::files::dsl::*;
+
+        let db_conn = &mut self.db_pool.get().await?;
+        db_conn
+            .transaction(|db_conn| {
+                async move {
+                    let current = files
+                        .filter(uuid.eq(file_uuid))
+                        .filter(deleted_at.is_null().eq(deleted))
+                        .for_update()
+                        .get_result::<RawFileDto>(db_conn)
+                        .await
+                        .optional()?;
+                    let current = match current {
+                        Some(current) => FileDto::from(current),
+                        None => return Ok(None),
+                    };
+
+                    let raw_item = diesel::update(files.filter(uuid.eq(file_uuid)))
+                        .set(deleted_at.eq(deleted.then(|| Utc::now().naive_utc())))
+                        .get_result::<RawFileDto>(db_conn)
+                        .await?;
+                    let item = FileDto::from(raw_item);
+
+                    record_event(
+                        db_conn,
+                        context,
+                        action,
+                        file_uuid,
+                        Some(snapshot(&current)),
+                        Some(snapshot(&item)),
+                    )
+                    .await?;
+
+                    Ok(Some(item))
+                }
+                .scope_boxed()
+            })
+            .await
+    }
+}
+
+fn filter_files<'a>(
+    status: FileStatusDto,
+    hits: Option<&'a [Uuid]>,
+    tag_filters: &'a [FindFilesTagFilterDto],
+) -> files::BoxedQuery<'a, Pg> {
+    let mut q = files::table
+        .filter(files::status.eq(status))
+        .filter(files::deleted_at.is_null())
+        .into_boxed();
+
+    if let Some(hits) = hits {
+        q = q.filter(files::uuid.eq_any(hits));
+    }
+
+    for tag in tag_filters {
+        let mut tag_q = tags::table
+            .select(tags::file_id)
+            .filter(tags::title.eq(&tag.title))
+            .into_boxed();
+
+        if let Some(value) = &tag.value {
+            if let Some(equal) = &value.equal {
+                tag_q = tag_q.filter(tags::value.eq(equal));
+            }
+
+            if let Some(not_equal) = &value.not_equal {
+                tag_q = tag_q.filter(tags::value.ne(not_equal));
+            }
+
+            if let Some(contains) = &value.contains {
+                tag_q =
+                    tag_q.filter(tags::value.like(format!("%{}%", escape_like_pattern(contains))));
+            }
+
+            if let Some(one_of) = &value.one_of {
+                tag_q = tag_q.filter(tags::value.eq_any(one_of));
+            }
+        }
+
+        q = q.filter(files::id.eq_any(tag_q));
+    }
+
+    q
+}
+
+/// Finds the upload policies a file is subject to: the one of every live collection it is in, or
+/// the server-wide one when it is in none.
+async fn find_upload_policies(
+    db_conn: &mut AsyncPgConnection,
+    upload_policy: &UploadPolicy,
+    file_id: i32,
+) -> QueryResult<Vec<UploadPolicy>> {
+    let overrides =
+        collection_file_pairs::table
+            .left_join(collection_upload_policies::table.on(
+                collection_upload_policies::collection_id.eq(collection_file_pairs::collection_id),
+            ))
+            .select((
+                collection_upload_policies::max_file_size.nullable(),
+                collection_upload_policies::allowed_mime_types.nullable(),
+                collection_upload_policies::denied_mime_types.nullable(),
+            ))
+            .filter(collection_file_pairs::file_id.eq(file_id))
+            .filter(collection_file_pairs::collection_id.eq_any(live_collection_ids()))
+            .load::<(Option<i64>, Option<Vec<String>>, Option<Vec<String>>)>(db_conn)
+            .await?;
+
+    if overrides.is_empty() {
+        return Ok(vec![upload_policy.clone()]);
+    }
+
+    Ok(overrides
+        .into_iter()
+        .map(|(max_file_size, allowed_mime_types, denied_mime_types)| {
+            upload_policy.override_with(
+                max_file_size.map(|max_file_size| max_file_size as u64),
+                allowed_mime_types,
+                denied_mime_types,
+            )
+        })
+        .collect())
+}
+
+/// Inserts a file whose content is already stored under `file_uuid`, along with its tags, and
+/// records its creation; it fails if the quota of the actor has no room for it.
+///
+/// It runs on the connection of the caller, so that it can be part of a larger transaction.
+pub async fn insert_stored_file(
+    db_conn: &mut AsyncPgConnection,
+    file_uuid: Uuid,
+    name: &str,
+    file_info: &FileInfo,
+    file_size: u64,
+    file_tags: &[CreateFileTagDto],
+    context: &AuditContext,
+) -> Result<FileDto, QuotaError> {
+    ensure_user_file_room(db_conn, context.actor.as_deref(), file_size as i64).await?;
+
+    // System tags always come from the content itself, never from the caller.
+    let file_tags = file_tags
+        .iter()
+        .filter(|tag| !is_system_tag_title(&tag.title))
+        .chain(&file_info.system_tags)
+        .cloned()
+        .collect::<Vec<_>>();
+
+    let raw_item = diesel::insert_into(files::table)
+        .values((
+            files::uuid.eq(file_uuid),
+            files::name.eq(name),
+            files::mime.eq(file_info.mime),
+            files::size.eq(file_size as i64),
+            files::hash.eq(file_info.hash as i64),
+            files::uploaded_at.eq(now),
+            files::status.eq(FileStatusDto::Ready),
+            files::owner.eq(&context.actor),
+        ))
+        .get_result::<RawFileDto>(db_conn)
+        .await?;
+    insert_file_version(
+        db_conn,
+        raw_item.id,
+        raw_item
+            .uploaded_at
+            .expect("stored file must have an upload time"),
+        file_size as i64,
+        raw_item.hash,
+        raw_item.mime.as_deref(),
+    )
+    .await?;
+    insert_tags(db_conn, raw_item.id, &file_tags).await?;
+    request_thumbnails(db_conn, raw_item.id, raw_item.uuid).await?;
+
+    let item = FileDto::from(raw_item);
+    let mut after = snapshot(&item);
+    after["tags"] = snapshot(&file_tags);
+
+    record_event(
+        db_conn,
+        context,
+        AuditActionDto::CreateFile,
+        item.uuid,
+        None,
+        Some(after),
+    )
+    .await?;
+
+    Ok(item)
+}
+
+/// Adds the tags whose titles a file does not have yet, except system tags, and records the change if there is any.
+///
+/// It runs on the connection of the caller, so that it can be part of a larger transaction.
+pub async fn merge_file_tags(
+    db_conn: &mut AsyncPgConnection,
+    file_id: i32,
+    file_tags: &[CreateFileTagDto],
+    context: &AuditContext,
+) -> QueryResult<()> {
+    let current_tags = tags::table
+        .select((tags::title, tags::value))
+        .filter(tags::file_id.eq(file_id))
+        .order(tags::id)
+        .load::<(String, Option<String>)>(db_conn)
+        .await?
+        .into_iter()
+        .map(|(title, value)| CreateFileTagDto { title, value })
+        .collect::<Vec<_>>();
+    let missing_tags = file_tags
+        .iter()
+        .filter(|tag| {
+            current_tags
+                .iter()
+                .all(|current| current.title != tag.title)
+        })
+        .cloned()
+        .collect::<Vec<_>>();
+
+    if missing_tags.is_empty() {
+        return Ok(());
+    }
+
+    insert_tags(db_conn, file_id, &missing_tags).await?;
+
+    let item = FileDto::from(
+        files::table
+            .find(file_id)
+            .get_result::<RawFileDto>(db_conn)
+            .await?,
+    );
+    let mut before = snapshot(&item);
+    before["tags"] = snapshot(&current_tags);
+    let mut after = snapshot(&item);
+    after["tags"] = snapshot(&[current_tags, missing_tags].concat());
+
+    record_event(
+        db_conn,
+        context,
+        AuditActionDto::UpdateFile,
+        item.uuid,
+        Some(before),
+        Some(after),
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Stores the type, hash and system tags of the content of a file uploaded at `uploaded_at`, and
+/// the type and hash of its version, and queues the work depending on them; does nothing if the
+/// file was uploaded again or removed since.
+///
+/// It runs on the connection of the caller, so that it can be part of a larger transaction.
+pub async fn store_processed_file(
+    db_conn: &mut AsyncPgConnection,
+    file_uuid: Uuid,
+    uploaded_at: NaiveDateTime,
+    file_info: &FileInfo,
+    context: &AuditContext,
+) -> QueryResult<()> {
+    let current = match lock_pending_upload(db_conn, file_uuid, uploaded_at).await? {
+        Some(current) => current,
+        None => return Ok(()),
+    };
+
+    let raw_item = diesel::update(files::table.find(current.id))
+        .set((
+            files::mime.eq(file_info.mime),
+            files::hash.eq(file_info.hash as i64),
+            files::status.eq(FileStatusDto::Ready),
+            files::failure_reason.eq(None::<String>),
+        ))
+        .get_result::<RawFileDto>(db_conn)
+        .await?;
+    diesel::update(
+        file_versions::table
+            .filter(file_versions::file_id.eq(current.id))
+            .filter(file_versions::uploaded_at.eq(uploaded_at)),
+    )
+    .set((
+        file_versions::mime.eq(file_info.mime),
+        file_versions::hash.eq(file_info.hash as i64),
+    ))
+    .execute(db_conn)
+    .await?;
+    replace_system_tags(db_conn, raw_item.id, &file_info.system_tags).await?;
+    request_thumbnails(db_conn, raw_item.id, raw_item.uuid).await?;
+    enqueue(db_conn, &Job::IndexFile(IndexFileJob { file_uuid })).await?;
+
+    let before = snapshot(&FileDto::from(current));
+    let mut after = snapshot(&FileDto::from(raw_item));
+    after["systemTags"] = snapshot(&file_info.system_tags);
+
+    record_event(
+        db_conn,
+        context,
+        AuditActionDto::ProcessFile,
+        file_uuid,
+        Some(before),
+        Some(after),
+    )
+    .await
+}
+
+/// Records why the content of a file uploaded at `uploaded_at` could not be processed; does
+/// nothing if the file was uploaded again or removed since.
+///
+/// It runs on the connection of the caller, so that it can be part of a larger transaction.
+pub async fn fail_file_processing(
+    db_conn: &mut AsyncPgConnection,
+    file_uuid: Uuid,
+    uploaded_at: NaiveDateTime,
+    error: &str,
+    context: &AuditContext,
+) -> QueryResult<()> {
+    let current = match lock_pending_upload(db_conn, file_uuid, uploaded_at).await? {
+        Some(current) => current,
+        None => return Ok(()),
+    };
+
+    let raw_item = diesel::update(files::table.find(current.id))
+        .set((
+            files::status.eq(FileStatusDto::Failed),
+            files::failure_reason.eq(error),
+        ))
+        .get_result::<RawFileDto>(db_conn)
+        .await?;
+
+    record_event(
+        db_conn,
+        context,
+        AuditActionDto::ProcessFile,
+        file_uuid,
+        Some(snapshot(&FileDto::from(current))),
+        Some(snapshot(&FileDto::from(raw_item))),
+    )
+    .await
+}
+
+/// Removes a file whose content was never completely uploaded, and that was left alone since
+/// before `idle_since`; does nothing if the upload went on since. Returns whether it was removed.
+///
+/// It runs on the connection of the caller, so that it can be part of a larger transaction.
+pub async fn expire_upload(
+    db_conn: &mut AsyncPgConnection,
+    file_uuid: Uuid,
+    idle_since: NaiveDateTime,
+    context: &AuditContext,
+) -> QueryResult<bool> {
+    let current = files::table
+        .filter(files::uuid.eq(file_uuid))
+        .filter(files::uploaded_at.is_null())
+        .filter(files::status.ne(FileStatusDto::Processing))
+        .filter(files::status.ne(FileStatusDto::Ready))
+        .filter(files::updated_at.lt(idle_since))
+        .for_update()
+        .get_result::<RawFileDto>(db_conn)
+        .await
+        .optional()?;
+    let current = match current {
+        Some(current) => current,
+        None => return Ok(false),
+    };
+
+    diesel::delete(files::table.find(current.id))
+        .execute(db_conn)
+        .await?;
+
+    record_event(
+        db_conn,
+        context,
+        AuditActionDto::ExpireFile,
+        file_uuid,
+        Some(snapshot(&FileDto::from(current))),
+        None,
+    )
+    .await?;
+
+    Ok(true)
+}
+
+/// Marks as failed a file whose new content stopped being uploaded before `idle_since`; does
+/// nothing if the upload went on since. Returns whether it was marked.
+///
+/// It runs on the connection of the caller, so that it can be part of a larger transaction.
+pub async fn interrupt_upload(
+    db_conn: &mut AsyncPgConnection,
+    file_uuid: Uuid,
+    idle_since: NaiveDateTime,
+    context: &AuditContext,
+) -> QueryResult<bool> {
+    let current = files::table
+        .filter(files::uuid.eq(file_uuid))
+        .filter(files::status.eq(FileStatusDto::Uploading))
+        .filter(files::updated_at.lt(idle_since))
+        .for_update()
+        .get_result::<RawFileDto>(db_conn)
+        .await
+        .optional()?;
+
+    match current {
+        Some(current) => fail_file(
+            db_conn,
+            current,
+            AuditActionDto::FailFile,
+            "the upload was interrupted",
+            context,
+        )
+        .await
+        .map(|()| true),
+        None => Ok(false),
+    }
+}
+
+/// Marks as failed a file whose content uploaded at `uploaded_at` is found missing or corrupted
+/// in the storage, which alerts on it; does nothing if the file was uploaded again since.
+/// Returns whether it was marked.
+///
+/// It runs on the connection of the caller, so that it can be part of a larger transaction.
+pub async fn fail_damaged_content(
+    db_conn: &mut AsyncPgConnection,
+    file_uuid: Uuid,
+    uploaded_at: NaiveDateTime,
+    reason: &str,
+    context: &AuditContext,
+) -> QueryResult<bool> {
+    let current = files::table
+        .filter(files::uuid.eq(file_uuid))
+        .filter(files::uploaded_at.eq(uploaded_at))
+        .filter(
+            files::status
+                .eq(FileStatusDto::Processing)
+                .or(files::status.eq(FileStatusDto::Ready)),
+        )
+        .for_update()
+        .get_result::<RawFileDto>(db_conn)
+        .await
+        .optional()?;
+
+    match current {
+        Some(current) => fail_file(
+            db_conn,
+            current,
+            AuditActionDto::CorruptFile,
+            reason,
+            context,
+        )
+        .await
+        .map(|()| true),
+        None => Ok(false),
+    }
+}
+
+/// Marks a locked file as failed for a reason found out of any request on it.
+async fn fail_file(
+    db_conn: &mut AsyncPgConnection,
+    current: RawFileDto,
+    action: AuditActionDto,
+    reason: &str,
+    context: &AuditContext,
+) -> QueryResult<()> {
+    let raw_item = diesel::update(files::table.find(current.id))
+        .set((
+            files::status.eq(FileStatusDto::Failed),
+            files::failure_reason.eq(reason),
+        ))
+        .get_result::<RawFileDto>(db_conn)
+        .await?;
+
+    record_event(
+        db_conn,
+        context,
+        action,
+        raw_item.uuid,
+        Some(snapshot(&FileDto::from(current))),
+        Some(snapshot(&FileDto::from(raw_item))),
+    )
+    .await
+}
+
+/// Locks a file whose content uploaded at `uploaded_at` waits to be processed.
+async fn lock_pending_upload(
+    db_conn: &mut AsyncPgConnection,
+    file_uuid: Uuid,
+    uploaded_at: NaiveDateTime,
+) -> QueryResult<Option<RawFileDto>> {
+    files::table
+        .filter(files::uuid.eq(file_uuid))
+        .filter(files::uploaded_at.eq(uploaded_at))
+        .filter(files::deleted_at.is_null())
+        .filter(files::status.eq(FileStatusDto::Processing))
+        .for_update()
+        .get_result::<RawFileDto>(db_conn)
+        .await
+        .optional()
+}
+
+/// Replaces the system tags of a file with the ones extracted from its current content.
+async fn replace_system_tags(
+    db_conn: &mut AsyncPgConnection,
+    file_id: i32,
+    system_tags: &[CreateFileTagDto],
+) -> QueryResult<()> {
+    diesel::delete(
+        tags::table
+            .filter(tags::file_id.eq(file_id))
+            .filter(tags::title.like(format!("{}%", SYSTEM_TAG_PREFIX))),
+    )
+    .execute(db_conn)
+    .await?;
+
+    insert_tags(db_conn, file_id, system_tags).await
+}
+
+async fn insert_tags(
+    db_conn: &mut AsyncPgConnection,
+    file_id: i32,
+    file_tags: &[CreateFileTagDto],
+) -> QueryResult<()> {
+    if file_tags.is_empty() {
+        return Ok(());
+    }
+
+    let values = file_tags
+        .iter()
+        .map(|tag| {
+            (
+                tags::file_id.eq(file_id),
+                tags::title.eq(&tag.title),
+                tags::value.eq(&tag.value),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    diesel::insert_into(tags::table)
+        .values(values)
+        .execute(db_conn)
+        .await?;
+
+    Ok(())
+}
+
+fn ensure_unique_tag_titles<'a>(
+    titles: impl Iterator<Item = &'a str>,
+) -> Result<(), FileServiceError> {
+    match find_duplicated_tag_title(titles) {
+        Some(title) => Err(FileServiceError::DuplicatedTag(title.to_owned())),
+        None => Ok(()),
+    }
+}
+
+pub fn find_duplicated_tag_title<'a>(titles: impl Iterator<Item = &'a str>) -> Option<&'a str> {
+    let mut titles = titles.collect::<Vec<_>>();
+    titles.sort_unstable();
+
+    (1..titles.len())
+        .find(|&index| titles[index - 1] == titles[index])
+        .map(|index| titles[index])
+}
+
+/// Position of a file in a listing sorted by one of its columns, using `uuid` as a tie-breaker.
+///
+/// Files that are not uploaded yet have no size nor upload time; they come last in ascending
+/// order and first in descending order, as Postgres sorts nulls.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+enum FileKey {
+    Name(String, Uuid),
+    Size(Option<i64>, Uuid),
+    UploadedAt(Option<NaiveDateTime>, Uuid),
+}
+
+impl Keyset for FileKey {
+    type Table = files::table;
+    type Sort = FileSortDto;
+    type Row = RawFileDto;
+
+    fn new(row: &RawFileDto, sort: FileSortDto) -> Self {
+        match sort {
+            FileSortDto::Name => Self::Name(row.name.clone(), row.uuid),
+            FileSortDto::Size => Self::Size(row.size, row.uuid),
+            FileSortDto::UploadedAt => Self::UploadedAt(row.uploaded_at, row.uuid),
+        }
+    }
+
+    fn sort(&self) -> FileSortDto {
+        match self {
+            Self::Name(..) => FileSortDto::Name,
+            Self::Size(..) => FileSortDto::Size,
+            Self::UploadedAt(..) => FileSortDto::UploadedAt,
+        }
+    }
+
+    fn beyond(&self, ascending: bool) -> BoxedPredicate<'_, files::table> {
+        match (self, ascending) {
+            (Self::Name(name, uuid), true) => Box::new(
+                files::name
+                    .gt(name)
+                    .or(files::name.eq(name).and(files::uuid.gt(uuid))),
+            ),
+            (Self::Name(name, uuid), false) => Box::new(
+                files::name
+                    .lt(name)
+                    .or(files::name.eq(name).and(files::uuid.lt(uuid))),
+            ),
+            (Self::Size(Some(size), uuid), true) => Box::new(
+                files::size
+                    .gt(size)
+                    .or(files::size.eq(size).and(files::uuid.gt(uuid)))
+                    .or(files::size.is_null())
+                    .assume_not_null(),
+            ),
+            (Self::Size(Some(size), uuid), false) => Box::new(
+                files::size
+                    .lt(size)
+                    .or(files::size.eq(size).and(files::uuid.lt(uuid)))
+                    .assume_not_null(),
+            ),
+            (Self::Size(None, uuid), true) => {
+                Box::new(files::size.is_null().and(files::uuid.gt(uuid)))
+            }
+            (Self::Size(None, uuid), false) => {
+                Box::new(files::size.is_not_null().or(files::uuid.lt(uuid)))
+            }
+            (Self::UploadedAt(Some(uploaded_at), uuid), true) => Box::new(
+                files::uploaded_at
+                    .gt(uploaded_at)
+                    .or(files::uploaded_at.eq(uploaded_at).and(files::uuid.gt(uuid)))
+                    .or(files::uploaded_at.is_null())
+                    .assume_not_null(),
+            ),
+            (Self::UploadedAt(Some(uploaded_at), uuid), false) => Box::new(
+                files::uploaded_at
+                    .lt(uploaded_at)
+                    .or(files::uploaded_at.eq(uploaded_at).and(files::uuid.lt(uuid)))
+                    .assume_not_null(),
+            ),
+            (Self::UploadedAt(None, uuid), true) => {
+                Box::new(files::uploaded_at.is_null().and(files::uuid.gt(uuid)))
+            }
+            (Self::UploadedAt(None, uuid), false) => {
+                Box::new(files::uploaded_at.is_not_null().or(files::uuid.lt(uuid)))
+            }
+        }
+    }
+
+    fn order(
+        query: IntoBoxed<'_, files::table, Pg>,
+        sort: FileSortDto,
+        ascending: bool,
+    ) -> IntoBoxed<'_, files::table, Pg> {
+        match (sort, ascending) {
+            (FileSortDto::Name, true) => query.order((files::name.asc(), files::uuid.asc())),
+            (FileSortDto::Name, false) => query.order((files::name.desc(), files::uuid.desc())),
+            (FileSortDto::Size, true) => query.order((files::size.asc(), files::uuid.asc())),
+            (FileSortDto::Size, false) => query.order((files::size.desc(), files::uuid.desc())),
+            (FileSortDto::UploadedAt, true) => {
+                query.order((files::uploaded_at.asc(), files::uuid.asc()))
+            }
+            (FileSortDto::UploadedAt, false) => {
+                query.order((files::uploaded_at.desc(), files::uuid.desc()))
+            }
+        }
+    }
+}
+
+/// Position of a file in the trash, which is ordered by deletion time.
+#[derive(Serialize, Deserialize, Debug)]
+struct TrashedFileKey(NaiveDateTime, Uuid);
+
+impl Keyset for TrashedFileKey {
+    type Table = files::table;
+    type Sort = ();
+    type Row = RawFileDto;
+
+    fn new(row: &RawFileDto, _sort: ()) -> Self {
+        Self(row.deleted_at.unwrap_or_default(), row.uuid)
+    }
+
+    fn sort(&self) {}
+
+    fn beyond(&self, ascending: bool) -> BoxedPredicate<'_, files::table> {
+        let Self(deleted_at, uuid) = self;
+        let deleted_at_column = files::deleted_at.assume_not_null();
+
+        if ascending {
+            Box::new(
+                deleted_at_column
+                    .gt(deleted_at)
+                    .or(deleted_at_column.eq(deleted_at).and(files::uuid.gt(uuid))),
+            )
+        } else {
+            Box::new(
+                deleted_at_column
+                    .lt(deleted_at)
+                    .or(deleted_at_column.eq(deleted_at).and(files::uuid.lt(uuid))),
+            )
+        }
+    }
+
+    fn order(
+        query: IntoBoxed<'_, files::table, Pg>,
+        _sort: (),
+        ascending: bool,
+    ) -> IntoBoxed<'_, files::table, Pg> {
+        if ascending {
+            query.order((files::deleted_at.asc(), files::uuid.asc()))
+        } else {
+            query.order((files::deleted_at.desc(), files::uuid.desc()))
+        }
+    }
+}
+
+/// The editable metadata of a file, as patched by `PATCH /files/{identifier}`.
+#[derive(Serialize, Deserialize, Debug)]
+struct FileMetadata {
+    name: String,
+}
+
+#[derive(Queryable, Clone, Debug)]
+struct RawFileDto {
+    id: i32,
+    uuid: Uuid,
+    name: String,
+    mime: Option<String>,
+    size: Option<i64>,
+    hash: Option<i64>,
+    created_at: NaiveDateTime,
+    uploaded_at: Option<NaiveDateTime>,
+    deleted_at: Option<NaiveDateTime>,
+    status: FileStatusDto,
+    failure_reason: Option<String>,
+    updated_at: NaiveDateTime,
+    owner: Option<String>,
+    kept_bytes: i64,
+}
+
+impl From<RawFileDto> for FileDto {
+    fn from(item: RawFileDto) -> Self {
+        Self {
+            uuid: item.uuid,
+            name: item.name,
+            mime: item.mime,
+            size: item.size,
+            hash: item.hash,
+            created_at: item.created_at.and_utc(),
+            uploaded_at: item.uploaded_at.map(|uploaded_at| uploaded_at.and_utc()),
+            deleted_at: item.deleted_at.map(|deleted_at| deleted_at.and_utc()),
+            status: item.status,
+            failure_reason: item.failure_reason,
+            updated_at: item.updated_at.and_utc(),
+            owner: item.owner,
+            kept_bytes: item.kept_bytes,
+        }
+    }
+}
@@ -0,0 +1,451 @@
+use crate::app_state::AppState;
+use axum::{
+    routing::{delete, get, patch, post, put},
+    Router,
+};
+
+pub mod file_service;
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/files", get(handlers::find_files))
+        .route("/files/trash", get(handlers::find_trashed_files))
+        .route("/files/system-tags", get(handlers::find_system_tags))
+        .route("/files", post(handlers::create_file))
+        .route("/files/:identifier", put(handlers::upload_file))
+        .route("/files/:identifier", patch(handlers::patch_file))
+        .route("/files/:identifier", delete(handlers::remove_file))
+        .route("/files/:identifier/restore", post(handlers::restore_file))
+        .route(
+            "/files/:identifier/versions",
+            get(handlers::find_file_versions),
+        )
+        .route(
+            "/files/:identifier/versions/prune",
+            post(handlers::prune_file_versions),
+        )
+        .route(
+            "/files/:identifier/versions/:version/content",
+            get(handlers::download_file_version),
+        )
+        .route(
+            "/files/:identifier/versions/:version/restore",
+            post(handlers::restore_file_version),
+        )
+}
+
+pub mod handlers {
+    use super::file_service::{FileService, FileServiceError};
+    use crate::{
+        app_state::AppState,
+        audit::AuditContext,
+        file_driver::SYSTEM_TAG_TEMPLATES,
+        schema::{
+            dto_in::{
+                CreateFileBodyDto, DownloadFileVersionPathDto, FindFileVersionsPathDto,
+                FindFilesBodyDto, FindFilesQueryDto, FindTrashQueryDto, PatchFilePathDto,
+                PruneFileVersionsBodyDto, PruneFileVersionsPathDto, RemoveFilePathDto,
+                RestoreFilePathDto, RestoreFileVersionPathDto, UploadFilePathDto,
+                UploadFileQueryDto,
+            },
+            dto_out::{FileDto, FindFilesResultDto, SystemTagDto},
+        },
+    };
+    use axum::{
+        body::Body,
+        debug_handler,
+        extract::{Path, Query, State},
+        http::{
+            header::{CONTENT_LENGTH, CONTENT_TYPE},
+            HeaderMap, StatusCode,
+        },
+        response::{IntoResponse, Response},
+        Json,
+    };
+    use tokio_util::io::ReaderStream;
+
+    /// Finds files with optional filters.
+    ///
+    /// A text query is resolved into at most the 10,000 most relevant files, which the other
+    /// filters and the pagination then apply to; matches beyond are left out of every page, and
+    /// `truncated` reports it. Refine the query to reach them.
+    #[utoipa::path(
+        get,
+        operation_id = "find-files",
+        tag = "file",
+        path = "/files",
+        params(
+            FindFilesQueryDto
+        ),
+        request_body = FindFilesBodyDto,
+        responses(
+            (status = OK, body = FindFilesResultDto),
+            (status = UNPROCESSABLE_ENTITY, description = "the request is invalid", body = ErrorBody, example = json!({
+                "error": "tag `Author` is duplicated"
+            })),
+            (status = INTERNAL_SERVER_ERROR, description = "an error has occurred", body = ErrorBody),
+        ),
+    )]
+    #[debug_handler(state = AppState)]
+    pub async fn find_files(
+        State(file_service): State<FileService>,
+        Query(query): Query<FindFilesQueryDto>,
+        Json(body): Json<FindFilesBodyDto>,
+    ) -> Result<(StatusCode, Json<FindFilesResultDto>), FileServiceError> {
+        let result = file_service.find_files(query, body).await?;
+
+        Ok((StatusCode::OK, Json(result)))
+    }
+
+    /// Create a new file to be uploaded.
+    #[utoipa::path(
+        post,
+        operation_id = "create-file",
+        tag = "file",
+        path = "/files",
+        request_body = CreateFileBodyDto,
+        responses(
+            (status = CREATED, body = FileDto),
+            (status = UNPROCESSABLE_ENTITY, description = "the request is invalid", body = ErrorBody),
+            (status = INTERNAL_SERVER_ERROR, description = "an error has occurred", body = ErrorBody),
+            (status = INSUFFICIENT_STORAGE, description = "the storage quota of the actor allows no more files", body = ErrorBody),
+        ),
+    )]
+    #[debug_handler(state = AppState)]
+    pub async fn create_file(
+        State(file_service): State<FileService>,
+        context: AuditContext,
+        Json(body): Json<CreateFileBodyDto>,
+    ) -> Result<(StatusCode, Json<FileDto>), FileServiceError> {
+        let result = file_service.create_file(body, &context).await?;
+
+        Ok((StatusCode::CREATED, Json(result)))
+    }
+
+    /// Upload the content of a file; it is processed in the background, as reported by the
+    /// `status` of the file.
+    ///
+    /// The content may not grow beyond the storage quotas of the owner of the file and of the
+    /// collections it is in. An upload whose `Content-Length` goes beyond is refused up front;
+    /// any other is stopped once it does, and what was written counts toward the quotas.
+    ///
+    /// An upload from the start keeps the previous content as a version and begins a new one; an
+    /// upload resumed at a later `offset` extends the content of the current version.
+    #[utoipa::path(
+        put,
+        operation_id = "upload-file",
+        tag = "file",
+        path = "/files/{identifier}",
+        params(
+            UploadFilePathDto,
+            UploadFileQueryDto,
+        ),
+        request_body(content = Vec<u8>, content_type = "application/octet-stream"),
+        responses(
+            (status = ACCEPTED, description = "the content was stored, and is queued for processing", body = FileDto),
+            (status = NOT_FOUND, description = "the file does not exist"),
+            (status = PAYLOAD_TOO_LARGE, description = "the content would exceed a storage quota", body = ErrorBody),
+            (status = CONFLICT, description = "the file is already being uploaded, or was trashed or abandoned during the upload", body = ErrorBody),
+            (status = UNPROCESSABLE_ENTITY, description = "the offset is invalid", body = ErrorBody),
+            (status = INTERNAL_SERVER_ERROR, description = "an error has occurred", body = ErrorBody),
+            (status = INSUFFICIENT_STORAGE, description = "the content exceeded a storage quota", body = ErrorBody),
+        ),
+    )]
+    #[debug_handler(state = AppState)]
+    pub async fn upload_file(
+        State(file_service): State<FileService>,
+        context: AuditContext,
+        Path(path): Path<UploadFilePathDto>,
+        Query(query): Query<UploadFileQueryDto>,
+        headers: HeaderMap,
+        body: Body,
+    ) -> Result<Response, FileServiceError> {
+        let content_length = headers
+            .get(CONTENT_LENGTH)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<u64>().ok());
+
+        match file_service
+            .upload_file(
+                path,
+                query,
+                content_length,
+                body.into_data_stream(),
+                &context,
+            )
+            .await?
+        {
+            Some(result) => Ok((StatusCode::ACCEPTED, Json(result)).into_response()),
+            None => Ok(StatusCode::NOT_FOUND.into_response()),
+        }
+    }
+
+    /// Update the metadata of a file with a JSON Merge Patch.
+    #[utoipa::path(
+        patch,
+        operation_id = "patch-file",
+        tag = "file",
+        path = "/files/{identifier}",
+        params(
+            PatchFilePathDto
+        ),
+        request_body(content = PatchFileBodyDto, content_type = "application/merge-patch+json"),
+        responses(
+            (status = OK, body = FileDto),
+            (status = NOT_FOUND, description = "the file does not exist"),
+            (status = UNPROCESSABLE_ENTITY, description = "the patched file is invalid", body = ErrorBody),
+            (status = INTERNAL_SERVER_ERROR, description = "an error has occurred", body = ErrorBody),
+        ),
+    )]
+    #[debug_handler(state = AppState)]
+    pub async fn patch_file(
+        State(file_service): State<FileService>,
+        context: AuditContext,
+        Path(path): Path<PatchFilePathDto>,
+        Json(patch): Json<serde_json::Value>,
+    ) -> Result<Response, FileServiceError> {
+        match file_service.patch_file(path, patch, &context).await? {
+            Some(result) => Ok((StatusCode::OK, Json(result)).into_response()),
+            None => Ok(StatusCode::NOT_FOUND.into_response()),
+        }
+    }
+
+    /// Move a file to the trash.
+    #[utoipa::path(
+        delete,
+        operation_id = "remove-file",
+        tag = "file",
+        path = "/files/{identifier}",
+        params(
+            RemoveFilePathDto
+        ),
+        responses(
+            (status = OK, body = FileDto),
+            (status = NOT_FOUND, description = "the file does not exist"),
+            (status = INTERNAL_SERVER_ERROR, description = "an error has occurred", body = ErrorBody),
+        ),
+    )]
+    #[debug_handler(state = AppState)]
+    pub async fn remove_file(
+        State(file_service): State<FileService>,
+        context: AuditContext,
+        Path(path): Path<RemoveFilePathDto>,
+    ) -> Result<Response, FileServiceError> {
+        match file_service.remove_file(path, &context).await? {
+            Some(result) => Ok((StatusCode::OK, Json(result)).into_response()),
+            None => Ok(StatusCode::NOT_FOUND.into_response()),
+        }
+    }
+
+    /// Finds the reserved system tags, set from the metadata of uploaded files.
+    #[utoipa::path(
+        get,
+        operation_id = "find-system-tags",
+        tag = "file",
+        path = "/files/system-tags",
+        responses(
+            (status = OK, body = Vec<SystemTagDto>),
+        ),
+    )]
+    #[debug_handler(state = AppState)]
+    pub async fn find_system_tags() -> (StatusCode, Json<Vec<SystemTagDto>>) {
+        let result = SYSTEM_TAG_TEMPLATES
+            .iter()
+            .map(|template| SystemTagDto {
+                title: template.title.to_owned(),
+                description: template.description.to_owned(),
+            })
+            .collect();
+
+        (StatusCode::OK, Json(result))
+    }
+
+    /// Finds files in the trash, most recently deleted first by default.
+    #[utoipa::path(
+        get,
+        operation_id = "find-trashed-files",
+        tag = "file",
+        path = "/files/trash",
+        params(
+            FindTrashQueryDto
+        ),
+        responses(
+            (status = OK, body = FindFilesResultDto),
+            (status = UNPROCESSABLE_ENTITY, description = "the pagination parameters are invalid", body = ErrorBody),
+            (status = INTERNAL_SERVER_ERROR, description = "an error has occurred", body = ErrorBody),
+        ),
+    )]
+    #[debug_handler(state = AppState)]
+    pub async fn find_trashed_files(
+        State(file_service): State<FileService>,
+        Query(query): Query<FindTrashQueryDto>,
+    ) -> Result<(StatusCode, Json<FindFilesResultDto>), FileServiceError> {
+        let result = file_service.find_trashed_files(query).await?;
+
+        Ok((StatusCode::OK, Json(result)))
+    }
+
+    /// Restore a file from the trash.
+    #[utoipa::path(
+        post,
+        operation_id = "restore-file",
+        tag = "file",
+        path = "/files/{identifier}/restore",
+        params(
+            RestoreFilePathDto
+        ),
+        responses(
+            (status = OK, body = FileDto),
+            (status = NOT_FOUND, description = "the file does not exist"),
+            (status = INTERNAL_SERVER_ERROR, description = "an error has occurred", body = ErrorBody),
+        ),
+    )]
+    #[debug_handler(state = AppState)]
+    pub async fn restore_file(
+        State(file_service): State<FileService>,
+        context: AuditContext,
+        Path(path): Path<RestoreFilePathDto>,
+    ) -> Result<Response, FileServiceError> {
+        match file_service.restore_file(path, &context).await? {
+            Some(result) => Ok((StatusCode::OK, Json(result)).into_response()),
+            None => Ok(StatusCode::NOT_FOUND.into_response()),
+        }
+    }
+
+    /// Finds the versions of a file, most recent first; each upload from the start or restore adds
+    /// one.
+    #[utoipa::path(
+        get,
+        operation_id = "find-file-versions",
+        tag = "file",
+        path = "/files/{identifier}/versions",
+        params(
+            FindFileVersionsPathDto
+        ),
+        responses(
+            (status = OK, body = Vec<FileVersionDto>),
+            (status = NOT_FOUND, description = "the file does not exist"),
+            (status = INTERNAL_SERVER_ERROR, description = "an error has occurred", body = ErrorBody),
+        ),
+    )]
+    #[debug_handler(state = AppState)]
+    pub async fn find_file_versions(
+        State(file_service): State<FileService>,
+        Path(path): Path<FindFileVersionsPathDto>,
+    ) -> Result<Response, FileServiceError> {
+        match file_service.find_file_versions(path).await? {
+            Some(result) => Ok((StatusCode::OK, Json(result)).into_response()),
+            None => Ok(StatusCode::NOT_FOUND.into_response()),
+        }
+    }
+
+    /// Download the content of a version of a file.
+    #[utoipa::path(
+        get,
+        operation_id = "download-file-version",
+        tag = "file",
+        path = "/files/{identifier}/versions/{version}/content",
+        params(
+            DownloadFileVersionPathDto
+        ),
+        responses(
+            (status = OK, description = "the content of the version", body = Vec<u8>, content_type = "application/octet-stream"),
+            (status = NOT_FOUND, description = "the file or the version does not exist"),
+            (status = INTERNAL_SERVER_ERROR, description = "an error has occurred", body = ErrorBody),
+        ),
+    )]
+    #[debug_handler(state = AppState)]
+    pub async fn download_file_version(
+        State(file_service): State<FileService>,
+        Path(path): Path<DownloadFileVersionPathDto>,
+    ) -> Result<Response, FileServiceError> {
+        let content = match file_service.download_file_version(path).await? {
+            Some(content) => content,
+            None => return Ok(StatusCode::NOT_FOUND.into_response()),
+        };
+
+        Ok((
+            StatusCode::OK,
+            [
+                (
+                    CONTENT_TYPE,
+                    content
+                        .mime
+                        .unwrap_or_else(|| "application/octet-stream".to_owned()),
+                ),
+                (CONTENT_LENGTH, content.size.to_string()),
+            ],
+            Body::from_stream(ReaderStream::new(content.file)),
+        )
+            .into_response())
+    }
+
+    /// Restore a file to the content of one of its versions.
+    ///
+    /// The content is restored as a new version, so the history is kept; it is processed again in
+    /// the background, as reported by the `status` of the file.
+    #[utoipa::path(
+        post,
+        operation_id = "restore-file-version",
+        tag = "file",
+        path = "/files/{identifier}/versions/{version}/restore",
+        params(
+            RestoreFileVersionPathDto
+        ),
+        responses(
+            (status = ACCEPTED, description = "the content was restored, and is queued for processing", body = FileDto),
+            (status = NOT_FOUND, description = "the file or the version does not exist"),
+            (status = CONFLICT, description = "the file is being uploaded", body = ErrorBody),
+            (status = PAYLOAD_TOO_LARGE, description = "the content would exceed a storage quota", body = ErrorBody),
+            (status = INTERNAL_SERVER_ERROR, description = "an error has occurred", body = ErrorBody),
+        ),
+    )]
+    #[debug_handler(state = AppState)]
+    pub async fn restore_file_version(
+        State(file_service): State<FileService>,
+        context: AuditContext,
+        Path(path): Path<RestoreFileVersionPathDto>,
+    ) -> Result<Response, FileServiceError> {
+        match file_service.restore_file_version(path, &context).await? {
+            Some(result) => Ok((StatusCode::ACCEPTED, Json(result)).into_response()),
+            None => Ok(StatusCode::NOT_FOUND.into_response()),
+        }
+    }
+
+    /// Remove the versions of a file a retention does not keep, and return them.
+    ///
+    /// The settings absent from the request keep the server-wide ones, set by `VERSION_KEEP_LAST`
+    /// and `VERSION_KEEP_DAYS`; nothing is removed if neither is set. The current version is
+    /// always kept.
+    #[utoipa::path(
+        post,
+        operation_id = "prune-file-versions",
+        tag = "file",
+        path = "/files/{identifier}/versions/prune",
+        params(
+            PruneFileVersionsPathDto
+        ),
+        request_body = PruneFileVersionsBodyDto,
+        responses(
+            (status = OK, body = Vec<FileVersionDto>),
+            (status = NOT_FOUND, description = "the file does not exist"),
+            (status = UNPROCESSABLE_ENTITY, description = "the retention is invalid", body = ErrorBody),
+            (status = INTERNAL_SERVER_ERROR, description = "an error has occurred", body = ErrorBody),
+        ),
+    )]
+    #[debug_handler(state = AppState)]
+    pub async fn prune_file_versions(
+        State(file_service): State<FileService>,
+        context: AuditContext,
+        Path(path): Path<PruneFileVersionsPathDto>,
+        Json(body): Json<PruneFileVersionsBodyDto>,
+    ) -> Result<Response, FileServiceError> {
+        match file_service
+            .prune_file_versions(path, body, &context)
+            .await?
+        {
+            Some(result) => Ok((StatusCode::OK, Json(result)).into_response()),
+            None => Ok(StatusCode::NOT_FOUND.into_response()),
+        }
+    }
+}
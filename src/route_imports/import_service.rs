@@ -0,0 +1,268 @@
+use crate::{
+    audit::{enum_name, parse_enum_name, AuditContext},
+    db::{schema::import_jobs, DBPool},
+    import::{ImportError, ImportRunner, PathRule, PathRuleError},
+    schema::{
+        dto_in::{CancelImportPathDto, CreateImportBodyDto, FindImportPathDto},
+        dto_out::{ImportJobDto, ImportStatusDto},
+    },
+};
+use axum::http::StatusCode;
+use chrono::NaiveDateTime;
+use codegen::ErrorEnum;
+use diesel::{dsl::now, prelude::*};
+use diesel_async::RunQueryDsl;
+use std::path::{Component, Path, PathBuf};
+use thiserror::Error;
+use uuid::Uuid;
+
+#[derive(ErrorEnum, Error, Debug)]
+pub enum ImportServiceError {
+    #[error("internal server error")]
+    #[status(StatusCode::INTERNAL_SERVER_ERROR)]
+    PoolError(#[from] diesel_async::pooled_connection::deadpool::PoolError),
+    #[error("internal server error")]
+    #[status(StatusCode::INTERNAL_SERVER_ERROR)]
+    DieselError(#[from] diesel::result::Error),
+    #[error("internal server error")]
+    #[status(StatusCode::INTERNAL_SERVER_ERROR)]
+    ImportError(#[from] ImportError),
+    #[error("imports through the API are disabled; set `IMPORT_ROOT` to enable them")]
+    #[status(StatusCode::FORBIDDEN)]
+    ImportsDisabled,
+    #[error("`{0}` is not a directory inside `IMPORT_ROOT`")]
+    #[status(StatusCode::FORBIDDEN)]
+    RootOutsideImportRoot(String),
+    #[error("{0}")]
+    #[status(StatusCode::UNPROCESSABLE_ENTITY)]
+    InvalidRule(#[from] PathRuleError),
+    #[error("import is already finished")]
+    #[status(StatusCode::CONFLICT)]
+    AlreadyFinished,
+}
+
+#[derive(Clone)]
+pub struct ImportService {
+    db_pool: DBPool,
+    import_runner: ImportRunner,
+    /// Directory that imports through the API are confined to; they are disabled without it.
+    import_root: Option<PathBuf>,
+}
+
+impl ImportService {
+    pub fn new(db_pool: DBPool, import_runner: ImportRunner) -> Self {
+        Self {
+            db_pool,
+            import_runner,
+            import_root: std::env::var_os("IMPORT_ROOT").map(PathBuf::from),
+        }
+    }
+
+    pub async fn find_imports(&self) -> Result<Vec<ImportJobDto>, ImportServiceError> {
+        let db_conn = &mut self.db_pool.get().await?;
+        let jobs = import_jobs::table
+            .select(import_job_dto_columns())
+            .order((import_jobs::created_at.desc(), import_jobs::uuid.desc()))
+            .load::<RawImportJobDto>(db_conn)
+            .await?;
+
+        Ok(jobs.into_iter().map(|job| job.into()).collect())
+    }
+
+    pub async fn find_import(
+        &self,
+        path: FindImportPathDto,
+    ) -> Result<Option<ImportJobDto>, ImportServiceError> {
+        let db_conn = &mut self.db_pool.get().await?;
+        let job = import_jobs::table
+            .select(import_job_dto_columns())
+            .filter(import_jobs::uuid.eq(path.identifier))
+            .first::<RawImportJobDto>(db_conn)
+            .await
+            .optional()?;
+
+        Ok(job.map(|job| job.into()))
+    }
+
+    /// Queues an import, run by the next available server.
+    pub async fn create_import(
+        &self,
+        body: CreateImportBodyDto,
+        context: &AuditContext,
+    ) -> Result<ImportJobDto, ImportServiceError> {
+        let configured_root = self
+            .import_root
+            .as_ref()
+            .ok_or(ImportServiceError::ImportsDisabled)?;
+        let import_root = tokio::fs::canonicalize(configured_root)
+            .await
+            .map_err(|_| ImportServiceError::ImportsDisabled)?;
+
+        // The root is checked against the import root before the filesystem is looked at, so a
+        // path outside of it reveals nothing about what exists there; a path inside that is not
+        // an existing directory is answered alike.
+        let requested_root = Path::new(&body.root);
+        if !root_is_inside(configured_root, requested_root)
+            && !root_is_inside(&import_root, requested_root)
+        {
+            return Err(ImportServiceError::RootOutsideImportRoot(body.root));
+        }
+
+        let root = match tokio::fs::canonicalize(&body.root).await {
+            Ok(root) => root,
+            Err(_) => return Err(ImportServiceError::RootOutsideImportRoot(body.root)),
+        };
+        let is_directory = tokio::fs::metadata(&root)
+            .await
+            .is_ok_and(|metadata| metadata.is_dir());
+        let root = match root.to_str() {
+            // Symbolic links inside the import root may still lead outside of it.
+            Some(root) if is_directory && root_is_inside(&import_root, Path::new(root)) => {
+                root.to_owned()
+            }
+            _ => return Err(ImportServiceError::RootOutsideImportRoot(body.root)),
+        };
+
+        for rule in &body.rules {
+            PathRule::parse(rule)?;
+        }
+
+        let job = self
+            .import_runner
+            .create_job(&root, &body.rules, body.mode, context, false)
+            .await?;
+
+        Ok(self
+            .find_import(FindImportPathDto {
+                identifier: job.uuid,
+            })
+            .await?
+            .expect("created import must exist"))
+    }
+
+    /// Cancels an import; the files it imported so far are kept.
+    pub async fn cancel_import(
+        &self,
+        path: CancelImportPathDto,
+    ) -> Result<Option<ImportJobDto>, ImportServiceError> {
+        let db_conn = &mut self.db_pool.get().await?;
+        let job = diesel::update(import_jobs::table)
+            .filter(import_jobs::uuid.eq(path.identifier))
+            .filter(import_jobs::status.eq_any([
+                enum_name(&ImportStatusDto::Pending),
+                enum_name(&ImportStatusDto::Running),
+            ]))
+            .set((
+                import_jobs::status.eq(enum_name(&ImportStatusDto::Cancelled)),
+                import_jobs::finished_at.eq(now.nullable()),
+            ))
+            .returning(import_job_dto_columns())
+            .get_result::<RawImportJobDto>(db_conn)
+            .await
+            .optional()?;
+
+        match job {
+            Some(job) => Ok(Some(job.into())),
+            None => {
+                let exists = import_jobs::table
+                    .select(import_jobs::id)
+                    .filter(import_jobs::uuid.eq(path.identifier))
+                    .first::<i32>(db_conn)
+                    .await
+                    .optional()?
+                    .is_some();
+
+                if exists {
+                    Err(ImportServiceError::AlreadyFinished)
+                } else {
+                    Ok(None)
+                }
+            }
+        }
+    }
+}
+
+/// Returns whether `root` is an absolute path inside `import_root`, without parent directory
+/// components that could lead out of it.
+fn root_is_inside(import_root: &Path, root: &Path) -> bool {
+    root.is_absolute()
+        && !root
+            .components()
+            .any(|component| component == Component::ParentDir)
+        && root.starts_with(import_root)
+}
+
+type ImportJobDtoColumns = (
+    import_jobs::uuid,
+    import_jobs::root,
+    import_jobs::rules,
+    import_jobs::mode,
+    import_jobs::status,
+    import_jobs::total_count,
+    import_jobs::processed_count,
+    import_jobs::imported_count,
+    import_jobs::failed_count,
+    import_jobs::last_path,
+    import_jobs::last_error,
+    import_jobs::created_at,
+    import_jobs::updated_at,
+    import_jobs::finished_at,
+);
+
+fn import_job_dto_columns() -> ImportJobDtoColumns {
+    (
+        import_jobs::uuid,
+        import_jobs::root,
+        import_jobs::rules,
+        import_jobs::mode,
+        import_jobs::status,
+        import_jobs::total_count,
+        import_jobs::processed_count,
+        import_jobs::imported_count,
+        import_jobs::failed_count,
+        import_jobs::last_path,
+        import_jobs::last_error,
+        import_jobs::created_at,
+        import_jobs::updated_at,
+        import_jobs::finished_at,
+    )
+}
+
+#[derive(Queryable, Debug)]
+struct RawImportJobDto {
+    uuid: Uuid,
+    root: String,
+    rules: Vec<String>,
+    mode: String,
+    status: String,
+    total_count: Option<i64>,
+    processed_count: i64,
+    imported_count: i64,
+    failed_count: i64,
+    last_path: Option<String>,
+    last_error: Option<String>,
+    created_at: NaiveDateTime,
+    updated_at: NaiveDateTime,
+    finished_at: Option<NaiveDateTime>,
+}
+
+impl From<RawImportJobDto> for ImportJobDto {
+    fn from(item: RawImportJobDto) -> Self {
+        Self {
+            uuid: item.uuid,
+            root: item.root,
+            rules: item.rules,
+            mode: parse_enum_name(item.mode),
+            status: parse_enum_name(item.status),
+            total_count: item.total_count,
+            processed_count: item.processed_count,
+            imported_count: item.imported_count,
+            failed_count: item.failed_count,
+            last_path: item.last_path,
+            last_error: item.last_error,
+            created_at: item.created_at.and_utc(),
+            updated_at: item.updated_at.and_utc(),
+            finished_at: item.finished_at.map(|finished_at| finished_at.and_utc()),
+        }
+    }
+}
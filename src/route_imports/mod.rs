@@ -0,0 +1,132 @@
+use crate::app_state::AppState;
+use axum::{
+    routing::{get, post},
+    Router,
+};
+
+pub mod import_service;
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/imports", get(handlers::find_imports))
+        .route("/imports/:identifier", get(handlers::find_import))
+        .route("/imports", post(handlers::create_import))
+        .route("/imports/:identifier/cancel", post(handlers::cancel_import))
+}
+
+pub mod handlers {
+    use super::import_service::{ImportService, ImportServiceError};
+    use crate::{
+        app_state::AppState,
+        audit::AuditContext,
+        schema::{
+            dto_in::{CancelImportPathDto, CreateImportBodyDto, FindImportPathDto},
+            dto_out::ImportJobDto,
+        },
+    };
+    use axum::{
+        debug_handler,
+        extract::{Path, State},
+        http::StatusCode,
+        response::{IntoResponse, Response},
+        Json,
+    };
+
+    /// Finds all imports, most recent first.
+    #[utoipa::path(
+        get,
+        operation_id = "find-imports",
+        tag = "import",
+        path = "/imports",
+        responses(
+            (status = OK, body = Vec<ImportJobDto>),
+            (status = INTERNAL_SERVER_ERROR, description = "an error has occurred", body = ErrorBody),
+        ),
+    )]
+    #[debug_handler(state = AppState)]
+    pub async fn find_imports(
+        State(import_service): State<ImportService>,
+    ) -> Result<(StatusCode, Json<Vec<ImportJobDto>>), ImportServiceError> {
+        let result = import_service.find_imports().await?;
+
+        Ok((StatusCode::OK, Json(result)))
+    }
+
+    /// Find an import, along with its progress.
+    #[utoipa::path(
+        get,
+        operation_id = "find-import",
+        tag = "import",
+        path = "/imports/{identifier}",
+        params(
+            FindImportPathDto
+        ),
+        responses(
+            (status = OK, body = ImportJobDto),
+            (status = NOT_FOUND, description = "the import does not exist"),
+            (status = INTERNAL_SERVER_ERROR, description = "an error has occurred", body = ErrorBody),
+        ),
+    )]
+    #[debug_handler(state = AppState)]
+    pub async fn find_import(
+        State(import_service): State<ImportService>,
+        Path(path): Path<FindImportPathDto>,
+    ) -> Result<Response, ImportServiceError> {
+        match import_service.find_import(path).await? {
+            Some(result) => Ok((StatusCode::OK, Json(result)).into_response()),
+            None => Ok(StatusCode::NOT_FOUND.into_response()),
+        }
+    }
+
+    /// Queue an import of a server-side directory inside `IMPORT_ROOT`.
+    #[utoipa::path(
+        post,
+        operation_id = "create-import",
+        tag = "import",
+        path = "/imports",
+        request_body = CreateImportBodyDto,
+        responses(
+            (status = ACCEPTED, body = ImportJobDto),
+            (status = FORBIDDEN, description = "imports are disabled or the directory is not an existing directory inside `IMPORT_ROOT`", body = ErrorBody),
+            (status = UNPROCESSABLE_ENTITY, description = "a rule is invalid", body = ErrorBody),
+            (status = INTERNAL_SERVER_ERROR, description = "an error has occurred", body = ErrorBody),
+        ),
+    )]
+    #[debug_handler(state = AppState)]
+    pub async fn create_import(
+        State(import_service): State<ImportService>,
+        context: AuditContext,
+        Json(body): Json<CreateImportBodyDto>,
+    ) -> Result<(StatusCode, Json<ImportJobDto>), ImportServiceError> {
+        let result = import_service.create_import(body, &context).await?;
+
+        Ok((StatusCode::ACCEPTED, Json(result)))
+    }
+
+    /// Cancel a pending or running import; files imported so far are kept.
+    #[utoipa::path(
+        post,
+        operation_id = "cancel-import",
+        tag = "import",
+        path = "/imports/{identifier}/cancel",
+        params(
+            CancelImportPathDto
+        ),
+        responses(
+            (status = OK, body = ImportJobDto),
+            (status = NOT_FOUND, description = "the import does not exist"),
+            (status = CONFLICT, description = "the import is already finished", body = ErrorBody),
+            (status = INTERNAL_SERVER_ERROR, description = "an error has occurred", body = ErrorBody),
+        ),
+    )]
+    #[debug_handler(state = AppState)]
+    pub async fn cancel_import(
+        State(import_service): State<ImportService>,
+        Path(path): Path<CancelImportPathDto>,
+    ) -> Result<Response, ImportServiceError> {
+        match import_service.cancel_import(path).await? {
+            Some(result) => Ok((StatusCode::OK, Json(result)).into_response()),
+            None => Ok(StatusCode::NOT_FOUND.into_response()),
+        }
+    }
+}
@@ -0,0 +1,312 @@
+use crate::{
+    audit::{enum_name, parse_enum_name},
+    db::{schema::jobs, DBPool},
+    pagination::{BoxedPredicate, CursorCodec, CursorError, Keyset, PageRequest, PaginationError},
+    schema::{
+        dto_in::{FindJobPathDto, FindJobsQueryDto, JobStatusDto, RetryJobPathDto},
+        dto_out::{CursorPaginationMetadataDto, FindJobsResultDto, JobDto},
+    },
+};
+use axum::http::StatusCode;
+use chrono::NaiveDateTime;
+use codegen::ErrorEnum;
+use diesel::{
+    dsl::{exists, now, IntoBoxed},
+    pg::Pg,
+    prelude::*,
+};
+use diesel_async::{scoped_futures::ScopedFutureExt, AsyncConnection, RunQueryDsl};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use uuid::Uuid;
+
+#[derive(ErrorEnum, Error, Debug)]
+pub enum JobServiceError {
+    #[error("internal server error")]
+    #[status(StatusCode::INTERNAL_SERVER_ERROR)]
+    PoolError(#[from] diesel_async::pooled_connection::deadpool::PoolError),
+    #[error("internal server error")]
+    #[status(StatusCode::INTERNAL_SERVER_ERROR)]
+    DieselError(#[from] diesel::result::Error),
+    #[error("{0}")]
+    #[status("0")]
+    PaginationError(#[from] PaginationError),
+    #[error("{0}")]
+    #[status("0")]
+    CursorError(#[from] CursorError),
+    #[error("only dead jobs can be retried; job is `{0}`")]
+    #[status(StatusCode::CONFLICT)]
+    NotDead(String),
+    #[error("a pending job already does the same work")]
+    #[status(StatusCode::CONFLICT)]
+    AlreadyPending,
+}
+
+#[derive(Clone)]
+pub struct JobService {
+    db_pool: DBPool,
+    cursor_codec: CursorCodec,
+}
+
+impl JobService {
+    pub fn new(db_pool: DBPool, cursor_codec: CursorCodec) -> Self {
+        Self {
+            db_pool,
+            cursor_codec,
+        }
+    }
+
+    pub async fn find_jobs(
+        &self,
+        query: FindJobsQueryDto,
+    ) -> Result<FindJobsResultDto, JobServiceError> {
+        let first = query
+            .first_cursor
+            .as_deref()
+            .map(|cursor| self.cursor_codec.decode::<JobKey>(cursor))
+            .transpose()?;
+        let last = query
+            .last_cursor
+            .as_deref()
+            .map(|cursor| self.cursor_codec.decode::<JobKey>(cursor))
+            .transpose()?;
+        let request = PageRequest::new(first, last, (), query.order, query.page_size)?;
+
+        let status = query.status.map(|status| enum_name(&status));
+        let job_type = query.job_type.map(|job_type| enum_name(&job_type));
+
+        let db_conn = &mut self.db_pool.get().await?;
+        let matching = || {
+            let mut select = jobs::table.into_boxed();
+
+            if let Some(status) = &status {
+                select = select.filter(jobs::status.eq(status));
+            }
+
+            if let Some(job_type) = &job_type {
+                select = select.filter(jobs::job_type.eq(job_type));
+            }
+
+            select
+        };
+        let rows = request
+            .select(&matching, job_columns())
+            .load::<RawJobDto>(db_conn)
+            .await?;
+        let has_behind = match request.behind(&matching) {
+            Some(behind) => behind.get_result(db_conn).await?,
+            None => false,
+        };
+        let page = request.page(rows, has_behind);
+
+        let pagination = CursorPaginationMetadataDto {
+            has_prev: page.pagination.has_prev,
+            has_next: page.pagination.has_next,
+            first_cursor: page
+                .items
+                .as_slice()
+                .first()
+                .map(|item| self.cursor_codec.encode(&JobKey::new(item, ()))),
+            last_cursor: page
+                .items
+                .as_slice()
+                .last()
+                .map(|item| self.cursor_codec.encode(&JobKey::new(item, ()))),
+        };
+        let items = page
+            .items
+            .into_iter()
+            .map(|raw_item| raw_item.into())
+            .collect();
+
+        Ok(FindJobsResultDto { pagination, items })
+    }
+
+    pub async fn find_job(&self, path: FindJobPathDto) -> Result<Option<JobDto>, JobServiceError> {
+        let db_conn = &mut self.db_pool.get().await?;
+        let job = jobs::table
+            .select(job_columns())
+            .filter(jobs::uuid.eq(path.identifier))
+            .first::<RawJobDto>(db_conn)
+            .await
+            .optional()?;
+
+        Ok(job.map(|job| job.into()))
+    }
+
+    /// Queues a dead job again, due now and with all of its attempts.
+    pub async fn retry_job(
+        &self,
+        path: RetryJobPathDto,
+    ) -> Result<Option<JobDto>, JobServiceError> {
+        let db_conn = &mut self.db_pool.get().await?;
+        let job = db_conn
+            .transaction(|db_conn| {
+                async move {
+                    let current = jobs::table
+                        .select((jobs::id, jobs::job_type, jobs::dedup_key, jobs::status))
+                        .filter(jobs::uuid.eq(path.identifier))
+                        .for_update()
+                        .first::<(i64, String, Option<String>, String)>(db_conn)
+                        .await
+                        .optional()?;
+                    let (id, job_type, dedup_key, status) = match current {
+                        Some(current) => current,
+                        None => return Ok(None),
+                    };
+
+                    if status != enum_name(&JobStatusDto::Dead) {
+                        return Err(JobServiceError::NotDead(status));
+                    }
+
+                    let pending = enum_name(&JobStatusDto::Pending);
+
+                    if let Some(dedup_key) = &dedup_key {
+                        let duplicated = diesel::select(exists(
+                            jobs::table
+                                .filter(jobs::job_type.eq(&job_type))
+                                .filter(jobs::dedup_key.eq(dedup_key))
+                                .filter(jobs::status.eq(&pending)),
+                        ))
+                        .get_result::<bool>(db_conn)
+                        .await?;
+
+                        if duplicated {
+                            return Err(JobServiceError::AlreadyPending);
+                        }
+                    }
+
+                    let job = diesel::update(jobs::table.find(id))
+                        .set((
+                            jobs::status.eq(&pending),
+                            jobs::attempt_count.eq(0),
+                            jobs::run_at.eq(now),
+                            jobs::finished_at.eq(None::<NaiveDateTime>),
+                        ))
+                        .returning(job_columns())
+                        .get_result::<RawJobDto>(db_conn)
+                        .await?;
+
+                    Ok(Some(job))
+                }
+                .scope_boxed()
+            })
+            .await?;
+
+        Ok(job.map(|job| job.into()))
+    }
+}
+
+/// Position of a job in the queue, which is ordered by creation time.
+#[derive(Serialize, Deserialize, Debug)]
+struct JobKey(NaiveDateTime, Uuid);
+
+impl Keyset for JobKey {
+    type Table = jobs::table;
+    type Sort = ();
+    type Row = RawJobDto;
+
+    fn new(row: &RawJobDto, _sort: ()) -> Self {
+        Self(row.created_at, row.uuid)
+    }
+
+    fn sort(&self) {}
+
+    fn beyond(&self, ascending: bool) -> BoxedPredicate<'_, jobs::table> {
+        let Self(created_at, uuid) = self;
+
+        if ascending {
+            Box::new(
+                jobs::created_at
+                    .gt(created_at)
+                    .or(jobs::created_at.eq(created_at).and(jobs::uuid.gt(uuid))),
+            )
+        } else {
+            Box::new(
+                jobs::created_at
+                    .lt(created_at)
+                    .or(jobs::created_at.eq(created_at).and(jobs::uuid.lt(uuid))),
+            )
+        }
+    }
+
+    fn order(
+        query: IntoBoxed<'_, jobs::table, Pg>,
+        _sort: (),
+        ascending: bool,
+    ) -> IntoBoxed<'_, jobs::table, Pg> {
+        if ascending {
+            query.order((jobs::created_at.asc(), jobs::uuid.asc()))
+        } else {
+            query.order((jobs::created_at.desc(), jobs::uuid.desc()))
+        }
+    }
+}
+
+type JobColumns = (
+    jobs::uuid,
+    jobs::job_type,
+    jobs::payload,
+    jobs::status,
+    jobs::attempt_count,
+    jobs::max_attempts,
+    jobs::run_at,
+    jobs::last_error,
+    jobs::created_at,
+    jobs::updated_at,
+    jobs::finished_at,
+);
+
+fn job_columns() -> JobColumns {
+    (
+        jobs::uuid,
+        jobs::job_type,
+        jobs::payload,
+        jobs::status,
+        jobs::attempt_count,
+        jobs::max_attempts,
+        jobs::run_at,
+        jobs::last_error,
+        jobs::created_at,
+        jobs::updated_at,
+        jobs::finished_at,
+    )
+}
+
+#[derive(Queryable, Debug)]
+struct RawJobDto {
+    uuid: Uuid,
+    job_type: String,
+    payload: serde_json::Value,
+    status: String,
+    attempt_count: i32,
+    max_attempts: i32,
+    run_at: NaiveDateTime,
+    last_error: Option<String>,
+    created_at: NaiveDateTime,
+    updated_at: NaiveDateTime,
+    finished_at: Option<NaiveDateTime>,
+}
+
+impl From<RawJobDto> for JobDto {
+    fn from(item: RawJobDto) -> Self {
+        let status = parse_enum_name(item.status);
+        // Finished jobs are not scheduled anymore.
+        let run_at = matches!(status, JobStatusDto::Pending | JobStatusDto::Running)
+            .then(|| item.run_at.and_utc());
+
+        Self {
+            uuid: item.uuid,
+            job_type: parse_enum_name(item.job_type),
+            payload: item.payload,
+            status,
+            attempt_count: item.attempt_count,
+            max_attempts: item.max_attempts,
+            run_at,
+            last_error: item.last_error,
+            created_at: item.created_at.and_utc(),
+            updated_at: item.updated_at.and_utc(),
+            finished_at: item.finished_at.map(|finished_at| finished_at.and_utc()),
+        }
+    }
+}
@@ -0,0 +1,110 @@
+use crate::app_state::AppState;
+use axum::{
+    routing::{get, post},
+    Router,
+};
+
+pub mod job_service;
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/jobs", get(handlers::find_jobs))
+        .route("/jobs/:identifier", get(handlers::find_job))
+        .route("/jobs/:identifier/retry", post(handlers::retry_job))
+}
+
+pub mod handlers {
+    use super::job_service::{JobService, JobServiceError};
+    use crate::{
+        app_state::AppState,
+        schema::{
+            dto_in::{FindJobPathDto, FindJobsQueryDto, RetryJobPathDto},
+            dto_out::FindJobsResultDto,
+        },
+    };
+    use axum::{
+        debug_handler,
+        extract::{Path, Query, State},
+        http::StatusCode,
+        response::{IntoResponse, Response},
+        Json,
+    };
+
+    /// Finds background jobs, most recent first by default.
+    #[utoipa::path(
+        get,
+        operation_id = "find-jobs",
+        tag = "job",
+        path = "/jobs",
+        params(
+            FindJobsQueryDto
+        ),
+        responses(
+            (status = OK, body = FindJobsResultDto),
+            (status = UNPROCESSABLE_ENTITY, description = "the pagination parameters are invalid", body = ErrorBody),
+            (status = INTERNAL_SERVER_ERROR, description = "an error has occurred", body = ErrorBody),
+        ),
+    )]
+    #[debug_handler(state = AppState)]
+    pub async fn find_jobs(
+        State(job_service): State<JobService>,
+        Query(query): Query<FindJobsQueryDto>,
+    ) -> Result<(StatusCode, Json<FindJobsResultDto>), JobServiceError> {
+        let result = job_service.find_jobs(query).await?;
+
+        Ok((StatusCode::OK, Json(result)))
+    }
+
+    /// Finds a background job.
+    #[utoipa::path(
+        get,
+        operation_id = "find-job",
+        tag = "job",
+        path = "/jobs/{identifier}",
+        params(
+            FindJobPathDto
+        ),
+        responses(
+            (status = OK, body = JobDto),
+            (status = NOT_FOUND, description = "the job does not exist"),
+            (status = INTERNAL_SERVER_ERROR, description = "an error has occurred", body = ErrorBody),
+        ),
+    )]
+    #[debug_handler(state = AppState)]
+    pub async fn find_job(
+        State(job_service): State<JobService>,
+        Path(path): Path<FindJobPathDto>,
+    ) -> Result<Response, JobServiceError> {
+        match job_service.find_job(path).await? {
+            Some(result) => Ok((StatusCode::OK, Json(result)).into_response()),
+            None => Ok(StatusCode::NOT_FOUND.into_response()),
+        }
+    }
+
+    /// Queue a dead job again, with all of its attempts.
+    #[utoipa::path(
+        post,
+        operation_id = "retry-job",
+        tag = "job",
+        path = "/jobs/{identifier}/retry",
+        params(
+            RetryJobPathDto
+        ),
+        responses(
+            (status = ACCEPTED, description = "the job was queued", body = JobDto),
+            (status = NOT_FOUND, description = "the job does not exist"),
+            (status = CONFLICT, description = "the job is not dead, or a pending job does the same work", body = ErrorBody),
+            (status = INTERNAL_SERVER_ERROR, description = "an error has occurred", body = ErrorBody),
+        ),
+    )]
+    #[debug_handler(state = AppState)]
+    pub async fn retry_job(
+        State(job_service): State<JobService>,
+        Path(path): Path<RetryJobPathDto>,
+    ) -> Result<Response, JobServiceError> {
+        match job_service.retry_job(path).await? {
+            Some(result) => Ok((StatusCode::ACCEPTED, Json(result)).into_response()),
+            None => Ok(StatusCode::NOT_FOUND.into_response()),
+        }
+    }
+}
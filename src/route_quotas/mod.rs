@@ -0,0 +1,219 @@
+use crate::app_state::AppState;
+use axum::{
+    routing::{get, put},
+    Router,
+};
+
+pub mod quota_service;
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/quotas/users/:user", get(handlers::find_user_quota))
+        .route("/quotas/users/:user", put(handlers::update_user_quota))
+        .route("/quotas/anonymous", get(handlers::find_anonymous_quota))
+        .route("/quotas/anonymous", put(handlers::update_anonymous_quota))
+        .route(
+            "/quotas/collections/:identifier",
+            get(handlers::find_collection_quota),
+        )
+        .route(
+            "/quotas/collections/:identifier",
+            put(handlers::update_collection_quota),
+        )
+}
+
+pub mod handlers {
+    use super::quota_service::{QuotaService, QuotaServiceError};
+    use crate::{
+        app_state::AppState,
+        audit::AuditContext,
+        schema::{
+            dto_in::{
+                FindCollectionQuotaPathDto, FindUserQuotaPathDto, UpdateCollectionQuotaPathDto,
+                UpdateQuotaBodyDto, UpdateUserQuotaPathDto,
+            },
+            dto_out::QuotaDto,
+        },
+    };
+    use axum::{
+        debug_handler,
+        extract::{Path, State},
+        http::StatusCode,
+        response::{IntoResponse, Response},
+        Json,
+    };
+
+    /// Finds the storage quota of a user, and how much of it is used.
+    ///
+    /// User quotas are advisory until the service authenticates its users: the owner of a file is
+    /// the actor its creator claims in the `X-Actor` header, so a client can spread its files
+    /// across any number of users. Files created without an actor count toward the quota of
+    /// anonymous users. Collection quotas apply to every file.
+    #[utoipa::path(
+        get,
+        operation_id = "find-user-quota",
+        tag = "quota",
+        path = "/quotas/users/{user}",
+        params(
+            FindUserQuotaPathDto
+        ),
+        responses(
+            (status = OK, body = QuotaDto),
+            (status = INTERNAL_SERVER_ERROR, description = "an error has occurred", body = ErrorBody),
+        ),
+    )]
+    #[debug_handler(state = AppState)]
+    pub async fn find_user_quota(
+        State(quota_service): State<QuotaService>,
+        Path(path): Path<FindUserQuotaPathDto>,
+    ) -> Result<(StatusCode, Json<QuotaDto>), QuotaServiceError> {
+        let result = quota_service.find_user_quota(path).await?;
+
+        Ok((StatusCode::OK, Json(result)))
+    }
+
+    /// Set the limits of the storage quota of a user.
+    ///
+    /// Files already stored are kept when the new limits are below the usage; only new uploads and
+    /// files are refused. Like every user quota, it is advisory until the service authenticates
+    /// its users.
+    #[utoipa::path(
+        put,
+        operation_id = "update-user-quota",
+        tag = "quota",
+        path = "/quotas/users/{user}",
+        params(
+            UpdateUserQuotaPathDto
+        ),
+        request_body = UpdateQuotaBodyDto,
+        responses(
+            (status = OK, body = QuotaDto),
+            (status = UNPROCESSABLE_ENTITY, description = "the limits are invalid", body = ErrorBody),
+            (status = INTERNAL_SERVER_ERROR, description = "an error has occurred", body = ErrorBody),
+        ),
+    )]
+    #[debug_handler(state = AppState)]
+    pub async fn update_user_quota(
+        State(quota_service): State<QuotaService>,
+        context: AuditContext,
+        Path(path): Path<UpdateUserQuotaPathDto>,
+        Json(body): Json<UpdateQuotaBodyDto>,
+    ) -> Result<(StatusCode, Json<QuotaDto>), QuotaServiceError> {
+        let result = quota_service
+            .update_user_quota(path, body, &context)
+            .await?;
+
+        Ok((StatusCode::OK, Json(result)))
+    }
+
+    /// Finds the storage quota shared by the files created without an `X-Actor` header, and how
+    /// much of it is used.
+    #[utoipa::path(
+        get,
+        operation_id = "find-anonymous-quota",
+        tag = "quota",
+        path = "/quotas/anonymous",
+        responses(
+            (status = OK, body = QuotaDto),
+            (status = INTERNAL_SERVER_ERROR, description = "an error has occurred", body = ErrorBody),
+        ),
+    )]
+    #[debug_handler(state = AppState)]
+    pub async fn find_anonymous_quota(
+        State(quota_service): State<QuotaService>,
+    ) -> Result<(StatusCode, Json<QuotaDto>), QuotaServiceError> {
+        let result = quota_service.find_anonymous_quota().await?;
+
+        Ok((StatusCode::OK, Json(result)))
+    }
+
+    /// Set the limits of the storage quota shared by the files created without an `X-Actor`
+    /// header.
+    ///
+    /// Files already stored are kept when the new limits are below the usage; only new uploads and
+    /// files are refused.
+    #[utoipa::path(
+        put,
+        operation_id = "update-anonymous-quota",
+        tag = "quota",
+        path = "/quotas/anonymous",
+        request_body = UpdateQuotaBodyDto,
+        responses(
+            (status = OK, body = QuotaDto),
+            (status = UNPROCESSABLE_ENTITY, description = "the limits are invalid", body = ErrorBody),
+            (status = INTERNAL_SERVER_ERROR, description = "an error has occurred", body = ErrorBody),
+        ),
+    )]
+    #[debug_handler(state = AppState)]
+    pub async fn update_anonymous_quota(
+        State(quota_service): State<QuotaService>,
+        context: AuditContext,
+        Json(body): Json<UpdateQuotaBodyDto>,
+    ) -> Result<(StatusCode, Json<QuotaDto>), QuotaServiceError> {
+        let result = quota_service.update_anonymous_quota(body, &context).await?;
+
+        Ok((StatusCode::OK, Json(result)))
+    }
+
+    /// Finds the storage quota of a collection, and how much of it is used.
+    #[utoipa::path(
+        get,
+        operation_id = "find-collection-quota",
+        tag = "quota",
+        path = "/quotas/collections/{identifier}",
+        params(
+            FindCollectionQuotaPathDto
+        ),
+        responses(
+            (status = OK, body = QuotaDto),
+            (status = NOT_FOUND, description = "the collection does not exist"),
+            (status = INTERNAL_SERVER_ERROR, description = "an error has occurred", body = ErrorBody),
+        ),
+    )]
+    #[debug_handler(state = AppState)]
+    pub async fn find_collection_quota(
+        State(quota_service): State<QuotaService>,
+        Path(path): Path<FindCollectionQuotaPathDto>,
+    ) -> Result<Response, QuotaServiceError> {
+        match quota_service.find_collection_quota(path).await? {
+            Some(result) => Ok((StatusCode::OK, Json(result)).into_response()),
+            None => Ok(StatusCode::NOT_FOUND.into_response()),
+        }
+    }
+
+    /// Set the limits of the storage quota of a collection.
+    ///
+    /// Files already in the collection are kept when the new limits are below the usage; only new
+    /// uploads and additions are refused.
+    #[utoipa::path(
+        put,
+        operation_id = "update-collection-quota",
+        tag = "quota",
+        path = "/quotas/collections/{identifier}",
+        params(
+            UpdateCollectionQuotaPathDto
+        ),
+        request_body = UpdateQuotaBodyDto,
+        responses(
+            (status = OK, body = QuotaDto),
+            (status = NOT_FOUND, description = "the collection does not exist"),
+            (status = UNPROCESSABLE_ENTITY, description = "the limits are invalid", body = ErrorBody),
+            (status = INTERNAL_SERVER_ERROR, description = "an error has occurred", body = ErrorBody),
+        ),
+    )]
+    #[debug_handler(state = AppState)]
+    pub async fn update_collection_quota(
+        State(quota_service): State<QuotaService>,
+        context: AuditContext,
+        Path(path): Path<UpdateCollectionQuotaPathDto>,
+        Json(body): Json<UpdateQuotaBodyDto>,
+    ) -> Result<Response, QuotaServiceError> {
+        match quota_service
+            .update_collection_quota(path, body, &context)
+            .await?
+        {
+            Some(result) => Ok((StatusCode::OK, Json(result)).into_response()),
+            None => Ok(StatusCode::NOT_FOUND.into_response()),
+        }
+    }
+}
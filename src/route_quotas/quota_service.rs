@@ -0,0 +1,314 @@
+use crate::{
+    audit::{record_event, snapshot, AuditContext},
+    db::{
+        schema::{collection_quotas, collections, user_quotas},
+        DBPool,
+    },
+    quota::{RawQuotaDto, ANONYMOUS_OWNER},
+    schema::{
+        dto_in::{
+            AuditActionDto, FindCollectionQuotaPathDto, FindUserQuotaPathDto,
+            UpdateCollectionQuotaPathDto, UpdateQuotaBodyDto, UpdateUserQuotaPathDto,
+        },
+        dto_out::QuotaDto,
+    },
+};
+use axum::http::StatusCode;
+use codegen::ErrorEnum;
+use diesel::{prelude::*, upsert::excluded};
+use diesel_async::{scoped_futures::ScopedFutureExt, AsyncConnection, RunQueryDsl};
+use serde::Serialize;
+use thiserror::Error;
+use uuid::Uuid;
+
+#[derive(ErrorEnum, Error, Debug)]
+pub enum QuotaServiceError {
+    #[error("internal server error")]
+    #[status(StatusCode::INTERNAL_SERVER_ERROR)]
+    PoolError(#[from] diesel_async::pooled_connection::deadpool::PoolError),
+    #[error("internal server error")]
+    #[status(StatusCode::INTERNAL_SERVER_ERROR)]
+    DieselError(#[from] diesel::result::Error),
+    #[error("limits of a quota must not be negative")]
+    #[status(StatusCode::UNPROCESSABLE_ENTITY)]
+    NegativeLimit,
+}
+
+#[derive(Clone)]
+pub struct QuotaService {
+    db_pool: DBPool,
+}
+
+impl QuotaService {
+    pub fn new(db_pool: DBPool) -> Self {
+        Self { db_pool }
+    }
+
+    pub async fn find_user_quota(
+        &self,
+        path: FindUserQuotaPathDto,
+    ) -> Result<QuotaDto, QuotaServiceError> {
+        self.find_owner_quota(&path.user).await
+    }
+
+    pub async fn find_anonymous_quota(&self) -> Result<QuotaDto, QuotaServiceError> {
+        self.find_owner_quota(ANONYMOUS_OWNER).await
+    }
+
+    async fn find_owner_quota(&self, owner: &str) -> Result<QuotaDto, QuotaServiceError> {
+        let db_conn = &mut self.db_pool.get().await?;
+        let quota = user_quotas::table
+            .select((
+                user_quotas::max_bytes,
+                user_quotas::max_files,
+                user_quotas::used_bytes,
+                user_quotas::used_files,
+            ))
+            .filter(user_quotas::owner.eq(owner))
+            .get_result::<RawQuotaDto>(db_conn)
+            .await
+            .optional()?;
+
+        // A user that never stored a file has no quota yet.
+        Ok(quota.map_or_else(unlimited_quota, QuotaDto::from))
+    }
+
+    pub async fn update_user_quota(
+        &self,
+        path: UpdateUserQuotaPathDto,
+        body: UpdateQuotaBodyDto,
+        context: &AuditContext,
+    ) -> Result<QuotaDto, QuotaServiceError> {
+        self.update_owner_quota(&path.user, body, context).await
+    }
+
+    pub async fn update_anonymous_quota(
+        &self,
+        body: UpdateQuotaBodyDto,
+        context: &AuditContext,
+    ) -> Result<QuotaDto, QuotaServiceError> {
+        self.update_owner_quota(ANONYMOUS_OWNER, body, context)
+            .await
+    }
+
+    async fn update_owner_quota(
+        &self,
+        owner: &str,
+        body: UpdateQuotaBodyDto,
+        context: &AuditContext,
+    ) -> Result<QuotaDto, QuotaServiceError> {
+        validate_limits(&body)?;
+
+        let db_conn = &mut self.db_pool.get().await?;
+        db_conn
+            .transaction(|db_conn| {
+                async move {
+                    let before = user_quotas::table
+                        .select((
+                            user_quotas::max_bytes,
+                            user_quotas::max_files,
+                            user_quotas::used_bytes,
+                            user_quotas::used_files,
+                        ))
+                        .filter(user_quotas::owner.eq(owner))
+                        .for_update()
+                        .get_result::<RawQuotaDto>(db_conn)
+                        .await
+                        .optional()?
+                        .map(QuotaDto::from);
+
+                    let (quota_uuid, quota) = diesel::insert_into(user_quotas::table)
+                        .values((
+                            user_quotas::owner.eq(owner),
+                            user_quotas::max_bytes.eq(body.max_bytes),
+                            user_quotas::max_files.eq(body.max_files),
+                        ))
+                        .on_conflict(user_quotas::owner)
+                        .do_update()
+                        .set((
+                            user_quotas::max_bytes.eq(excluded(user_quotas::max_bytes)),
+                            user_quotas::max_files.eq(excluded(user_quotas::max_files)),
+                        ))
+                        .returning((
+                            user_quotas::uuid,
+                            (
+                                user_quotas::max_bytes,
+                                user_quotas::max_files,
+                                user_quotas::used_bytes,
+                                user_quotas::used_files,
+                            ),
+                        ))
+                        .get_result::<(Uuid, RawQuotaDto)>(db_conn)
+                        .await?;
+                    let quota = QuotaDto::from(quota);
+
+                    record_event(
+                        db_conn,
+                        context,
+                        AuditActionDto::UpdateUserQuota,
+                        quota_uuid,
+                        before.map(|before| snapshot(&UserQuotaSnapshot::new(owner, &before))),
+                        Some(snapshot(&UserQuotaSnapshot::new(owner, &quota))),
+                    )
+                    .await?;
+
+                    Ok(quota)
+                }
+                .scope_boxed()
+            })
+            .await
+    }
+
+    pub async fn find_collection_quota(
+        &self,
+        path: FindCollectionQuotaPathDto,
+    ) -> Result<Option<QuotaDto>, QuotaServiceError> {
+        let db_conn = &mut self.db_pool.get().await?;
+        let quota = collections::table
+            .left_join(collection_quotas::table)
+            .select((
+                collection_quotas::max_bytes.nullable(),
+                collection_quotas::max_files.nullable(),
+                collection_quotas::used_bytes.nullable(),
+                collection_quotas::used_files.nullable(),
+            ))
+            .filter(collections::uuid.eq(path.identifier))
+            .filter(collections::deleted_at.is_null())
+            .get_result::<(Option<i64>, Option<i64>, Option<i64>, Option<i64>)>(db_conn)
+            .await
+            .optional()?;
+
+        // A collection that never held a file has no quota yet.
+        Ok(
+            quota.map(|(max_bytes, max_files, used_bytes, used_files)| QuotaDto {
+                max_bytes,
+                max_files,
+                used_bytes: used_bytes.unwrap_or_default(),
+                used_files: used_files.unwrap_or_default(),
+            }),
+        )
+    }
+
+    pub async fn update_collection_quota(
+        &self,
+        path: UpdateCollectionQuotaPathDto,
+        body: UpdateQuotaBodyDto,
+        context: &AuditContext,
+    ) -> Result<Option<QuotaDto>, QuotaServiceError> {
+        validate_limits(&body)?;
+
+        let db_conn = &mut self.db_pool.get().await?;
+        db_conn
+            .transaction(|db_conn| {
+                async move {
+                    let collection_id = collections::table
+                        .select(collections::id)
+                        .filter(collections::uuid.eq(path.identifier))
+                        .filter(collections::deleted_at.is_null())
+                        .get_result::<i32>(db_conn)
+                        .await
+                        .optional()?;
+                    let collection_id = match collection_id {
+                        Some(collection_id) => collection_id,
+                        None => return Ok(None),
+                    };
+
+                    let before = collection_quotas::table
+                        .select((
+                            collection_quotas::max_bytes,
+                            collection_quotas::max_files,
+                            collection_quotas::used_bytes,
+                            collection_quotas::used_files,
+                        ))
+                        .filter(collection_quotas::collection_id.eq(collection_id))
+                        .for_update()
+                        .get_result::<RawQuotaDto>(db_conn)
+                        .await
+                        .optional()?
+                        .map(QuotaDto::from);
+
+                    let quota = diesel::insert_into(collection_quotas::table)
+                        .values((
+                            collection_quotas::collection_id.eq(collection_id),
+                            collection_quotas::max_bytes.eq(body.max_bytes),
+                            collection_quotas::max_files.eq(body.max_files),
+                        ))
+                        .on_conflict(collection_quotas::collection_id)
+                        .do_update()
+                        .set((
+                            collection_quotas::max_bytes.eq(excluded(collection_quotas::max_bytes)),
+                            collection_quotas::max_files.eq(excluded(collection_quotas::max_files)),
+                        ))
+                        .returning((
+                            collection_quotas::max_bytes,
+                            collection_quotas::max_files,
+                            collection_quotas::used_bytes,
+                            collection_quotas::used_files,
+                        ))
+                        .get_result::<RawQuotaDto>(db_conn)
+                        .await?;
+                    let quota = QuotaDto::from(quota);
+
+                    record_event(
+                        db_conn,
+                        context,
+                        AuditActionDto::UpdateCollectionQuota,
+                        path.identifier,
+                        before.as_ref().map(snapshot),
+                        Some(snapshot(&quota)),
+                    )
+                    .await?;
+
+                    Ok(Some(quota))
+                }
+                .scope_boxed()
+            })
+            .await
+    }
+}
+
+/// Snapshot of a user quota in the audit log, which tells whose quota it is.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct UserQuotaSnapshot<'a> {
+    /// Empty for the quota of anonymous users.
+    owner: &'a str,
+    #[serde(flatten)]
+    quota: &'a QuotaDto,
+}
+
+impl<'a> UserQuotaSnapshot<'a> {
+    fn new(owner: &'a str, quota: &'a QuotaDto) -> Self {
+        Self { owner, quota }
+    }
+}
+
+fn validate_limits(body: &UpdateQuotaBodyDto) -> Result<(), QuotaServiceError> {
+    if body.max_bytes.map_or(false, |max_bytes| max_bytes < 0)
+        || body.max_files.map_or(false, |max_files| max_files < 0)
+    {
+        return Err(QuotaServiceError::NegativeLimit);
+    }
+
+    Ok(())
+}
+
+fn unlimited_quota() -> QuotaDto {
+    QuotaDto {
+        max_bytes: None,
+        max_files: None,
+        used_bytes: 0,
+        used_files: 0,
+    }
+}
+
+impl From<RawQuotaDto> for QuotaDto {
+    fn from(quota: RawQuotaDto) -> Self {
+        Self {
+            max_bytes: quota.max_bytes,
+            max_files: quota.max_files,
+            used_bytes: quota.used_bytes,
+            used_files: quota.used_files,
+        }
+    }
+}
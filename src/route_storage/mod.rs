@@ -0,0 +1,111 @@
+use crate::app_state::AppState;
+use axum::{
+    routing::{get, post},
+    Router,
+};
+
+pub mod storage_service;
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/storage/reconcile", post(handlers::reconcile_storage))
+        .route("/storage/scrub", get(handlers::find_scrub_status))
+        .route(
+            "/storage/scrub/mismatches",
+            get(handlers::find_scrub_mismatches),
+        )
+}
+
+pub mod handlers {
+    use super::storage_service::{StorageService, StorageServiceError};
+    use crate::{
+        app_state::AppState,
+        schema::{
+            dto_in::{FindScrubMismatchesQueryDto, ReconcileStorageQueryDto},
+            dto_out::{FindScrubMismatchesResultDto, ReconcileStorageResultDto, ScrubStatusDto},
+        },
+    };
+    use axum::{
+        debug_handler,
+        extract::{Query, State},
+        http::StatusCode,
+        Json,
+    };
+
+    /// Reconcile the stored contents with the files.
+    ///
+    /// Removes the uploads abandoned for longer than `UPLOAD_TTL_HOURS` and the contents that
+    /// belong to no file, and marks as failed the files whose content is missing. It also runs
+    /// every hour in the background.
+    #[utoipa::path(
+        post,
+        operation_id = "reconcile-storage",
+        tag = "storage",
+        path = "/storage/reconcile",
+        params(
+            ReconcileStorageQueryDto
+        ),
+        responses(
+            (status = OK, body = ReconcileStorageResultDto),
+            (status = CONFLICT, description = "a reconciliation is already running", body = ErrorBody),
+            (status = INTERNAL_SERVER_ERROR, description = "an error has occurred", body = ErrorBody),
+        ),
+    )]
+    #[debug_handler(state = AppState)]
+    pub async fn reconcile_storage(
+        State(storage_service): State<StorageService>,
+        Query(query): Query<ReconcileStorageQueryDto>,
+    ) -> Result<(StatusCode, Json<ReconcileStorageResultDto>), StorageServiceError> {
+        let result = storage_service.reconcile_storage(query).await?;
+
+        Ok((StatusCode::OK, Json(result)))
+    }
+
+    /// Finds the progress of the integrity scrub.
+    #[utoipa::path(
+        get,
+        operation_id = "find-scrub-status",
+        tag = "storage",
+        path = "/storage/scrub",
+        responses(
+            (status = OK, body = ScrubStatusDto),
+            (status = INTERNAL_SERVER_ERROR, description = "an error has occurred", body = ErrorBody),
+        ),
+    )]
+    #[debug_handler(state = AppState)]
+    pub async fn find_scrub_status(
+        State(storage_service): State<StorageService>,
+    ) -> Result<(StatusCode, Json<ScrubStatusDto>), StorageServiceError> {
+        let result = storage_service.find_scrub_status().await?;
+
+        Ok((StatusCode::OK, Json(result)))
+    }
+
+    /// Finds the stored contents the integrity scrub found to differ from what was uploaded, most
+    /// recent first by default.
+    ///
+    /// A mismatch is left out once its file is uploaded again and checked as sound.
+    #[utoipa::path(
+        get,
+        operation_id = "find-scrub-mismatches",
+        tag = "storage",
+        path = "/storage/scrub/mismatches",
+        params(
+            FindScrubMismatchesQueryDto
+        ),
+        responses(
+            (status = OK, body = FindScrubMismatchesResultDto),
+            (status = UNPROCESSABLE_ENTITY, description = "the pagination parameters are invalid", body = ErrorBody),
+            (status = INTERNAL_SERVER_ERROR, description = "an error has occurred", body = ErrorBody),
+        ),
+    )]
+    #[debug_handler(state = AppState)]
+    pub async fn find_scrub_mismatches(
+        State(storage_service): State<StorageService>,
+        Query(query): Query<FindScrubMismatchesQueryDto>,
+    ) -> Result<(StatusCode, Json<FindScrubMismatchesResultDto>), StorageServiceError> {
+        let result = storage_service.find_scrub_mismatches(query).await?;
+
+        Ok((StatusCode::OK, Json(result)))
+    }
+}
@@ -0,0 +1,280 @@
+use crate::{
+    audit::{enum_name, parse_enum_name},
+    db::{
+        schema::{scrub_mismatches, scrub_state},
+        DBPool,
+    },
+    pagination::{BoxedPredicate, CursorCodec, CursorError, Keyset, PageRequest, PaginationError},
+    reconcile::{ReconcileError, Reconciler},
+    schema::{
+        dto_in::{FindScrubMismatchesQueryDto, ReconcileStorageQueryDto},
+        dto_out::{
+            CursorPaginationMetadataDto, FindScrubMismatchesResultDto, ReconcileStorageResultDto,
+            ScrubMismatchDto, ScrubStatusDto,
+        },
+    },
+};
+use axum::http::StatusCode;
+use chrono::NaiveDateTime;
+use codegen::ErrorEnum;
+use diesel::{dsl::IntoBoxed, pg::Pg, prelude::*};
+use diesel_async::RunQueryDsl;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use uuid::Uuid;
+
+#[derive(ErrorEnum, Error, Debug)]
+pub enum StorageServiceError {
+    #[error("internal server error")]
+    #[status(StatusCode::INTERNAL_SERVER_ERROR)]
+    PoolError(#[from] diesel_async::pooled_connection::deadpool::PoolError),
+    #[error("internal server error")]
+    #[status(StatusCode::INTERNAL_SERVER_ERROR)]
+    DieselError(#[from] diesel::result::Error),
+    #[error("{0}")]
+    #[status("0")]
+    PaginationError(#[from] PaginationError),
+    #[error("{0}")]
+    #[status("0")]
+    CursorError(#[from] CursorError),
+    #[error("{0}")]
+    #[status("0")]
+    Reconcile(#[from] ReconcileError),
+}
+
+#[derive(Clone)]
+pub struct StorageService {
+    db_pool: DBPool,
+    cursor_codec: CursorCodec,
+    reconciler: Reconciler,
+}
+
+impl StorageService {
+    pub fn new(db_pool: DBPool, cursor_codec: CursorCodec, reconciler: Reconciler) -> Self {
+        Self {
+            db_pool,
+            cursor_codec,
+            reconciler,
+        }
+    }
+
+    pub async fn reconcile_storage(
+        &self,
+        query: ReconcileStorageQueryDto,
+    ) -> Result<ReconcileStorageResultDto, StorageServiceError> {
+        Ok(self.reconciler.reconcile(query.dry_run).await?)
+    }
+
+    pub async fn find_scrub_status(&self) -> Result<ScrubStatusDto, StorageServiceError> {
+        let db_conn = &mut self.db_pool.get().await?;
+        let state = scrub_state::table
+            .select((
+                scrub_state::pass_started_at,
+                scrub_state::checked_count,
+                scrub_state::checked_size,
+                scrub_state::mismatch_count,
+                scrub_state::last_pass_completed_at,
+            ))
+            .get_result::<RawScrubStatusDto>(db_conn)
+            .await?;
+
+        Ok(state.into())
+    }
+
+    pub async fn find_scrub_mismatches(
+        &self,
+        query: FindScrubMismatchesQueryDto,
+    ) -> Result<FindScrubMismatchesResultDto, StorageServiceError> {
+        let first = query
+            .first_cursor
+            .as_deref()
+            .map(|cursor| self.cursor_codec.decode::<ScrubMismatchKey>(cursor))
+            .transpose()?;
+        let last = query
+            .last_cursor
+            .as_deref()
+            .map(|cursor| self.cursor_codec.decode::<ScrubMismatchKey>(cursor))
+            .transpose()?;
+        let request = PageRequest::new(first, last, (), query.order, query.page_size)?;
+
+        let kind = query.kind.map(|kind| enum_name(&kind));
+
+        let db_conn = &mut self.db_pool.get().await?;
+        let matching = || {
+            let mut select = scrub_mismatches::table.into_boxed();
+
+            if let Some(kind) = &kind {
+                select = select.filter(scrub_mismatches::kind.eq(kind));
+            }
+
+            select
+        };
+        let rows = request
+            .select(&matching, scrub_mismatch_columns())
+            .load::<RawScrubMismatchDto>(db_conn)
+            .await?;
+        let has_behind = match request.behind(&matching) {
+            Some(behind) => behind.get_result(db_conn).await?,
+            None => false,
+        };
+        let page = request.page(rows, has_behind);
+
+        let pagination = CursorPaginationMetadataDto {
+            has_prev: page.pagination.has_prev,
+            has_next: page.pagination.has_next,
+            first_cursor: page
+                .items
+                .as_slice()
+                .first()
+                .map(|item| self.cursor_codec.encode(&ScrubMismatchKey::new(item, ()))),
+            last_cursor: page
+                .items
+                .as_slice()
+                .last()
+                .map(|item| self.cursor_codec.encode(&ScrubMismatchKey::new(item, ()))),
+        };
+        let items = page
+            .items
+            .into_iter()
+            .map(|raw_item| raw_item.into())
+            .collect();
+
+        Ok(FindScrubMismatchesResultDto { pagination, items })
+    }
+}
+
+/// Position of a mismatch in the report, which is ordered by detection time.
+#[derive(Serialize, Deserialize, Debug)]
+struct ScrubMismatchKey(NaiveDateTime, Uuid);
+
+impl Keyset for ScrubMismatchKey {
+    type Table = scrub_mismatches::table;
+    type Sort = ();
+    type Row = RawScrubMismatchDto;
+
+    fn new(row: &RawScrubMismatchDto, _sort: ()) -> Self {
+        Self(row.detected_at, row.uuid)
+    }
+
+    fn sort(&self) {}
+
+    fn beyond(&self, ascending: bool) -> BoxedPredicate<'_, scrub_mismatches::table> {
+        let Self(detected_at, uuid) = self;
+
+        if ascending {
+            Box::new(
+                scrub_mismatches::detected_at
+                    .gt(detected_at)
+                    .or(scrub_mismatches::detected_at
+                        .eq(detected_at)
+                        .and(scrub_mismatches::uuid.gt(uuid))),
+            )
+        } else {
+            Box::new(
+                scrub_mismatches::detected_at
+                    .lt(detected_at)
+                    .or(scrub_mismatches::detected_at
+                        .eq(detected_at)
+                        .and(scrub_mismatches::uuid.lt(uuid))),
+            )
+        }
+    }
+
+    fn order(
+        query: IntoBoxed<'_, scrub_mismatches::table, Pg>,
+        _sort: (),
+        ascending: bool,
+    ) -> IntoBoxed<'_, scrub_mismatches::table, Pg> {
+        if ascending {
+            query.order((
+                scrub_mismatches::detected_at.asc(),
+                scrub_mismatches::uuid.asc(),
+            ))
+        } else {
+            query.order((
+                scrub_mismatches::detected_at.desc(),
+                scrub_mismatches::uuid.desc(),
+            ))
+        }
+    }
+}
+
+#[derive(Queryable, Debug)]
+struct RawScrubStatusDto {
+    pass_started_at: Option<NaiveDateTime>,
+    checked_count: i64,
+    checked_size: i64,
+    mismatch_count: i64,
+    last_pass_completed_at: Option<NaiveDateTime>,
+}
+
+impl From<RawScrubStatusDto> for ScrubStatusDto {
+    fn from(state: RawScrubStatusDto) -> Self {
+        Self {
+            pass_started_at: state
+                .pass_started_at
+                .map(|pass_started_at| pass_started_at.and_utc()),
+            checked_count: state.checked_count,
+            checked_size: state.checked_size,
+            mismatch_count: state.mismatch_count,
+            last_pass_completed_at: state
+                .last_pass_completed_at
+                .map(|last_pass_completed_at| last_pass_completed_at.and_utc()),
+        }
+    }
+}
+
+type ScrubMismatchColumns = (
+    scrub_mismatches::uuid,
+    scrub_mismatches::file_uuid,
+    scrub_mismatches::kind,
+    scrub_mismatches::expected_size,
+    scrub_mismatches::actual_size,
+    scrub_mismatches::expected_hash,
+    scrub_mismatches::actual_hash,
+    scrub_mismatches::error,
+    scrub_mismatches::detected_at,
+);
+
+fn scrub_mismatch_columns() -> ScrubMismatchColumns {
+    (
+        scrub_mismatches::uuid,
+        scrub_mismatches::file_uuid,
+        scrub_mismatches::kind,
+        scrub_mismatches::expected_size,
+        scrub_mismatches::actual_size,
+        scrub_mismatches::expected_hash,
+        scrub_mismatches::actual_hash,
+        scrub_mismatches::error,
+        scrub_mismatches::detected_at,
+    )
+}
+
+#[derive(Queryable, Debug)]
+struct RawScrubMismatchDto {
+    uuid: Uuid,
+    file_uuid: Uuid,
+    kind: String,
+    expected_size: i64,
+    actual_size: Option<i64>,
+    expected_hash: Option<i64>,
+    actual_hash: Option<i64>,
+    error: Option<String>,
+    detected_at: NaiveDateTime,
+}
+
+impl From<RawScrubMismatchDto> for ScrubMismatchDto {
+    fn from(mismatch: RawScrubMismatchDto) -> Self {
+        Self {
+            uuid: mismatch.uuid,
+            file_uuid: mismatch.file_uuid,
+            kind: parse_enum_name(mismatch.kind),
+            expected_size: mismatch.expected_size,
+            actual_size: mismatch.actual_size,
+            expected_hash: mismatch.expected_hash,
+            actual_hash: mismatch.actual_hash,
+            error: mismatch.error,
+            detected_at: mismatch.detected_at.and_utc(),
+        }
+    }
+}
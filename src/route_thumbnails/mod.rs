@@ -0,0 +1,159 @@
+use crate::app_state::AppState;
+use axum::{
+    routing::{get, post},
+    Router,
+};
+
+pub mod thumbnail_service;
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route(
+            "/files/:identifier/thumbnail",
+            get(handlers::find_file_thumbnail),
+        )
+        .route(
+            "/files/:identifier/thumbnail",
+            post(handlers::generate_file_thumbnail),
+        )
+        .route(
+            "/files/:identifier/thumbnail/status",
+            get(handlers::find_file_thumbnail_status),
+        )
+}
+
+pub mod handlers {
+    use super::thumbnail_service::{ThumbnailService, ThumbnailServiceError};
+    use crate::{
+        app_state::AppState,
+        etag::IfNoneMatch,
+        schema::dto_in::{
+            FindFileThumbnailPathDto, FindFileThumbnailQueryDto, GenerateFileThumbnailPathDto,
+        },
+    };
+    use axum::{
+        body::Body,
+        debug_handler,
+        extract::{Path, Query, State},
+        http::{header, StatusCode},
+        response::{IntoResponse, Response},
+        Json,
+    };
+    use tokio_util::io::ReaderStream;
+
+    /// Time clients may use a thumbnail before checking it again with its `ETag`.
+    const CACHE_CONTROL: &str = "public, max-age=3600";
+
+    /// Fetch a thumbnail of a file as JPEG.
+    ///
+    /// Thumbnails are rendered in the background after each upload; the latest rendered one is
+    /// served while they are rendered again.
+    #[utoipa::path(
+        get,
+        operation_id = "find-file-thumbnail",
+        tag = "file",
+        path = "/files/{identifier}/thumbnail",
+        params(
+            FindFileThumbnailPathDto,
+            FindFileThumbnailQueryDto,
+        ),
+        responses(
+            (status = OK, description = "the thumbnail", body = Vec<u8>, content_type = "image/jpeg",
+                headers(
+                    ("ETag" = String, description = "entity tag of the thumbnail, for use in `If-None-Match`"),
+                    ("Cache-Control" = String, description = "how long the thumbnail may be cached"),
+                ),
+            ),
+            (status = NOT_MODIFIED, description = "the thumbnail matches `If-None-Match`"),
+            (status = NOT_FOUND, description = "the file or the thumbnail does not exist", body = ErrorBody),
+            (status = CONFLICT, description = "the file is not uploaded yet", body = ErrorBody),
+            (status = INTERNAL_SERVER_ERROR, description = "an error has occurred", body = ErrorBody),
+        ),
+    )]
+    #[debug_handler(state = AppState)]
+    pub async fn find_file_thumbnail(
+        State(thumbnail_service): State<ThumbnailService>,
+        if_none_match: IfNoneMatch,
+        Path(path): Path<FindFileThumbnailPathDto>,
+        Query(query): Query<FindFileThumbnailQueryDto>,
+    ) -> Result<Response, ThumbnailServiceError> {
+        let thumbnail = match thumbnail_service.find_thumbnail(path, query).await? {
+            Some(thumbnail) => thumbnail,
+            None => return Ok(StatusCode::NOT_FOUND.into_response()),
+        };
+
+        if if_none_match.matches(&thumbnail.etag) {
+            return Ok((
+                StatusCode::NOT_MODIFIED,
+                thumbnail.etag.header(),
+                [(header::CACHE_CONTROL, CACHE_CONTROL)],
+            )
+                .into_response());
+        }
+
+        Ok((
+            StatusCode::OK,
+            thumbnail.etag.header(),
+            [
+                (header::CONTENT_TYPE, "image/jpeg"),
+                (header::CACHE_CONTROL, CACHE_CONTROL),
+            ],
+            Body::from_stream(ReaderStream::new(thumbnail.file)),
+        )
+            .into_response())
+    }
+
+    /// Find the state of the thumbnails of a file.
+    #[utoipa::path(
+        get,
+        operation_id = "find-file-thumbnail-status",
+        tag = "file",
+        path = "/files/{identifier}/thumbnail/status",
+        params(
+            FindFileThumbnailPathDto
+        ),
+        responses(
+            (status = OK, body = ThumbnailJobDto),
+            (status = NOT_FOUND, description = "the file does not exist"),
+            (status = CONFLICT, description = "the file is not uploaded yet", body = ErrorBody),
+            (status = INTERNAL_SERVER_ERROR, description = "an error has occurred", body = ErrorBody),
+        ),
+    )]
+    #[debug_handler(state = AppState)]
+    pub async fn find_file_thumbnail_status(
+        State(thumbnail_service): State<ThumbnailService>,
+        Path(path): Path<FindFileThumbnailPathDto>,
+    ) -> Result<Response, ThumbnailServiceError> {
+        match thumbnail_service.find_thumbnail_job(path).await? {
+            Some(result) => Ok((StatusCode::OK, Json(result)).into_response()),
+            None => Ok(StatusCode::NOT_FOUND.into_response()),
+        }
+    }
+
+    /// Render the thumbnails of a file again, such as after a failure.
+    #[utoipa::path(
+        post,
+        operation_id = "generate-file-thumbnail",
+        tag = "file",
+        path = "/files/{identifier}/thumbnail",
+        params(
+            GenerateFileThumbnailPathDto
+        ),
+        responses(
+            (status = ACCEPTED, body = ThumbnailJobDto),
+            (status = NOT_FOUND, description = "the file does not exist"),
+            (status = CONFLICT, description = "the file is not uploaded yet", body = ErrorBody),
+            (status = INTERNAL_SERVER_ERROR, description = "an error has occurred", body = ErrorBody),
+        ),
+    )]
+    #[debug_handler(state = AppState)]
+    pub async fn generate_file_thumbnail(
+        State(thumbnail_service): State<ThumbnailService>,
+        Path(path): Path<GenerateFileThumbnailPathDto>,
+    ) -> Result<Response, ThumbnailServiceError> {
+        match thumbnail_service.generate_thumbnails(path).await? {
+            Some(result) => Ok((StatusCode::ACCEPTED, Json(result)).into_response()),
+            None => Ok(StatusCode::NOT_FOUND.into_response()),
+        }
+    }
+}
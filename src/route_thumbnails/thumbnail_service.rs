@@ -0,0 +1,217 @@
+use crate::{
+    audit::{enum_name, parse_enum_name},
+    db::{
+        schema::{files, thumbnail_jobs},
+        DBPool,
+    },
+    etag::ETag,
+    file_driver::{FileDriver, OpenFileError},
+    jobs::{parse_sizes, request_thumbnails},
+    schema::{
+        dto_in::{
+            FileStatusDto, FindFileThumbnailPathDto, FindFileThumbnailQueryDto,
+            GenerateFileThumbnailPathDto,
+        },
+        dto_out::{ThumbnailJobDto, ThumbnailStatusDto},
+    },
+};
+use axum::http::StatusCode;
+use chrono::NaiveDateTime;
+use codegen::ErrorEnum;
+use diesel::prelude::*;
+use diesel_async::{scoped_futures::ScopedFutureExt, AsyncConnection, RunQueryDsl};
+use thiserror::Error;
+use tokio::fs::File;
+use uuid::Uuid;
+
+#[derive(ErrorEnum, Error, Debug)]
+pub enum ThumbnailServiceError {
+    #[error("internal server error")]
+    #[status(StatusCode::INTERNAL_SERVER_ERROR)]
+    PoolError(#[from] diesel_async::pooled_connection::deadpool::PoolError),
+    #[error("internal server error")]
+    #[status(StatusCode::INTERNAL_SERVER_ERROR)]
+    DieselError(#[from] diesel::result::Error),
+    #[error("{0}")]
+    #[status("0")]
+    OpenFileError(#[from] OpenFileError),
+    #[error("file is not ready; file is `{0}`")]
+    #[status(StatusCode::CONFLICT)]
+    NotReady(String),
+    #[error("thumbnail is not available; thumbnails of the file are `{0}`")]
+    #[status(StatusCode::NOT_FOUND)]
+    ThumbnailNotAvailable(String),
+}
+
+/// A stored thumbnail, along with the tag of its rendering.
+pub struct Thumbnail {
+    pub file: File,
+    pub etag: ETag,
+}
+
+#[derive(Clone)]
+pub struct ThumbnailService {
+    db_pool: DBPool,
+    file_driver: FileDriver,
+}
+
+impl ThumbnailService {
+    pub fn new(db_pool: DBPool, file_driver: FileDriver) -> Self {
+        Self {
+            db_pool,
+            file_driver,
+        }
+    }
+
+    /// Finds the latest rendered thumbnail of a file; it stays available while the thumbnails are
+    /// rendered again.
+    pub async fn find_thumbnail(
+        &self,
+        path: FindFileThumbnailPathDto,
+        query: FindFileThumbnailQueryDto,
+    ) -> Result<Option<Thumbnail>, ThumbnailServiceError> {
+        let file_uuid = path.identifier;
+        let job = match self.find_job(file_uuid).await? {
+            Some(job) => job,
+            None => return Ok(None),
+        };
+        let job = match job {
+            Ok(job) => ThumbnailJobDto::from(job),
+            Err(status) => return Err(ThumbnailServiceError::NotReady(enum_name(&status))),
+        };
+
+        let generated_at = match job.generated_at {
+            Some(generated_at) if job.sizes.contains(&query.size) => generated_at,
+            _ => {
+                return Err(ThumbnailServiceError::ThumbnailNotAvailable(enum_name(
+                    &job.status,
+                )))
+            }
+        };
+
+        let file = self
+            .file_driver
+            .open_thumbnail(file_uuid, query.size)
+            .await?;
+
+        Ok(Some(Thumbnail {
+            file,
+            etag: ETag::from_updated_at(generated_at),
+        }))
+    }
+
+    pub async fn find_thumbnail_job(
+        &self,
+        path: FindFileThumbnailPathDto,
+    ) -> Result<Option<ThumbnailJobDto>, ThumbnailServiceError> {
+        match self.find_job(path.identifier).await? {
+            Some(Ok(job)) => Ok(Some(job.into())),
+            Some(Err(status)) => Err(ThumbnailServiceError::NotReady(enum_name(&status))),
+            None => Ok(None),
+        }
+    }
+
+    /// Queues the rendering of the thumbnails of a file again, such as after a failure or once
+    /// a missing tool is installed.
+    pub async fn generate_thumbnails(
+        &self,
+        path: GenerateFileThumbnailPathDto,
+    ) -> Result<Option<ThumbnailJobDto>, ThumbnailServiceError> {
+        let file_uuid = path.identifier;
+        let db_conn = &mut self.db_pool.get().await?;
+        let job = db_conn
+            .transaction(|db_conn| {
+                async move {
+                    let file = files::table
+                        .select((files::id, files::status))
+                        .filter(files::uuid.eq(file_uuid))
+                        .filter(files::deleted_at.is_null())
+                        .get_result::<(i32, FileStatusDto)>(db_conn)
+                        .await
+                        .optional()?;
+                    let file_id = match file {
+                        Some((file_id, FileStatusDto::Ready)) => file_id,
+                        Some((_, status)) => {
+                            return Err(ThumbnailServiceError::NotReady(enum_name(&status)))
+                        }
+                        None => return Ok(None),
+                    };
+
+                    request_thumbnails(db_conn, file_id, file_uuid).await?;
+
+                    let job = thumbnail_jobs::table
+                        .select(thumbnail_job_dto_columns())
+                        .filter(thumbnail_jobs::file_id.eq(file_id))
+                        .get_result::<RawThumbnailJobDto>(db_conn)
+                        .await?;
+
+                    Ok(Some(job))
+                }
+                .scope_boxed()
+            })
+            .await?;
+
+        Ok(job.map(|job| job.into()))
+    }
+
+    /// Finds the thumbnail job of a file that is not in the trash; the inner result holds the
+    /// status of the file instead if it is not ready yet.
+    async fn find_job(
+        &self,
+        file_uuid: Uuid,
+    ) -> Result<Option<Result<RawThumbnailJobDto, FileStatusDto>>, ThumbnailServiceError> {
+        let db_conn = &mut self.db_pool.get().await?;
+        let job = files::table
+            .left_join(thumbnail_jobs::table)
+            .select((files::status, thumbnail_job_dto_columns().nullable()))
+            .filter(files::uuid.eq(file_uuid))
+            .filter(files::deleted_at.is_null())
+            .get_result::<(FileStatusDto, Option<RawThumbnailJobDto>)>(db_conn)
+            .await
+            .optional()?;
+
+        Ok(job.map(|(status, job)| match (status, job) {
+            (FileStatusDto::Ready, Some(job)) => Ok(job),
+            (status, _) => Err(status),
+        }))
+    }
+}
+
+type ThumbnailJobDtoColumns = (
+    thumbnail_jobs::status,
+    thumbnail_jobs::sizes,
+    thumbnail_jobs::last_error,
+    thumbnail_jobs::requested_at,
+    thumbnail_jobs::generated_at,
+);
+
+fn thumbnail_job_dto_columns() -> ThumbnailJobDtoColumns {
+    (
+        thumbnail_jobs::status,
+        thumbnail_jobs::sizes,
+        thumbnail_jobs::last_error,
+        thumbnail_jobs::requested_at,
+        thumbnail_jobs::generated_at,
+    )
+}
+
+#[derive(Queryable, Debug)]
+struct RawThumbnailJobDto {
+    status: String,
+    sizes: Vec<String>,
+    last_error: Option<String>,
+    requested_at: NaiveDateTime,
+    generated_at: Option<NaiveDateTime>,
+}
+
+impl From<RawThumbnailJobDto> for ThumbnailJobDto {
+    fn from(job: RawThumbnailJobDto) -> Self {
+        Self {
+            status: parse_enum_name::<ThumbnailStatusDto>(job.status),
+            sizes: parse_sizes(job.sizes),
+            last_error: job.last_error,
+            requested_at: job.requested_at.and_utc(),
+            generated_at: job.generated_at.map(|generated_at| generated_at.and_utc()),
+        }
+    }
+}
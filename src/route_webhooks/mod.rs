@@ -0,0 +1,225 @@
+use crate::app_state::AppState;
+use axum::{
+    routing::{delete, get, post, put},
+    Router,
+};
+
+pub mod webhook_service;
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/webhooks", get(handlers::find_webhooks))
+        .route("/webhooks/:identifier", get(handlers::find_webhook))
+        .route("/webhooks", post(handlers::create_webhook))
+        .route("/webhooks/:identifier", put(handlers::update_webhook))
+        .route("/webhooks/:identifier", delete(handlers::remove_webhook))
+        .route(
+            "/webhooks/:identifier/deliveries",
+            get(handlers::find_webhook_deliveries),
+        )
+        .route(
+            "/webhooks/:identifier/deliveries/:delivery/redeliver",
+            post(handlers::redeliver_webhook_delivery),
+        )
+}
+
+pub mod handlers {
+    use super::webhook_service::{WebhookService, WebhookServiceError};
+    use crate::{
+        app_state::AppState,
+        schema::{
+            dto_in::{
+                CreateWebhookBodyDto, FindWebhookDeliveriesPathDto, FindWebhookDeliveriesQueryDto,
+                FindWebhookPathDto, RedeliverWebhookDeliveryPathDto, RemoveWebhookPathDto,
+                UpdateWebhookBodyDto, UpdateWebhookPathDto,
+            },
+            dto_out::WebhookDto,
+        },
+    };
+    use axum::{
+        debug_handler,
+        extract::{Path, Query, State},
+        http::StatusCode,
+        response::{IntoResponse, Response},
+        Json,
+    };
+
+    /// Finds all webhooks, oldest first.
+    #[utoipa::path(
+        get,
+        operation_id = "find-webhooks",
+        tag = "webhook",
+        path = "/webhooks",
+        responses(
+            (status = OK, body = Vec<WebhookDto>),
+            (status = INTERNAL_SERVER_ERROR, description = "an error has occurred", body = ErrorBody),
+        ),
+    )]
+    #[debug_handler(state = AppState)]
+    pub async fn find_webhooks(
+        State(webhook_service): State<WebhookService>,
+    ) -> Result<(StatusCode, Json<Vec<WebhookDto>>), WebhookServiceError> {
+        let result = webhook_service.find_webhooks().await?;
+
+        Ok((StatusCode::OK, Json(result)))
+    }
+
+    /// Find a webhook.
+    #[utoipa::path(
+        get,
+        operation_id = "find-webhook",
+        tag = "webhook",
+        path = "/webhooks/{identifier}",
+        params(
+            FindWebhookPathDto
+        ),
+        responses(
+            (status = OK, body = WebhookDto),
+            (status = NOT_FOUND, description = "the webhook does not exist"),
+            (status = INTERNAL_SERVER_ERROR, description = "an error has occurred", body = ErrorBody),
+        ),
+    )]
+    #[debug_handler(state = AppState)]
+    pub async fn find_webhook(
+        State(webhook_service): State<WebhookService>,
+        Path(path): Path<FindWebhookPathDto>,
+    ) -> Result<Response, WebhookServiceError> {
+        match webhook_service.find_webhook(path).await? {
+            Some(result) => Ok((StatusCode::OK, Json(result)).into_response()),
+            None => Ok(StatusCode::NOT_FOUND.into_response()),
+        }
+    }
+
+    /// Subscribe a new webhook to audited actions.
+    #[utoipa::path(
+        post,
+        operation_id = "create-webhook",
+        tag = "webhook",
+        path = "/webhooks",
+        request_body = CreateWebhookBodyDto,
+        responses(
+            (status = CREATED, body = WebhookDto),
+            (status = UNPROCESSABLE_ENTITY, description = "the url, secret or event types are invalid", body = ErrorBody),
+            (status = INTERNAL_SERVER_ERROR, description = "an error has occurred", body = ErrorBody),
+        ),
+    )]
+    #[debug_handler(state = AppState)]
+    pub async fn create_webhook(
+        State(webhook_service): State<WebhookService>,
+        Json(body): Json<CreateWebhookBodyDto>,
+    ) -> Result<(StatusCode, Json<WebhookDto>), WebhookServiceError> {
+        let result = webhook_service.create_webhook(body).await?;
+
+        Ok((StatusCode::CREATED, Json(result)))
+    }
+
+    /// Update a webhook; pending deliveries are sent with the new settings.
+    #[utoipa::path(
+        put,
+        operation_id = "update-webhook",
+        tag = "webhook",
+        path = "/webhooks/{identifier}",
+        params(
+            UpdateWebhookPathDto
+        ),
+        request_body = UpdateWebhookBodyDto,
+        responses(
+            (status = OK, body = WebhookDto),
+            (status = NOT_FOUND, description = "the webhook does not exist"),
+            (status = UNPROCESSABLE_ENTITY, description = "the url, secret or event types are invalid", body = ErrorBody),
+            (status = INTERNAL_SERVER_ERROR, description = "an error has occurred", body = ErrorBody),
+        ),
+    )]
+    #[debug_handler(state = AppState)]
+    pub async fn update_webhook(
+        State(webhook_service): State<WebhookService>,
+        Path(path): Path<UpdateWebhookPathDto>,
+        Json(body): Json<UpdateWebhookBodyDto>,
+    ) -> Result<Response, WebhookServiceError> {
+        match webhook_service.update_webhook(path, body).await? {
+            Some(result) => Ok((StatusCode::OK, Json(result)).into_response()),
+            None => Ok(StatusCode::NOT_FOUND.into_response()),
+        }
+    }
+
+    /// Remove a webhook, along with its deliveries.
+    #[utoipa::path(
+        delete,
+        operation_id = "remove-webhook",
+        tag = "webhook",
+        path = "/webhooks/{identifier}",
+        params(
+            RemoveWebhookPathDto
+        ),
+        responses(
+            (status = NO_CONTENT, description = "the webhook was removed"),
+            (status = NOT_FOUND, description = "the webhook does not exist"),
+            (status = INTERNAL_SERVER_ERROR, description = "an error has occurred", body = ErrorBody),
+        ),
+    )]
+    #[debug_handler(state = AppState)]
+    pub async fn remove_webhook(
+        State(webhook_service): State<WebhookService>,
+        Path(path): Path<RemoveWebhookPathDto>,
+    ) -> Result<StatusCode, WebhookServiceError> {
+        match webhook_service.remove_webhook(path).await? {
+            Some(()) => Ok(StatusCode::NO_CONTENT),
+            None => Ok(StatusCode::NOT_FOUND),
+        }
+    }
+
+    /// Finds deliveries of a webhook, most recent first by default.
+    #[utoipa::path(
+        get,
+        operation_id = "find-webhook-deliveries",
+        tag = "webhook",
+        path = "/webhooks/{identifier}/deliveries",
+        params(
+            FindWebhookDeliveriesPathDto,
+            FindWebhookDeliveriesQueryDto
+        ),
+        responses(
+            (status = OK, body = FindWebhookDeliveriesResultDto),
+            (status = NOT_FOUND, description = "the webhook does not exist"),
+            (status = UNPROCESSABLE_ENTITY, description = "the pagination parameters are invalid", body = ErrorBody),
+            (status = INTERNAL_SERVER_ERROR, description = "an error has occurred", body = ErrorBody),
+        ),
+    )]
+    #[debug_handler(state = AppState)]
+    pub async fn find_webhook_deliveries(
+        State(webhook_service): State<WebhookService>,
+        Path(path): Path<FindWebhookDeliveriesPathDto>,
+        Query(query): Query<FindWebhookDeliveriesQueryDto>,
+    ) -> Result<Response, WebhookServiceError> {
+        match webhook_service.find_webhook_deliveries(path, query).await? {
+            Some(result) => Ok((StatusCode::OK, Json(result)).into_response()),
+            None => Ok(StatusCode::NOT_FOUND.into_response()),
+        }
+    }
+
+    /// Send the payload of a past delivery again, as a new delivery.
+    #[utoipa::path(
+        post,
+        operation_id = "redeliver-webhook-delivery",
+        tag = "webhook",
+        path = "/webhooks/{identifier}/deliveries/{delivery}/redeliver",
+        params(
+            RedeliverWebhookDeliveryPathDto
+        ),
+        responses(
+            (status = ACCEPTED, description = "the new delivery was queued", body = WebhookDeliveryDto),
+            (status = NOT_FOUND, description = "the webhook or the delivery does not exist"),
+            (status = INTERNAL_SERVER_ERROR, description = "an error has occurred", body = ErrorBody),
+        ),
+    )]
+    #[debug_handler(state = AppState)]
+    pub async fn redeliver_webhook_delivery(
+        State(webhook_service): State<WebhookService>,
+        Path(path): Path<RedeliverWebhookDeliveryPathDto>,
+    ) -> Result<Response, WebhookServiceError> {
+        match webhook_service.redeliver_webhook_delivery(path).await? {
+            Some(result) => Ok((StatusCode::ACCEPTED, Json(result)).into_response()),
+            None => Ok(StatusCode::NOT_FOUND.into_response()),
+        }
+    }
+}
@@ -0,0 +1,458 @@
+use crate::{
+    audit::{enum_name, parse_enum_name},
+    db::{
+        schema::{webhook_deliveries, webhooks},
+        DBPool,
+    },
+    pagination::{BoxedPredicate, CursorCodec, CursorError, Keyset, PageRequest, PaginationError},
+    schema::{
+        dto_in::{
+            AuditActionDto, CreateWebhookBodyDto, FindWebhookDeliveriesPathDto,
+            FindWebhookDeliveriesQueryDto, FindWebhookPathDto, RedeliverWebhookDeliveryPathDto,
+            RemoveWebhookPathDto, UpdateWebhookBodyDto, UpdateWebhookPathDto,
+        },
+        dto_out::{
+            CursorPaginationMetadataDto, FindWebhookDeliveriesResultDto, WebhookDeliveryDto,
+            WebhookDto,
+        },
+    },
+};
+use axum::http::{StatusCode, Uri};
+use chrono::NaiveDateTime;
+use codegen::ErrorEnum;
+use diesel::{dsl::IntoBoxed, pg::Pg, prelude::*};
+use diesel_async::RunQueryDsl;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use uuid::Uuid;
+
+/// Minimum length of the secret of a webhook.
+const MIN_SECRET_LENGTH: usize = 16;
+
+#[derive(ErrorEnum, Error, Debug)]
+pub enum WebhookServiceError {
+    #[error("internal server error")]
+    #[status(StatusCode::INTERNAL_SERVER_ERROR)]
+    PoolError(#[from] diesel_async::pooled_connection::deadpool::PoolError),
+    #[error("internal server error")]
+    #[status(StatusCode::INTERNAL_SERVER_ERROR)]
+    DieselError(#[from] diesel::result::Error),
+    #[error("{0}")]
+    #[status("0")]
+    PaginationError(#[from] PaginationError),
+    #[error("{0}")]
+    #[status("0")]
+    CursorError(#[from] CursorError),
+    #[error("`{0}` is not an absolute http or https url")]
+    #[status(StatusCode::UNPROCESSABLE_ENTITY)]
+    InvalidUrl(String),
+    #[error("secret must have at least {} characters", MIN_SECRET_LENGTH)]
+    #[status(StatusCode::UNPROCESSABLE_ENTITY)]
+    SecretTooShort,
+    #[error("at least one event type is required")]
+    #[status(StatusCode::UNPROCESSABLE_ENTITY)]
+    NoEventTypes,
+}
+
+#[derive(Clone)]
+pub struct WebhookService {
+    db_pool: DBPool,
+    cursor_codec: CursorCodec,
+}
+
+impl WebhookService {
+    pub fn new(db_pool: DBPool, cursor_codec: CursorCodec) -> Self {
+        Self {
+            db_pool,
+            cursor_codec,
+        }
+    }
+
+    pub async fn find_webhooks(&self) -> Result<Vec<WebhookDto>, WebhookServiceError> {
+        let db_conn = &mut self.db_pool.get().await?;
+        let webhooks = webhooks::table
+            .select(webhook_columns())
+            .order((webhooks::created_at.asc(), webhooks::uuid.asc()))
+            .load::<RawWebhookDto>(db_conn)
+            .await?;
+
+        Ok(webhooks.into_iter().map(|webhook| webhook.into()).collect())
+    }
+
+    pub async fn find_webhook(
+        &self,
+        path: FindWebhookPathDto,
+    ) -> Result<Option<WebhookDto>, WebhookServiceError> {
+        let db_conn = &mut self.db_pool.get().await?;
+        let webhook = webhooks::table
+            .select(webhook_columns())
+            .filter(webhooks::uuid.eq(path.identifier))
+            .first::<RawWebhookDto>(db_conn)
+            .await
+            .optional()?;
+
+        Ok(webhook.map(|webhook| webhook.into()))
+    }
+
+    pub async fn create_webhook(
+        &self,
+        body: CreateWebhookBodyDto,
+    ) -> Result<WebhookDto, WebhookServiceError> {
+        let event_types = validate_webhook(&body.url, &body.secret, &body.event_types)?;
+
+        let db_conn = &mut self.db_pool.get().await?;
+        let webhook = diesel::insert_into(webhooks::table)
+            .values((
+                webhooks::url.eq(body.url),
+                webhooks::secret.eq(body.secret),
+                webhooks::event_types.eq(event_types),
+            ))
+            .returning(webhook_columns())
+            .get_result::<RawWebhookDto>(db_conn)
+            .await?;
+
+        Ok(webhook.into())
+    }
+
+    pub async fn update_webhook(
+        &self,
+        path: UpdateWebhookPathDto,
+        body: UpdateWebhookBodyDto,
+    ) -> Result<Option<WebhookDto>, WebhookServiceError> {
+        let event_types = validate_webhook(&body.url, &body.secret, &body.event_types)?;
+
+        let db_conn = &mut self.db_pool.get().await?;
+        let webhook = diesel::update(webhooks::table)
+            .filter(webhooks::uuid.eq(path.identifier))
+            .set((
+                webhooks::url.eq(body.url),
+                webhooks::secret.eq(body.secret),
+                webhooks::event_types.eq(event_types),
+            ))
+            .returning(webhook_columns())
+            .get_result::<RawWebhookDto>(db_conn)
+            .await
+            .optional()?;
+
+        Ok(webhook.map(|webhook| webhook.into()))
+    }
+
+    /// Removes a webhook, along with its deliveries.
+    pub async fn remove_webhook(
+        &self,
+        path: RemoveWebhookPathDto,
+    ) -> Result<Option<()>, WebhookServiceError> {
+        let db_conn = &mut self.db_pool.get().await?;
+        let count = diesel::delete(webhooks::table)
+            .filter(webhooks::uuid.eq(path.identifier))
+            .execute(db_conn)
+            .await?;
+
+        Ok((count != 0).then_some(()))
+    }
+
+    pub async fn find_webhook_deliveries(
+        &self,
+        path: FindWebhookDeliveriesPathDto,
+        query: FindWebhookDeliveriesQueryDto,
+    ) -> Result<Option<FindWebhookDeliveriesResultDto>, WebhookServiceError> {
+        let first = query
+            .first_cursor
+            .as_deref()
+            .map(|cursor| self.cursor_codec.decode::<WebhookDeliveryKey>(cursor))
+            .transpose()?;
+        let last = query
+            .last_cursor
+            .as_deref()
+            .map(|cursor| self.cursor_codec.decode::<WebhookDeliveryKey>(cursor))
+            .transpose()?;
+        let request = PageRequest::new(first, last, (), query.order, query.page_size)?;
+
+        let db_conn = &mut self.db_pool.get().await?;
+        let webhook_id = match find_webhook_id(db_conn, path.identifier).await? {
+            Some(webhook_id) => webhook_id,
+            None => return Ok(None),
+        };
+        let status = query.status.map(|status| enum_name(&status));
+
+        let matching = || {
+            let mut select = webhook_deliveries::table
+                .filter(webhook_deliveries::webhook_id.eq(webhook_id))
+                .into_boxed();
+
+            if let Some(status) = &status {
+                select = select.filter(webhook_deliveries::status.eq(status));
+            }
+
+            select
+        };
+        let rows = request
+            .select(&matching, delivery_columns())
+            .load::<RawWebhookDeliveryDto>(db_conn)
+            .await?;
+        let has_behind = match request.behind(&matching) {
+            Some(behind) => behind.get_result(db_conn).await?,
+            None => false,
+        };
+        let page = request.page(rows, has_behind);
+
+        let pagination = CursorPaginationMetadataDto {
+            has_prev: page.pagination.has_prev,
+            has_next: page.pagination.has_next,
+            first_cursor: page
+                .items
+                .as_slice()
+                .first()
+                .map(|item| self.cursor_codec.encode(&WebhookDeliveryKey::new(item, ()))),
+            last_cursor: page
+                .items
+                .as_slice()
+                .last()
+                .map(|item| self.cursor_codec.encode(&WebhookDeliveryKey::new(item, ()))),
+        };
+        let items = page
+            .items
+            .into_iter()
+            .map(|raw_item| raw_item.into())
+            .collect();
+
+        Ok(Some(FindWebhookDeliveriesResultDto { pagination, items }))
+    }
+
+    /// Queues a new delivery of the payload of a past delivery, attempted as soon as possible.
+    pub async fn redeliver_webhook_delivery(
+        &self,
+        path: RedeliverWebhookDeliveryPathDto,
+    ) -> Result<Option<WebhookDeliveryDto>, WebhookServiceError> {
+        let db_conn = &mut self.db_pool.get().await?;
+        let delivery = webhook_deliveries::table
+            .inner_join(webhooks::table)
+            .select((
+                webhook_deliveries::webhook_id,
+                webhook_deliveries::event_type,
+                webhook_deliveries::payload,
+            ))
+            .filter(webhooks::uuid.eq(path.identifier))
+            .filter(webhook_deliveries::uuid.eq(path.delivery))
+            .first::<(i32, String, serde_json::Value)>(db_conn)
+            .await
+            .optional()?;
+        let (webhook_id, event_type, payload) = match delivery {
+            Some(delivery) => delivery,
+            None => return Ok(None),
+        };
+
+        let delivery = diesel::insert_into(webhook_deliveries::table)
+            .values((
+                webhook_deliveries::webhook_id.eq(webhook_id),
+                webhook_deliveries::event_type.eq(event_type),
+                webhook_deliveries::payload.eq(payload),
+            ))
+            .returning(delivery_columns())
+            .get_result::<RawWebhookDeliveryDto>(db_conn)
+            .await?;
+
+        Ok(Some(delivery.into()))
+    }
+}
+
+/// Validates the settings of a webhook, and returns its event types as stored.
+fn validate_webhook(
+    url: &str,
+    secret: &str,
+    event_types: &[AuditActionDto],
+) -> Result<Vec<String>, WebhookServiceError> {
+    let is_valid_url = url.parse::<Uri>().is_ok_and(|uri| {
+        matches!(uri.scheme_str(), Some("http") | Some("https")) && uri.host().is_some()
+    });
+    if !is_valid_url {
+        return Err(WebhookServiceError::InvalidUrl(url.to_owned()));
+    }
+
+    if secret.chars().count() < MIN_SECRET_LENGTH {
+        return Err(WebhookServiceError::SecretTooShort);
+    }
+
+    if event_types.is_empty() {
+        return Err(WebhookServiceError::NoEventTypes);
+    }
+
+    let mut event_types = event_types.iter().map(enum_name).collect::<Vec<_>>();
+    event_types.sort();
+    event_types.dedup();
+
+    Ok(event_types)
+}
+
+async fn find_webhook_id(
+    db_conn: &mut diesel_async::AsyncPgConnection,
+    uuid: Uuid,
+) -> QueryResult<Option<i32>> {
+    webhooks::table
+        .select(webhooks::id)
+        .filter(webhooks::uuid.eq(uuid))
+        .first::<i32>(db_conn)
+        .await
+        .optional()
+}
+
+/// Position of a delivery in the log of a webhook, which is ordered by creation time.
+#[derive(Serialize, Deserialize, Debug)]
+struct WebhookDeliveryKey(NaiveDateTime, Uuid);
+
+impl Keyset for WebhookDeliveryKey {
+    type Table = webhook_deliveries::table;
+    type Sort = ();
+    type Row = RawWebhookDeliveryDto;
+
+    fn new(row: &RawWebhookDeliveryDto, _sort: ()) -> Self {
+        Self(row.created_at, row.uuid)
+    }
+
+    fn sort(&self) {}
+
+    fn beyond(&self, ascending: bool) -> BoxedPredicate<'_, webhook_deliveries::table> {
+        let Self(created_at, uuid) = self;
+
+        if ascending {
+            Box::new(
+                webhook_deliveries::created_at
+                    .gt(created_at)
+                    .or(webhook_deliveries::created_at
+                        .eq(created_at)
+                        .and(webhook_deliveries::uuid.gt(uuid))),
+            )
+        } else {
+            Box::new(
+                webhook_deliveries::created_at
+                    .lt(created_at)
+                    .or(webhook_deliveries::created_at
+                        .eq(created_at)
+                        .and(webhook_deliveries::uuid.lt(uuid))),
+            )
+        }
+    }
+
+    fn order(
+        query: IntoBoxed<'_, webhook_deliveries::table, Pg>,
+        _sort: (),
+        ascending: bool,
+    ) -> IntoBoxed<'_, webhook_deliveries::table, Pg> {
+        if ascending {
+            query.order((
+                webhook_deliveries::created_at.asc(),
+                webhook_deliveries::uuid.asc(),
+            ))
+        } else {
+            query.order((
+                webhook_deliveries::created_at.desc(),
+                webhook_deliveries::uuid.desc(),
+            ))
+        }
+    }
+}
+
+type WebhookColumns = (
+    webhooks::uuid,
+    webhooks::url,
+    webhooks::event_types,
+    webhooks::created_at,
+    webhooks::updated_at,
+);
+
+/// Returns the columns of a webhook; the secret is never read back.
+fn webhook_columns() -> WebhookColumns {
+    (
+        webhooks::uuid,
+        webhooks::url,
+        webhooks::event_types,
+        webhooks::created_at,
+        webhooks::updated_at,
+    )
+}
+
+#[derive(Queryable, Debug)]
+struct RawWebhookDto {
+    uuid: Uuid,
+    url: String,
+    event_types: Vec<String>,
+    created_at: NaiveDateTime,
+    updated_at: NaiveDateTime,
+}
+
+impl From<RawWebhookDto> for WebhookDto {
+    fn from(item: RawWebhookDto) -> Self {
+        Self {
+            uuid: item.uuid,
+            url: item.url,
+            event_types: item.event_types.into_iter().map(parse_enum_name).collect(),
+            created_at: item.created_at.and_utc(),
+            updated_at: item.updated_at.and_utc(),
+        }
+    }
+}
+
+type DeliveryColumns = (
+    webhook_deliveries::uuid,
+    webhook_deliveries::event_type,
+    webhook_deliveries::payload,
+    webhook_deliveries::status,
+    webhook_deliveries::attempt_count,
+    webhook_deliveries::next_attempt_at,
+    webhook_deliveries::last_attempt_at,
+    webhook_deliveries::response_status,
+    webhook_deliveries::last_error,
+    webhook_deliveries::created_at,
+);
+
+fn delivery_columns() -> DeliveryColumns {
+    (
+        webhook_deliveries::uuid,
+        webhook_deliveries::event_type,
+        webhook_deliveries::payload,
+        webhook_deliveries::status,
+        webhook_deliveries::attempt_count,
+        webhook_deliveries::next_attempt_at,
+        webhook_deliveries::last_attempt_at,
+        webhook_deliveries::response_status,
+        webhook_deliveries::last_error,
+        webhook_deliveries::created_at,
+    )
+}
+
+#[derive(Queryable, Debug)]
+struct RawWebhookDeliveryDto {
+    uuid: Uuid,
+    event_type: String,
+    payload: serde_json::Value,
+    status: String,
+    attempt_count: i32,
+    next_attempt_at: Option<NaiveDateTime>,
+    last_attempt_at: Option<NaiveDateTime>,
+    response_status: Option<i32>,
+    last_error: Option<String>,
+    created_at: NaiveDateTime,
+}
+
+impl From<RawWebhookDeliveryDto> for WebhookDeliveryDto {
+    fn from(item: RawWebhookDeliveryDto) -> Self {
+        Self {
+            uuid: item.uuid,
+            event_type: parse_enum_name(item.event_type),
+            // Payloads are serialized audit events.
+            payload: serde_json::from_value(item.payload)
+                .expect("failed to deserialize webhook payload"),
+            status: parse_enum_name(item.status),
+            attempt_count: item.attempt_count,
+            next_attempt_at: item
+                .next_attempt_at
+                .map(|next_attempt_at| next_attempt_at.and_utc()),
+            last_attempt_at: item
+                .last_attempt_at
+                .map(|last_attempt_at| last_attempt_at.and_utc()),
+            response_status: item.response_status,
+            last_error: item.last_error,
+            created_at: item.created_at.and_utc(),
+        }
+    }
+}
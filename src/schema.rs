@@ -1,2 +1,3 @@
 pub mod dto_in;
 pub mod dto_out;
+pub mod merge_patch;
@@ -1,4 +1,6 @@
-use serde::Deserialize;
+use chrono::{DateTime, Utc};
+use diesel_derive_enum::DbEnum;
+use serde::{Deserialize, Serialize};
 use utoipa::{IntoParams, ToSchema};
 use uuid::Uuid;
 
@@ -6,7 +8,11 @@ fn default_page_size() -> u64 {
     25
 }
 
-#[derive(Deserialize, ToSchema, Debug, Clone, PartialEq, Eq, Hash)]
+fn default_file_status() -> FileStatusDto {
+    FileStatusDto::Ready
+}
+
+#[derive(Deserialize, ToSchema, Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[serde(rename_all = "camelCase")]
 pub enum PaginationOrderDto {
     Asc,
@@ -19,22 +25,72 @@ impl Default for PaginationOrderDto {
     }
 }
 
+#[derive(Deserialize, ToSchema, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub enum CollectionSortDto {
+    Name,
+    CreatedAt,
+    FileCount,
+}
+
+impl Default for CollectionSortDto {
+    fn default() -> Self {
+        Self::CreatedAt
+    }
+}
+
+#[derive(Deserialize, ToSchema, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub enum NameMatchDto {
+    Contains,
+    Prefix,
+}
+
+impl Default for NameMatchDto {
+    fn default() -> Self {
+        Self::Contains
+    }
+}
+
 #[derive(Deserialize, IntoParams, Debug, Clone, PartialEq, Eq, Hash)]
 #[serde(rename_all = "camelCase")]
 #[into_params(parameter_in = Query)]
 pub struct FindCollectionsQueryDto {
-    #[into_params(example = "1")]
-    pub first_id: Option<i32>,
-    #[into_params(example = "1")]
-    pub last_id: Option<i32>,
+    /// Cursor of the first item of the current page; the previous page is returned.
+    pub first_cursor: Option<String>,
+    /// Cursor of the last item of the current page; the next page is returned.
+    pub last_cursor: Option<String>,
+    #[into_params(example = "name", default = "createdAt")]
+    #[serde(default)]
+    pub sort: CollectionSortDto,
     #[into_params(example = "desc", default = "desc")]
     #[serde(default)]
     pub order: PaginationOrderDto,
     #[into_params(example = "25", default = "25", minimum = 1, maximum = 100)]
     #[serde(default = "default_page_size")]
     pub page_size: u64,
-    #[into_params(example = "Movies")]
+    /// Case-insensitive search in the name of the collections.
+    #[into_params(example = "movie")]
     pub filter_name: Option<String>,
+    /// How `filterName` matches the name.
+    #[into_params(example = "prefix", default = "contains")]
+    #[serde(default)]
+    pub name_match: NameMatchDto,
+    /// Case-insensitive search in the description of the collections.
+    #[into_params(example = "i like")]
+    pub filter_description: Option<String>,
+    /// Only collections created at or after this time are returned.
+    #[into_params(example = "2024-01-01T00:00:00Z")]
+    pub created_after: Option<DateTime<Utc>>,
+    /// Only collections created before this time are returned.
+    #[into_params(example = "2025-01-01T00:00:00Z")]
+    pub created_before: Option<DateTime<Utc>>,
+    /// Only the direct children of this collection are returned.
+    #[into_params(example = "550e8400-e29b-41d4-a716-446655440000")]
+    pub parent_uuid: Option<Uuid>,
+    /// Only the descendants of this collection, at any depth, are returned.
+    #[into_params(example = "550e8400-e29b-41d4-a716-446655440000")]
+    pub ancestor_uuid: Option<Uuid>,
 }
 
 #[derive(Deserialize, IntoParams, Debug, Clone, PartialEq, Eq, Hash)]
@@ -52,29 +108,843 @@ pub struct CreateCollectionBodyDto {
     pub name: String,
     #[schema(example = "Movies I like.")]
     pub description: Option<String>,
+    #[schema(example = "550e8400-e29b-41d4-a716-446655440000")]
+    pub parent_uuid: Option<Uuid>,
+    /// Makes the collection smart: its files are the ones matched by this search.
+    pub smart_query: Option<FindFilesBodyDto>,
 }
 
 #[derive(Deserialize, IntoParams, Debug, Clone, PartialEq, Eq, Hash)]
 #[serde(rename_all = "camelCase")]
 #[into_params(parameter_in = Path)]
 pub struct RemoveCollectionPathDto {
-    #[into_params(example = "1")]
-    pub identifier: i32,
+    #[into_params(example = "550e8400-e29b-41d4-a716-446655440000")]
+    pub identifier: Uuid,
+}
+
+#[derive(Deserialize, IntoParams, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Path)]
+pub struct RestoreCollectionPathDto {
+    #[into_params(example = "550e8400-e29b-41d4-a716-446655440000")]
+    pub identifier: Uuid,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub enum RemoveCollectionModeDto {
+    /// Refuses to remove a collection that still has files.
+    Refuse,
+    /// Moves the collection to the trash along with its files, which stay in other collections.
+    Detach,
+    /// Like `detach`, but also moves the files that belong to no other live collection to the
+    /// trash.
+    DeleteOrphans,
+}
+
+impl Default for RemoveCollectionModeDto {
+    fn default() -> Self {
+        Self::Refuse
+    }
+}
+
+#[derive(Deserialize, IntoParams, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Query)]
+pub struct RemoveCollectionQueryDto {
+    #[into_params(example = "detach", default = "refuse")]
+    #[serde(default)]
+    pub mode: RemoveCollectionModeDto,
 }
 
 #[derive(Deserialize, IntoParams, Debug, Clone, PartialEq, Eq, Hash)]
 #[serde(rename_all = "camelCase")]
 #[into_params(parameter_in = Path)]
 pub struct UpdateCollectionPathDto {
-    #[into_params(example = "1")]
-    pub identifier: i32,
+    #[into_params(example = "550e8400-e29b-41d4-a716-446655440000")]
+    pub identifier: Uuid,
 }
 
-#[derive(Deserialize, ToSchema, Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Serialize, Deserialize, ToSchema, Debug, Clone, PartialEq, Eq, Hash)]
 #[serde(rename_all = "camelCase")]
 pub struct UpdateCollectionBodyDto {
     #[schema(example = "Movies")]
     pub name: String,
     #[schema(example = "Movies I like.")]
     pub description: Option<String>,
+    /// The search of a smart collection; required for smart collections, and forbidden otherwise.
+    pub smart_query: Option<FindFilesBodyDto>,
+}
+
+#[derive(Deserialize, IntoParams, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Path)]
+pub struct PatchCollectionPathDto {
+    #[into_params(example = "550e8400-e29b-41d4-a716-446655440000")]
+    pub identifier: Uuid,
+}
+
+/// A JSON Merge Patch (RFC 7396) of a collection.
+///
+/// Absent members are left untouched and `null` members are cleared; `smartQuery` is merged
+/// member by member.
+#[derive(Deserialize, ToSchema, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub struct PatchCollectionBodyDto {
+    #[schema(example = "Movies")]
+    pub name: Option<String>,
+    #[schema(example = "Movies I like.", nullable)]
+    pub description: Option<String>,
+    #[schema(nullable)]
+    pub smart_query: Option<FindFilesBodyDto>,
+}
+
+#[derive(Deserialize, IntoParams, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Path)]
+pub struct MoveCollectionPathDto {
+    #[into_params(example = "550e8400-e29b-41d4-a716-446655440000")]
+    pub identifier: Uuid,
+}
+
+#[derive(Deserialize, ToSchema, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub struct MoveCollectionBodyDto {
+    /// The new parent of the collection; the collection becomes a root if absent.
+    #[schema(example = "550e8400-e29b-41d4-a716-446655440000")]
+    pub parent_uuid: Option<Uuid>,
+}
+
+#[derive(Deserialize, IntoParams, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Path)]
+pub struct FindCollectionFilesPathDto {
+    #[into_params(example = "550e8400-e29b-41d4-a716-446655440000")]
+    pub identifier: Uuid,
+}
+
+#[derive(Deserialize, IntoParams, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Query)]
+pub struct FindCollectionFilesQueryDto {
+    /// Whether the files of the descendants of the collection are returned too; ignored for smart
+    /// collections.
+    #[into_params(example = "true", default = "false")]
+    #[serde(default)]
+    pub recursive: bool,
+}
+
+#[derive(Deserialize, IntoParams, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Path)]
+pub struct AddCollectionFilePathDto {
+    #[into_params(example = "550e8400-e29b-41d4-a716-446655440000")]
+    pub identifier: Uuid,
+    #[into_params(example = "550e8400-e29b-41d4-a716-446655440000")]
+    pub file: Uuid,
+}
+
+#[derive(Deserialize, IntoParams, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Path)]
+pub struct RemoveCollectionFilePathDto {
+    #[into_params(example = "550e8400-e29b-41d4-a716-446655440000")]
+    pub identifier: Uuid,
+    #[into_params(example = "550e8400-e29b-41d4-a716-446655440000")]
+    pub file: Uuid,
+}
+
+#[derive(Deserialize, IntoParams, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Path)]
+pub struct ExportCollectionPathDto {
+    #[into_params(example = "550e8400-e29b-41d4-a716-446655440000")]
+    pub identifier: Uuid,
+}
+
+#[derive(Deserialize, IntoParams, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Query)]
+pub struct ImportCollectionQueryDto {
+    /// Parent of the recreated collection; it is created at the root if omitted.
+    #[into_params(example = "550e8400-e29b-41d4-a716-446655440000")]
+    pub parent_uuid: Option<Uuid>,
+}
+
+#[derive(Deserialize, ToSchema, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub enum FileSortDto {
+    Name,
+    Size,
+    UploadedAt,
+}
+
+impl Default for FileSortDto {
+    fn default() -> Self {
+        Self::UploadedAt
+    }
+}
+
+/// Stage of a file in its lifecycle, from its creation to its content being usable.
+#[derive(Serialize, Deserialize, ToSchema, DbEnum, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+#[ExistingTypePath = "crate::db::schema::sql_types::FileStatus"]
+pub enum FileStatusDto {
+    /// The file was created, and no content was uploaded yet.
+    Prepared,
+    /// Content is being uploaded; an interrupted upload can be resumed from its size.
+    Uploading,
+    /// The content is uploaded, and waits to be hashed and inspected; its type, hash and system
+    /// tags are absent.
+    Processing,
+    /// The content is uploaded and processed.
+    Ready,
+    /// The last upload or processing of the content failed; see `failureReason`.
+    Failed,
+}
+
+#[derive(Deserialize, IntoParams, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Query)]
+pub struct FindFilesQueryDto {
+    /// Cursor of the first item of the current page; the previous page is returned.
+    pub first_cursor: Option<String>,
+    /// Cursor of the last item of the current page; the next page is returned.
+    pub last_cursor: Option<String>,
+    #[into_params(example = "uploadedAt", default = "uploadedAt")]
+    #[serde(default)]
+    pub sort: FileSortDto,
+    #[into_params(example = "desc", default = "desc")]
+    #[serde(default)]
+    pub order: PaginationOrderDto,
+    #[into_params(example = "25", default = "25", minimum = 1, maximum = 100)]
+    #[serde(default = "default_page_size")]
+    pub page_size: u64,
+    /// Only files in this stage are returned.
+    #[into_params(example = "ready", default = "ready")]
+    #[serde(default = "default_file_status")]
+    pub status: FileStatusDto,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub struct FindFilesBodyDto {
+    #[schema(example = "john wick")]
+    pub query: Option<String>,
+    pub tags: Option<Vec<FindFilesTagFilterDto>>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub struct FindFilesTagFilterDto {
+    #[schema(example = "Author")]
+    pub title: String,
+    pub value: Option<FindFilesTagValueFilterDto>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub struct FindFilesTagValueFilterDto {
+    #[schema(example = "John Doe")]
+    pub equal: Option<String>,
+    #[schema(example = "John Doe")]
+    pub not_equal: Option<String>,
+    #[schema(example = "John")]
+    pub contains: Option<String>,
+    pub one_of: Option<Vec<String>>,
+}
+
+#[derive(Deserialize, ToSchema, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateFileBodyDto {
+    #[schema(example = "Foo.txt")]
+    pub name: String,
+    #[serde(default)]
+    pub tags: Vec<CreateFileTagDto>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateFileTagDto {
+    #[schema(example = "Author")]
+    pub title: String,
+    #[schema(example = "John Doe")]
+    pub value: Option<String>,
+}
+
+#[derive(Deserialize, IntoParams, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Path)]
+pub struct RemoveFilePathDto {
+    #[into_params(example = "550e8400-e29b-41d4-a716-446655440000")]
+    pub identifier: Uuid,
+}
+
+#[derive(Deserialize, IntoParams, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Path)]
+pub struct RestoreFilePathDto {
+    #[into_params(example = "550e8400-e29b-41d4-a716-446655440000")]
+    pub identifier: Uuid,
+}
+
+#[derive(Deserialize, IntoParams, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Query)]
+pub struct FindTrashQueryDto {
+    /// Cursor of the first item of the current page; the previous page is returned.
+    pub first_cursor: Option<String>,
+    /// Cursor of the last item of the current page; the next page is returned.
+    pub last_cursor: Option<String>,
+    /// Order of deletion time.
+    #[into_params(example = "desc", default = "desc")]
+    #[serde(default)]
+    pub order: PaginationOrderDto,
+    #[into_params(example = "25", default = "25", minimum = 1, maximum = 100)]
+    #[serde(default = "default_page_size")]
+    pub page_size: u64,
+}
+
+#[derive(Deserialize, IntoParams, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Path)]
+pub struct PatchFilePathDto {
+    #[into_params(example = "550e8400-e29b-41d4-a716-446655440000")]
+    pub identifier: Uuid,
+}
+
+/// A JSON Merge Patch (RFC 7396) of the metadata of a file.
+#[derive(Deserialize, ToSchema, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub struct PatchFileBodyDto {
+    #[schema(example = "Foo.txt")]
+    pub name: Option<String>,
+}
+
+#[derive(Deserialize, IntoParams, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Path)]
+pub struct UploadFilePathDto {
+    #[into_params(example = "550e8400-e29b-41d4-a716-446655440000")]
+    pub identifier: Uuid,
+}
+
+#[derive(Deserialize, IntoParams, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Query)]
+pub struct UploadFileQueryDto {
+    #[into_params(example = "0")]
+    pub offset: Option<u64>,
+}
+
+/// Size of a thumbnail, as the largest width or height it fits in.
+#[derive(Serialize, Deserialize, ToSchema, Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub enum ThumbnailSizeDto {
+    /// 128 pixels.
+    Small,
+    /// 512 pixels.
+    #[default]
+    Medium,
+    /// 1024 pixels.
+    Large,
+}
+
+#[derive(Deserialize, IntoParams, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Path)]
+pub struct FindFileThumbnailPathDto {
+    #[into_params(example = "550e8400-e29b-41d4-a716-446655440000")]
+    pub identifier: Uuid,
+}
+
+#[derive(Deserialize, IntoParams, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Query)]
+pub struct FindFileThumbnailQueryDto {
+    #[into_params(example = "small", default = "medium")]
+    #[serde(default)]
+    pub size: ThumbnailSizeDto,
+}
+
+#[derive(Deserialize, IntoParams, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Path)]
+pub struct GenerateFileThumbnailPathDto {
+    #[into_params(example = "550e8400-e29b-41d4-a716-446655440000")]
+    pub identifier: Uuid,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub enum AuditActionDto {
+    CreateCollection,
+    UpdateCollection,
+    MoveCollection,
+    RemoveCollection,
+    RestoreCollection,
+    PurgeCollection,
+    AddCollectionFile,
+    RemoveCollectionFile,
+    CreateFile,
+    UploadFile,
+    /// The uploaded content of a file was processed in the background.
+    ProcessFile,
+    UpdateFile,
+    RemoveFile,
+    RestoreFile,
+    PurgeFile,
+    /// An upload that was never completed was abandoned, and its file removed.
+    ExpireFile,
+    /// The upload of a file was interrupted.
+    FailFile,
+    /// The stored content of a file was found missing or corrupted.
+    CorruptFile,
+    /// A file was brought back to the content of one of its versions.
+    RestoreFileVersion,
+    /// Versions of a file were removed by a retention policy.
+    PruneFileVersions,
+    /// The limits of the storage quota of a collection were set.
+    UpdateCollectionQuota,
+    /// The upload policy of a collection was set.
+    UpdateCollectionUploadPolicy,
+    /// The limits of the storage quota of a user, or of anonymous users, were set.
+    UpdateUserQuota,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub enum AuditResourceDto {
+    Collection,
+    File,
+    UserQuota,
+}
+
+#[derive(Deserialize, IntoParams, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Query)]
+pub struct FindAuditEventsQueryDto {
+    /// Cursor of the first item of the current page; the previous page is returned.
+    pub first_cursor: Option<String>,
+    /// Cursor of the last item of the current page; the next page is returned.
+    pub last_cursor: Option<String>,
+    /// Order of occurrence time.
+    #[into_params(example = "desc", default = "desc")]
+    #[serde(default)]
+    pub order: PaginationOrderDto,
+    #[into_params(example = "25", default = "25", minimum = 1, maximum = 100)]
+    #[serde(default = "default_page_size")]
+    pub page_size: u64,
+    #[into_params(example = "removeCollection")]
+    pub action: Option<AuditActionDto>,
+    #[into_params(example = "collection")]
+    pub resource_type: Option<AuditResourceDto>,
+    #[into_params(example = "550e8400-e29b-41d4-a716-446655440000")]
+    pub resource_uuid: Option<Uuid>,
+    #[into_params(example = "c0ffee00-0000-4000-8000-000000000000")]
+    pub request_id: Option<String>,
+    #[into_params(example = "alice")]
+    pub actor: Option<String>,
+    /// Only events that occurred at or after this time are returned.
+    #[into_params(example = "2024-01-01T00:00:00Z")]
+    pub occurred_after: Option<DateTime<Utc>>,
+    /// Only events that occurred before this time are returned.
+    #[into_params(example = "2025-01-01T00:00:00Z")]
+    pub occurred_before: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub enum ChangeResourceDto {
+    Collection,
+    File,
+    Tag,
+}
+
+/// Filters of a subscription to change events; an event is sent when it matches all of them.
+#[derive(Deserialize, IntoParams, Debug, Clone, Default, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Query)]
+pub struct SubscribeChangesQueryDto {
+    /// Only changes of this kind of resource are sent.
+    #[into_params(example = "collection")]
+    pub resource: Option<ChangeResourceDto>,
+    /// Only changes of this collection and of its direct sub-collections are sent, including files
+    /// being added to or removed from it, and changes of the files it holds and of their tags.
+    #[into_params(example = "550e8400-e29b-41d4-a716-446655440000")]
+    pub collection_uuid: Option<Uuid>,
+    /// Only changes of tags with this title are sent.
+    #[into_params(example = "artist")]
+    pub tag: Option<String>,
+}
+
+#[derive(Deserialize, ToSchema, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateWebhookBodyDto {
+    /// HTTP(S) URL to which events are posted.
+    #[schema(example = "https://example.com/hooks/poly-tag")]
+    pub url: String,
+    /// Key of the HMAC-SHA256 signature of deliveries; at least 16 characters.
+    #[schema(example = "5f2b6c0e9d8a4f1b")]
+    pub secret: String,
+    /// Audited actions that trigger a delivery.
+    pub event_types: Vec<AuditActionDto>,
+}
+
+#[derive(Deserialize, IntoParams, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Path)]
+pub struct FindWebhookPathDto {
+    #[into_params(example = "550e8400-e29b-41d4-a716-446655440000")]
+    pub identifier: Uuid,
+}
+
+#[derive(Deserialize, IntoParams, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Path)]
+pub struct UpdateWebhookPathDto {
+    #[into_params(example = "550e8400-e29b-41d4-a716-446655440000")]
+    pub identifier: Uuid,
+}
+
+#[derive(Deserialize, ToSchema, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateWebhookBodyDto {
+    /// HTTP(S) URL to which events are posted.
+    #[schema(example = "https://example.com/hooks/poly-tag")]
+    pub url: String,
+    /// Key of the HMAC-SHA256 signature of deliveries; at least 16 characters.
+    #[schema(example = "5f2b6c0e9d8a4f1b")]
+    pub secret: String,
+    /// Audited actions that trigger a delivery.
+    pub event_types: Vec<AuditActionDto>,
+}
+
+#[derive(Deserialize, IntoParams, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Path)]
+pub struct RemoveWebhookPathDto {
+    #[into_params(example = "550e8400-e29b-41d4-a716-446655440000")]
+    pub identifier: Uuid,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub enum WebhookDeliveryStatusDto {
+    /// The delivery is waiting for its next attempt.
+    Pending,
+    Succeeded,
+    /// Every attempt of the delivery failed.
+    Failed,
+}
+
+#[derive(Deserialize, IntoParams, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Path)]
+pub struct FindWebhookDeliveriesPathDto {
+    #[into_params(example = "550e8400-e29b-41d4-a716-446655440000")]
+    pub identifier: Uuid,
+}
+
+#[derive(Deserialize, IntoParams, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Query)]
+pub struct FindWebhookDeliveriesQueryDto {
+    /// Cursor of the first item of the current page; the previous page is returned.
+    pub first_cursor: Option<String>,
+    /// Cursor of the last item of the current page; the next page is returned.
+    pub last_cursor: Option<String>,
+    /// Order of creation time.
+    #[into_params(example = "desc", default = "desc")]
+    #[serde(default)]
+    pub order: PaginationOrderDto,
+    #[into_params(example = "25", default = "25", minimum = 1, maximum = 100)]
+    #[serde(default = "default_page_size")]
+    pub page_size: u64,
+    #[into_params(example = "failed")]
+    pub status: Option<WebhookDeliveryStatusDto>,
+}
+
+#[derive(Deserialize, IntoParams, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Path)]
+pub struct RedeliverWebhookDeliveryPathDto {
+    #[into_params(example = "550e8400-e29b-41d4-a716-446655440000")]
+    pub identifier: Uuid,
+    #[into_params(example = "550e8400-e29b-41d4-a716-446655440000")]
+    pub delivery: Uuid,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub enum ImportModeDto {
+    /// Files are copied into the storage.
+    #[default]
+    Copy,
+    /// Files are hard-linked into the storage, which must be on the same file system.
+    HardLink,
+}
+
+#[derive(Deserialize, ToSchema, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateImportBodyDto {
+    /// Directory to import, on the server; it must be inside `IMPORT_ROOT`.
+    #[schema(example = "/srv/music")]
+    pub root: String,
+    /// Rules deriving tags from the path of a file relative to the root; the first matching rule
+    /// applies.
+    ///
+    /// A rule is a `/`-separated list of segments: `{title}` tags the file with the path segment
+    /// at its position, `*` matches any segment, a trailing `...` matches any remaining segments,
+    /// and any other segment must match literally.
+    #[schema(example = json!(["{artist}/{album}/..."]))]
+    #[serde(default)]
+    pub rules: Vec<String>,
+    #[serde(default)]
+    pub mode: ImportModeDto,
+}
+
+#[derive(Deserialize, IntoParams, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Path)]
+pub struct FindImportPathDto {
+    #[into_params(example = "550e8400-e29b-41d4-a716-446655440000")]
+    pub identifier: Uuid,
+}
+
+#[derive(Deserialize, IntoParams, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Path)]
+pub struct CancelImportPathDto {
+    #[into_params(example = "550e8400-e29b-41d4-a716-446655440000")]
+    pub identifier: Uuid,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub enum JobTypeDto {
+    /// Hashes the uploaded content of a file, and extracts its type and system tags.
+    ProcessFile,
+    /// Indexes a file in the search backend.
+    IndexFile,
+    /// Renders the thumbnails of a file.
+    RenderThumbnails,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub enum JobStatusDto {
+    /// The job waits for its next attempt.
+    Pending,
+    Running,
+    Succeeded,
+    /// Every attempt of the job failed; it is only run again when retried.
+    Dead,
+}
+
+#[derive(Deserialize, IntoParams, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Query)]
+pub struct FindJobsQueryDto {
+    /// Cursor of the first item of the current page; the previous page is returned.
+    pub first_cursor: Option<String>,
+    /// Cursor of the last item of the current page; the next page is returned.
+    pub last_cursor: Option<String>,
+    /// Order of creation time.
+    #[into_params(example = "desc", default = "desc")]
+    #[serde(default)]
+    pub order: PaginationOrderDto,
+    #[into_params(example = "25", default = "25", minimum = 1, maximum = 100)]
+    #[serde(default = "default_page_size")]
+    pub page_size: u64,
+    #[into_params(example = "dead")]
+    pub status: Option<JobStatusDto>,
+    #[into_params(example = "processFile")]
+    pub job_type: Option<JobTypeDto>,
+}
+
+#[derive(Deserialize, IntoParams, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Path)]
+pub struct FindJobPathDto {
+    #[into_params(example = "550e8400-e29b-41d4-a716-446655440000")]
+    pub identifier: Uuid,
+}
+
+#[derive(Deserialize, IntoParams, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Path)]
+pub struct RetryJobPathDto {
+    #[into_params(example = "550e8400-e29b-41d4-a716-446655440000")]
+    pub identifier: Uuid,
+}
+
+#[derive(Deserialize, IntoParams, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Query)]
+pub struct ReconcileStorageQueryDto {
+    /// Reports what would be done without doing it.
+    #[into_params(example = "true", default = "false")]
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+/// How a stored content was found to differ from what was uploaded.
+#[derive(Serialize, Deserialize, ToSchema, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub enum ScrubMismatchKindDto {
+    /// The content is missing from the storage.
+    Missing,
+    /// The content could not be read.
+    Unreadable,
+    /// The content does not have the uploaded size.
+    Size,
+    /// The content does not have the uploaded hash.
+    Hash,
+}
+
+#[derive(Deserialize, IntoParams, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Query)]
+pub struct FindScrubMismatchesQueryDto {
+    /// Cursor of the first item of the current page; the previous page is returned.
+    pub first_cursor: Option<String>,
+    /// Cursor of the last item of the current page; the next page is returned.
+    pub last_cursor: Option<String>,
+    /// Order of detection time.
+    #[into_params(example = "desc", default = "desc")]
+    #[serde(default)]
+    pub order: PaginationOrderDto,
+    #[into_params(example = "25", default = "25", minimum = 1, maximum = 100)]
+    #[serde(default = "default_page_size")]
+    pub page_size: u64,
+    #[into_params(example = "hash")]
+    pub kind: Option<ScrubMismatchKindDto>,
+}
+
+#[derive(Deserialize, IntoParams, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Path)]
+pub struct FindUserQuotaPathDto {
+    /// Actor, as sent in the `X-Actor` header.
+    #[into_params(example = "alice")]
+    pub user: String,
+}
+
+#[derive(Deserialize, IntoParams, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Path)]
+pub struct UpdateUserQuotaPathDto {
+    /// Actor, as sent in the `X-Actor` header.
+    #[into_params(example = "alice")]
+    pub user: String,
+}
+
+#[derive(Deserialize, IntoParams, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Path)]
+pub struct FindCollectionQuotaPathDto {
+    #[into_params(example = "550e8400-e29b-41d4-a716-446655440000")]
+    pub identifier: Uuid,
+}
+
+#[derive(Deserialize, IntoParams, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Path)]
+pub struct UpdateCollectionQuotaPathDto {
+    #[into_params(example = "550e8400-e29b-41d4-a716-446655440000")]
+    pub identifier: Uuid,
+}
+
+/// Limits of a storage quota; an absent limit is unlimited.
+#[derive(Deserialize, ToSchema, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateQuotaBodyDto {
+    /// Total size of the stored contents, in bytes.
+    #[schema(example = "1073741824", minimum = 0)]
+    pub max_bytes: Option<i64>,
+    /// Number of stored files.
+    #[schema(example = "1000", minimum = 0)]
+    pub max_files: Option<i64>,
+}
+
+#[derive(Deserialize, IntoParams, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Path)]
+pub struct FindFileVersionsPathDto {
+    #[into_params(example = "550e8400-e29b-41d4-a716-446655440000")]
+    pub identifier: Uuid,
+}
+
+#[derive(Deserialize, IntoParams, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Path)]
+pub struct DownloadFileVersionPathDto {
+    #[into_params(example = "550e8400-e29b-41d4-a716-446655440000")]
+    pub identifier: Uuid,
+    #[into_params(example = "c0ffee00-0000-4000-8000-000000000000")]
+    pub version: Uuid,
+}
+
+#[derive(Deserialize, IntoParams, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Path)]
+pub struct RestoreFileVersionPathDto {
+    #[into_params(example = "550e8400-e29b-41d4-a716-446655440000")]
+    pub identifier: Uuid,
+    #[into_params(example = "c0ffee00-0000-4000-8000-000000000000")]
+    pub version: Uuid,
+}
+
+#[derive(Deserialize, IntoParams, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Path)]
+pub struct PruneFileVersionsPathDto {
+    #[into_params(example = "550e8400-e29b-41d4-a716-446655440000")]
+    pub identifier: Uuid,
+}
+
+/// Versions to keep; a version is kept if any setting keeps it, and an absent setting keeps the
+/// server-wide one.
+#[derive(Deserialize, ToSchema, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub struct PruneFileVersionsBodyDto {
+    /// Number of most recent versions.
+    #[schema(example = "10", minimum = 0)]
+    pub keep_last: Option<i64>,
+    /// Number of days versions uploaded since are kept.
+    #[schema(example = "30", minimum = 0)]
+    pub keep_days: Option<i64>,
+}
+
+#[derive(Deserialize, IntoParams, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Path)]
+pub struct FindCollectionUploadPolicyPathDto {
+    #[into_params(example = "550e8400-e29b-41d4-a716-446655440000")]
+    pub identifier: Uuid,
+}
+
+#[derive(Deserialize, IntoParams, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Path)]
+pub struct UpdateCollectionUploadPolicyPathDto {
+    #[into_params(example = "550e8400-e29b-41d4-a716-446655440000")]
+    pub identifier: Uuid,
+}
+
+/// Settings of the server-wide upload policy a collection overrides; an absent setting keeps the
+/// server-wide one.
+#[derive(Deserialize, ToSchema, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateUploadPolicyBodyDto {
+    /// Size of the content of a file, in bytes.
+    #[schema(example = "104857600", minimum = 0)]
+    pub max_file_size: Option<i64>,
+    /// Types accepted, such as `image/png` or `video/*`; an empty list accepts none.
+    #[schema(example = json!(["image/*", "application/pdf"]))]
+    pub allowed_mime_types: Option<Vec<String>>,
+    /// Types refused, even when accepted.
+    #[schema(example = json!(["image/svg+xml"]))]
+    pub denied_mime_types: Option<Vec<String>>,
 }
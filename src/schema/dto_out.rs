@@ -1,5 +1,10 @@
+use crate::schema::dto_in::{
+    AuditActionDto, AuditResourceDto, ChangeResourceDto, FileStatusDto, FindFilesBodyDto,
+    ImportModeDto, JobStatusDto, JobTypeDto, RemoveCollectionModeDto, ScrubMismatchKindDto,
+    ThumbnailSizeDto, WebhookDeliveryStatusDto,
+};
 use chrono::{DateTime, Utc};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 use uuid::Uuid;
 
@@ -10,11 +15,29 @@ pub struct PaginationMetadataDto {
     pub has_next: bool,
 }
 
+#[derive(Serialize, ToSchema, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub struct CursorPaginationMetadataDto {
+    pub has_prev: bool,
+    pub has_next: bool,
+    /// Cursor of the first item; pass it as `firstCursor` to fetch the previous page.
+    pub first_cursor: Option<String>,
+    /// Cursor of the last item; pass it as `lastCursor` to fetch the next page.
+    pub last_cursor: Option<String>,
+}
+
+#[derive(Serialize, ToSchema, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub enum CollectionKindDto {
+    /// Files are added to and removed from the collection one by one.
+    Manual,
+    /// Files are the ones matched by the search of the collection.
+    Smart,
+}
+
 #[derive(Serialize, ToSchema, Debug, Clone, PartialEq, Eq, Hash)]
 #[serde(rename_all = "camelCase")]
 pub struct CollectionDto {
-    #[schema(example = "1")]
-    pub id: i32,
     #[schema(example = "550e8400-e29b-41d4-a716-446655440000")]
     pub uuid: Uuid,
     #[schema(example = "Movies")]
@@ -22,11 +45,417 @@ pub struct CollectionDto {
     #[schema(example = "Movies I like.")]
     pub description: Option<String>,
     pub created_at: DateTime<Utc>,
+    /// Number of files added to the collection; always zero for smart collections.
+    #[schema(example = "42")]
+    pub file_count: i64,
+    #[schema(example = "550e8400-e29b-41d4-a716-446655440000")]
+    pub parent_uuid: Option<Uuid>,
+    pub kind: CollectionKindDto,
+    pub smart_query: Option<FindFilesBodyDto>,
+    /// When the collection was moved to the trash.
+    pub deleted_at: Option<DateTime<Utc>>,
+    /// When the collection was last modified; its entity tag is derived from it.
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, ToSchema, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub struct RemoveCollectionResultDto {
+    pub mode: RemoveCollectionModeDto,
+    pub collection: CollectionDto,
+    /// Number of files the collection held. They stay in it while it is in the trash and come
+    /// back with it when it is restored.
+    #[schema(example = "3")]
+    pub detached_file_count: i64,
+    /// Files moved to the trash because they belonged to no other collection.
+    pub trashed_files: Vec<Uuid>,
+}
+
+#[derive(Serialize, ToSchema, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportCollectionResultDto {
+    pub collection: CollectionDto,
+    /// Files created from the archive.
+    #[schema(example = "3")]
+    pub created_file_count: u64,
+    /// Files of the archive that already existed, identified by their content, and were reused.
+    #[schema(example = "1")]
+    pub reused_file_count: u64,
 }
 
 #[derive(Serialize, ToSchema, Debug, Clone, PartialEq, Eq, Hash)]
 #[serde(rename_all = "camelCase")]
 pub struct FindCollectionsResultDto {
-    pub pagination: PaginationMetadataDto,
+    pub pagination: CursorPaginationMetadataDto,
     pub items: Vec<CollectionDto>,
 }
+
+#[derive(Serialize, ToSchema, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub struct FileDto {
+    #[schema(example = "550e8400-e29b-41d4-a716-446655440000")]
+    pub uuid: Uuid,
+    #[schema(example = "Foo.txt")]
+    pub name: String,
+    #[schema(example = "text/plain")]
+    pub mime: Option<String>,
+    #[schema(example = "1024")]
+    pub size: Option<i64>,
+    #[schema(example = "1234567890")]
+    pub hash: Option<i64>,
+    pub created_at: DateTime<Utc>,
+    pub uploaded_at: Option<DateTime<Utc>>,
+    /// When the file was moved to the trash.
+    pub deleted_at: Option<DateTime<Utc>>,
+    pub status: FileStatusDto,
+    /// Why the last upload or processing of the content failed.
+    pub failure_reason: Option<String>,
+    /// When the file was last modified.
+    pub updated_at: DateTime<Utc>,
+    /// Actor that created the file, whose storage quota it counts toward.
+    #[schema(example = "alice")]
+    pub owner: Option<String>,
+    /// Bytes of the earlier versions kept, which count toward the storage quotas along with
+    /// `size`.
+    #[schema(example = "2048")]
+    pub kept_bytes: i64,
+}
+
+#[derive(Serialize, ToSchema, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub struct SystemTagDto {
+    #[schema(example = "system:camera")]
+    pub title: String,
+    #[schema(example = "make and model of the camera that took an image")]
+    pub description: String,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub enum ThumbnailStatusDto {
+    /// The thumbnails wait for a server to render them.
+    Pending,
+    Running,
+    Completed,
+    /// The thumbnails could not be rendered; see `lastError`.
+    Failed,
+    /// The type of the file has no preview, or the tool rendering it is not installed.
+    Unsupported,
+}
+
+#[derive(Serialize, ToSchema, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub struct ThumbnailJobDto {
+    pub status: ThumbnailStatusDto,
+    /// Sizes that can be fetched; they stay available while the thumbnails are rendered again.
+    pub sizes: Vec<ThumbnailSizeDto>,
+    pub last_error: Option<String>,
+    pub requested_at: DateTime<Utc>,
+    pub generated_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, ToSchema, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub struct FindFilesResultDto {
+    pub pagination: CursorPaginationMetadataDto,
+    pub items: Vec<FileDto>,
+    /// Whether the text query matched more files than are searched; the matches beyond are left
+    /// out of every page.
+    pub truncated: bool,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditEventDto {
+    #[schema(example = "550e8400-e29b-41d4-a716-446655440000")]
+    pub uuid: Uuid,
+    pub occurred_at: DateTime<Utc>,
+    /// Identifier of the request that caused the event, from its `X-Request-Id` header if any.
+    #[schema(example = "c0ffee00-0000-4000-8000-000000000000")]
+    pub request_id: String,
+    /// Who caused the event, as claimed by the `X-Actor` header of the request.
+    #[schema(example = "alice")]
+    pub actor: Option<String>,
+    pub action: AuditActionDto,
+    pub resource_type: AuditResourceDto,
+    #[schema(example = "550e8400-e29b-41d4-a716-446655440000")]
+    pub resource_uuid: Uuid,
+    /// The resource before the event; absent for creations.
+    #[schema(value_type = Option<Object>)]
+    pub before: Option<serde_json::Value>,
+    /// The resource after the event.
+    #[schema(value_type = Option<Object>)]
+    pub after: Option<serde_json::Value>,
+}
+
+#[derive(Serialize, ToSchema, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct FindAuditEventsResultDto {
+    pub pagination: CursorPaginationMetadataDto,
+    pub items: Vec<AuditEventDto>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub enum ChangeActionDto {
+    Created,
+    Updated,
+    /// The resource was moved to the trash.
+    Trashed,
+    /// The resource was restored from the trash.
+    Restored,
+    /// The resource was permanently removed.
+    Deleted,
+}
+
+/// A change of a resource, pushed to subscribers of `/events`.
+#[derive(Serialize, Deserialize, ToSchema, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub struct ChangeEventDto {
+    pub resource: ChangeResourceDto,
+    pub action: ChangeActionDto,
+    /// UUID of the changed collection or file; the file of a changed tag.
+    #[schema(example = "550e8400-e29b-41d4-a716-446655440000")]
+    pub uuid: Uuid,
+    /// UUID of the parent of a changed collection, or of the collection a file was added to or
+    /// removed from.
+    pub collection_uuid: Option<Uuid>,
+    /// UUID of the file added to or removed from a collection, or of the file of a changed tag.
+    pub file_uuid: Option<Uuid>,
+    /// Title of a changed tag.
+    #[schema(example = "artist")]
+    pub tag: Option<String>,
+}
+
+#[derive(Serialize, ToSchema, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub struct WebhookDto {
+    #[schema(example = "550e8400-e29b-41d4-a716-446655440000")]
+    pub uuid: Uuid,
+    #[schema(example = "https://example.com/hooks/poly-tag")]
+    pub url: String,
+    pub event_types: Vec<AuditActionDto>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, ToSchema, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct WebhookDeliveryDto {
+    #[schema(example = "550e8400-e29b-41d4-a716-446655440000")]
+    pub uuid: Uuid,
+    pub event_type: AuditActionDto,
+    /// The posted body: the audit event that triggered the delivery.
+    pub payload: AuditEventDto,
+    pub status: WebhookDeliveryStatusDto,
+    #[schema(example = "1")]
+    pub attempt_count: i32,
+    /// When the delivery is attempted next, if it is pending.
+    pub next_attempt_at: Option<DateTime<Utc>>,
+    pub last_attempt_at: Option<DateTime<Utc>>,
+    /// Status code of the response to the last attempt, if any.
+    #[schema(example = "200")]
+    pub response_status: Option<i32>,
+    /// Why the last attempt failed.
+    pub last_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, ToSchema, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct FindWebhookDeliveriesResultDto {
+    pub pagination: CursorPaginationMetadataDto,
+    pub items: Vec<WebhookDeliveryDto>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub enum ImportStatusDto {
+    /// The import waits for a server to run it.
+    Pending,
+    Running,
+    Completed,
+    /// The import stopped on an error; see `lastError`.
+    Failed,
+    Cancelled,
+}
+
+#[derive(Serialize, ToSchema, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportJobDto {
+    #[schema(example = "550e8400-e29b-41d4-a716-446655440000")]
+    pub uuid: Uuid,
+    #[schema(example = "/srv/music")]
+    pub root: String,
+    #[schema(example = json!(["{artist}/{album}/..."]))]
+    pub rules: Vec<String>,
+    pub mode: ImportModeDto,
+    pub status: ImportStatusDto,
+    /// Number of files under the root; absent until the root has been scanned.
+    #[schema(example = "1200")]
+    pub total_count: Option<i64>,
+    /// Number of files imported or failed so far.
+    #[schema(example = "300")]
+    pub processed_count: i64,
+    #[schema(example = "298")]
+    pub imported_count: i64,
+    #[schema(example = "2")]
+    pub failed_count: i64,
+    /// Path of the last processed file, relative to the root; the import resumes after it.
+    #[schema(example = "Artist/Album/03.flac")]
+    pub last_path: Option<String>,
+    /// The last error, about a file or the whole import.
+    pub last_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, ToSchema, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct JobDto {
+    #[schema(example = "550e8400-e29b-41d4-a716-446655440000")]
+    pub uuid: Uuid,
+    pub job_type: JobTypeDto,
+    /// Arguments of the job, depending on its type.
+    #[schema(value_type = Object, example = json!({"fileUuid": "550e8400-e29b-41d4-a716-446655440000"}))]
+    pub payload: serde_json::Value,
+    pub status: JobStatusDto,
+    #[schema(example = "1")]
+    pub attempt_count: i32,
+    #[schema(example = "5")]
+    pub max_attempts: i32,
+    /// When a pending job is attempted next, or when a running job is given up as abandoned.
+    pub run_at: Option<DateTime<Utc>>,
+    /// Why the last attempt failed.
+    pub last_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, ToSchema, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct FindJobsResultDto {
+    pub pagination: CursorPaginationMetadataDto,
+    pub items: Vec<JobDto>,
+}
+
+/// What a reconciliation of the stored contents with the files found, or did.
+#[derive(Serialize, ToSchema, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ReconcileStorageResultDto {
+    pub dry_run: bool,
+    /// Files whose upload was never completed and was abandoned; they are removed.
+    pub expired_files: Vec<Uuid>,
+    /// Files whose new content stopped being uploaded; they are marked as failed.
+    pub interrupted_files: Vec<Uuid>,
+    /// Files whose content is missing from the storage; they are marked as failed.
+    pub missing_files: Vec<Uuid>,
+    /// Stored contents that belong to no file; they are removed.
+    pub orphaned_contents: Vec<Uuid>,
+}
+
+/// Progress of the integrity scrub, which reads the content of every ready file again pass after
+/// pass.
+#[derive(Serialize, ToSchema, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ScrubStatusDto {
+    /// When the current pass started; absent between two passes.
+    pub pass_started_at: Option<DateTime<Utc>>,
+    /// Number of files checked by the current pass, or by the last one between two passes.
+    #[schema(example = "1024")]
+    pub checked_count: i64,
+    /// Total size of the files checked by the current pass, or by the last one.
+    #[schema(example = "1073741824")]
+    pub checked_size: i64,
+    /// Number of mismatches found by the current pass, or by the last one.
+    #[schema(example = "0")]
+    pub mismatch_count: i64,
+    pub last_pass_completed_at: Option<DateTime<Utc>>,
+}
+
+/// A stored content found to differ from what was uploaded; the file is marked as failed.
+#[derive(Serialize, ToSchema, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ScrubMismatchDto {
+    #[schema(example = "550e8400-e29b-41d4-a716-446655440000")]
+    pub uuid: Uuid,
+    #[schema(example = "550e8400-e29b-41d4-a716-446655440000")]
+    pub file_uuid: Uuid,
+    pub kind: ScrubMismatchKindDto,
+    #[schema(example = "1024")]
+    pub expected_size: i64,
+    #[schema(example = "1024")]
+    pub actual_size: Option<i64>,
+    #[schema(example = "1234567890")]
+    pub expected_hash: Option<i64>,
+    #[schema(example = "1234567891")]
+    pub actual_hash: Option<i64>,
+    /// Why the content could not be read.
+    pub error: Option<String>,
+    pub detected_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, ToSchema, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct FindScrubMismatchesResultDto {
+    pub pagination: CursorPaginationMetadataDto,
+    pub items: Vec<ScrubMismatchDto>,
+}
+
+/// Limits and usage of a storage quota. Every stored file counts, including the trashed ones and
+/// the partial content of an interrupted upload.
+#[derive(Serialize, ToSchema, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub struct QuotaDto {
+    /// Total size of the stored contents, in bytes; absent is unlimited.
+    #[schema(example = "1073741824")]
+    pub max_bytes: Option<i64>,
+    /// Number of stored files; absent is unlimited.
+    #[schema(example = "1000")]
+    pub max_files: Option<i64>,
+    /// Total size of the stored contents, including the kept versions of the files, in bytes.
+    #[schema(example = "52428800")]
+    pub used_bytes: i64,
+    #[schema(example = "42")]
+    pub used_files: i64,
+}
+
+/// Settings of the server-wide upload policy a collection overrides for the uploads of the files
+/// added to it; an absent setting keeps the server-wide one.
+#[derive(Serialize, ToSchema, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub struct UploadPolicyDto {
+    /// Size of the content of a file, in bytes.
+    #[schema(example = "104857600")]
+    pub max_file_size: Option<i64>,
+    /// Types accepted, such as `image/png` or `video/*`.
+    #[schema(example = json!(["image/*", "application/pdf"]))]
+    pub allowed_mime_types: Option<Vec<String>>,
+    /// Types refused, even when accepted.
+    #[schema(example = json!(["image/svg+xml"]))]
+    pub denied_mime_types: Option<Vec<String>>,
+}
+
+/// A content a file held, from an upload or a restore.
+#[derive(Serialize, ToSchema, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub struct FileVersionDto {
+    #[schema(example = "c0ffee00-0000-4000-8000-000000000000")]
+    pub uuid: Uuid,
+    /// Position of the version in the history of the file, from 1.
+    #[schema(example = "3")]
+    pub number: i32,
+    #[schema(example = "1024")]
+    pub size: i64,
+    /// Absent until the content is processed.
+    #[schema(example = "1234567890")]
+    pub hash: Option<i64>,
+    /// Absent until the content is processed.
+    #[schema(example = "text/plain")]
+    pub mime: Option<String>,
+    pub uploaded_at: DateTime<Utc>,
+    /// Whether the file was last uploaded or restored to this version; it is never pruned.
+    pub current: bool,
+}
@@ -0,0 +1,87 @@
+use serde_json::Value;
+
+/// Applies a JSON Merge Patch (RFC 7396) to a document.
+///
+/// Members of the patch replace the members of the same name in the document, `null` members
+/// remove them, and objects are merged recursively; any other patch replaces the whole document.
+pub fn merge_patch(document: &mut Value, patch: &Value) {
+    let patch = match patch {
+        Value::Object(patch) => patch,
+        _ => {
+            *document = patch.clone();
+            return;
+        }
+    };
+
+    if !document.is_object() {
+        *document = Value::Object(Default::default());
+    }
+
+    let document = document.as_object_mut().unwrap();
+    for (key, value) in patch {
+        if value.is_null() {
+            document.remove(key);
+        } else {
+            merge_patch(document.entry(key).or_insert(Value::Null), value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn patched(mut document: Value, patch: Value) -> Value {
+        merge_patch(&mut document, &patch);
+        document
+    }
+
+    #[test]
+    fn replaces_and_adds_members() {
+        assert_eq!(
+            patched(json!({ "a": "b", "c": 1 }), json!({ "a": "z", "d": true })),
+            json!({ "a": "z", "c": 1, "d": true })
+        );
+    }
+
+    #[test]
+    fn removes_null_members() {
+        assert_eq!(
+            patched(
+                json!({ "a": "b", "c": 1 }),
+                json!({ "a": null, "missing": null })
+            ),
+            json!({ "c": 1 })
+        );
+    }
+
+    #[test]
+    fn merges_objects_recursively() {
+        assert_eq!(
+            patched(
+                json!({ "a": { "b": 1, "c": 2 } }),
+                json!({ "a": { "b": null, "d": 3 } })
+            ),
+            json!({ "a": { "c": 2, "d": 3 } })
+        );
+    }
+
+    #[test]
+    fn replaces_with_non_objects() {
+        assert_eq!(
+            patched(json!({ "a": [1, 2] }), json!({ "a": [3] })),
+            json!({ "a": [3] })
+        );
+        assert_eq!(patched(json!({ "a": "b" }), json!(["c"])), json!(["c"]));
+        assert_eq!(
+            patched(json!("a"), json!({ "b": "c" })),
+            json!({ "b": "c" })
+        );
+    }
+
+    #[test]
+    fn keeps_document_for_empty_patch() {
+        assert_eq!(patched(json!({ "a": "b" }), json!({})), json!({ "a": "b" }));
+    }
+}
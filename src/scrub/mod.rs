@@ -0,0 +1,328 @@
+use crate::{
+    audit::{enum_name, AuditContext},
+    db::{
+        schema::{files, scrub_mismatches, scrub_state},
+        DBPool,
+    },
+    file_driver::{CheckedFile, ComputeFileHashError, FileDriver},
+    reconcile::MISSING_CONTENT,
+    route_files::file_service::fail_damaged_content,
+    schema::dto_in::{FileStatusDto, ScrubMismatchKindDto},
+};
+use chrono::{Duration, NaiveDateTime, Utc};
+use diesel::{dsl::now, prelude::*};
+use diesel_async::{scoped_futures::ScopedFutureExt, AsyncConnection, RunQueryDsl};
+use uuid::Uuid;
+
+/// Number of bytes read per second when `SCRUB_MAX_BYTES_PER_SECOND` is not set.
+const DEFAULT_MAX_BYTES_PER_SECOND: u64 = 16 * 1024 * 1024;
+
+/// Number of days between the starts of two passes when `SCRUB_INTERVAL_DAYS` is not set.
+const DEFAULT_INTERVAL_DAYS: i64 = 7;
+
+/// Interval between two looks for a pass to start, or after an error.
+const POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// Maximum length of the error recorded for an unreadable content.
+const MAX_ERROR_LENGTH: usize = 1024;
+
+#[derive(thiserror::Error, Debug)]
+pub enum ScrubError {
+    #[error("{0}")]
+    PoolError(#[from] diesel_async::pooled_connection::deadpool::PoolError),
+    #[error("{0}")]
+    DieselError(#[from] diesel::result::Error),
+}
+
+/// Checks the stored contents against the size and hash recorded when they were uploaded, to
+/// catch them rotting on the disk. Files are checked one after another in order of id; the
+/// position is stored, so that a pass goes on where it stopped after a restart.
+#[derive(Clone)]
+pub struct Scrubber {
+    db_pool: DBPool,
+    file_driver: FileDriver,
+    /// Reading speed of the contents, set by `SCRUB_MAX_BYTES_PER_SECOND`; zero is unlimited.
+    max_bytes_per_second: Option<u64>,
+    /// Interval between the starts of two passes, set by `SCRUB_INTERVAL_DAYS`.
+    interval: Duration,
+}
+
+/// A file to check, with its content as recorded.
+#[derive(Queryable, Debug)]
+struct ScrubbedFile {
+    id: i32,
+    uuid: Uuid,
+    size: i64,
+    hash: Option<i64>,
+    uploaded_at: NaiveDateTime,
+}
+
+/// How a checked content differs from the recorded one.
+#[derive(Debug)]
+struct Mismatch {
+    kind: ScrubMismatchKindDto,
+    actual_size: Option<i64>,
+    actual_hash: Option<i64>,
+    error: Option<String>,
+}
+
+impl Scrubber {
+    pub fn new(db_pool: DBPool, file_driver: FileDriver) -> Self {
+        let max_bytes_per_second = match std::env::var("SCRUB_MAX_BYTES_PER_SECOND") {
+            Ok(bytes) => bytes.parse::<u64>().unwrap_or_else(|_| {
+                tracing::warn!(
+                    "env var `SCRUB_MAX_BYTES_PER_SECOND` is not a number; using {} bytes",
+                    DEFAULT_MAX_BYTES_PER_SECOND
+                );
+                DEFAULT_MAX_BYTES_PER_SECOND
+            }),
+            Err(_) => DEFAULT_MAX_BYTES_PER_SECOND,
+        };
+        let interval_days = match std::env::var("SCRUB_INTERVAL_DAYS") {
+            Ok(days) => days.parse::<i64>().unwrap_or_else(|_| {
+                tracing::warn!(
+                    "env var `SCRUB_INTERVAL_DAYS` is not a number; using {} days",
+                    DEFAULT_INTERVAL_DAYS
+                );
+                DEFAULT_INTERVAL_DAYS
+            }),
+            Err(_) => DEFAULT_INTERVAL_DAYS,
+        };
+
+        Self {
+            db_pool,
+            file_driver,
+            max_bytes_per_second: (max_bytes_per_second != 0).then_some(max_bytes_per_second),
+            interval: Duration::days(interval_days),
+        }
+    }
+
+    /// Spawns a background task that checks the stored contents, a pass every interval.
+    pub fn spawn_worker(self) {
+        tokio::spawn(async move {
+            loop {
+                match self.scrub_next().await {
+                    Ok(true) => continue,
+                    Ok(false) => {}
+                    Err(err) => tracing::error!("failed to scrub the storage: {:#?}", err),
+                }
+
+                tokio::time::sleep(POLL_INTERVAL).await;
+            }
+        });
+    }
+
+    /// Checks the next file of the current pass, starting one if it is due; returns whether
+    /// there may be more to check right away.
+    async fn scrub_next(&self) -> Result<bool, ScrubError> {
+        let file = {
+            let db_conn = &mut self.db_pool.get().await?;
+            let (last_file_id, pass_started_at, last_pass_completed_at) = scrub_state::table
+                .select((
+                    scrub_state::last_file_id,
+                    scrub_state::pass_started_at,
+                    scrub_state::last_pass_completed_at,
+                ))
+                .get_result::<(Option<i32>, Option<NaiveDateTime>, Option<NaiveDateTime>)>(db_conn)
+                .await?;
+
+            if pass_started_at.is_none() {
+                let due = last_pass_completed_at.map_or(true, |completed_at| {
+                    completed_at + self.interval <= Utc::now().naive_utc()
+                });
+
+                if !due {
+                    return Ok(false);
+                }
+
+                diesel::update(scrub_state::table)
+                    .set((
+                        scrub_state::last_file_id.eq(None::<i32>),
+                        scrub_state::pass_started_at.eq(now),
+                        scrub_state::checked_count.eq(0),
+                        scrub_state::checked_size.eq(0),
+                        scrub_state::mismatch_count.eq(0),
+                    ))
+                    .execute(db_conn)
+                    .await?;
+                tracing::info!("starting a scrub pass");
+            }
+
+            let file = files::table
+                .select((
+                    files::id,
+                    files::uuid,
+                    files::size.assume_not_null(),
+                    files::hash,
+                    files::uploaded_at.assume_not_null(),
+                ))
+                .filter(files::id.gt(last_file_id.unwrap_or_default()))
+                .filter(files::status.eq(FileStatusDto::Ready))
+                .order(files::id.asc())
+                .first::<ScrubbedFile>(db_conn)
+                .await
+                .optional()?;
+
+            match file {
+                Some(file) => file,
+                None => {
+                    let (checked_count, mismatch_count) = diesel::update(scrub_state::table)
+                        .set((
+                            scrub_state::last_file_id.eq(None::<i32>),
+                            scrub_state::pass_started_at.eq(None::<NaiveDateTime>),
+                            scrub_state::last_pass_completed_at.eq(now),
+                        ))
+                        .returning((scrub_state::checked_count, scrub_state::mismatch_count))
+                        .get_result::<(i64, i64)>(db_conn)
+                        .await?;
+                    tracing::info!(
+                        "completed a scrub pass: checked {} files, found {} mismatches",
+                        checked_count,
+                        mismatch_count
+                    );
+
+                    return Ok(false);
+                }
+            }
+        };
+
+        let outcome = self
+            .file_driver
+            .check_file(file.uuid, self.max_bytes_per_second)
+            .await;
+        let mismatch = compare(&file, outcome);
+
+        let db_conn = &mut self.db_pool.get().await?;
+        db_conn
+            .transaction(|db_conn| {
+                async move {
+                    let mut mismatch_count = 0;
+
+                    match &mismatch {
+                        Some(mismatch) => {
+                            tracing::warn!(
+                                "content of file `{}` is corrupted: {:?}",
+                                file.uuid,
+                                mismatch
+                            );
+
+                            let failed = fail_damaged_content(
+                                db_conn,
+                                file.uuid,
+                                file.uploaded_at,
+                                &mismatch.reason(&file),
+                                &AuditContext::system(),
+                            )
+                            .await?;
+
+                            // The file was uploaded again or removed while it was checked.
+                            if failed {
+                                diesel::insert_into(scrub_mismatches::table)
+                                    .values((
+                                        scrub_mismatches::file_id.eq(file.id),
+                                        scrub_mismatches::file_uuid.eq(file.uuid),
+                                        scrub_mismatches::kind.eq(enum_name(&mismatch.kind)),
+                                        scrub_mismatches::expected_size.eq(file.size),
+                                        scrub_mismatches::actual_size.eq(mismatch.actual_size),
+                                        scrub_mismatches::expected_hash.eq(file.hash),
+                                        scrub_mismatches::actual_hash.eq(mismatch.actual_hash),
+                                        scrub_mismatches::error.eq(&mismatch.error),
+                                    ))
+                                    .execute(db_conn)
+                                    .await?;
+                                mismatch_count = 1;
+                            }
+                        }
+                        // The content is sound again, such as after it was uploaded again.
+                        None => {
+                            diesel::delete(
+                                scrub_mismatches::table
+                                    .filter(scrub_mismatches::file_id.eq(file.id)),
+                            )
+                            .execute(db_conn)
+                            .await?;
+                        }
+                    }
+
+                    diesel::update(scrub_state::table)
+                        .set((
+                            scrub_state::last_file_id.eq(file.id),
+                            scrub_state::checked_count.eq(scrub_state::checked_count + 1),
+                            scrub_state::checked_size.eq(scrub_state::checked_size + file.size),
+                            scrub_state::mismatch_count
+                                .eq(scrub_state::mismatch_count + mismatch_count),
+                        ))
+                        .execute(db_conn)
+                        .await?;
+
+                    Ok::<_, diesel::result::Error>(())
+                }
+                .scope_boxed()
+            })
+            .await?;
+
+        Ok(true)
+    }
+}
+
+/// Compares a content as read again with the recorded one.
+fn compare(
+    file: &ScrubbedFile,
+    outcome: Result<Option<CheckedFile>, ComputeFileHashError>,
+) -> Option<Mismatch> {
+    match outcome {
+        Ok(None) => Some(Mismatch {
+            kind: ScrubMismatchKindDto::Missing,
+            actual_size: None,
+            actual_hash: None,
+            error: None,
+        }),
+        Err(err) => Some(Mismatch {
+            kind: ScrubMismatchKindDto::Unreadable,
+            actual_size: None,
+            actual_hash: None,
+            error: Some(err.to_string().chars().take(MAX_ERROR_LENGTH).collect()),
+        }),
+        Ok(Some(checked)) => {
+            let actual_size = checked.size as i64;
+            let actual_hash = checked.hash as i64;
+            let kind = if actual_size != file.size {
+                ScrubMismatchKindDto::Size
+            } else if file.hash.map_or(false, |hash| hash != actual_hash) {
+                ScrubMismatchKindDto::Hash
+            } else {
+                return None;
+            };
+
+            Some(Mismatch {
+                kind,
+                actual_size: Some(actual_size),
+                actual_hash: Some(actual_hash),
+                error: None,
+            })
+        }
+    }
+}
+
+impl Mismatch {
+    /// Why the file fails.
+    fn reason(&self, file: &ScrubbedFile) -> String {
+        match self.kind {
+            ScrubMismatchKindDto::Missing => MISSING_CONTENT.to_owned(),
+            ScrubMismatchKindDto::Unreadable => format!(
+                "the content of the file cannot be read: {}",
+                self.error.as_deref().unwrap_or_default()
+            ),
+            ScrubMismatchKindDto::Size => format!(
+                "the content of the file is corrupted; its size is `{}` instead of `{}`",
+                self.actual_size.unwrap_or_default(),
+                file.size
+            ),
+            ScrubMismatchKindDto::Hash => format!(
+                "the content of the file is corrupted; its hash is `{}` instead of `{}`",
+                self.actual_hash.unwrap_or_default(),
+                file.hash.unwrap_or_default()
+            ),
+        }
+    }
+}
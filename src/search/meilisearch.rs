@@ -0,0 +1,103 @@
+use super::{FileDocument, SearchBackend, SearchError, SEARCH_HIT_LIMIT};
+use async_trait::async_trait;
+use meilisearch_sdk::{
+    search::{SearchQuery, Selectors},
+    settings::PaginationSetting,
+    Client,
+};
+use serde::Deserialize;
+use std::time::Duration;
+use uuid::Uuid;
+
+const FILES_INDEX: &str = "files";
+
+pub struct MeilisearchBackend {
+    client: Client,
+}
+
+impl MeilisearchBackend {
+    pub async fn init() -> Self {
+        tracing::info!("initializing meilisearch search backend");
+
+        let meilisearch_url =
+            std::env::var("MEILISEARCH_URL").expect("env var `MEILISEARCH_URL` must be set");
+        let meilisearch_api_key = std::env::var("MEILISEARCH_API_KEY")
+            .expect("env var `MEILISEARCH_API_KEY` must be set");
+
+        let client = Client::new(meilisearch_url, Some(meilisearch_api_key));
+
+        client
+            .create_index(FILES_INDEX, Some("uuid"))
+            .await
+            .expect("failed to create meilisearch index")
+            .wait_for_completion(&client, Some(Duration::from_secs(1)), None)
+            .await
+            .expect("failed to wait for meilisearch index creation");
+
+        // Hits are paged up to the limit, and one more tells whether the results are truncated.
+        client
+            .index(FILES_INDEX)
+            .set_pagination(PaginationSetting {
+                max_total_hits: SEARCH_HIT_LIMIT + 1,
+            })
+            .await
+            .expect("failed to set meilisearch pagination")
+            .wait_for_completion(&client, Some(Duration::from_secs(1)), None)
+            .await
+            .expect("failed to wait for meilisearch pagination setting");
+
+        Self { client }
+    }
+}
+
+#[async_trait]
+impl SearchBackend for MeilisearchBackend {
+    async fn index_file(&self, document: &FileDocument) -> Result<(), SearchError> {
+        self.client
+            .index(FILES_INDEX)
+            .add_documents(&[document], Some("uuid"))
+            .await?;
+
+        Ok(())
+    }
+
+    async fn remove_files(&self, uuids: &[Uuid]) -> Result<(), SearchError> {
+        if uuids.is_empty() {
+            return Ok(());
+        }
+
+        self.client
+            .index(FILES_INDEX)
+            .delete_documents(uuids)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn search_files(
+        &self,
+        query: &str,
+        offset: usize,
+        limit: usize,
+    ) -> Result<Vec<Uuid>, SearchError> {
+        let index = self.client.index(FILES_INDEX);
+        let hits = SearchQuery::execute::<FileUuid>(
+            index
+                .search()
+                .with_attributes_to_retrieve(Selectors::Some(&["uuid"]))
+                .with_attributes_to_highlight(Selectors::Some(&[]))
+                .with_offset(offset)
+                .with_limit(limit)
+                .with_query(query),
+        )
+        .await?
+        .hits;
+
+        Ok(hits.into_iter().map(|hit| hit.result.uuid).collect())
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct FileUuid {
+    uuid: Uuid,
+}
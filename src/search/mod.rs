@@ -0,0 +1,98 @@
+use crate::db::DBPool;
+use async_trait::async_trait;
+use axum::http::StatusCode;
+use codegen::ErrorEnum;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use thiserror::Error;
+use uuid::Uuid;
+
+mod meilisearch;
+mod postgres;
+
+pub use self::meilisearch::*;
+pub use self::postgres::*;
+
+/// Maximum number of hits of a text query that are filtered and paginated; the matches beyond are
+/// left out of every page, which the result reports.
+pub const SEARCH_HIT_LIMIT: usize = 10_000;
+
+#[derive(ErrorEnum, Error, Debug)]
+#[allow(clippy::enum_variant_names)]
+pub enum SearchError {
+    #[error("internal server error")]
+    #[status(StatusCode::INTERNAL_SERVER_ERROR)]
+    PoolError(#[from] diesel_async::pooled_connection::deadpool::PoolError),
+    #[error("internal server error")]
+    #[status(StatusCode::INTERNAL_SERVER_ERROR)]
+    DieselError(#[from] diesel::result::Error),
+    #[error("internal server error")]
+    #[status(StatusCode::INTERNAL_SERVER_ERROR)]
+    MeilisearchError(#[from] meilisearch_sdk::errors::Error),
+}
+
+/// A searchable representation of a file.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FileDocument {
+    pub uuid: Uuid,
+    pub name: String,
+}
+
+/// A full-text search engine over files.
+///
+/// Backends only resolve a text query into file uuids; tag filters and pagination are applied by
+/// the caller against the database, so every backend behaves the same from the API's view.
+#[async_trait]
+pub trait SearchBackend: Send + Sync {
+    /// Makes the given file searchable, replacing any previous document of the same file.
+    async fn index_file(&self, document: &FileDocument) -> Result<(), SearchError>;
+
+    /// Makes the given files unsearchable; unknown files are ignored.
+    async fn remove_files(&self, uuids: &[Uuid]) -> Result<(), SearchError>;
+
+    /// Returns uuids of the files matching the query, most relevant first, skipping the first
+    /// `offset` ones.
+    async fn search_files(
+        &self,
+        query: &str,
+        offset: usize,
+        limit: usize,
+    ) -> Result<Vec<Uuid>, SearchError>;
+}
+
+/// Resolves a text query into the uuids of the files matching it, up to [`SEARCH_HIT_LIMIT`], in a
+/// single request to the backend; also returns whether more files match.
+pub async fn search_all_files(
+    search_backend: &dyn SearchBackend,
+    query: &str,
+) -> Result<(Vec<Uuid>, bool), SearchError> {
+    // One hit past the limit tells whether matches are left out.
+    let mut hits = search_backend
+        .search_files(query, 0, SEARCH_HIT_LIMIT + 1)
+        .await?;
+    let truncated = hits.len() > SEARCH_HIT_LIMIT;
+    hits.truncate(SEARCH_HIT_LIMIT);
+
+    Ok((hits, truncated))
+}
+
+/// Initializes the search backend selected by `SEARCH_BACKEND`.
+///
+/// When `SEARCH_BACKEND` is not set, Meilisearch is used if `MEILISEARCH_URL` is set, and
+/// PostgreSQL otherwise.
+pub async fn init_search_backend(db_pool: DBPool) -> Arc<dyn SearchBackend> {
+    let backend = match std::env::var("SEARCH_BACKEND") {
+        Ok(backend) => backend,
+        Err(_) if std::env::var("MEILISEARCH_URL").is_ok() => "meilisearch".to_owned(),
+        Err(_) => "postgres".to_owned(),
+    };
+
+    match backend.as_str() {
+        "meilisearch" => Arc::new(MeilisearchBackend::init().await),
+        "postgres" => Arc::new(PostgresBackend::new(db_pool)),
+        backend => panic!(
+            "env var `SEARCH_BACKEND` must be either `meilisearch` or `postgres`, but was `{}`",
+            backend
+        ),
+    }
+}
@@ -0,0 +1,64 @@
+use super::{FileDocument, SearchBackend, SearchError};
+use crate::db::DBPool;
+use async_trait::async_trait;
+use diesel::{sql_query, sql_types::BigInt, QueryableByName};
+use diesel_async::RunQueryDsl;
+use uuid::Uuid;
+
+/// Searches file names with PostgreSQL full-text search, falling back to trigram similarity so
+/// that partial words and typos still match.
+#[derive(Clone)]
+pub struct PostgresBackend {
+    db_pool: DBPool,
+}
+
+impl PostgresBackend {
+    pub fn new(db_pool: DBPool) -> Self {
+        tracing::info!("initializing postgres search backend");
+
+        Self { db_pool }
+    }
+}
+
+#[async_trait]
+impl SearchBackend for PostgresBackend {
+    async fn index_file(&self, _document: &FileDocument) -> Result<(), SearchError> {
+        // Files are searched directly in the `files` table, which is indexed by the database.
+        Ok(())
+    }
+
+    async fn remove_files(&self, _uuids: &[Uuid]) -> Result<(), SearchError> {
+        // Removed files are gone from the `files` table, and so from the results.
+        Ok(())
+    }
+
+    async fn search_files(
+        &self,
+        query: &str,
+        offset: usize,
+        limit: usize,
+    ) -> Result<Vec<Uuid>, SearchError> {
+        let db_conn = &mut self.db_pool.get().await?;
+        let hits = sql_query(
+            "SELECT uuid FROM files \
+            WHERE to_tsvector('simple', name) @@ plainto_tsquery('simple', $1) OR $1 <% name \
+            ORDER BY ts_rank(to_tsvector('simple', name), plainto_tsquery('simple', $1)) DESC, \
+            word_similarity($1, name) DESC, \
+            id ASC \
+            OFFSET $2 LIMIT $3",
+        )
+        .bind::<diesel::sql_types::Text, _>(query)
+        .bind::<BigInt, _>(offset as i64)
+        .bind::<BigInt, _>(limit as i64)
+        .load::<FileUuid>(db_conn)
+        .await?;
+
+        Ok(hits.into_iter().map(|hit| hit.uuid).collect())
+    }
+}
+
+#[derive(QueryableByName, Debug)]
+struct FileUuid {
+    #[diesel(sql_type = diesel::sql_types::Uuid)]
+    uuid: Uuid,
+}
@@ -0,0 +1,214 @@
+use crate::{
+    audit::{record_event, AuditContext},
+    db::{
+        schema::{collection_file_pairs, collections, file_versions, files},
+        DBPool,
+    },
+    file_driver::FileDriver,
+    schema::dto_in::AuditActionDto,
+    search::SearchBackend,
+};
+use chrono::{Duration, Utc};
+use diesel::{
+    alias,
+    dsl::{exists, not},
+    prelude::*,
+};
+use diesel_async::{scoped_futures::ScopedFutureExt, AsyncConnection, RunQueryDsl};
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// Number of days items stay in the trash when `TRASH_RETENTION_DAYS` is not set.
+const DEFAULT_RETENTION_DAYS: i64 = 30;
+
+/// Interval between two purges of the trash.
+const PURGE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60 * 60);
+
+/// Spawns a background task that permanently removes items trashed longer than the retention
+/// period set by `TRASH_RETENTION_DAYS`.
+pub fn spawn_purge_job(
+    db_pool: DBPool,
+    file_driver: FileDriver,
+    search_backend: Arc<dyn SearchBackend>,
+) {
+    let retention_days = match std::env::var("TRASH_RETENTION_DAYS") {
+        Ok(days) => days.parse::<i64>().unwrap_or_else(|_| {
+            tracing::warn!(
+                "env var `TRASH_RETENTION_DAYS` is not a number; using {} days",
+                DEFAULT_RETENTION_DAYS
+            );
+            DEFAULT_RETENTION_DAYS
+        }),
+        Err(_) => DEFAULT_RETENTION_DAYS,
+    };
+    let retention = Duration::days(retention_days);
+
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(PURGE_INTERVAL);
+
+        loop {
+            interval.tick().await;
+
+            let threshold = (Utc::now() - retention).naive_utc();
+            if let Err(err) =
+                purge_trash(&db_pool, &file_driver, search_backend.as_ref(), threshold).await
+            {
+                tracing::error!("failed to purge the trash: {:#?}", err);
+            }
+        }
+    });
+}
+
+async fn purge_trash(
+    db_pool: &DBPool,
+    file_driver: &FileDriver,
+    search_backend: &dyn SearchBackend,
+    threshold: chrono::NaiveDateTime,
+) -> Result<(), PurgeTrashError> {
+    let db_conn = &mut db_pool.get().await?;
+    let context = AuditContext::system();
+
+    let (uuids, version_uuids) = db_conn
+        .transaction(|db_conn| {
+            async {
+                // The versions go along with their files.
+                let version_uuids = file_versions::table
+                    .inner_join(files::table)
+                    .select(file_versions::uuid)
+                    .filter(files::deleted_at.lt(threshold))
+                    .load::<Uuid>(db_conn)
+                    .await?;
+                let uuids = diesel::delete(files::table.filter(files::deleted_at.lt(threshold)))
+                    .returning(files::uuid)
+                    .get_results::<Uuid>(db_conn)
+                    .await?;
+
+                for uuid in &uuids {
+                    record_event(
+                        db_conn,
+                        &context,
+                        AuditActionDto::PurgeFile,
+                        *uuid,
+                        None,
+                        None,
+                    )
+                    .await?;
+                }
+
+                Ok::<_, diesel::result::Error>((uuids, version_uuids))
+            }
+            .scope_boxed()
+        })
+        .await?;
+
+    for uuid in &uuids {
+        if let Err(err) = file_driver.remove_file(*uuid).await {
+            tracing::error!(
+                "failed to remove the contents of file `{}`: {:#?}",
+                uuid,
+                err
+            );
+        }
+
+        if let Err(err) = file_driver.remove_thumbnails(*uuid).await {
+            tracing::error!(
+                "failed to remove the thumbnails of file `{}`: {:#?}",
+                uuid,
+                err
+            );
+        }
+    }
+
+    for uuid in &version_uuids {
+        if let Err(err) = file_driver.remove_version(*uuid).await {
+            tracing::error!(
+                "failed to remove the contents of version `{}`: {:#?}",
+                uuid,
+                err
+            );
+        }
+    }
+
+    if !uuids.is_empty() {
+        if let Err(err) = search_backend.remove_files(&uuids).await {
+            tracing::error!(
+                "failed to remove purged files from the search index: {:#?}",
+                err
+            );
+        }
+    }
+
+    // Sub-collections are trashed before their parents, so they expire first; removing the
+    // leaves round by round purges a whole expired tree.
+    let mut collection_count = 0;
+    loop {
+        let children = alias!(collections as children);
+        let expired = collections::table
+            .select(collections::id)
+            .filter(collections::deleted_at.lt(threshold))
+            .filter(not(exists(
+                children.filter(
+                    children
+                        .field(collections::parent_id)
+                        .eq(collections::id.nullable()),
+                ),
+            )))
+            .load::<i32>(db_conn)
+            .await?;
+
+        if expired.is_empty() {
+            break;
+        }
+
+        collection_count += db_conn
+            .transaction(|db_conn| {
+                async {
+                    diesel::delete(
+                        collection_file_pairs::table
+                            .filter(collection_file_pairs::collection_id.eq_any(&expired)),
+                    )
+                    .execute(db_conn)
+                    .await?;
+                    let uuids =
+                        diesel::delete(collections::table.filter(collections::id.eq_any(&expired)))
+                            .returning(collections::uuid)
+                            .get_results::<Uuid>(db_conn)
+                            .await?;
+
+                    for uuid in &uuids {
+                        record_event(
+                            db_conn,
+                            &context,
+                            AuditActionDto::PurgeCollection,
+                            *uuid,
+                            None,
+                            None,
+                        )
+                        .await?;
+                    }
+
+                    Ok::<_, diesel::result::Error>(uuids.len())
+                }
+                .scope_boxed()
+            })
+            .await?;
+    }
+
+    if !uuids.is_empty() || collection_count != 0 {
+        tracing::info!(
+            "purged {} files and {} collections from the trash",
+            uuids.len(),
+            collection_count
+        );
+    }
+
+    Ok(())
+}
+
+#[derive(thiserror::Error, Debug)]
+enum PurgeTrashError {
+    #[error("{0}")]
+    PoolError(#[from] diesel_async::pooled_connection::deadpool::PoolError),
+    #[error("{0}")]
+    DieselError(#[from] diesel::result::Error),
+}
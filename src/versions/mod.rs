@@ -0,0 +1,319 @@
+use crate::{
+    audit::{record_event, snapshot, AuditContext},
+    db::{
+        schema::{file_versions, files},
+        DBPool,
+    },
+    file_driver::FileDriver,
+    schema::{dto_in::AuditActionDto, dto_out::FileVersionDto},
+};
+use chrono::{Duration, NaiveDateTime, Utc};
+use diesel::{dsl::max, prelude::*};
+use diesel_async::{
+    scoped_futures::ScopedFutureExt, AsyncConnection, AsyncPgConnection, RunQueryDsl,
+};
+use uuid::Uuid;
+
+/// Interval between two prunings of the versions.
+const PRUNE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60 * 60);
+
+/// Which versions of a file are kept. The server-wide retention is set by `VERSION_KEEP_LAST` and
+/// `VERSION_KEEP_DAYS`; without either, every version is kept.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct VersionRetention {
+    /// Number of most recent versions kept.
+    pub keep_last: Option<i64>,
+    /// Number of days versions uploaded since are kept.
+    pub keep_days: Option<i64>,
+}
+
+impl VersionRetention {
+    pub fn from_env() -> Self {
+        let setting = |name: &str| match std::env::var(name) {
+            Ok(value) => value
+                .parse::<i64>()
+                .ok()
+                .filter(|value| *value >= 0)
+                .or_else(|| {
+                    tracing::warn!("env var `{}` is invalid; not pruning by it", name);
+                    None
+                }),
+            Err(_) => None,
+        };
+
+        Self {
+            keep_last: setting("VERSION_KEEP_LAST"),
+            keep_days: setting("VERSION_KEEP_DAYS"),
+        }
+    }
+
+    /// Returns this retention with the settings given replaced.
+    pub fn override_with(&self, keep_last: Option<i64>, keep_days: Option<i64>) -> Self {
+        Self {
+            keep_last: keep_last.or(self.keep_last),
+            keep_days: keep_days.or(self.keep_days),
+        }
+    }
+
+    pub fn keeps_all(&self) -> bool {
+        self.keep_last.is_none() && self.keep_days.is_none()
+    }
+}
+
+/// Spawns a background task that prunes the versions of every file by the server-wide retention;
+/// it does nothing if the retention keeps every version.
+pub fn spawn_prune_job(db_pool: DBPool, file_driver: FileDriver) {
+    let retention = VersionRetention::from_env();
+    if retention.keeps_all() {
+        return;
+    }
+
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(PRUNE_INTERVAL);
+
+        loop {
+            interval.tick().await;
+
+            if let Err(err) = prune_versions(&db_pool, &file_driver, &retention).await {
+                tracing::error!("failed to prune the versions of files: {:#?}", err);
+            }
+        }
+    });
+}
+
+async fn prune_versions(
+    db_pool: &DBPool,
+    file_driver: &FileDriver,
+    retention: &VersionRetention,
+) -> Result<(), PruneVersionsError> {
+    let db_conn = &mut db_pool.get().await?;
+    let context = AuditContext::system();
+
+    let file_ids = file_versions::table
+        .select(file_versions::file_id)
+        .distinct()
+        .load::<i32>(db_conn)
+        .await?;
+
+    let mut removed_count = 0;
+    for file_id in file_ids {
+        let removed = db_conn
+            .transaction(|db_conn| {
+                async {
+                    let file = files::table
+                        .select((files::uuid, files::uploaded_at))
+                        .filter(files::id.eq(file_id))
+                        .for_update()
+                        .get_result::<(Uuid, Option<NaiveDateTime>)>(db_conn)
+                        .await
+                        .optional()?;
+                    let (file_uuid, current) = match file {
+                        Some(file) => file,
+                        None => return Ok(Vec::new()),
+                    };
+
+                    prune_file_versions(db_conn, file_id, file_uuid, current, retention, &context)
+                        .await
+                }
+                .scope_boxed()
+            })
+            .await?;
+
+        removed_count += removed.len();
+        remove_version_contents(file_driver, &removed).await;
+    }
+
+    if removed_count != 0 {
+        tracing::info!("pruned {} versions of files", removed_count);
+    }
+
+    Ok(())
+}
+
+#[derive(thiserror::Error, Debug)]
+enum PruneVersionsError {
+    #[error("{0}")]
+    PoolError(#[from] diesel_async::pooled_connection::deadpool::PoolError),
+    #[error("{0}")]
+    DieselError(#[from] diesel::result::Error),
+}
+
+/// Records the content a file holds since `uploaded_at` as its next version.
+///
+/// It runs on the connection of the caller, which must hold the lock of the file.
+pub async fn insert_file_version(
+    db_conn: &mut AsyncPgConnection,
+    file_id: i32,
+    uploaded_at: NaiveDateTime,
+    size: i64,
+    hash: Option<i64>,
+    mime: Option<&str>,
+) -> QueryResult<RawFileVersionDto> {
+    let last_number = file_versions::table
+        .select(max(file_versions::number))
+        .filter(file_versions::file_id.eq(file_id))
+        .get_result::<Option<i32>>(db_conn)
+        .await?;
+
+    diesel::insert_into(file_versions::table)
+        .values((
+            file_versions::file_id.eq(file_id),
+            file_versions::number.eq(last_number.unwrap_or_default() + 1),
+            file_versions::size.eq(size),
+            file_versions::hash.eq(hash),
+            file_versions::mime.eq(mime),
+            file_versions::uploaded_at.eq(uploaded_at),
+        ))
+        .returning(file_version_columns())
+        .get_result::<RawFileVersionDto>(db_conn)
+        .await
+}
+
+/// Finds the version a file was last uploaded or restored to at `uploaded_at`.
+pub async fn find_current_version(
+    db_conn: &mut AsyncPgConnection,
+    file_id: i32,
+    uploaded_at: Option<NaiveDateTime>,
+) -> QueryResult<Option<RawFileVersionDto>> {
+    let uploaded_at = match uploaded_at {
+        Some(uploaded_at) => uploaded_at,
+        None => return Ok(None),
+    };
+
+    file_versions::table
+        .select(file_version_columns())
+        .filter(file_versions::file_id.eq(file_id))
+        .filter(file_versions::uploaded_at.eq(uploaded_at))
+        .get_result::<RawFileVersionDto>(db_conn)
+        .await
+        .optional()
+}
+
+/// Removes the versions of a file the retention does not keep, except the current one uploaded
+/// at `current`, and records the removal if there is any. Returns the removed versions, whose
+/// contents are left to the caller to remove once committed.
+///
+/// It runs on the connection of the caller, which must hold the lock of the file.
+pub async fn prune_file_versions(
+    db_conn: &mut AsyncPgConnection,
+    file_id: i32,
+    file_uuid: Uuid,
+    current: Option<NaiveDateTime>,
+    retention: &VersionRetention,
+    context: &AuditContext,
+) -> QueryResult<Vec<RawFileVersionDto>> {
+    if retention.keeps_all() {
+        return Ok(Vec::new());
+    }
+
+    let versions = file_versions::table
+        .select(file_version_columns())
+        .filter(file_versions::file_id.eq(file_id))
+        .order(file_versions::number.desc())
+        .load::<RawFileVersionDto>(db_conn)
+        .await?;
+    let threshold = retention
+        .keep_days
+        .map(|days| (Utc::now() - Duration::days(days)).naive_utc());
+    let removed = versions
+        .into_iter()
+        .enumerate()
+        .filter(|(index, version)| {
+            Some(version.uploaded_at) != current
+                && retention
+                    .keep_last
+                    .map_or(true, |keep_last| *index as i64 >= keep_last)
+                && threshold.map_or(true, |threshold| version.uploaded_at < threshold)
+        })
+        .map(|(_, version)| version)
+        .collect::<Vec<_>>();
+
+    if removed.is_empty() {
+        return Ok(removed);
+    }
+
+    diesel::delete(
+        file_versions::table
+            .filter(file_versions::id.eq_any(removed.iter().map(|version| version.id))),
+    )
+    .execute(db_conn)
+    .await?;
+
+    let before = removed
+        .iter()
+        .map(|version| version.clone().into_dto(current))
+        .collect::<Vec<_>>();
+
+    record_event(
+        db_conn,
+        context,
+        AuditActionDto::PruneFileVersions,
+        file_uuid,
+        Some(snapshot(&before)),
+        None,
+    )
+    .await?;
+
+    Ok(removed)
+}
+
+/// Removes the kept contents of removed versions; failures are logged, not returned.
+pub async fn remove_version_contents(file_driver: &FileDriver, versions: &[RawFileVersionDto]) {
+    for version in versions {
+        if let Err(err) = file_driver.remove_version(version.uuid).await {
+            tracing::error!(
+                "failed to remove the contents of version `{}`: {:#?}",
+                version.uuid,
+                err
+            );
+        }
+    }
+}
+
+pub type FileVersionColumns = (
+    file_versions::id,
+    file_versions::uuid,
+    file_versions::number,
+    file_versions::size,
+    file_versions::hash,
+    file_versions::mime,
+    file_versions::uploaded_at,
+);
+
+pub fn file_version_columns() -> FileVersionColumns {
+    (
+        file_versions::id,
+        file_versions::uuid,
+        file_versions::number,
+        file_versions::size,
+        file_versions::hash,
+        file_versions::mime,
+        file_versions::uploaded_at,
+    )
+}
+
+#[derive(Queryable, Clone, Debug)]
+pub struct RawFileVersionDto {
+    pub id: i32,
+    pub uuid: Uuid,
+    pub number: i32,
+    pub size: i64,
+    pub hash: Option<i64>,
+    pub mime: Option<String>,
+    pub uploaded_at: NaiveDateTime,
+}
+
+impl RawFileVersionDto {
+    /// Converts the version of a file last uploaded or restored at `current`.
+    pub fn into_dto(self, current: Option<NaiveDateTime>) -> FileVersionDto {
+        FileVersionDto {
+            uuid: self.uuid,
+            number: self.number,
+            size: self.size,
+            hash: self.hash,
+            mime: self.mime,
+            uploaded_at: self.uploaded_at.and_utc(),
+            current: current == Some(self.uploaded_at),
+        }
+    }
+}
@@ -0,0 +1,422 @@
+use crate::{
+    audit::{enum_name, snapshot},
+    db::{
+        schema::{webhook_deliveries, webhooks},
+        DBPool,
+    },
+    schema::{dto_in::WebhookDeliveryStatusDto, dto_out::AuditEventDto},
+};
+use chrono::{DateTime, Duration, Utc};
+use diesel::{
+    alias,
+    dsl::now,
+    prelude::*,
+    sql_types::{Jsonb, Text},
+};
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use hmac::{Hmac, Mac};
+use isahc::{config::Configurable, config::RedirectPolicy, HttpClient, Request};
+use sha2::Sha256;
+use uuid::Uuid;
+
+/// Interval between two looks for due deliveries.
+const POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(2);
+
+/// Maximum number of deliveries attempted at once.
+const BATCH_SIZE: i64 = 16;
+
+/// Time a claimed delivery stays hidden from other workers; a delivery claimed by a worker that
+/// died is attempted again once it elapses.
+const CLAIM_LEASE_SECONDS: i64 = 5 * 60;
+
+/// Time a receiver has to respond to a delivery.
+const REQUEST_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// Number of attempts after which a delivery is given up.
+const MAX_ATTEMPTS: i32 = 8;
+
+/// Delay before the first retry; it doubles with each further attempt.
+const BASE_RETRY_DELAY_SECONDS: i64 = 30;
+
+/// Maximum length of the error recorded for a failed attempt.
+const MAX_ERROR_LENGTH: usize = 1024;
+
+/// Queues the delivery of an audit event to every webhook subscribed to its action.
+///
+/// It runs in the transaction recording the event, so deliveries are queued if and only if the
+/// mutation is committed.
+pub async fn enqueue_deliveries(
+    db_conn: &mut AsyncPgConnection,
+    event: &AuditEventDto,
+) -> QueryResult<()> {
+    let event_type = enum_name(&event.action);
+
+    diesel::insert_into(webhook_deliveries::table)
+        .values(
+            webhooks::table
+                .filter(webhooks::event_types.contains(vec![event_type.clone()]))
+                .select((
+                    webhooks::id,
+                    event_type.into_sql::<Text>(),
+                    snapshot(event).into_sql::<Jsonb>(),
+                )),
+        )
+        .into_columns((
+            webhook_deliveries::webhook_id,
+            webhook_deliveries::event_type,
+            webhook_deliveries::payload,
+        ))
+        .execute(db_conn)
+        .await?;
+
+    Ok(())
+}
+
+/// Returns the signature of a delivery: the hex-encoded HMAC-SHA256 of `<timestamp>.<body>`.
+fn sign_payload(secret: &str, timestamp: i64, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("hmac accepts keys of any size");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+
+    mac.finalize()
+        .into_bytes()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Spawns a background task that posts due deliveries to their webhooks.
+///
+/// Each delivery is a POST of the audit event as JSON, with the headers:
+/// - `X-Poly-Tag-Event`: the action of the event;
+/// - `X-Poly-Tag-Delivery`: the uuid of the delivery, which stays the same across retries;
+/// - `X-Poly-Tag-Timestamp`: the Unix time of the attempt;
+/// - `X-Poly-Tag-Signature`: `sha256=` followed by the result of [`sign_payload`].
+///
+/// Any response other than 2xx fails the attempt, and the delivery is retried with an exponential
+/// backoff until it has been attempted `MAX_ATTEMPTS` times.
+pub fn spawn_delivery_worker(db_pool: DBPool) {
+    tokio::spawn(async move {
+        let http_client = HttpClient::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .redirect_policy(RedirectPolicy::None)
+            .build()
+            .expect("failed to create webhook http client");
+        let mut interval = tokio::time::interval(POLL_INTERVAL);
+
+        loop {
+            interval.tick().await;
+
+            if let Err(err) = deliver_due(&db_pool, &http_client).await {
+                tracing::error!("failed to deliver webhooks: {:#?}", err);
+            }
+        }
+    });
+}
+
+async fn deliver_due(db_pool: &DBPool, http_client: &HttpClient) -> Result<(), DeliveryError> {
+    loop {
+        let deliveries = claim_due(db_pool).await?;
+        if deliveries.is_empty() {
+            return Ok(());
+        }
+
+        let attempts = deliveries.into_iter().map(|delivery| async move {
+            let outcome = attempt(http_client, &delivery).await;
+            record_attempt(db_pool, &delivery, outcome).await
+        });
+        for result in futures::future::join_all(attempts).await {
+            result?;
+        }
+    }
+}
+
+#[derive(Queryable, Debug)]
+struct DueDelivery {
+    id: i64,
+    uuid: Uuid,
+    event_type: String,
+    payload: serde_json::Value,
+    attempt_count: i32,
+    url: String,
+    secret: String,
+}
+
+/// Claims a batch of due deliveries, skipping those claimed by other workers.
+async fn claim_due(db_pool: &DBPool) -> Result<Vec<DueDelivery>, DeliveryError> {
+    let db_conn = &mut db_pool.get().await?;
+
+    let due = alias!(webhook_deliveries as due);
+    let due_ids = due
+        .select(due.field(webhook_deliveries::id))
+        .filter(
+            due.field(webhook_deliveries::status)
+                .eq(enum_name(&WebhookDeliveryStatusDto::Pending)),
+        )
+        .filter(due.field(webhook_deliveries::next_attempt_at).le(now))
+        .order(due.field(webhook_deliveries::next_attempt_at).asc())
+        .limit(BATCH_SIZE)
+        .for_update()
+        .skip_locked();
+    let claimed = diesel::update(webhook_deliveries::table)
+        .filter(webhook_deliveries::id.eq_any(due_ids))
+        .set(
+            webhook_deliveries::next_attempt_at
+                .eq((Utc::now() + Duration::seconds(CLAIM_LEASE_SECONDS)).naive_utc()),
+        )
+        .returning(webhook_deliveries::id)
+        .get_results::<i64>(db_conn)
+        .await?;
+
+    let deliveries = webhook_deliveries::table
+        .inner_join(webhooks::table)
+        .select((
+            webhook_deliveries::id,
+            webhook_deliveries::uuid,
+            webhook_deliveries::event_type,
+            webhook_deliveries::payload,
+            webhook_deliveries::attempt_count,
+            webhooks::url,
+            webhooks::secret,
+        ))
+        .filter(webhook_deliveries::id.eq_any(claimed))
+        .load::<DueDelivery>(db_conn)
+        .await?;
+
+    Ok(deliveries)
+}
+
+/// Outcome of an attempt: the status code of the response if any, and the failure if any.
+type AttemptOutcome = (Option<u16>, Option<String>);
+
+async fn attempt(http_client: &HttpClient, delivery: &DueDelivery) -> AttemptOutcome {
+    let body = serde_json::to_vec(&delivery.payload).expect("failed to serialize webhook payload");
+    let timestamp = Utc::now().timestamp();
+    let signature = sign_payload(&delivery.secret, timestamp, &body);
+
+    let request = Request::post(&delivery.url)
+        .header("content-type", "application/json")
+        .header("x-poly-tag-event", &delivery.event_type)
+        .header("x-poly-tag-delivery", delivery.uuid.to_string())
+        .header("x-poly-tag-timestamp", timestamp.to_string())
+        .header("x-poly-tag-signature", format!("sha256={}", signature))
+        .body(body);
+    let request = match request {
+        Ok(request) => request,
+        Err(err) => return (None, Some(format!("invalid request: {}", err))),
+    };
+
+    match http_client.send_async(request).await {
+        Ok(response) if response.status().is_success() => (Some(response.status().as_u16()), None),
+        Ok(response) => (
+            Some(response.status().as_u16()),
+            Some(format!("receiver responded with {}", response.status())),
+        ),
+        Err(err) => (None, Some(err.to_string())),
+    }
+}
+
+async fn record_attempt(
+    db_pool: &DBPool,
+    delivery: &DueDelivery,
+    (response_status, error): AttemptOutcome,
+) -> Result<(), DeliveryError> {
+    let attempt_count = delivery.attempt_count + 1;
+    let attempted_at = Utc::now();
+    let (status, next_attempt_at) = schedule(attempt_count, attempted_at, error.is_some());
+
+    if let Some(error) = &error {
+        tracing::warn!(
+            "webhook delivery `{}` failed on attempt {}: {}",
+            delivery.uuid,
+            attempt_count,
+            error
+        );
+    }
+
+    let db_conn = &mut db_pool.get().await?;
+    diesel::update(webhook_deliveries::table.find(delivery.id))
+        .set((
+            webhook_deliveries::status.eq(enum_name(&status)),
+            webhook_deliveries::attempt_count.eq(attempt_count),
+            webhook_deliveries::next_attempt_at
+                .eq(next_attempt_at.map(|next_attempt_at| next_attempt_at.naive_utc())),
+            webhook_deliveries::last_attempt_at.eq(attempted_at.naive_utc()),
+            webhook_deliveries::response_status.eq(response_status.map(i32::from)),
+            webhook_deliveries::last_error
+                .eq(error.map(|error| error.chars().take(MAX_ERROR_LENGTH).collect::<String>())),
+        ))
+        .execute(db_conn)
+        .await?;
+
+    Ok(())
+}
+
+/// Returns the status of a delivery after its n-th attempt, and when to attempt it again if ever.
+fn schedule(
+    attempt_count: i32,
+    attempted_at: DateTime<Utc>,
+    failed: bool,
+) -> (WebhookDeliveryStatusDto, Option<DateTime<Utc>>) {
+    if !failed {
+        (WebhookDeliveryStatusDto::Succeeded, None)
+    } else if MAX_ATTEMPTS <= attempt_count {
+        (WebhookDeliveryStatusDto::Failed, None)
+    } else {
+        (
+            WebhookDeliveryStatusDto::Pending,
+            Some(attempted_at + retry_delay(attempt_count)),
+        )
+    }
+}
+
+/// Returns the delay before attempting a delivery again after its n-th failed attempt.
+fn retry_delay(attempt_count: i32) -> Duration {
+    let exponent = (attempt_count - 1).clamp(0, 16) as u32;
+    Duration::seconds(BASE_RETRY_DELAY_SECONDS * 2i64.pow(exponent))
+}
+
+#[derive(thiserror::Error, Debug)]
+enum DeliveryError {
+    #[error("{0}")]
+    PoolError(#[from] diesel_async::pooled_connection::deadpool::PoolError),
+    #[error("{0}")]
+    DieselError(#[from] diesel::result::Error),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Bytes, extract::State, http::HeaderMap, http::StatusCode, routing::post};
+    use std::sync::{Arc, Mutex};
+    use tokio::sync::mpsc;
+
+    /// A request received by the stand-in receiver.
+    struct Received {
+        headers: HeaderMap,
+        body: Bytes,
+    }
+
+    /// Serves a stand-in receiver answering every delivery with `status`, and returns its url
+    /// along with the requests it receives.
+    async fn serve_receiver(
+        status: Arc<Mutex<StatusCode>>,
+    ) -> (String, mpsc::UnboundedReceiver<Received>) {
+        let (sender, received) = mpsc::unbounded_channel();
+        let app = axum::Router::new()
+            .route(
+                "/hook",
+                post(
+                    |State((status, sender)): State<(
+                        Arc<Mutex<StatusCode>>,
+                        mpsc::UnboundedSender<Received>,
+                    )>,
+                     headers: HeaderMap,
+                     body: Bytes| async move {
+                        sender.send(Received { headers, body }).unwrap();
+                        *status.lock().unwrap()
+                    },
+                ),
+            )
+            .with_state((status, sender));
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        (format!("http://{}/hook", address), received)
+    }
+
+    fn delivery(url: String, attempt_count: i32) -> DueDelivery {
+        DueDelivery {
+            id: 1,
+            uuid: Uuid::new_v4(),
+            event_type: "removeFile".to_owned(),
+            payload: serde_json::json!({ "action": "removeFile" }),
+            attempt_count,
+            url,
+            secret: "secret".to_owned(),
+        }
+    }
+
+    fn header<'a>(received: &'a Received, name: &str) -> &'a str {
+        received.headers.get(name).unwrap().to_str().unwrap()
+    }
+
+    #[test]
+    fn retry_delay_doubles_from_the_base_delay() {
+        assert_eq!(retry_delay(1), Duration::seconds(BASE_RETRY_DELAY_SECONDS));
+        assert_eq!(
+            retry_delay(2),
+            Duration::seconds(BASE_RETRY_DELAY_SECONDS * 2)
+        );
+        assert_eq!(
+            retry_delay(3),
+            Duration::seconds(BASE_RETRY_DELAY_SECONDS * 4)
+        );
+        assert_eq!(retry_delay(0), retry_delay(1));
+        assert_eq!(retry_delay(100), retry_delay(17));
+    }
+
+    #[tokio::test]
+    async fn signs_deliveries_and_retries_failures_until_giving_up() {
+        let status = Arc::new(Mutex::new(StatusCode::OK));
+        let (url, mut received) = serve_receiver(status.clone()).await;
+        let http_client = HttpClient::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .redirect_policy(RedirectPolicy::None)
+            .build()
+            .unwrap();
+
+        let outcome = attempt(&http_client, &delivery(url.clone(), 0)).await;
+        assert_eq!(outcome, (Some(200), None));
+
+        let request = received.recv().await.unwrap();
+        let timestamp = header(&request, "x-poly-tag-timestamp");
+        let mut mac = Hmac::<Sha256>::new_from_slice(b"secret").unwrap();
+        mac.update(format!("{}.", timestamp).as_bytes());
+        mac.update(&request.body);
+        let expected = mac
+            .finalize()
+            .into_bytes()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect::<String>();
+        assert_eq!(
+            header(&request, "x-poly-tag-signature"),
+            format!("sha256={}", expected)
+        );
+        assert_eq!(header(&request, "x-poly-tag-event"), "removeFile");
+        assert_eq!(
+            schedule(1, Utc::now(), outcome.1.is_some()),
+            (WebhookDeliveryStatusDto::Succeeded, None)
+        );
+
+        *status.lock().unwrap() = StatusCode::INTERNAL_SERVER_ERROR;
+        let mut delays = vec![];
+        for attempt_count in 1..=MAX_ATTEMPTS {
+            let outcome = attempt(&http_client, &delivery(url.clone(), attempt_count - 1)).await;
+            assert_eq!(outcome.0, Some(500));
+            assert!(received.recv().await.is_some());
+
+            let attempted_at = Utc::now();
+            match schedule(attempt_count, attempted_at, outcome.1.is_some()) {
+                (WebhookDeliveryStatusDto::Pending, Some(next_attempt_at)) => {
+                    delays.push(next_attempt_at - attempted_at)
+                }
+                (WebhookDeliveryStatusDto::Failed, None) => {
+                    assert_eq!(attempt_count, MAX_ATTEMPTS)
+                }
+                other => panic!("unexpected schedule {:?}", other),
+            }
+        }
+
+        assert_eq!(delays.len(), MAX_ATTEMPTS as usize - 1);
+        assert_eq!(delays[0], Duration::seconds(BASE_RETRY_DELAY_SECONDS));
+        for pair in delays.windows(2) {
+            assert_eq!(pair[1], pair[0] * 2);
+        }
+    }
+}